@@ -6,8 +6,8 @@ use crate::{
         read_tx_and_calculate_txid, validate_threshold_and_add_work, HeaderWithoutPrevBlockHash,
     },
     constants::{
-        BLOCKHASH_MERKLE_TREE_DEPTH, BRIDGE_AMOUNT_SATS, CLAIM_MERKLE_TREE_DEPTH,
-        MAX_BLOCK_HANDLE_OPS, NUM_ROUNDS, PERIOD_CLAIM_MT_ROOTS, WITHDRAWAL_MERKLE_TREE_DEPTH,
+        BLOCKHASH_MERKLE_TREE_DEPTH, CLAIM_MERKLE_TREE_DEPTH, MAX_BLOCK_HANDLE_OPS, NUM_ROUNDS,
+        PERIOD_CLAIM_MT_ROOTS, WITHDRAWAL_MERKLE_TREE_DEPTH,
     },
     double_sha256_hash,
     env::Environment,
@@ -144,15 +144,19 @@ pub fn read_merkle_tree_proof<E: Environment, const D: usize>(
     hash
 }
 
-/// Reads a withdrawal proof, adds output address to incremental merkle tree
+/// Reads a withdrawal proof, adds output address to incremental merkle tree.
+/// The withdrawn amount must be one of the given bridge denominations.
 pub fn read_withdrawal_proof<E: Environment>(
     block_mt_root: [u8; 32],
+    bridge_amounts_sats: &[u64],
     imt: &mut IncrementalMerkleTree<WITHDRAWAL_MERKLE_TREE_DEPTH>,
 ) {
+    let bridge_amount_sats = E::read_u64();
+    assert!(bridge_amounts_sats.contains(&bridge_amount_sats));
     let output_address = E::read_32bytes();
     // tracing::debug!("READ output_address: {:?}", output_address);
     let txid =
-        read_tx_and_calculate_txid::<E>(None, Some((Some(BRIDGE_AMOUNT_SATS), output_address)));
+        read_tx_and_calculate_txid::<E>(None, Some((Some(bridge_amount_sats), output_address)));
     // tracing::debug!("READ tx and calculated txid: {:?}", txid);
     let block_tx_mt_root = read_and_verify_bitcoin_merkle_path::<E>(txid);
     // tracing::debug!(
@@ -205,7 +209,9 @@ pub fn read_and_verify_verifiers_challenge_proof<E: Environment>() -> (U256, [u8
     (max_pow_u256, lc_cutoff_blockhash, period_num as u8)
 }
 
-pub fn bridge_proof<E: Environment>() -> (U256, [u8; 32], u8) {
+/// Bridge denominations are given by the prover, so they must be committed
+/// with the proof and checked against the bridge's config by its verifier.
+pub fn bridge_proof<E: Environment>(bridge_amounts_sats: &[u64]) -> (U256, [u8; 32], u8) {
     let mut blockhashes_mt = IncrementalMerkleTree::new();
     let mut withdrawal_mt = IncrementalMerkleTree::new();
     let mut total_pow = U256::ZERO;
@@ -231,7 +237,11 @@ pub fn bridge_proof<E: Environment>() -> (U256, [u8; 32], u8) {
         let num_withdrawals = E::read_u32();
         // // tracing::debug!("READ num_withdrawals: {:?}", num_withdrawals);
         for _ in 0..num_withdrawals {
-            read_withdrawal_proof::<E>(blockhashes_mt.root, &mut withdrawal_mt);
+            read_withdrawal_proof::<E>(
+                blockhashes_mt.root,
+                bridge_amounts_sats,
+                &mut withdrawal_mt,
+            );
         }
         total_num_withdrawals += num_withdrawals;
 
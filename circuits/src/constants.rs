@@ -36,8 +36,6 @@ pub const PERIOD_CLAIM_MT_ROOTS: [[u8; 32]; NUM_ROUNDS] = [
 /// Block heights at which each period ends
 /// After each period_end_block_height, the corresponding connector source utxo opens after K_DEEP + MAX_BITVM_CHALLENGE_RESPONSE blocks.
 pub const PERIOD_END_BLOCK_HEIGHTS: [u32; NUM_ROUNDS] = [0; NUM_ROUNDS];
-/// Constant bridge amount in sats, for now only used in clementine-circuits
-pub const BRIDGE_AMOUNT_SATS: u64 = 100_000_000;
 /// Constant withdrawal request txout value in sats
 // pub const WITHDRAWAL_EMPTY_UTXO_SATS: u64 = 550;
 /// Constant block count for user to take deposit back if deposit does not happen
@@ -1,203 +1,287 @@
 use crate::{
-    actor::Actor,
-    builder,
+    builder::{
+        deposit_graph::{DepositGraph, GraphSighash},
+        transaction::{MoveTxDeposit, TxHandler},
+    },
     config::BridgeConfig,
+    database::Database,
+    epoch::{self, EpochSweep, SweptDepositGraph, VerifierEpoch},
     errors::BridgeError,
-    musig2::{
-        aggregate_nonces, aggregate_partial_signatures, AggregateFromPublicKeys, MuSigAggNonce,
-        MuSigPartialSignature, MuSigPubNonce,
-    },
-    traits::rpc::AggregatorServer,
+    frost::{self, FrostCommitment, FrostSignatureShare, FrostSignerId},
+    musig2::{aggregate_nonces, MuSigAggNonce, MuSigPartialSignature, MuSigPubNonce, NofnContext},
+    traits::rpc::{AggregatorServer, OperatorRpcClient, VerifierRpcClient},
     utils::handle_taproot_witness_new,
     ByteArray32, ByteArray66, EVMAddress, UTXO,
 };
 use async_trait::async_trait;
-use bitcoin::{address::NetworkUnchecked, Address, OutPoint};
-use bitcoin::{hashes::Hash, Txid};
+use bitcoin::Txid;
+use bitcoin::{address::NetworkUnchecked, Address, Amount, OutPoint};
 use bitcoincore_rpc::RawTx;
-use secp256k1::schnorr;
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use secp256k1::rand::{rngs::OsRng, RngCore};
+use secp256k1::{schnorr, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Maximum number of FROST signing sets that are tried for a deposit in a
+/// single signing round.
+const MAX_FROST_SIGNING_SETS: usize = 3;
+
+/// Time a deposit call holds the lease of a signing session. Lease of a call
+/// that didn't release it, e.g. because of a crash, can be claimed by others
+/// after that.
+pub const DEPOSIT_SESSION_LEASE: Duration = Duration::from_secs(600);
+
+/// Progress of a deposit that is driven by the aggregator. Every signing step
+/// fills its fields when it is completed, so that an interrupted deposit can be
+/// resumed from the first missing one.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DepositSession {
+    pub pub_nonces: Option<Vec<Vec<MuSigPubNonce>>>,
+    pub agg_nonces: Option<Vec<MuSigAggNonce>>,
+    pub kickoff_utxos: Option<Vec<UTXO>>,
+    pub kickoff_sigs: Option<Vec<schnorr::Signature>>,
+    pub slash_or_take_sigs: Option<Vec<schnorr::Signature>>,
+    pub burn_sigs: Option<Vec<schnorr::Signature>>,
+    pub operator_take_sigs: Option<Vec<schnorr::Signature>>,
+    pub move_tx: Option<(String, Txid)>,
+    /// Deposits of the batched move_tx that moves the deposit, in input
+    /// order. `None` if the deposit has its own move_tx.
+    pub batch: Option<Vec<OutPoint>>,
+    /// Verifiers that sign the deposit with the FROST key, in the order of
+    /// `pub_nonces`, which are their commitments. `None` if the deposit is
+    /// signed with MuSig2 by every verifier.
+    pub frost_signers: Option<Vec<FrostSignerId>>,
+}
+
+impl DepositSession {
+    /// Returns true if the move transaction of the deposit is signed.
+    pub fn is_completed(&self) -> bool {
+        self.move_tx.is_some()
+    }
+}
 
 /// Aggregator struct.
 /// This struct is responsible for aggregating partial signatures from the verifiers.
-/// It will have in total 3 * num_operator + 1 aggregated nonces.
-/// [0] -> Aggregated nonce for the move transaction.
-/// [1..num_operator + 1] -> Aggregated nonces for the operator_takes transactions.
-/// [num_operator + 1..2 * num_operator + 1] -> Aggregated nonces for the slash_or_take transactions.
-/// [2 * num_operator + 1..3 * num_operator + 1] -> Aggregated nonces for the burn transactions.
-/// For now, we do not have the last bit.
+/// Aggregated nonces of a deposit are laid out as in
+/// [`NonceIndex`](crate::builder::deposit_graph::NonceIndex).
 #[derive(Debug, Clone)]
 pub struct Aggregator {
+    db: Database,
     config: BridgeConfig,
-    nofn_xonly_pk: secp256k1::XOnlyPublicKey,
+    nofn: NofnContext,
+    verifier_clients: Vec<HttpClient>,
+    operator_clients: Vec<HttpClient>,
 }
 
 impl Aggregator {
     #[tracing::instrument(err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
     pub async fn new(config: BridgeConfig) -> Result<Self, BridgeError> {
-        let nofn_xonly_pk = secp256k1::XOnlyPublicKey::from_musig2_pks(
-            config.verifiers_public_keys.clone(),
-            None,
-            false,
-        );
+        let db = Database::new(&config).await?;
+
+        let verifier_clients = config
+            .verifier_endpoints
+            .clone()
+            .unwrap_or_default()
+            .iter()
+            .map(|endpoint| HttpClientBuilder::default().build(endpoint))
+            .collect::<Result<Vec<_>, _>>()?;
+        let operator_clients = config
+            .operator_endpoints
+            .clone()
+            .unwrap_or_default()
+            .iter()
+            .map(|endpoint| HttpClientBuilder::default().build(endpoint))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let nofn = NofnContext::from_config(&config)?;
+        epoch::save_config_epochs(&db, &config, &nofn).await?;
 
         Ok(Aggregator {
+            db,
             config,
-            nofn_xonly_pk,
+            nofn,
+            verifier_clients,
+            operator_clients,
         })
     }
 
+    /// Verifies the verifiers' partial signatures of a sighash of the deposit
+    /// graph and aggregates them.
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    fn aggregate_slash_or_take_partial_sigs(
+    fn aggregate_partial_sigs(
         &self,
-        deposit_outpoint: OutPoint,
-        kickoff_utxo: UTXO,
-        operator_xonly_pk: secp256k1::XOnlyPublicKey,
-        operator_idx: usize,
+        sighash: GraphSighash,
         agg_nonce: &MuSigAggNonce,
+        pub_nonces: Vec<MuSigPubNonce>,
         partial_sigs: Vec<MuSigPartialSignature>,
-    ) -> Result<[u8; 64], BridgeError> {
-        let mut tx = builder::transaction::create_slash_or_take_tx(
-            deposit_outpoint,
-            kickoff_utxo,
-            operator_xonly_pk,
-            operator_idx,
-            self.nofn_xonly_pk,
-            self.config.network,
-            self.config.user_takes_after,
-            self.config.operator_takes_after,
-            self.config.bridge_amount_sats,
-        );
-        // tracing::debug!("SLASH_OR_TAKE_TX: {:?}", tx);
-        tracing::debug!("SLASH_OR_TAKE_TX weight: {:?}", tx.tx.weight());
-        let message: [u8; 32] =
-            Actor::convert_tx_to_sighash_script_spend(&mut tx, 0, 0)?.to_byte_array();
-        // tracing::debug!("aggregate SLASH_OR_TAKE_TX message: {:?}", message);
-        let final_sig: [u8; 64] = aggregate_partial_signatures(
-            self.config.verifiers_public_keys.clone(),
-            None,
-            false,
+    ) -> Result<schnorr::Signature, BridgeError> {
+        self.nofn.verify_partial_signatures(
+            sighash.tweak,
+            sighash.tweak_flag,
+            agg_nonce,
+            pub_nonces,
+            partial_sigs.clone(),
+            sighash.sighash,
+        )?;
+        let final_sig: [u8; 64] = self.nofn.aggregate_partial_signatures(
+            sighash.tweak,
+            sighash.tweak_flag,
             agg_nonce,
             partial_sigs,
-            ByteArray32(message),
+            sighash.sighash,
         )?;
-        // tracing::debug!("aggregate SLASH_OR_TAKE_TX final_sig: {:?}", final_sig);
-        // tracing::debug!(
-        //     "aggregate SLASH_OR_TAKE_TX for verifiers: {:?}",
-        //     self.config.verifiers_public_keys.clone()
-        // );
-        // tracing::debug!(
-        //     "aggregate SLASH_OR_TAKE_TX for operator: {:?}",
-        //     operator_xonly_pk
-        // );
-        Ok(final_sig)
+
+        Ok(schnorr::Signature::from_slice(&final_sig)?)
     }
 
+    /// Aggregates the partial signatures of a group of sighashes. Nonces of
+    /// the group start at `nonce_offset` and every verifier's partial
+    /// signatures are in the order of the sighashes.
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    fn aggregate_operator_takes_partial_sigs(
+    fn aggregate_sigs(
         &self,
-        deposit_outpoint: OutPoint,
-        kickoff_utxo: UTXO,
-        operator_xonly_pk: &secp256k1::XOnlyPublicKey,
-        operator_idx: usize,
-        agg_nonce: &MuSigAggNonce,
-        partial_sigs: Vec<MuSigPartialSignature>,
-    ) -> Result<[u8; 64], BridgeError> {
-        let move_tx = builder::transaction::create_move_tx(
-            deposit_outpoint,
-            self.nofn_xonly_pk,
-            self.config.bridge_amount_sats,
-            self.config.network,
-        );
-        let bridge_fund_outpoint = OutPoint {
-            txid: move_tx.compute_txid(),
-            vout: 0,
-        };
-        let slash_or_take_tx_handler = builder::transaction::create_slash_or_take_tx(
-            deposit_outpoint,
-            kickoff_utxo,
-            *operator_xonly_pk,
-            operator_idx,
-            self.nofn_xonly_pk,
-            self.config.network,
-            self.config.user_takes_after,
-            self.config.operator_takes_after,
-            self.config.bridge_amount_sats,
-        );
-        let slash_or_take_utxo = UTXO {
-            outpoint: OutPoint {
-                txid: slash_or_take_tx_handler.tx.compute_txid(),
-                vout: 0,
-            },
-            txout: slash_or_take_tx_handler.tx.output[0].clone(),
-        };
-        // tracing::debug!(
-        //     "SERDE_UTXO: {:#?}",
-        //     serde_json::to_string(&slash_or_take_utxo)?
-        // );
-
-        let mut tx_handler = builder::transaction::create_operator_takes_tx(
-            bridge_fund_outpoint,
-            slash_or_take_utxo,
-            *operator_xonly_pk,
-            self.nofn_xonly_pk,
-            self.config.network,
-            self.config.operator_takes_after,
-            self.config.bridge_amount_sats,
-            self.config.operator_wallet_addresses[operator_idx].clone(),
-        );
-        // tracing::debug!(
-        //     "OPERATOR_TAKES_TX with operator_idx:{:?} {:?}",
-        //     operator_idx,
-        //     tx_handler.tx
-        // );
-        // tracing::debug!("OPERATOR_TAKES_TX_HEX: {:?}", tx_handler.tx.raw_hex());
-        tracing::debug!("OPERATOR_TAKES_TX weight: {:?}", tx_handler.tx.weight());
-        let message: [u8; 32] =
-            Actor::convert_tx_to_sighash_pubkey_spend(&mut tx_handler, 0)?.to_byte_array();
-        let final_sig: [u8; 64] = aggregate_partial_signatures(
-            self.config.verifiers_public_keys.clone(),
-            None,
-            true,
-            agg_nonce,
-            partial_sigs,
-            ByteArray32(message),
-        )?;
-        // tracing::debug!("OPERATOR_TAKES_TX final_sig: {:?}", final_sig);
-        Ok(final_sig)
+        sighashes: &[GraphSighash],
+        nonce_offset: usize,
+        agg_nonces: &[MuSigAggNonce],
+        pub_nonces: &[Vec<MuSigPubNonce>],
+        partial_sigs: &[Vec<MuSigPartialSignature>],
+    ) -> Result<Vec<schnorr::Signature>, BridgeError> {
+        let mut sigs = Vec::new();
+        for (i, sighash) in sighashes.iter().enumerate() {
+            let agg_nonce = agg_nonces
+                .get(nonce_offset + i)
+                .ok_or(BridgeError::NoncesNotFound)?;
+            let pub_nonces = pub_nonces
+                .iter()
+                .map(|v| v.get(nonce_offset + i).cloned())
+                .collect::<Option<Vec<ByteArray66>>>()
+                .ok_or(BridgeError::NoncesNotFound)?;
+            let partial_sigs = partial_sigs
+                .iter()
+                .map(|v| v.get(i).cloned())
+                .collect::<Option<Vec<ByteArray32>>>()
+                .ok_or(BridgeError::NoncesNotFound)?;
+
+            sigs.push(self.aggregate_partial_sigs(
+                *sighash,
+                agg_nonce,
+                pub_nonces,
+                partial_sigs,
+            )?);
+        }
+
+        Ok(sigs)
     }
 
+    /// Same as [`Self::aggregate_sigs`], for the signature shares of the FROST
+    /// signers. Commitments and signature shares are in the order of the
+    /// signers.
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    fn aggregate_move_partial_sigs(
+    async fn aggregate_frost_sigs(
         &self,
-        deposit_outpoint: OutPoint,
-        evm_address: EVMAddress,
-        recovery_taproot_address: &Address<NetworkUnchecked>,
-        agg_nonce: &MuSigAggNonce,
-        partial_sigs: Vec<MuSigPartialSignature>,
-    ) -> Result<[u8; 64], BridgeError> {
-        let mut tx = builder::transaction::create_move_tx_handler(
-            deposit_outpoint,
-            evm_address,
-            recovery_taproot_address,
-            self.nofn_xonly_pk,
-            self.config.network,
-            self.config.user_takes_after,
-            self.config.bridge_amount_sats,
-        );
-        // println!("MOVE_TX: {:?}", tx);
-        // println!("MOVE_TXID: {:?}", tx.tx.compute_txid());
-        let message: [u8; 32] =
-            Actor::convert_tx_to_sighash_script_spend(&mut tx, 0, 0)?.to_byte_array();
-        let final_sig: [u8; 64] = aggregate_partial_signatures(
-            self.config.verifiers_public_keys.clone(),
-            None,
-            false,
-            agg_nonce,
-            partial_sigs,
-            ByteArray32(message),
-        )?;
+        sighashes: &[GraphSighash],
+        nonce_offset: usize,
+        signers: &[FrostSignerId],
+        commitments: &[Vec<FrostCommitment>],
+        signature_shares: &[Vec<FrostSignatureShare>],
+    ) -> Result<Vec<schnorr::Signature>, BridgeError> {
+        let public_key_package = self
+            .db
+            .get_frost_public_key_package(None)
+            .await?
+            .ok_or(BridgeError::FrostKeyNotFound)?;
+        if !self.nofn.is_threshold_key()
+            || frost::group_xonly_pk(&public_key_package)? != self.nofn.xonly_pk()
+        {
+            return Err(BridgeError::FrostGroupKeyMismatch);
+        }
+        if signers.len() != commitments.len() || signers.len() != signature_shares.len() {
+            return Err(BridgeError::InvalidFrostSigningSet);
+        }
 
-        Ok(final_sig)
+        let mut sigs = Vec::new();
+        for (i, sighash) in sighashes.iter().enumerate() {
+            let commitments = signers
+                .iter()
+                .zip(commitments)
+                .map(|(id, v)| v.get(nonce_offset + i).map(|commitment| (*id, *commitment)))
+                .collect::<Option<BTreeMap<_, _>>>()
+                .ok_or(BridgeError::NoncesNotFound)?;
+            let signature_shares = signers
+                .iter()
+                .zip(signature_shares)
+                .map(|(id, v)| v.get(i).map(|signature_share| (*id, *signature_share)))
+                .collect::<Option<BTreeMap<_, _>>>()
+                .ok_or(BridgeError::NoncesNotFound)?;
+
+            sigs.push(frost::aggregate(
+                &public_key_package,
+                sighash.tweak,
+                sighash.tweak_flag,
+                &commitments,
+                &signature_shares,
+                sighash.sighash,
+            )?);
+        }
+
+        Ok(sigs)
+    }
+
+    /// Aggregates the partial signatures of a group of sighashes with the
+    /// nonces of the deposit's signing session, either with MuSig2 or FROST.
+    #[tracing::instrument(skip(self, session), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn aggregate_session_sigs(
+        &self,
+        sighashes: &[GraphSighash],
+        nonce_offset: usize,
+        session: &DepositSession,
+        partial_sigs: &[Vec<MuSigPartialSignature>],
+    ) -> Result<Vec<schnorr::Signature>, BridgeError> {
+        let pub_nonces = session
+            .pub_nonces
+            .as_ref()
+            .ok_or(BridgeError::NoncesNotFound)?;
+
+        match &session.frost_signers {
+            Some(signers) => {
+                self.aggregate_frost_sigs(
+                    sighashes,
+                    nonce_offset,
+                    signers,
+                    pub_nonces,
+                    partial_sigs,
+                )
+                .await
+            }
+            None => self.aggregate_sigs(
+                sighashes,
+                nonce_offset,
+                session
+                    .agg_nonces
+                    .as_ref()
+                    .ok_or(BridgeError::NoncesNotFound)?,
+                pub_nonces,
+                partial_sigs,
+            ),
+        }
+    }
+
+    /// Returns the clients of the verifiers that sign the deposit.
+    fn signer_clients(&self, session: &DepositSession) -> Result<Vec<&HttpClient>, BridgeError> {
+        match &session.frost_signers {
+            Some(signers) => signers
+                .iter()
+                .map(|id| {
+                    usize::from(*id)
+                        .checked_sub(1)
+                        .and_then(|i| self.verifier_clients.get(i))
+                        .ok_or(BridgeError::InvalidFrostSigningSet)
+                })
+                .collect(),
+            None => Ok(self.verifier_clients.iter().collect()),
+        }
     }
 
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
@@ -213,7 +297,7 @@ impl Aggregator {
                 .collect::<Option<Vec<ByteArray66>>>()
                 .ok_or(BridgeError::NoncesNotFound)?;
 
-            agg_nonces.push(aggregate_nonces(pub_nonces));
+            agg_nonces.push(aggregate_nonces(pub_nonces)?);
         }
 
         Ok(agg_nonces)
@@ -223,62 +307,93 @@ impl Aggregator {
     pub async fn aggregate_slash_or_take_sigs(
         &self,
         deposit_outpoint: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        bridge_amount_sats: Amount,
         kickoff_utxos: Vec<UTXO>,
         agg_nonces: Vec<MuSigAggNonce>,
+        pub_nonces: Vec<Vec<MuSigPubNonce>>,
         partial_sigs: Vec<Vec<MuSigPartialSignature>>,
     ) -> Result<Vec<schnorr::Signature>, BridgeError> {
-        tracing::debug!(
-            "Aggregate slash or take sigs called with inputs: {:?}\n {:?}\n{:?}\n{:?}",
-            deposit_outpoint,
+        let graph = self.create_deposit_graph(
+            MoveTxDeposit {
+                deposit_outpoint,
+                recovery_taproot_address,
+                evm_address,
+                bridge_amount_sats,
+            },
             kickoff_utxos,
-            agg_nonces,
-            partial_sigs
-        );
+        )?;
 
-        let mut slash_or_take_sigs = Vec::new();
-        for i in 0..partial_sigs[0].len() {
-            let partial_sigs = partial_sigs
-                .iter()
-                .map(|v| v.get(i).cloned())
-                .collect::<Option<Vec<ByteArray32>>>()
-                .ok_or(BridgeError::NoncesNotFound)?;
+        self.aggregate_sigs(
+            graph.slash_or_take_sighashes(),
+            0,
+            &agg_nonces,
+            &pub_nonces,
+            &partial_sigs,
+        )
+    }
 
-            let agg_sig = self.aggregate_slash_or_take_partial_sigs(
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn aggregate_burn_sigs(
+        &self,
+        deposit_outpoint: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        bridge_amount_sats: Amount,
+        kickoff_utxos: Vec<UTXO>,
+        agg_nonces: Vec<MuSigAggNonce>,
+        pub_nonces: Vec<Vec<MuSigPubNonce>>,
+        partial_sigs: Vec<Vec<MuSigPartialSignature>>,
+    ) -> Result<Vec<schnorr::Signature>, BridgeError> {
+        let graph = self.create_deposit_graph(
+            MoveTxDeposit {
                 deposit_outpoint,
-                kickoff_utxos[i].clone(),
-                self.config.operators_xonly_pks[i],
-                i,
-                &agg_nonces[i].clone(),
-                partial_sigs,
-            )?;
+                recovery_taproot_address,
+                evm_address,
+                bridge_amount_sats,
+            },
+            kickoff_utxos,
+        )?;
 
-            slash_or_take_sigs.push(secp256k1::schnorr::Signature::from_slice(&agg_sig)?);
-        }
-        Ok(slash_or_take_sigs)
+        self.aggregate_sigs(
+            graph.burn_sighashes(),
+            0,
+            &agg_nonces,
+            &pub_nonces,
+            &partial_sigs,
+        )
     }
 
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
     pub async fn aggregate_operator_take_sigs(
         &self,
         deposit_outpoint: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        bridge_amount_sats: Amount,
         kickoff_utxos: Vec<UTXO>,
         agg_nonces: Vec<MuSigAggNonce>,
+        pub_nonces: Vec<Vec<MuSigPubNonce>>,
         partial_sigs: Vec<Vec<MuSigPartialSignature>>,
     ) -> Result<Vec<schnorr::Signature>, BridgeError> {
-        let mut operator_take_sigs = Vec::new();
-        for i in 0..partial_sigs[0].len() {
-            let agg_sig = self.aggregate_operator_takes_partial_sigs(
+        let graph = self.create_deposit_graph(
+            MoveTxDeposit {
                 deposit_outpoint,
-                kickoff_utxos[i].clone(),
-                &self.config.operators_xonly_pks[i].clone(),
-                i,
-                &agg_nonces[i].clone(),
-                partial_sigs.iter().map(|v| v[i]).collect(),
-            )?;
+                recovery_taproot_address,
+                evm_address,
+                bridge_amount_sats,
+            },
+            kickoff_utxos,
+        )?;
 
-            operator_take_sigs.push(secp256k1::schnorr::Signature::from_slice(&agg_sig)?);
-        }
-        Ok(operator_take_sigs)
+        self.aggregate_sigs(
+            graph.operator_takes_sighashes(),
+            0,
+            &agg_nonces,
+            &pub_nonces,
+            &partial_sigs,
+        )
     }
 
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
@@ -287,34 +402,1041 @@ impl Aggregator {
         deposit_outpoint: OutPoint,
         recovery_taproot_address: Address<NetworkUnchecked>,
         evm_address: EVMAddress,
+        bridge_amount_sats: Amount,
+        kickoff_utxos: Vec<UTXO>,
         agg_nonce: MuSigAggNonce,
+        pub_nonces: Vec<MuSigPubNonce>,
         partial_sigs: Vec<MuSigPartialSignature>,
     ) -> Result<(String, Txid), BridgeError> {
-        let agg_move_tx_final_sig = self.aggregate_move_partial_sigs(
+        let graph = self.create_deposit_graph(
+            MoveTxDeposit {
+                deposit_outpoint,
+                recovery_taproot_address,
+                evm_address,
+                bridge_amount_sats,
+            },
+            kickoff_utxos,
+        )?;
+
+        self.sign_move_tx(&graph, &agg_nonce, pub_nonces, partial_sigs)
+    }
+
+    /// Builds the graph of a deposit that has its own move_tx.
+    fn create_deposit_graph(
+        &self,
+        deposit: MoveTxDeposit,
+        kickoff_utxos: Vec<UTXO>,
+    ) -> Result<DepositGraph, BridgeError> {
+        let deposit_outpoint = deposit.deposit_outpoint;
+
+        DepositGraph::new(
+            &[deposit],
             deposit_outpoint,
-            evm_address,
-            &recovery_taproot_address,
-            &agg_nonce,
+            kickoff_utxos,
+            &self.nofn,
+            &self.config,
+        )
+    }
+
+    /// Aggregates the partial signatures of the move_tx and returns the
+    /// signed move_tx.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    fn sign_move_tx(
+        &self,
+        graph: &DepositGraph,
+        agg_nonce: &MuSigAggNonce,
+        pub_nonces: Vec<MuSigPubNonce>,
+        partial_sigs: Vec<MuSigPartialSignature>,
+    ) -> Result<(String, Txid), BridgeError> {
+        let move_tx_sig = self.aggregate_partial_sigs(
+            graph.move_tx_sighash(),
+            agg_nonce,
+            pub_nonces,
             partial_sigs,
         )?;
 
-        let move_tx_sig = secp256k1::schnorr::Signature::from_slice(&agg_move_tx_final_sig)?;
+        self.finalize_move_tx(graph.move_tx(), &[move_tx_sig])
+    }
 
-        let mut move_tx_handler = builder::transaction::create_move_tx_handler(
-            deposit_outpoint,
-            evm_address,
-            &recovery_taproot_address,
-            self.nofn_xonly_pk,
-            self.config.network,
-            self.config.user_takes_after,
-            self.config.bridge_amount_sats,
-        );
-        let move_tx_witness_elements = vec![move_tx_sig.serialize().to_vec()];
-        handle_taproot_witness_new(&mut move_tx_handler, &move_tx_witness_elements, 0, Some(0))?;
+    /// Adds the aggregated signatures of the move_tx inputs, in input order,
+    /// and returns the signed move_tx.
+    fn finalize_move_tx(
+        &self,
+        move_tx: &TxHandler,
+        move_tx_sigs: &[schnorr::Signature],
+    ) -> Result<(String, Txid), BridgeError> {
+        let mut move_tx_handler = move_tx.clone();
+        for (txin_index, move_tx_sig) in move_tx_sigs.iter().enumerate() {
+            handle_taproot_witness_new(
+                &mut move_tx_handler,
+                &[move_tx_sig.serialize()],
+                txin_index,
+                Some(0),
+            )?;
+        }
 
         let txid = move_tx_handler.tx.compute_txid();
         Ok((move_tx_handler.tx.raw_hex(), txid))
     }
+
+    /// Checks that the deposits can be signed with the configured bridge
+    /// amounts and endpoints.
+    fn check_new_deposits(&self, deposits: &[MoveTxDeposit]) -> Result<(), BridgeError> {
+        for deposit in deposits {
+            if !self
+                .config
+                .bridge_amounts_sats
+                .contains(&deposit.bridge_amount_sats)
+            {
+                return Err(BridgeError::InvalidBridgeAmount(deposit.bridge_amount_sats));
+            }
+        }
+        if self.verifier_clients.len() != self.config.verifiers_public_keys.len() {
+            return Err(BridgeError::ConfigError(format!(
+                "Aggregator needs {} verifier endpoints, but {} given",
+                self.config.verifiers_public_keys.len(),
+                self.verifier_clients.len()
+            )));
+        }
+        if self.operator_clients.len() != self.config.num_operators {
+            return Err(BridgeError::ConfigError(format!(
+                "Aggregator needs {} operator endpoints, but {} given",
+                self.config.num_operators,
+                self.operator_clients.len()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the signing session of the deposit, which is created under the
+    /// current verifier epoch if there isn't one already. Sessions of other
+    /// epochs or of other deposit infos can't be signed.
+    ///
+    /// Session's lease is claimed for `lease_owner`, so that it isn't run by
+    /// two calls at the same time. It must be released with
+    /// [`Database::release_deposit_session`] once the call is done.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn get_or_create_session(
+        &self,
+        deposit: &MoveTxDeposit,
+        lease_owner: &str,
+    ) -> Result<DepositSession, BridgeError> {
+        self.db
+            .save_deposit_session(
+                None,
+                deposit.deposit_outpoint,
+                deposit.recovery_taproot_address.clone(),
+                deposit.evm_address,
+                deposit.bridge_amount_sats,
+                self.config.verifier_epoch,
+            )
+            .await?;
+
+        match self
+            .db
+            .get_deposit_session_info(None, deposit.deposit_outpoint)
+            .await?
+        {
+            Some((session_deposit, _)) if session_deposit != *deposit => {
+                return Err(BridgeError::DepositSessionMismatch(
+                    deposit.deposit_outpoint,
+                ))
+            }
+            Some((_, epoch)) if epoch == self.config.verifier_epoch => {}
+            _ => return Err(BridgeError::InvalidDepositEpoch(deposit.deposit_outpoint)),
+        }
+
+        self.db
+            .claim_deposit_session(
+                None,
+                deposit.deposit_outpoint,
+                lease_owner,
+                DEPOSIT_SESSION_LEASE,
+            )
+            .await?
+            .ok_or(BridgeError::DepositSessionInProgress(
+                deposit.deposit_outpoint,
+            ))
+    }
+
+    /// Releases the leases of the given deposits' signing sessions.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn release_sessions(
+        &self,
+        deposit_outpoints: &[OutPoint],
+        lease_owner: &str,
+    ) -> Result<(), BridgeError> {
+        for deposit_outpoint in deposit_outpoints {
+            self.db
+                .release_deposit_session(None, *deposit_outpoint, lease_owner)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Collects pub nonces from verifiers and aggregates them, then collects
+    /// kickoff UTXOs from operators. Steps that are completed in the session
+    /// are skipped.
+    #[tracing::instrument(skip(self, session), err(level = tracing::Level::ERROR))]
+    async fn collect_nonces_and_kickoffs(
+        &self,
+        deposit: &MoveTxDeposit,
+        session: &mut DepositSession,
+    ) -> Result<(), BridgeError> {
+        if let Some(threshold) = self.config.frost_threshold {
+            if session.pub_nonces.is_none() || session.frost_signers.is_none() {
+                let (frost_signers, pub_nonces) =
+                    self.collect_frost_nonces(deposit, threshold).await?;
+
+                session.pub_nonces = Some(pub_nonces);
+                session.frost_signers = Some(frost_signers);
+                self.db
+                    .update_deposit_session(None, deposit.deposit_outpoint, session)
+                    .await?;
+            }
+        } else if session.pub_nonces.is_none() || session.agg_nonces.is_none() {
+            let pub_nonces =
+                futures::future::try_join_all(self.verifier_clients.iter().map(|client| {
+                    client.verifier_new_deposit_rpc(
+                        deposit.deposit_outpoint,
+                        deposit.recovery_taproot_address.clone(),
+                        deposit.evm_address,
+                    )
+                }))
+                .await?;
+            let agg_nonces = self.aggregate_pub_nonces(pub_nonces.clone()).await?;
+
+            session.pub_nonces = Some(pub_nonces);
+            session.agg_nonces = Some(agg_nonces);
+            self.db
+                .update_deposit_session(None, deposit.deposit_outpoint, session)
+                .await?;
+        }
+
+        if session.kickoff_utxos.is_none() || session.kickoff_sigs.is_none() {
+            let (kickoff_utxos, kickoff_sigs): (Vec<UTXO>, Vec<schnorr::Signature>) =
+                futures::future::try_join_all(self.operator_clients.iter().map(|client| {
+                    client.new_deposit_rpc(
+                        deposit.deposit_outpoint,
+                        deposit.recovery_taproot_address.clone(),
+                        deposit.evm_address,
+                    )
+                }))
+                .await?
+                .into_iter()
+                .unzip();
+
+            session.kickoff_utxos = Some(kickoff_utxos);
+            session.kickoff_sigs = Some(kickoff_sigs);
+            self.db
+                .update_deposit_session(None, deposit.deposit_outpoint, session)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Collects FROST commitments from verifiers and returns the first
+    /// `threshold` verifiers that responded, with their commitments. Deposit
+    /// can be signed as long as that many verifiers are online.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn collect_frost_nonces(
+        &self,
+        deposit: &MoveTxDeposit,
+        threshold: u16,
+    ) -> Result<(Vec<FrostSignerId>, Vec<Vec<FrostCommitment>>), BridgeError> {
+        let responses = futures::future::join_all(self.verifier_clients.iter().map(|client| {
+            client.verifier_new_deposit_rpc(
+                deposit.deposit_outpoint,
+                deposit.recovery_taproot_address.clone(),
+                deposit.evm_address,
+            )
+        }))
+        .await;
+
+        select_frost_signers(deposit.deposit_outpoint, responses, threshold)
+    }
+
+    /// Collects the slash_or_take and burn signature shares of the deposit's
+    /// FROST signers. If a signer doesn't respond, the signing set is
+    /// abandoned and the shares are collected from a new set, which signs
+    /// with fresh commitments. Verifiers that already signed with their old
+    /// commitments refuse to renew them, so they are left out of the new set.
+    #[tracing::instrument(skip(self, graph, session, kickoff_sigs), err(level = tracing::Level::ERROR))]
+    async fn frost_sign_kickoffs(
+        &self,
+        graph: &DepositGraph,
+        deposit_outpoint: OutPoint,
+        session: &mut DepositSession,
+        kickoff_sigs: Vec<schnorr::Signature>,
+    ) -> Result<Vec<(Vec<FrostSignatureShare>, Vec<FrostSignatureShare>)>, BridgeError> {
+        let threshold = self
+            .config
+            .frost_threshold
+            .ok_or(BridgeError::FrostNotEnabled)?;
+
+        let mut num_signing_sets = 1;
+        loop {
+            let signing_commitments = session
+                .frost_signers
+                .clone()
+                .ok_or(BridgeError::InvalidFrostSigningSet)?
+                .into_iter()
+                .zip(
+                    session
+                        .pub_nonces
+                        .clone()
+                        .ok_or(BridgeError::NoncesNotFound)?,
+                )
+                .collect::<BTreeMap<_, _>>();
+
+            let result = futures::future::try_join_all(
+                self.signer_clients(session)?.into_iter().map(|client| {
+                    client.frost_operator_kickoffs_generated_rpc(
+                        deposit_outpoint,
+                        graph.kickoff_utxos().to_vec(),
+                        kickoff_sigs.clone(),
+                        signing_commitments.clone(),
+                    )
+                }),
+            )
+            .await;
+            let e = match result {
+                Ok(signature_shares) => return Ok(signature_shares),
+                Err(e) if num_signing_sets < MAX_FROST_SIGNING_SETS => e,
+                Err(e) => return Err(e.into()),
+            };
+            tracing::warn!(
+                "Signing set of deposit {} can't sign: {}, selecting a new one",
+                deposit_outpoint,
+                e
+            );
+
+            let responses = futures::future::join_all(
+                self.verifier_clients
+                    .iter()
+                    .map(|client| client.frost_renew_deposit_nonces_rpc(deposit_outpoint)),
+            )
+            .await;
+            let (frost_signers, pub_nonces) =
+                select_frost_signers(deposit_outpoint, responses, threshold)?;
+
+            session.frost_signers = Some(frost_signers);
+            session.pub_nonces = Some(pub_nonces);
+            self.db
+                .update_deposit_session(None, deposit_outpoint, session)
+                .await?;
+            num_signing_sets += 1;
+
+            // New signers need the batch to build the deposit graph.
+            if let Some(batch) = &session.batch {
+                futures::future::try_join_all(
+                    self.signer_clients(session)?
+                        .into_iter()
+                        .map(|client| client.verifier_new_deposit_batch_rpc(batch.clone())),
+                )
+                .await?;
+            }
+        }
+    }
+
+    /// Collects and aggregates slash_or_take and burn partial signatures, then
+    /// operator_takes partial signatures of the deposit graph. Steps that are
+    /// completed in the session are skipped.
+    #[tracing::instrument(skip(self, graph, session), err(level = tracing::Level::ERROR))]
+    async fn sign_deposit_graph(
+        &self,
+        graph: &DepositGraph,
+        deposit_outpoint: OutPoint,
+        session: &mut DepositSession,
+    ) -> Result<(), BridgeError> {
+        let nonce_index = graph.nonce_index();
+
+        let (slash_or_take_sigs, burn_sigs) = match (
+            session.slash_or_take_sigs.clone(),
+            session.burn_sigs.clone(),
+        ) {
+            (Some(slash_or_take_sigs), Some(burn_sigs)) => (slash_or_take_sigs, burn_sigs),
+            _ => {
+                let kickoff_sigs = session
+                    .kickoff_sigs
+                    .clone()
+                    .ok_or(BridgeError::KickoffOutpointsNotFound)?;
+                let partial_sigs = if session.frost_signers.is_some() {
+                    self.frost_sign_kickoffs(graph, deposit_outpoint, session, kickoff_sigs)
+                        .await?
+                } else {
+                    let agg_nonces = session
+                        .agg_nonces
+                        .clone()
+                        .ok_or(BridgeError::NoncesNotFound)?;
+
+                    futures::future::try_join_all(self.verifier_clients.iter().map(|client| {
+                        client.operator_kickoffs_generated_rpc(
+                            deposit_outpoint,
+                            graph.kickoff_utxos().to_vec(),
+                            kickoff_sigs.clone(),
+                            agg_nonces.clone(),
+                        )
+                    }))
+                    .await?
+                };
+                let (slash_or_take_partial_sigs, burn_partial_sigs): (Vec<_>, Vec<_>) =
+                    partial_sigs.into_iter().unzip();
+                let slash_or_take_sigs = self
+                    .aggregate_session_sigs(
+                        graph.slash_or_take_sighashes(),
+                        nonce_index.slash_or_take().start,
+                        session,
+                        &slash_or_take_partial_sigs,
+                    )
+                    .await?;
+                let burn_sigs = self
+                    .aggregate_session_sigs(
+                        graph.burn_sighashes(),
+                        nonce_index.burn().start,
+                        session,
+                        &burn_partial_sigs,
+                    )
+                    .await?;
+
+                session.slash_or_take_sigs = Some(slash_or_take_sigs.clone());
+                session.burn_sigs = Some(burn_sigs.clone());
+                self.db
+                    .update_deposit_session(None, deposit_outpoint, session)
+                    .await?;
+
+                (slash_or_take_sigs, burn_sigs)
+            }
+        };
+
+        if session.operator_take_sigs.is_none() {
+            let operator_take_partial_sigs = futures::future::try_join_all(
+                self.signer_clients(session)?.into_iter().map(|client| {
+                    client.burn_txs_signed_rpc(
+                        deposit_outpoint,
+                        burn_sigs.clone(),
+                        slash_or_take_sigs.clone(),
+                    )
+                }),
+            )
+            .await?;
+            let operator_take_sigs = self
+                .aggregate_session_sigs(
+                    graph.operator_takes_sighashes(),
+                    nonce_index.operator_takes().start,
+                    session,
+                    &operator_take_partial_sigs,
+                )
+                .await?;
+
+            session.operator_take_sigs = Some(operator_take_sigs);
+            self.db
+                .update_deposit_session(None, deposit_outpoint, session)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Collects the move_tx partial signatures of the deposit's input from
+    /// verifiers and aggregates them.
+    #[tracing::instrument(skip(self, graph, session), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn collect_move_tx_sig(
+        &self,
+        graph: &DepositGraph,
+        deposit_outpoint: OutPoint,
+        session: &DepositSession,
+    ) -> Result<schnorr::Signature, BridgeError> {
+        let operator_take_sigs = session
+            .operator_take_sigs
+            .clone()
+            .ok_or(BridgeError::OperatorTakesSigNotFound)?;
+
+        let move_tx_partial_sigs = futures::future::try_join_all(
+            self.signer_clients(session)?.into_iter().map(|client| {
+                client.operator_take_txs_signed_rpc(deposit_outpoint, operator_take_sigs.clone())
+            }),
+        )
+        .await?;
+
+        self.aggregate_session_sigs(
+            &[graph.move_tx_sighash()],
+            graph.nonce_index().move_tx(),
+            session,
+            &move_tx_partial_sigs
+                .into_iter()
+                .map(|partial_sig| vec![partial_sig])
+                .collect::<Vec<_>>(),
+        )
+        .await?
+        .pop()
+        .ok_or(BridgeError::NoncesNotFound)
+    }
+
+    /// Runs the whole deposit signing flow against the configured verifier and
+    /// operator endpoints and returns the signed move transaction. Deposit
+    /// UTXO must hold `bridge_amount_sats`, which is checked by every verifier.
+    ///
+    /// 1. Collect pub nonces from verifiers and aggregate them
+    /// 2. Collect kickoff UTXOs from operators
+    /// 3. Collect and aggregate slash_or_take and burn partial signatures
+    /// 4. Collect and aggregate operator_takes partial signatures
+    /// 5. Collect and aggregate move_tx partial signatures
+    ///
+    /// Result of each step is saved to the deposit's signing session. If the
+    /// deposit was interrupted before, already completed steps are skipped.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn new_deposit(
+        &self,
+        deposit_outpoint: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        bridge_amount_sats: Amount,
+    ) -> Result<(String, Txid), BridgeError> {
+        let deposit = MoveTxDeposit {
+            deposit_outpoint,
+            recovery_taproot_address,
+            evm_address,
+            bridge_amount_sats,
+        };
+        self.check_new_deposits(std::slice::from_ref(&deposit))?;
+
+        let lease_owner = new_lease_owner();
+        let session = self.get_or_create_session(&deposit, &lease_owner).await?;
+        let result = self.sign_deposit(deposit, session).await;
+        self.release_sessions(&[deposit_outpoint], &lease_owner)
+            .await?;
+
+        result
+    }
+
+    /// Runs the steps of [`Self::new_deposit`] that are not completed in the
+    /// deposit's signing session.
+    #[tracing::instrument(skip(self, session), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn sign_deposit(
+        &self,
+        deposit: MoveTxDeposit,
+        mut session: DepositSession,
+    ) -> Result<(String, Txid), BridgeError> {
+        let deposit_outpoint = deposit.deposit_outpoint;
+        // Deposit is signed with a batched move_tx.
+        if session.batch.is_some() {
+            return Err(BridgeError::InvalidDepositBatch(deposit_outpoint));
+        }
+        if let Some(move_tx) = session.move_tx.clone() {
+            return Ok(move_tx);
+        }
+
+        self.collect_nonces_and_kickoffs(&deposit, &mut session)
+            .await?;
+        let kickoff_utxos = session
+            .kickoff_utxos
+            .clone()
+            .ok_or(BridgeError::KickoffOutpointsNotFound)?;
+        let graph = self.create_deposit_graph(deposit, kickoff_utxos)?;
+
+        self.sign_deposit_graph(&graph, deposit_outpoint, &mut session)
+            .await?;
+
+        let move_tx_sig = self
+            .collect_move_tx_sig(&graph, deposit_outpoint, &session)
+            .await?;
+        let move_tx = self.finalize_move_tx(graph.move_tx(), &[move_tx_sig])?;
+
+        session.move_tx = Some(move_tx.clone());
+        self.db
+            .update_deposit_session(None, deposit_outpoint, &session)
+            .await?;
+
+        Ok(move_tx)
+    }
+
+    /// Runs the deposit signing flow of every deposit with a single batched
+    /// move_tx, which has an input and a bridge fund output for every deposit
+    /// in the given order. Returns the signed batched move_tx.
+    ///
+    /// Steps are the same as [`Self::new_deposit`], but they run for every
+    /// deposit at once and the batch is saved by the verifiers and the
+    /// operators before the deposit graphs are signed. Verifiers sign every input of the move_tx in
+    /// the same round, so the deposits share the move_tx's fee.
+    ///
+    /// Deposits that are already signed with another move_tx can't be
+    /// batched. An interrupted batch is resumed with the same deposits.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn new_deposit_batch(
+        &self,
+        deposits: Vec<MoveTxDeposit>,
+    ) -> Result<(String, Txid), BridgeError> {
+        let batch = deposits
+            .iter()
+            .map(|deposit| deposit.deposit_outpoint)
+            .collect::<Vec<_>>();
+        if batch.is_empty() {
+            return Err(BridgeError::EmptyDepositBatch);
+        }
+        for (i, deposit_outpoint) in batch.iter().enumerate() {
+            if batch[..i].contains(deposit_outpoint) {
+                return Err(BridgeError::InvalidDepositBatch(*deposit_outpoint));
+            }
+        }
+        self.check_new_deposits(&deposits)?;
+
+        let lease_owner = new_lease_owner();
+        let mut sessions = Vec::with_capacity(deposits.len());
+        for deposit in &deposits {
+            match self.get_or_create_session(deposit, &lease_owner).await {
+                Ok(session) => sessions.push(session),
+                Err(e) => {
+                    self.release_sessions(&batch[..sessions.len()], &lease_owner)
+                        .await?;
+                    return Err(e);
+                }
+            }
+        }
+        let result = self.sign_deposit_batch(&deposits, &batch, sessions).await;
+        self.release_sessions(&batch, &lease_owner).await?;
+
+        result
+    }
+
+    /// Runs the steps of [`Self::new_deposit_batch`] that are not completed in
+    /// the deposits' signing sessions.
+    #[tracing::instrument(skip(self, sessions), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn sign_deposit_batch(
+        &self,
+        deposits: &[MoveTxDeposit],
+        batch: &[OutPoint],
+        mut sessions: Vec<DepositSession>,
+    ) -> Result<(String, Txid), BridgeError> {
+        for (deposit, session) in deposits.iter().zip(sessions.iter_mut()) {
+            match &session.batch {
+                Some(session_batch) if *session_batch == batch => {}
+                Some(_) => {
+                    return Err(BridgeError::InvalidDepositBatch(deposit.deposit_outpoint));
+                }
+                // Deposit graph is signed with its own move_tx.
+                None if session.slash_or_take_sigs.is_some() => {
+                    return Err(BridgeError::InvalidDepositBatch(deposit.deposit_outpoint));
+                }
+                None => {
+                    session.batch = Some(batch.to_vec());
+                    self.db
+                        .update_deposit_session(None, deposit.deposit_outpoint, session)
+                        .await?;
+                }
+            }
+        }
+        if let Some(move_tx) = sessions[0].move_tx.clone() {
+            return Ok(move_tx);
+        }
+
+        futures::future::try_join_all(
+            deposits
+                .iter()
+                .zip(sessions.iter_mut())
+                .map(|(deposit, session)| self.collect_nonces_and_kickoffs(deposit, session)),
+        )
+        .await?;
+
+        // Verifiers save the deposit infos while generating the nonces, so
+        // the batch can be saved only after that. With FROST, only the
+        // signers of the deposits need the batch.
+        let mut batch_clients = Vec::new();
+        for session in &sessions {
+            for client in self.signer_clients(session)? {
+                if !batch_clients.iter().any(|c| std::ptr::eq(*c, client)) {
+                    batch_clients.push(client);
+                }
+            }
+        }
+        futures::future::try_join_all(
+            batch_clients
+                .into_iter()
+                .map(|client| client.verifier_new_deposit_batch_rpc(batch.to_vec())),
+        )
+        .await?;
+        // Operators need the batch to build the move_tx of their
+        // reimbursements.
+        futures::future::try_join_all(
+            self.operator_clients
+                .iter()
+                .map(|client| client.new_deposit_batch_rpc(batch.to_vec())),
+        )
+        .await?;
+
+        let graphs = deposits
+            .iter()
+            .zip(sessions.iter())
+            .map(|(deposit, session)| {
+                DepositGraph::new(
+                    deposits,
+                    deposit.deposit_outpoint,
+                    session
+                        .kickoff_utxos
+                        .clone()
+                        .ok_or(BridgeError::KickoffOutpointsNotFound)?,
+                    &self.nofn,
+                    &self.config,
+                )
+            })
+            .collect::<Result<Vec<_>, BridgeError>>()?;
+
+        futures::future::try_join_all(
+            graphs
+                .iter()
+                .zip(batch.iter())
+                .zip(sessions.iter_mut())
+                .map(|((graph, deposit_outpoint), session)| {
+                    self.sign_deposit_graph(graph, *deposit_outpoint, session)
+                }),
+        )
+        .await?;
+
+        let move_tx_sigs = futures::future::try_join_all(
+            graphs.iter().zip(batch.iter()).zip(sessions.iter()).map(
+                |((graph, deposit_outpoint), session)| {
+                    self.collect_move_tx_sig(graph, *deposit_outpoint, session)
+                },
+            ),
+        )
+        .await?;
+        let move_tx = self.finalize_move_tx(graphs[0].move_tx(), &move_tx_sigs)?;
+
+        // Deposits of a batch are completed together.
+        let mut dbtx = self.db.begin_transaction().await?;
+        for (deposit_outpoint, session) in batch.iter().zip(sessions.iter_mut()) {
+            session.move_tx = Some(move_tx.clone());
+            self.db
+                .update_deposit_session(Some(&mut dbtx), *deposit_outpoint, session)
+                .await?;
+        }
+        dbtx.commit().await?;
+
+        Ok(move_tx)
+    }
+
+    /// Runs the DKG ceremony of the FROST key between every verifier and
+    /// returns the x-only group key, which is the N-of-N key of the bridge
+    /// once it is set as `frost_group_public_key` in the configs.
+    ///
+    /// Aggregator only relays the packages. Round 2 packages are encrypted to
+    /// their recipients and bound to the round 1 packages, so a verifier
+    /// rejects the ceremony if the aggregator changes any of them.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn frost_dkg(&self) -> Result<XOnlyPublicKey, BridgeError> {
+        if self.config.frost_threshold.is_none() {
+            return Err(BridgeError::FrostNotEnabled);
+        }
+        if self.verifier_clients.len() != self.config.verifiers_public_keys.len() {
+            return Err(BridgeError::ConfigError(format!(
+                "Aggregator needs {} verifier endpoints, but {} given",
+                self.config.verifiers_public_keys.len(),
+                self.verifier_clients.len()
+            )));
+        }
+
+        let round1_packages = futures::future::try_join_all(
+            self.verifier_clients
+                .iter()
+                .map(|client| client.frost_dkg_round1_rpc()),
+        )
+        .await?
+        .into_iter()
+        .enumerate()
+        .map(|(i, package)| Ok((frost::signer_id(i)?, package)))
+        .collect::<Result<BTreeMap<_, _>, BridgeError>>()?;
+
+        let round2_packages = futures::future::try_join_all(
+            self.verifier_clients
+                .iter()
+                .map(|client| client.frost_dkg_round2_rpc(round1_packages.clone())),
+        )
+        .await?;
+
+        // Every verifier receives the round 2 packages that are sent to it.
+        let mut received_packages = vec![BTreeMap::new(); self.verifier_clients.len()];
+        for (i, packages) in round2_packages.into_iter().enumerate() {
+            let sender = frost::signer_id(i)?;
+            for (recipient, package) in packages {
+                usize::from(recipient)
+                    .checked_sub(1)
+                    .and_then(|i| received_packages.get_mut(i))
+                    .ok_or(BridgeError::InvalidFrostDkgPackage(sender))?
+                    .insert(sender, package);
+            }
+        }
+
+        let public_key_packages = futures::future::try_join_all(
+            self.verifier_clients
+                .iter()
+                .zip(received_packages)
+                .map(|(client, packages)| client.frost_dkg_finalize_rpc(packages)),
+        )
+        .await?;
+        if public_key_packages
+            .windows(2)
+            .any(|packages| packages[0] != packages[1])
+        {
+            return Err(BridgeError::FrostGroupKeyMismatch);
+        }
+
+        self.db
+            .save_frost_public_key_package(None, &public_key_packages[0])
+            .await?;
+
+        frost::group_xonly_pk(&public_key_packages[0])
+    }
+
+    /// Resumes every deposit whose signing session was interrupted, e.g. by a
+    /// restart, from its last completed step. Batched deposits are resumed
+    /// together. Deposits that still can't be completed are logged and left
+    /// for the next call.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn resume_deposits(&self) -> Result<Vec<(String, Txid)>, BridgeError> {
+        let deposits = self
+            .db
+            .get_unfinished_deposit_sessions(self.config.verifier_epoch)
+            .await?
+            .into_iter()
+            .map(
+                |(deposit_outpoint, recovery_taproot_address, evm_address, bridge_amount_sats)| {
+                    MoveTxDeposit {
+                        deposit_outpoint,
+                        recovery_taproot_address,
+                        evm_address,
+                        bridge_amount_sats,
+                    }
+                },
+            )
+            .collect::<Vec<_>>();
+
+        let mut move_txs = Vec::new();
+        let mut resumed_batches = Vec::new();
+        for deposit in &deposits {
+            let batch = self
+                .db
+                .get_deposit_session(None, deposit.deposit_outpoint)
+                .await?
+                .and_then(|session| session.batch);
+
+            let result = match batch {
+                None => {
+                    self.new_deposit(
+                        deposit.deposit_outpoint,
+                        deposit.recovery_taproot_address.clone(),
+                        deposit.evm_address,
+                        deposit.bridge_amount_sats,
+                    )
+                    .await
+                }
+                Some(batch) if resumed_batches.contains(&batch) => continue,
+                Some(batch) => {
+                    resumed_batches.push(batch.clone());
+
+                    // Deposits of a batch are completed together, so every
+                    // deposit of an unfinished batch is unfinished.
+                    match batch
+                        .iter()
+                        .map(|deposit_outpoint| {
+                            deposits
+                                .iter()
+                                .find(|deposit| deposit.deposit_outpoint == *deposit_outpoint)
+                                .cloned()
+                        })
+                        .collect::<Option<Vec<_>>>()
+                    {
+                        Some(batch_deposits) => self.new_deposit_batch(batch_deposits).await,
+                        None => Err(BridgeError::InvalidDepositBatch(deposit.deposit_outpoint)),
+                    }
+                }
+            };
+
+            match result {
+                Ok(move_tx) => move_txs.push(move_tx),
+                Err(e) => tracing::error!(
+                    "Deposit {} can't be resumed: {}",
+                    deposit.deposit_outpoint,
+                    e
+                ),
+            }
+        }
+
+        Ok(move_txs)
+    }
+
+    /// Returns the deposits of the move_tx that moves the deposit's bridge
+    /// funds, in input order. Deposit's signing session must be completed.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn get_move_tx_deposits(
+        &self,
+        deposit_outpoint: OutPoint,
+    ) -> Result<Vec<MoveTxDeposit>, BridgeError> {
+        let (deposit, epoch) = self
+            .db
+            .get_deposit_session_info(None, deposit_outpoint)
+            .await?
+            .ok_or(BridgeError::DepositInfoNotFound)?;
+        if epoch != self.config.verifier_epoch {
+            return Err(BridgeError::InvalidDepositEpoch(deposit_outpoint));
+        }
+
+        // Bridge funds exist only after the move_tx is signed.
+        let session = self
+            .db
+            .get_deposit_session(None, deposit_outpoint)
+            .await?
+            .unwrap_or_default();
+        if !session.is_completed() {
+            return Err(BridgeError::DepositInfoNotFound);
+        }
+
+        match session.batch {
+            Some(batch) => {
+                let mut move_tx_deposits = Vec::with_capacity(batch.len());
+                for batch_outpoint in batch {
+                    let (batch_deposit, _) = self
+                        .db
+                        .get_deposit_session_info(None, batch_outpoint)
+                        .await?
+                        .ok_or(BridgeError::DepositInfoNotFound)?;
+                    move_tx_deposits.push(batch_deposit);
+                }
+
+                Ok(move_tx_deposits)
+            }
+            None => Ok(vec![deposit]),
+        }
+    }
+
+    /// Sweeps the bridge funds of the deposits, which are signed under the
+    /// current verifier epoch, to the N-of-N MuSig2 key of the next epoch and
+    /// returns the signed sweep_tx. Every verifier of the current epoch signs
+    /// the sweep_tx, which has an input and an output for every deposit in the
+    /// given order.
+    ///
+    /// 1. Verify the next epoch's graphs of the swept outputs
+    /// 2. Collect pub nonces of the sweep_tx inputs from verifiers
+    /// 3. Collect and aggregate the partial signatures of the sweep_tx inputs
+    ///
+    /// `swept_graphs` are the graphs of the deposits' swept outputs, in the
+    /// same order, which are signed by the next epoch's verifiers beforehand
+    /// so that operators can still be reimbursed after the sweep. Verifiers
+    /// refuse to sweep a deposit that has a withdrawal in progress.
+    ///
+    /// A verifier signs every deposit's input once, so an interrupted sweep
+    /// can only be retried with the same deposits.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn sweep_epoch(
+        &self,
+        deposit_outpoints: Vec<OutPoint>,
+        swept_graphs: Vec<SweptDepositGraph>,
+    ) -> Result<(String, Txid), BridgeError> {
+        if deposit_outpoints.is_empty() {
+            return Err(BridgeError::EmptySweep);
+        }
+        if self.nofn.is_threshold_key() {
+            return Err(BridgeError::MuSigWithThresholdKey);
+        }
+        if self.verifier_clients.len() != self.config.verifiers_public_keys.len() {
+            return Err(BridgeError::ConfigError(format!(
+                "Aggregator needs {} verifier endpoints, but {} given",
+                self.config.verifiers_public_keys.len(),
+                self.verifier_clients.len()
+            )));
+        }
+
+        let current_epoch = VerifierEpoch::current(&self.config, &self.nofn);
+        let next_epoch = VerifierEpoch::next(&self.config)?.ok_or(
+            BridgeError::VerifierEpochNotFound(self.config.verifier_epoch.saturating_add(1)),
+        )?;
+
+        let mut deposits = Vec::with_capacity(deposit_outpoints.len());
+        for deposit_outpoint in &deposit_outpoints {
+            deposits.push((
+                *deposit_outpoint,
+                self.get_move_tx_deposits(*deposit_outpoint).await?,
+            ));
+        }
+        let sweep = EpochSweep::new(&deposits, &current_epoch, &next_epoch, &self.config)?;
+        sweep.check_swept_graphs(&swept_graphs, &next_epoch, &self.config)?;
+
+        let pub_nonces = futures::future::try_join_all(
+            self.verifier_clients
+                .iter()
+                .map(|client| client.sweep_nonces_rpc(deposit_outpoints.clone())),
+        )
+        .await?;
+        let agg_nonces = self.aggregate_pub_nonces(pub_nonces.clone()).await?;
+
+        let partial_sigs =
+            futures::future::try_join_all(self.verifier_clients.iter().map(|client| {
+                client.sign_sweep_rpc(
+                    deposit_outpoints.clone(),
+                    agg_nonces.clone(),
+                    swept_graphs.clone(),
+                )
+            }))
+            .await?;
+        let sweep_sigs = self.aggregate_sigs(
+            sweep.sighashes(),
+            0,
+            &agg_nonces,
+            &pub_nonces,
+            &partial_sigs,
+        )?;
+
+        // Bridge funds are spent with the key path of the N-of-N key.
+        let mut sweep_tx = sweep.sweep_tx().clone();
+        for (txin_index, sweep_sig) in sweep_sigs.iter().enumerate() {
+            handle_taproot_witness_new(&mut sweep_tx, &[sweep_sig.serialize()], txin_index, None)?;
+        }
+
+        let txid = sweep_tx.tx.compute_txid();
+        Ok((sweep_tx.tx.raw_hex(), txid))
+    }
+}
+
+/// Returns the first `threshold` verifiers that sent their FROST commitments
+/// for a deposit, with their commitments.
+fn select_frost_signers(
+    deposit_outpoint: OutPoint,
+    responses: Vec<Result<Vec<FrostCommitment>, jsonrpsee::core::client::Error>>,
+    threshold: u16,
+) -> Result<(Vec<FrostSignerId>, Vec<Vec<FrostCommitment>>), BridgeError> {
+    let mut signers = Vec::new();
+    let mut commitments = Vec::new();
+    for (i, response) in responses.into_iter().enumerate() {
+        match response {
+            Ok(pub_nonces) if signers.len() < usize::from(threshold) => {
+                signers.push(frost::signer_id(i)?);
+                commitments.push(pub_nonces);
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!(
+                "Verifier {} didn't send commitments for deposit {}: {}",
+                i,
+                deposit_outpoint,
+                e
+            ),
+        }
+    }
+    if signers.len() < usize::from(threshold) {
+        return Err(BridgeError::NotEnoughFrostSigners(signers.len(), threshold));
+    }
+
+    Ok((signers, commitments))
+}
+
+/// Returns a new owner for the leases of signing sessions, which identifies
+/// a single deposit call.
+fn new_lease_owner() -> String {
+    let mut owner = [0u8; 16];
+    OsRng.fill_bytes(&mut owner);
+
+    hex::encode(owner)
 }
 
 #[async_trait]
@@ -329,23 +1451,73 @@ impl AggregatorServer for Aggregator {
     async fn aggregate_slash_or_take_sigs_rpc(
         &self,
         deposit_outpoint: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        bridge_amount_sats: Amount,
         kickoff_utxos: Vec<UTXO>,
         agg_nonces: Vec<MuSigAggNonce>,
+        pub_nonces: Vec<Vec<MuSigPubNonce>>,
         partial_sigs: Vec<Vec<MuSigPartialSignature>>,
     ) -> Result<Vec<schnorr::Signature>, BridgeError> {
-        self.aggregate_slash_or_take_sigs(deposit_outpoint, kickoff_utxos, agg_nonces, partial_sigs)
-            .await
+        self.aggregate_slash_or_take_sigs(
+            deposit_outpoint,
+            recovery_taproot_address,
+            evm_address,
+            bridge_amount_sats,
+            kickoff_utxos,
+            agg_nonces,
+            pub_nonces,
+            partial_sigs,
+        )
+        .await
+    }
+
+    async fn aggregate_burn_sigs_rpc(
+        &self,
+        deposit_outpoint: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        bridge_amount_sats: Amount,
+        kickoff_utxos: Vec<UTXO>,
+        agg_nonces: Vec<MuSigAggNonce>,
+        pub_nonces: Vec<Vec<MuSigPubNonce>>,
+        partial_sigs: Vec<Vec<MuSigPartialSignature>>,
+    ) -> Result<Vec<schnorr::Signature>, BridgeError> {
+        self.aggregate_burn_sigs(
+            deposit_outpoint,
+            recovery_taproot_address,
+            evm_address,
+            bridge_amount_sats,
+            kickoff_utxos,
+            agg_nonces,
+            pub_nonces,
+            partial_sigs,
+        )
+        .await
     }
 
     async fn aggregate_operator_take_sigs_rpc(
         &self,
         deposit_outpoint: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        bridge_amount_sats: Amount,
         kickoff_utxos: Vec<UTXO>,
         agg_nonces: Vec<MuSigAggNonce>,
+        pub_nonces: Vec<Vec<MuSigPubNonce>>,
         partial_sigs: Vec<Vec<MuSigPartialSignature>>,
     ) -> Result<Vec<schnorr::Signature>, BridgeError> {
-        self.aggregate_operator_take_sigs(deposit_outpoint, kickoff_utxos, agg_nonces, partial_sigs)
-            .await
+        self.aggregate_operator_take_sigs(
+            deposit_outpoint,
+            recovery_taproot_address,
+            evm_address,
+            bridge_amount_sats,
+            kickoff_utxos,
+            agg_nonces,
+            pub_nonces,
+            partial_sigs,
+        )
+        .await
     }
 
     async fn aggregate_move_tx_sigs_rpc(
@@ -353,16 +1525,57 @@ impl AggregatorServer for Aggregator {
         deposit_outpoint: OutPoint,
         recovery_taproot_address: Address<NetworkUnchecked>,
         evm_address: EVMAddress,
+        bridge_amount_sats: Amount,
+        kickoff_utxos: Vec<UTXO>,
         agg_nonce: MuSigAggNonce,
+        pub_nonces: Vec<MuSigPubNonce>,
         partial_sigs: Vec<MuSigPartialSignature>,
     ) -> Result<(String, Txid), BridgeError> {
         self.aggregate_move_tx_sigs(
             deposit_outpoint,
             recovery_taproot_address,
             evm_address,
+            bridge_amount_sats,
+            kickoff_utxos,
             agg_nonce,
+            pub_nonces,
             partial_sigs,
         )
         .await
     }
+
+    async fn aggregator_new_deposit_rpc(
+        &self,
+        deposit_outpoint: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        bridge_amount_sats: Amount,
+    ) -> Result<(String, Txid), BridgeError> {
+        self.new_deposit(
+            deposit_outpoint,
+            recovery_taproot_address,
+            evm_address,
+            bridge_amount_sats,
+        )
+        .await
+    }
+
+    async fn aggregator_new_deposit_batch_rpc(
+        &self,
+        deposits: Vec<MoveTxDeposit>,
+    ) -> Result<(String, Txid), BridgeError> {
+        self.new_deposit_batch(deposits).await
+    }
+
+    async fn aggregator_frost_dkg_rpc(&self) -> Result<XOnlyPublicKey, BridgeError> {
+        self.frost_dkg().await
+    }
+
+    async fn aggregator_sweep_epoch_rpc(
+        &self,
+        deposit_outpoints: Vec<OutPoint>,
+        swept_graphs: Vec<SweptDepositGraph>,
+    ) -> Result<(String, Txid), BridgeError> {
+        self.sweep_epoch(deposit_outpoints, swept_graphs).await
+    }
 }
@@ -1,5 +1,5 @@
 use clementine_core::{
-    musig2::AggregateFromPublicKeys, servers::create_verifiers_and_operators,
+    musig2::NofnContext, servers::create_verifiers_and_operators,
     utils::get_configuration_for_binaries,
 };
 
@@ -26,8 +26,7 @@ async fn main() {
             .collect::<Vec<_>>()
             .join(",")
     );
-    let xonly =
-        secp256k1::XOnlyPublicKey::from_musig2_pks(config.verifiers_public_keys, None, false);
+    let xonly = NofnContext::from_config(&config).unwrap().xonly_pk();
     println!(
         "AGGREGATOR_URL={}",
         format!("http://127.0.0.1:{}", aggregator.2.port())
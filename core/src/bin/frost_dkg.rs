@@ -0,0 +1,31 @@
+use clementine_core::aggregator::Aggregator;
+use clementine_core::database::Database;
+use clementine_core::utils::get_configuration_for_binaries;
+use std::process::exit;
+
+#[tokio::main]
+async fn main() {
+    let (config, _) = get_configuration_for_binaries();
+
+    Database::run_schema_script(&config).await.unwrap();
+
+    let aggregator = match Aggregator::new(config).await {
+        Ok(aggregator) => aggregator,
+        Err(e) => {
+            eprintln!("Can't create aggregator: {}", e);
+            exit(1);
+        }
+    };
+
+    match aggregator.frost_dkg().await {
+        Ok(group_public_key) => {
+            println!("FROST key is generated.");
+            println!("frost_group_public_key = \"{}\"", group_public_key);
+            println!("N-of-N key changes with it, so it must be set with a new verifier_epoch.");
+        }
+        Err(e) => {
+            eprintln!("FROST DKG failed: {}", e);
+            exit(1);
+        }
+    }
+}
@@ -14,11 +14,13 @@ async fn main() {
         exit(1);
     }
 
-    let rpc = ExtendedRpc::<bitcoincore_rpc::Client>::new(
-        config.bitcoin_rpc_url.clone(),
-        config.bitcoin_rpc_user.clone(),
-        config.bitcoin_rpc_password.clone(),
-    );
+    let rpc = match ExtendedRpc::<bitcoincore_rpc::Client>::from_config(&config) {
+        Ok(rpc) => rpc,
+        Err(e) => {
+            eprintln!("Can't connect to Bitcoin RPC: {}", e);
+            exit(1);
+        }
+    };
 
     Database::run_schema_script(&config).await.unwrap();
 
@@ -4,7 +4,7 @@
 //! addresses.
 
 use crate::builder;
-use crate::{utils, EVMAddress};
+use crate::{errors::BridgeError, utils, EVMAddress};
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::Amount;
 use bitcoin::{
@@ -27,14 +27,15 @@ use secp256k1::XOnlyPublicKey;
 /// - [`Address`]: Generated taproot address
 /// - [`TaprootSpendInfo`]: Taproot spending information
 ///
-/// # Panics
+/// # Errors
 ///
-/// Will panic if some of the operations have invalid paramaters.
+/// Returns [`BridgeError::TaprootBuilderError`] if the script tree can't be
+/// built.
 pub fn create_taproot_address(
     scripts: &[ScriptBuf],
     internal_key: Option<XOnlyPublicKey>,
     network: bitcoin::Network,
-) -> (Address, TaprootSpendInfo) {
+) -> Result<(Address, TaprootSpendInfo), BridgeError> {
     let n = scripts.len();
 
     let taproot_builder = if n == 0 {
@@ -42,22 +43,22 @@ pub fn create_taproot_address(
     } else if n > 1 {
         let m: u8 = ((n - 1).ilog2() + 1) as u8; // m = ceil(log(n))
         let k = 2_usize.pow(m.into()) - n;
-        (0..n).fold(TaprootBuilder::new(), |acc, i| {
+        (0..n).try_fold(TaprootBuilder::new(), |acc, i| {
             acc.add_leaf(m - ((i >= n - k) as u8), scripts[i].clone())
-                .unwrap()
-        })
+                .map_err(|_| BridgeError::TaprootBuilderError)
+        })?
     } else {
         TaprootBuilder::new()
             .add_leaf(0, scripts[0].clone())
-            .unwrap()
+            .map_err(|_| BridgeError::TaprootBuilderError)?
     };
 
-    let tree_info = match internal_key {
-        Some(xonly_pk) => taproot_builder.finalize(&utils::SECP, xonly_pk).unwrap(),
-        None => taproot_builder
-            .finalize(&utils::SECP, *utils::UNSPENDABLE_XONLY_PUBKEY)
-            .unwrap(),
-    };
+    let tree_info = taproot_builder
+        .finalize(
+            &utils::SECP,
+            internal_key.unwrap_or(*utils::UNSPENDABLE_XONLY_PUBKEY),
+        )
+        .map_err(|_| BridgeError::TaprootBuilderError)?;
 
     let taproot_address = match internal_key {
         Some(xonly_pk) => Address::p2tr(&utils::SECP, xonly_pk, tree_info.merkle_root(), network),
@@ -69,7 +70,26 @@ pub fn create_taproot_address(
         ),
     };
 
-    (taproot_address, tree_info)
+    Ok((taproot_address, tree_info))
+}
+
+/// Extracts the tweaked x-only public key of a taproot address.
+///
+/// # Errors
+///
+/// Returns [`BridgeError::InvalidRecoveryAddress`] if the address is not a
+/// P2TR address.
+pub fn extract_taproot_xonly_pk(
+    taproot_address: &Address<NetworkUnchecked>,
+) -> Result<XOnlyPublicKey, BridgeError> {
+    let script_pubkey = taproot_address.clone().assume_checked().script_pubkey();
+    if !script_pubkey.is_p2tr() {
+        return Err(BridgeError::InvalidRecoveryAddress);
+    }
+
+    Ok(XOnlyPublicKey::from_slice(
+        &script_pubkey.as_bytes()[2..34],
+    )?)
 }
 
 /// Generates a deposit address for the user. Funds can be spend by N-of-N or
@@ -91,9 +111,10 @@ pub fn create_taproot_address(
 /// - [`Address`]: Deposit taproot Bitcoin address
 /// - [`TaprootSpendInfo`]: Deposit address's taproot spending information
 ///
-/// # Panics
+/// # Errors
 ///
-/// Panics if given parameters are malformed.
+/// Returns [`BridgeError::InvalidRecoveryAddress`] if the recovery address is
+/// not a taproot address.
 pub fn generate_deposit_address(
     nofn_xonly_pk: XOnlyPublicKey,
     recovery_taproot_address: &Address<NetworkUnchecked>,
@@ -101,16 +122,11 @@ pub fn generate_deposit_address(
     amount: Amount,
     network: bitcoin::Network,
     user_takes_after: u32,
-) -> (Address, TaprootSpendInfo) {
+) -> Result<(Address, TaprootSpendInfo), BridgeError> {
     let deposit_script =
         builder::script::create_deposit_script(nofn_xonly_pk, user_evm_address, amount);
 
-    let recovery_script_pubkey = recovery_taproot_address
-        .clone()
-        .assume_checked()
-        .script_pubkey();
-    let recovery_extracted_xonly_pk =
-        XOnlyPublicKey::from_slice(&recovery_script_pubkey.as_bytes()[2..34]).unwrap();
+    let recovery_extracted_xonly_pk = extract_taproot_xonly_pk(recovery_taproot_address)?;
 
     let script_timelock = builder::script::generate_relative_timelock_script(
         recovery_extracted_xonly_pk,
@@ -132,7 +148,7 @@ pub fn generate_deposit_address(
 pub fn create_musig2_address(
     nofn_xonly_pk: XOnlyPublicKey,
     network: bitcoin::Network,
-) -> (Address, TaprootSpendInfo) {
+) -> Result<(Address, TaprootSpendInfo), BridgeError> {
     create_taproot_address(&[], Some(nofn_xonly_pk), network)
 }
 
@@ -148,7 +164,7 @@ pub fn create_kickoff_address(
     nofn_xonly_pk: XOnlyPublicKey,
     operator_xonly_pk: XOnlyPublicKey,
     network: bitcoin::Network,
-) -> (Address, TaprootSpendInfo) {
+) -> Result<(Address, TaprootSpendInfo), BridgeError> {
     let musig2_and_operator_script = builder::script::create_musig2_and_operator_multisig_script(
         nofn_xonly_pk,
         operator_xonly_pk,
@@ -161,6 +177,7 @@ pub fn create_kickoff_address(
 mod tests {
     use crate::{
         builder,
+        errors::BridgeError,
         musig2::AggregateFromPublicKeys,
         utils::{self, SECP},
     };
@@ -176,7 +193,7 @@ mod tests {
 
         // No internal key or scripts (key path spend).
         let (address, spend_info) =
-            builder::address::create_taproot_address(&[], None, bitcoin::Network::Regtest);
+            builder::address::create_taproot_address(&[], None, bitcoin::Network::Regtest).unwrap();
         assert_eq!(address.address_type().unwrap(), AddressType::P2tr);
         assert!(address.is_related_to_xonly_pubkey(
             &utils::UNSPENDABLE_XONLY_PUBKEY
@@ -192,7 +209,8 @@ mod tests {
             &[],
             Some(internal_key),
             bitcoin::Network::Regtest,
-        );
+        )
+        .unwrap();
         assert_eq!(address.address_type().unwrap(), AddressType::P2tr);
         assert!(address.is_related_to_xonly_pubkey(
             &internal_key
@@ -208,7 +226,8 @@ mod tests {
             &scripts,
             Some(internal_key),
             bitcoin::Network::Regtest,
-        );
+        )
+        .unwrap();
         assert_eq!(address.address_type().unwrap(), AddressType::P2tr);
         assert!(address.is_related_to_xonly_pubkey(
             &internal_key
@@ -224,7 +243,8 @@ mod tests {
             &scripts,
             Some(internal_key),
             bitcoin::Network::Regtest,
-        );
+        )
+        .unwrap();
         assert_eq!(address.address_type().unwrap(), AddressType::P2tr);
         assert!(address.is_related_to_xonly_pubkey(
             &internal_key
@@ -251,7 +271,7 @@ mod tests {
             .iter()
             .map(|pk| PublicKey::from_str(pk).unwrap())
             .collect();
-        let nofn_xonly_pk = XOnlyPublicKey::from_musig2_pks(verifier_pks, None, false);
+        let nofn_xonly_pk = XOnlyPublicKey::from_musig2_pks(verifier_pks, None, false).unwrap();
 
         let evm_address: [u8; 20] = hex::decode("1234567890123456789012345678901234567890")
             .unwrap()
@@ -269,7 +289,8 @@ mod tests {
             Amount::from_sat(100_000_000),
             bitcoin::Network::Regtest,
             200,
-        );
+        )
+        .unwrap();
 
         // Comparing it to the taproot address generated in bridge backend.
         assert_eq!(
@@ -277,4 +298,26 @@ mod tests {
             "bcrt1ptlz698wumzl7uyk6pgrvsx5ep29thtvngxftywnd4mwq24fuwkwsxasqf5" // TODO: check this later
         )
     }
+
+    #[test]
+    fn generate_deposit_address_non_taproot_recovery_address() {
+        let secret_key = SecretKey::new(&mut rand::thread_rng());
+        let nofn_xonly_pk =
+            XOnlyPublicKey::from_keypair(&Keypair::from_secret_key(&SECP, &secret_key)).0;
+
+        let recovery_address =
+            Address::from_str("bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080").unwrap();
+
+        assert!(matches!(
+            builder::address::generate_deposit_address(
+                nofn_xonly_pk,
+                &recovery_address,
+                crate::EVMAddress([0x45u8; 20]),
+                Amount::from_sat(100_000_000),
+                bitcoin::Network::Regtest,
+                200,
+            ),
+            Err(BridgeError::InvalidRecoveryAddress)
+        ));
+    }
 }
@@ -0,0 +1,638 @@
+//! # Deposit Graph
+//!
+//! Deposit graph is the set of pre-signed transactions of a deposit: the
+//! move_tx and, for every operator, a slash_or_take_tx, a burn_tx and an
+//! operator_takes_tx. Every actor builds the graph from the same inputs, so
+//! they agree on the transactions, their sighashes and which MuSig2 nonce is
+//! used for which sighash.
+//!
+//! A move_tx can move a batch of deposits. Every deposit of the batch has its
+//! own graph, which signs the deposit's input of the shared move_tx and
+//! spends the deposit's bridge fund output.
+//!
+//! When bridge funds are swept to a new verifier epoch, the deposit needs a
+//! new graph on its swept output, where the sweep_tx takes the place of the
+//! move_tx.
+
+use crate::actor::Actor;
+use crate::builder::{
+    self,
+    transaction::{MoveTxDeposit, TxHandler},
+};
+use crate::config::BridgeConfig;
+use crate::errors::BridgeError;
+use crate::fee;
+use crate::musig2::{MuSigSigHash, NofnContext};
+use crate::{ByteArray32, UTXO};
+use bitcoin::hashes::Hash;
+use bitcoin::{OutPoint, TapNodeHash, XOnlyPublicKey};
+use std::ops::Range;
+
+/// Positions of a deposit's MuSig2 nonces. There are `3 * num_operators + 1`
+/// nonces in total:
+///
+/// - `[0]`: move_tx
+/// - `[1..num_operators + 1]`: operator_takes_txs
+/// - `[num_operators + 1..2 * num_operators + 1]`: slash_or_take_txs
+/// - `[2 * num_operators + 1..3 * num_operators + 1]`: burn_txs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NonceIndex {
+    num_operators: usize,
+}
+
+impl NonceIndex {
+    pub fn new(num_operators: usize) -> Self {
+        NonceIndex { num_operators }
+    }
+
+    /// Number of nonces a verifier generates for a deposit.
+    pub fn num_nonces(&self) -> usize {
+        3 * self.num_operators + 1
+    }
+
+    pub fn move_tx(&self) -> usize {
+        0
+    }
+
+    pub fn operator_takes(&self) -> Range<usize> {
+        1..self.num_operators + 1
+    }
+
+    pub fn slash_or_take(&self) -> Range<usize> {
+        self.num_operators + 1..2 * self.num_operators + 1
+    }
+
+    pub fn burn(&self) -> Range<usize> {
+        2 * self.num_operators + 1..3 * self.num_operators + 1
+    }
+}
+
+/// A sighash that verifiers sign with MuSig2 or FROST, with the tweak of the
+/// N-of-N key that the signature is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphSighash {
+    pub sighash: MuSigSigHash,
+    /// Merkle root of the spent output's script tree, for key path spends.
+    pub tweak: Option<TapNodeHash>,
+    /// True for key path spends, whose signing key is the taproot tweaked
+    /// N-of-N key.
+    pub tweak_flag: bool,
+}
+
+/// Pre-signed transactions of a deposit, with their sighashes.
+#[derive(Debug, Clone)]
+pub struct DepositGraph {
+    deposit_idx: usize,
+    kickoff_utxos: Vec<UTXO>,
+    move_tx: TxHandler,
+    slash_or_take_txs: Vec<TxHandler>,
+    burn_txs: Vec<TxHandler>,
+    operator_takes_txs: Vec<TxHandler>,
+    move_tx_sighash: GraphSighash,
+    slash_or_take_sighashes: Vec<GraphSighash>,
+    burn_sighashes: Vec<GraphSighash>,
+    operator_takes_sighashes: Vec<GraphSighash>,
+    nonce_index: NonceIndex,
+}
+
+impl DepositGraph {
+    /// Builds the graph of the deposit at `deposit_outpoint`, which is moved
+    /// by the move_tx of `move_tx_deposits`. A deposit that is not batched is
+    /// the only deposit of its move_tx. There must be a kickoff UTXO for every
+    /// operator, in the order of `config.operators_xonly_pks`. `nofn` is the
+    /// key aggregation of `config.verifiers_public_keys`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BridgeError::InvalidDepositBatch`] if the deposit is not one
+    /// of `move_tx_deposits`.
+    ///
+    /// Returns [`BridgeError::InvalidKickoffUtxo`] if the number of kickoff
+    /// UTXOs doesn't match the number of operators, or a kickoff UTXO is not
+    /// locked to its operator's kickoff address.
+    pub fn new(
+        move_tx_deposits: &[MoveTxDeposit],
+        deposit_outpoint: OutPoint,
+        kickoff_utxos: Vec<UTXO>,
+        nofn: &NofnContext,
+        config: &BridgeConfig,
+    ) -> Result<Self, BridgeError> {
+        let deposit_idx = move_tx_deposits
+            .iter()
+            .position(|deposit| deposit.deposit_outpoint == deposit_outpoint)
+            .ok_or(BridgeError::InvalidDepositBatch(deposit_outpoint))?;
+        if kickoff_utxos.len() != config.operators_xonly_pks.len() {
+            return Err(BridgeError::InvalidKickoffUtxo);
+        }
+
+        let mut move_tx = builder::transaction::create_batch_move_tx_handler(
+            move_tx_deposits,
+            nofn.xonly_pk(),
+            config.network,
+            config.user_takes_after,
+            fee::presigned_fee_rate(config)?,
+        )?;
+        let move_tx_sighash = GraphSighash {
+            sighash: ByteArray32(
+                Actor::convert_tx_to_sighash_script_spend(&mut move_tx, deposit_idx, 0)?
+                    .to_byte_array(),
+            ),
+            tweak: None,
+            tweak_flag: false,
+        };
+
+        Self::with_move_tx(
+            move_tx,
+            deposit_idx,
+            move_tx_sighash,
+            kickoff_utxos,
+            nofn.xonly_pk(),
+            config,
+        )
+    }
+
+    /// Builds the graph of a deposit whose bridge funds are swept to the next
+    /// verifier epoch by `sweep_tx`, at input and output `deposit_idx`. Graph
+    /// spends the swept output instead of the move_tx output and is locked to
+    /// the next epoch's N-of-N key, `nofn_xonly_pk`. The sweep_tx takes the
+    /// place of the move_tx, so `sweep_sighash` is the graph's move_tx sighash.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BridgeError::InvalidKickoffUtxo`] if the number of kickoff
+    /// UTXOs doesn't match the number of operators, or a kickoff UTXO is not
+    /// locked to its operator's kickoff address.
+    pub fn new_swept(
+        sweep_tx: TxHandler,
+        deposit_idx: usize,
+        sweep_sighash: GraphSighash,
+        kickoff_utxos: Vec<UTXO>,
+        nofn_xonly_pk: XOnlyPublicKey,
+        config: &BridgeConfig,
+    ) -> Result<Self, BridgeError> {
+        if kickoff_utxos.len() != config.operators_xonly_pks.len() {
+            return Err(BridgeError::InvalidKickoffUtxo);
+        }
+
+        Self::with_move_tx(
+            sweep_tx,
+            deposit_idx,
+            sweep_sighash,
+            kickoff_utxos,
+            nofn_xonly_pk,
+            config,
+        )
+    }
+
+    /// Builds the operators' transactions of the graph, which spend the
+    /// bridge fund output at `deposit_idx` of `move_tx`.
+    fn with_move_tx(
+        move_tx: TxHandler,
+        deposit_idx: usize,
+        move_tx_sighash: GraphSighash,
+        kickoff_utxos: Vec<UTXO>,
+        nofn_xonly_pk: XOnlyPublicKey,
+        config: &BridgeConfig,
+    ) -> Result<Self, BridgeError> {
+        let fee_rate = fee::presigned_fee_rate(config)?;
+        let move_txid = move_tx.tx.compute_txid();
+        let bridge_fund_utxo = UTXO {
+            outpoint: OutPoint {
+                txid: move_txid,
+                vout: deposit_idx as u32,
+            },
+            txout: move_tx.tx.output[deposit_idx].clone(),
+        };
+
+        let mut slash_or_take_txs = Vec::with_capacity(kickoff_utxos.len());
+        let mut burn_txs = Vec::with_capacity(kickoff_utxos.len());
+        let mut operator_takes_txs = Vec::with_capacity(kickoff_utxos.len());
+        let mut slash_or_take_sighashes = Vec::with_capacity(kickoff_utxos.len());
+        let mut burn_sighashes = Vec::with_capacity(kickoff_utxos.len());
+        let mut operator_takes_sighashes = Vec::with_capacity(kickoff_utxos.len());
+
+        for (operator_idx, kickoff_utxo) in kickoff_utxos.iter().enumerate() {
+            let operator_xonly_pk = config.operators_xonly_pks[operator_idx];
+
+            let mut slash_or_take_tx = builder::transaction::create_slash_or_take_tx(
+                move_txid,
+                kickoff_utxo.clone(),
+                operator_xonly_pk,
+                operator_idx,
+                nofn_xonly_pk,
+                config.network,
+                config.user_takes_after,
+                config.operator_takes_after,
+                fee_rate,
+            )?;
+            slash_or_take_sighashes.push(GraphSighash {
+                sighash: ByteArray32(
+                    Actor::convert_tx_to_sighash_script_spend(&mut slash_or_take_tx, 0, 0)?
+                        .to_byte_array(),
+                ),
+                tweak: None,
+                tweak_flag: false,
+            });
+            let slash_or_take_utxo = UTXO {
+                outpoint: OutPoint {
+                    txid: slash_or_take_tx.tx.compute_txid(),
+                    vout: 0,
+                },
+                txout: slash_or_take_tx.tx.output[0].clone(),
+            };
+
+            // burn_tx spends the slash_or_take_tx output with the N-of-N key,
+            // tweaked with the operator's timelock script.
+            let mut burn_tx = builder::transaction::create_burn_tx(
+                slash_or_take_utxo.clone(),
+                operator_xonly_pk,
+                nofn_xonly_pk,
+                config.network,
+                config.operator_takes_after,
+                fee_rate,
+            )?;
+            burn_sighashes.push(GraphSighash {
+                sighash: ByteArray32(
+                    Actor::convert_tx_to_sighash_pubkey_spend(&mut burn_tx, 0)?.to_byte_array(),
+                ),
+                tweak: burn_tx.taproot_spend_infos[0].merkle_root(),
+                tweak_flag: true,
+            });
+
+            let mut operator_takes_tx = builder::transaction::create_operator_takes_tx(
+                bridge_fund_utxo.clone(),
+                slash_or_take_utxo,
+                operator_xonly_pk,
+                nofn_xonly_pk,
+                config.network,
+                config.operator_takes_after,
+                config.operator_wallet_addresses[operator_idx].clone(),
+                fee_rate,
+            )?;
+            operator_takes_sighashes.push(GraphSighash {
+                sighash: ByteArray32(
+                    Actor::convert_tx_to_sighash_pubkey_spend(&mut operator_takes_tx, 0)?
+                        .to_byte_array(),
+                ),
+                tweak: None,
+                tweak_flag: true,
+            });
+
+            slash_or_take_txs.push(slash_or_take_tx);
+            burn_txs.push(burn_tx);
+            operator_takes_txs.push(operator_takes_tx);
+        }
+
+        let nonce_index = NonceIndex::new(kickoff_utxos.len());
+
+        Ok(DepositGraph {
+            deposit_idx,
+            kickoff_utxos,
+            move_tx,
+            slash_or_take_txs,
+            burn_txs,
+            operator_takes_txs,
+            move_tx_sighash,
+            slash_or_take_sighashes,
+            burn_sighashes,
+            operator_takes_sighashes,
+            nonce_index,
+        })
+    }
+
+    /// Index of the deposit's input and bridge fund output in the move_tx.
+    pub fn deposit_idx(&self) -> usize {
+        self.deposit_idx
+    }
+
+    pub fn kickoff_utxos(&self) -> &[UTXO] {
+        &self.kickoff_utxos
+    }
+
+    pub fn move_tx(&self) -> &TxHandler {
+        &self.move_tx
+    }
+
+    /// Output of the move_tx that holds the bridge funds of the deposit.
+    pub fn bridge_fund_outpoint(&self) -> OutPoint {
+        OutPoint {
+            txid: self.move_tx.tx.compute_txid(),
+            vout: self.deposit_idx as u32,
+        }
+    }
+
+    pub fn slash_or_take_txs(&self) -> &[TxHandler] {
+        &self.slash_or_take_txs
+    }
+
+    pub fn burn_txs(&self) -> &[TxHandler] {
+        &self.burn_txs
+    }
+
+    pub fn operator_takes_txs(&self) -> &[TxHandler] {
+        &self.operator_takes_txs
+    }
+
+    pub fn move_tx_sighash(&self) -> GraphSighash {
+        self.move_tx_sighash
+    }
+
+    pub fn slash_or_take_sighashes(&self) -> &[GraphSighash] {
+        &self.slash_or_take_sighashes
+    }
+
+    pub fn burn_sighashes(&self) -> &[GraphSighash] {
+        &self.burn_sighashes
+    }
+
+    pub fn operator_takes_sighashes(&self) -> &[GraphSighash] {
+        &self.operator_takes_sighashes
+    }
+
+    pub fn nonce_index(&self) -> NonceIndex {
+        self.nonce_index
+    }
+
+    /// Returns every sighash of the graph, ordered by their nonce indexes.
+    pub fn sighashes(&self) -> Vec<GraphSighash> {
+        let mut sighashes = Vec::with_capacity(self.nonce_index.num_nonces());
+
+        sighashes.push(self.move_tx_sighash);
+        sighashes.extend_from_slice(&self.operator_takes_sighashes);
+        sighashes.extend_from_slice(&self.slash_or_take_sighashes);
+        sighashes.extend_from_slice(&self.burn_sighashes);
+
+        sighashes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DepositGraph, NonceIndex};
+    use crate::builder::transaction::MoveTxDeposit;
+    use crate::errors::BridgeError;
+    use crate::fee;
+    use crate::mock::common;
+    use crate::musig2::NofnContext;
+    use crate::utils::SECP;
+    use crate::{builder, config::BridgeConfig, EVMAddress, UTXO};
+    use bitcoin::{hashes::Hash, Address, Amount, OutPoint, TxOut, Txid};
+
+    fn create_kickoff_utxos(config: &BridgeConfig) -> Vec<UTXO> {
+        let nofn_xonly_pk = NofnContext::new(config.verifiers_public_keys.clone())
+            .unwrap()
+            .xonly_pk();
+
+        config
+            .operators_xonly_pks
+            .iter()
+            .enumerate()
+            .map(|(i, operator_xonly_pk)| UTXO {
+                outpoint: OutPoint {
+                    txid: Txid::all_zeros(),
+                    vout: i as u32,
+                },
+                txout: TxOut {
+                    value: builder::transaction::KICKOFF_UTXO_AMOUNT_SATS,
+                    script_pubkey: builder::address::create_kickoff_address(
+                        nofn_xonly_pk,
+                        *operator_xonly_pk,
+                        config.network,
+                    )
+                    .unwrap()
+                    .0
+                    .script_pubkey(),
+                },
+            })
+            .collect()
+    }
+
+    fn create_move_tx_deposit(
+        config: &BridgeConfig,
+        vout: u32,
+        bridge_amount_sats: Amount,
+    ) -> MoveTxDeposit {
+        let recovery_taproot_address =
+            Address::p2tr(&SECP, config.operators_xonly_pks[0], None, config.network);
+
+        MoveTxDeposit {
+            deposit_outpoint: OutPoint {
+                txid: Txid::all_zeros(),
+                vout,
+            },
+            recovery_taproot_address: recovery_taproot_address.as_unchecked().clone(),
+            evm_address: EVMAddress([vout as u8; 20]),
+            bridge_amount_sats,
+        }
+    }
+
+    fn create_deposit_graph(
+        config: &BridgeConfig,
+        bridge_amount_sats: Amount,
+        kickoff_utxos: Vec<UTXO>,
+    ) -> Result<DepositGraph, BridgeError> {
+        let deposit = create_move_tx_deposit(config, 0x45, bridge_amount_sats);
+
+        DepositGraph::new(
+            &[deposit.clone()],
+            deposit.deposit_outpoint,
+            kickoff_utxos,
+            &NofnContext::new(config.verifiers_public_keys.clone()).unwrap(),
+            config,
+        )
+    }
+
+    #[test]
+    fn nonce_index() {
+        let nonce_index = NonceIndex::new(3);
+
+        assert_eq!(nonce_index.num_nonces(), 10);
+        assert_eq!(nonce_index.move_tx(), 0);
+        assert_eq!(nonce_index.operator_takes(), 1..4);
+        assert_eq!(nonce_index.slash_or_take(), 4..7);
+        assert_eq!(nonce_index.burn(), 7..10);
+    }
+
+    #[test]
+    fn sighashes_follow_nonce_index() {
+        let config = common::get_test_config("test_config.toml").unwrap();
+        let graph = create_deposit_graph(
+            &config,
+            config.bridge_amounts_sats[0],
+            create_kickoff_utxos(&config),
+        )
+        .unwrap();
+        let nonce_index = graph.nonce_index();
+
+        let sighashes = graph.sighashes();
+        assert_eq!(sighashes.len(), nonce_index.num_nonces());
+        assert_eq!(sighashes[nonce_index.move_tx()], graph.move_tx_sighash());
+        assert_eq!(
+            sighashes[nonce_index.operator_takes()],
+            *graph.operator_takes_sighashes()
+        );
+        assert_eq!(
+            sighashes[nonce_index.slash_or_take()],
+            *graph.slash_or_take_sighashes()
+        );
+        assert_eq!(sighashes[nonce_index.burn()], *graph.burn_sighashes());
+
+        // Every actor computes the same graph.
+        let other_graph = create_deposit_graph(
+            &config,
+            config.bridge_amounts_sats[0],
+            create_kickoff_utxos(&config),
+        )
+        .unwrap();
+        assert_eq!(other_graph.sighashes(), sighashes);
+    }
+
+    #[test]
+    fn transactions_are_chained() {
+        let config = common::get_test_config("test_config.toml").unwrap();
+        let kickoff_utxos = create_kickoff_utxos(&config);
+        let graph = create_deposit_graph(
+            &config,
+            config.bridge_amounts_sats[0],
+            kickoff_utxos.clone(),
+        )
+        .unwrap();
+
+        for (i, kickoff_utxo) in kickoff_utxos.iter().enumerate() {
+            let slash_or_take_tx = &graph.slash_or_take_txs()[i].tx;
+            assert_eq!(
+                slash_or_take_tx.input[0].previous_output,
+                kickoff_utxo.outpoint
+            );
+
+            let slash_or_take_outpoint = OutPoint {
+                txid: slash_or_take_tx.compute_txid(),
+                vout: 0,
+            };
+            assert_eq!(
+                graph.burn_txs()[i].tx.input[0].previous_output,
+                slash_or_take_outpoint
+            );
+            assert_eq!(
+                graph.operator_takes_txs()[i].tx.input[0].previous_output,
+                graph.bridge_fund_outpoint()
+            );
+            assert_eq!(
+                graph.operator_takes_txs()[i].tx.input[1].previous_output,
+                slash_or_take_outpoint
+            );
+        }
+    }
+
+    #[test]
+    fn bridge_amounts() {
+        let config = common::get_test_config("test_config.toml").unwrap();
+        let kickoff_utxos = create_kickoff_utxos(&config);
+
+        let graphs = config
+            .bridge_amounts_sats
+            .iter()
+            .map(|amount| create_deposit_graph(&config, *amount, kickoff_utxos.clone()).unwrap())
+            .collect::<Vec<_>>();
+
+        // Bridge funds are the deposit amounts, minus the move_tx fee and the
+        // anchor output, which don't depend on the amount.
+        let nofn_xonly_pk = NofnContext::new(config.verifiers_public_keys.clone())
+            .unwrap()
+            .xonly_pk();
+        let move_tx_cost = builder::script::anyone_can_spend_txout().value
+            + builder::transaction::move_tx_fee(
+                nofn_xonly_pk,
+                &config.bridge_amounts_sats[..1],
+                config.network,
+                fee::presigned_fee_rate(&config).unwrap(),
+            )
+            .unwrap();
+        for (graph, amount) in graphs.iter().zip(config.bridge_amounts_sats.iter()) {
+            assert_eq!(graph.move_tx().tx.output[0].value, *amount - move_tx_cost);
+        }
+        assert_ne!(graphs[0].sighashes(), graphs[1].sighashes());
+    }
+
+    #[test]
+    fn batched_deposits() {
+        let config = common::get_test_config("test_config.toml").unwrap();
+        let kickoff_utxos = create_kickoff_utxos(&config);
+        let deposits = config
+            .bridge_amounts_sats
+            .iter()
+            .enumerate()
+            .map(|(i, amount)| create_move_tx_deposit(&config, i as u32, *amount))
+            .collect::<Vec<_>>();
+
+        let nofn = NofnContext::new(config.verifiers_public_keys.clone()).unwrap();
+        let graphs = deposits
+            .iter()
+            .map(|deposit| {
+                DepositGraph::new(
+                    &deposits,
+                    deposit.deposit_outpoint,
+                    kickoff_utxos.clone(),
+                    &nofn,
+                    &config,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        // Deposits share the move_tx, but sign and spend their own input and
+        // bridge fund output.
+        let move_txid = graphs[0].move_tx().tx.compute_txid();
+        for (i, graph) in graphs.iter().enumerate() {
+            assert_eq!(graph.deposit_idx(), i);
+            assert_eq!(graph.move_tx().tx.compute_txid(), move_txid);
+            assert_eq!(
+                graph.bridge_fund_outpoint(),
+                OutPoint {
+                    txid: move_txid,
+                    vout: i as u32,
+                }
+            );
+            for operator_takes_tx in graph.operator_takes_txs() {
+                assert_eq!(
+                    operator_takes_tx.tx.input[0].previous_output,
+                    graph.bridge_fund_outpoint()
+                );
+            }
+        }
+        assert_ne!(graphs[0].move_tx_sighash(), graphs[1].move_tx_sighash());
+
+        // Deposit must be in the batch.
+        let other_deposit = create_move_tx_deposit(&config, 0x45, config.bridge_amounts_sats[0]);
+        assert!(matches!(
+            DepositGraph::new(
+                &deposits,
+                other_deposit.deposit_outpoint,
+                kickoff_utxos,
+                &nofn,
+                &config,
+            ),
+            Err(BridgeError::InvalidDepositBatch(outpoint)) if outpoint == other_deposit.deposit_outpoint
+        ));
+    }
+
+    #[test]
+    fn invalid_kickoff_utxos() {
+        let config = common::get_test_config("test_config.toml").unwrap();
+
+        let mut kickoff_utxos = create_kickoff_utxos(&config);
+        kickoff_utxos.pop();
+        assert!(matches!(
+            create_deposit_graph(&config, config.bridge_amounts_sats[0], kickoff_utxos),
+            Err(BridgeError::InvalidKickoffUtxo)
+        ));
+
+        // Kickoff UTXOs are in the wrong order.
+        let mut kickoff_utxos = create_kickoff_utxos(&config);
+        kickoff_utxos.swap(0, 1);
+        assert!(matches!(
+            create_deposit_graph(&config, config.bridge_amounts_sats[0], kickoff_utxos),
+            Err(BridgeError::InvalidKickoffUtxo)
+        ));
+    }
+}
@@ -4,5 +4,7 @@
 //! structures.
 
 pub mod address;
+pub mod deposit_graph;
+pub mod psbt;
 pub mod script;
 pub mod transaction;
@@ -0,0 +1,721 @@
+//! # PSBT Builder
+//!
+//! Converts [`TxHandler`]s to and from BIP-174 PSBTs with the BIP-371 taproot
+//! fields, so that bridge transactions can be inspected and signed outside of
+//! the bridge.
+
+use crate::builder::transaction::TxHandler;
+use crate::errors::BridgeError;
+use crate::utils::{self, handle_taproot_witness_new};
+use bitcoin::hashes::Hash;
+use bitcoin::opcodes::all::{OP_CHECKSIG, OP_CHECKSIGADD, OP_CHECKSIGVERIFY};
+use bitcoin::psbt::raw::ProprietaryKey;
+use bitcoin::psbt::{self, Psbt};
+use bitcoin::script::Instruction;
+use bitcoin::sighash::{Prevouts, SighashCache};
+use bitcoin::taproot::{
+    self, ControlBlock, LeafVersion, NodeInfo, TapLeafHash, TapNodeHash, TaprootSpendInfo,
+};
+use bitcoin::{ScriptBuf, TapSighash, XOnlyPublicKey};
+use secp256k1::Message;
+use std::collections::BTreeMap;
+
+/// Prefix of the bridge's proprietary PSBT fields.
+const PSBT_PROPRIETARY_PREFIX: &[u8] = b"clementine";
+/// Subtype of the proprietary input field that keeps the index of a script
+/// in [`TxHandler::scripts`]. Key data is the index as a big endian `u32` and
+/// value is the leaf hash of the script.
+const PSBT_IN_SCRIPT_IDX: u8 = 0x00;
+
+impl TxHandler {
+    /// Converts the transaction to a PSBT. Every input has its prevout as the
+    /// witness UTXO. Taproot inputs also have their internal key, merkle root
+    /// and the scripts that can be spent, with their control blocks and
+    /// indexes. Witnesses that are already set are given as final witnesses.
+    pub fn to_psbt(&self) -> Result<Psbt, BridgeError> {
+        let mut unsigned_tx = self.tx.clone();
+        let witnesses = unsigned_tx
+            .input
+            .iter_mut()
+            .map(|txin| std::mem::take(&mut txin.witness))
+            .collect::<Vec<_>>();
+
+        let mut psbt = Psbt::from_unsigned_tx(unsigned_tx)?;
+
+        for (idx, (input, witness)) in psbt.inputs.iter_mut().zip(witnesses).enumerate() {
+            input.witness_utxo = Some(
+                self.prevouts
+                    .get(idx)
+                    .ok_or(BridgeError::TxInputNotFound)?
+                    .clone(),
+            );
+
+            if !witness.is_empty() {
+                input.final_script_witness = Some(witness);
+            }
+
+            let Some(spend_info) = self.taproot_spend_infos.get(idx) else {
+                continue;
+            };
+            input.tap_internal_key = Some(spend_info.internal_key());
+            input.tap_merkle_root = spend_info.merkle_root();
+
+            for (script_idx, script) in self.scripts.get(idx).into_iter().flatten().enumerate() {
+                let leaf = (script.clone(), LeafVersion::TapScript);
+                let control_block = spend_info
+                    .control_block(&leaf)
+                    .ok_or(BridgeError::ControlBlockError)?;
+
+                input.tap_scripts.insert(control_block, leaf);
+                input.proprietary.insert(
+                    script_idx_key(script_idx)?,
+                    TapLeafHash::from_script(script, LeafVersion::TapScript)
+                        .to_byte_array()
+                        .to_vec(),
+                );
+            }
+        }
+
+        Ok(psbt)
+    }
+
+    /// Converts a PSBT, which is created with [`TxHandler::to_psbt`] or by an
+    /// external wallet, to a [`TxHandler`]. Taproot spend infos are rebuilt
+    /// from the control blocks of the scripts, so only the given scripts can
+    /// be spent with the returned handler. Scripts of an input keep the
+    /// indexes that are saved by [`TxHandler::to_psbt`]; an input without
+    /// them can have at most one script.
+    pub fn from_psbt(psbt: &Psbt) -> Result<Self, BridgeError> {
+        let mut tx = psbt.unsigned_tx.clone();
+        if psbt.inputs.len() != tx.input.len() {
+            return Err(BridgeError::PsbtInputCountMismatch(
+                psbt.inputs.len(),
+                tx.input.len(),
+            ));
+        }
+
+        let mut prevouts = Vec::new();
+        let mut scripts = Vec::new();
+        let mut taproot_spend_infos = Vec::new();
+
+        for (idx, input) in psbt.inputs.iter().enumerate() {
+            prevouts.push(
+                input
+                    .witness_utxo
+                    .clone()
+                    .ok_or(BridgeError::PsbtWitnessUtxoNotFound(idx))?,
+            );
+
+            if let Some(witness) = &input.final_script_witness {
+                tx.input[idx].witness = witness.clone();
+            }
+
+            scripts.push(ordered_scripts(idx, input)?);
+
+            // Spend infos are positional, so they can't be skipped for an
+            // input while a later input has one.
+            if let Some(internal_key) = input.tap_internal_key {
+                if taproot_spend_infos.len() != idx {
+                    return Err(BridgeError::PsbtTaprootInfoNotFound(
+                        taproot_spend_infos.len(),
+                    ));
+                }
+
+                taproot_spend_infos.push(rebuild_spend_info(
+                    internal_key,
+                    input.tap_merkle_root,
+                    &input.tap_scripts,
+                )?);
+            }
+        }
+
+        Ok(TxHandler {
+            tx,
+            prevouts,
+            scripts,
+            taproot_spend_infos,
+        })
+    }
+
+    /// Sets the witnesses of the inputs that are signed in the PSBT. A key
+    /// path signature must verify against the output key of the prevout. For
+    /// a script path, every key that is checked by the script must have a
+    /// signature in `tap_script_sigs`; signatures are pushed in reverse order
+    /// of their keys, so that the first key's signature is on top of the
+    /// stack. Every applied signature must verify against its key and the
+    /// input's sighash. Inputs that are not fully signed are not changed.
+    ///
+    /// # Returns
+    ///
+    /// Indexes of the inputs whose witnesses are set.
+    pub fn apply_psbt_signatures(&mut self, psbt: &Psbt) -> Result<Vec<usize>, BridgeError> {
+        if psbt.inputs.len() != self.tx.input.len() {
+            return Err(BridgeError::PsbtInputCountMismatch(
+                psbt.inputs.len(),
+                self.tx.input.len(),
+            ));
+        }
+
+        let mut signed_inputs = Vec::new();
+
+        for (idx, input) in psbt.inputs.iter().enumerate() {
+            if !self.tx.input[idx].witness.is_empty() {
+                continue;
+            }
+
+            if let Some(signature) = input.tap_key_sig {
+                let output_key = self
+                    .prevouts
+                    .get(idx)
+                    .filter(|prevout| prevout.script_pubkey.is_p2tr())
+                    .and_then(|prevout| {
+                        XOnlyPublicKey::from_slice(&prevout.script_pubkey.as_bytes()[2..]).ok()
+                    })
+                    .ok_or(BridgeError::PsbtInvalidSignature(idx))?;
+                let sighash = SighashCache::new(&self.tx).taproot_key_spend_signature_hash(
+                    idx,
+                    &Prevouts::All(&self.prevouts),
+                    signature.sighash_type,
+                )?;
+                verify_signature(idx, &signature, sighash, &output_key)?;
+
+                handle_taproot_witness_new(self, &[signature.to_vec()], idx, None)?;
+                signed_inputs.push(idx);
+                continue;
+            }
+
+            let scripts = self.scripts.get(idx).cloned().unwrap_or_default();
+            for (script_idx, script) in scripts.iter().enumerate() {
+                let leaf_hash = TapLeafHash::from_script(script, LeafVersion::TapScript);
+
+                let keys = script_keys(script);
+                let Some(signatures) = keys
+                    .iter()
+                    .map(|key| input.tap_script_sigs.get(&(*key, leaf_hash)))
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|signatures| !signatures.is_empty())
+                else {
+                    continue;
+                };
+
+                for (key, signature) in keys.iter().zip(signatures.iter()) {
+                    let sighash = SighashCache::new(&self.tx).taproot_script_spend_signature_hash(
+                        idx,
+                        &Prevouts::All(&self.prevouts),
+                        leaf_hash,
+                        signature.sighash_type,
+                    )?;
+                    verify_signature(idx, signature, sighash, key)?;
+                }
+
+                let signatures = signatures
+                    .iter()
+                    .rev()
+                    .map(|signature| signature.to_vec())
+                    .collect::<Vec<_>>();
+                handle_taproot_witness_new(self, &signatures, idx, Some(script_idx))?;
+                signed_inputs.push(idx);
+                break;
+            }
+        }
+
+        Ok(signed_inputs)
+    }
+}
+
+/// Returns the key of the proprietary input field that keeps the index of a
+/// script.
+fn script_idx_key(script_idx: usize) -> Result<ProprietaryKey, BridgeError> {
+    let script_idx = u32::try_from(script_idx).map_err(|_| BridgeError::TaprootBuilderError)?;
+
+    Ok(ProprietaryKey {
+        prefix: PSBT_PROPRIETARY_PREFIX.to_vec(),
+        subtype: PSBT_IN_SCRIPT_IDX,
+        key: script_idx.to_be_bytes().to_vec(),
+    })
+}
+
+/// Returns the scripts of a PSBT input in the order of their indexes. Every
+/// script must have an index and indexes must start from zero without gaps,
+/// unless the input has at most one script.
+fn ordered_scripts(idx: usize, input: &psbt::Input) -> Result<Vec<ScriptBuf>, BridgeError> {
+    let scripts = input
+        .tap_scripts
+        .values()
+        .map(|(script, _)| script.clone())
+        .collect::<Vec<_>>();
+
+    let mut leaf_hashes = BTreeMap::new();
+    for (key, value) in input.proprietary.iter() {
+        if key.prefix != PSBT_PROPRIETARY_PREFIX || key.subtype != PSBT_IN_SCRIPT_IDX {
+            continue;
+        }
+
+        let script_idx = <[u8; 4]>::try_from(key.key.as_slice())
+            .map(u32::from_be_bytes)
+            .map_err(|_| BridgeError::PsbtScriptOrderNotFound(idx))?;
+        let leaf_hash = TapLeafHash::from_slice(value)
+            .map_err(|_| BridgeError::PsbtScriptOrderNotFound(idx))?;
+        leaf_hashes.insert(script_idx, leaf_hash);
+    }
+
+    if leaf_hashes.is_empty() && scripts.len() <= 1 {
+        return Ok(scripts);
+    }
+    if leaf_hashes.len() != scripts.len()
+        || leaf_hashes
+            .keys()
+            .enumerate()
+            .any(|(i, script_idx)| u32::try_from(i) != Ok(*script_idx))
+    {
+        return Err(BridgeError::PsbtScriptOrderNotFound(idx));
+    }
+
+    leaf_hashes
+        .values()
+        .map(|leaf_hash| {
+            scripts
+                .iter()
+                .find(|script| {
+                    TapLeafHash::from_script(script, LeafVersion::TapScript) == *leaf_hash
+                })
+                .cloned()
+                .ok_or(BridgeError::PsbtScriptOrderNotFound(idx))
+        })
+        .collect()
+}
+
+/// Checks a signature of a PSBT input against its sighash and key.
+fn verify_signature(
+    idx: usize,
+    signature: &taproot::Signature,
+    sighash: TapSighash,
+    key: &XOnlyPublicKey,
+) -> Result<(), BridgeError> {
+    utils::SECP
+        .verify_schnorr(
+            &signature.signature,
+            &Message::from_digest(sighash.to_byte_array()),
+            key,
+        )
+        .map_err(|_| BridgeError::PsbtInvalidSignature(idx))
+}
+
+/// Returns the keys that are checked by the script with `OP_CHECKSIG`,
+/// `OP_CHECKSIGVERIFY` or `OP_CHECKSIGADD`, in order. Other pushes, like the
+/// hashes of hashlocks, are not keys.
+fn script_keys(script: &ScriptBuf) -> Vec<XOnlyPublicKey> {
+    let instructions = script
+        .instructions()
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+
+    instructions
+        .windows(2)
+        .filter_map(|window| match window {
+            [Instruction::PushBytes(bytes), Instruction::Op(op)]
+                if [OP_CHECKSIG, OP_CHECKSIGVERIFY, OP_CHECKSIGADD].contains(op) =>
+            {
+                XOnlyPublicKey::from_slice(bytes.as_bytes()).ok()
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Rebuilds a taproot spend info from the internal key and the control
+/// blocks of the known leaves. Branches of the tree without a known leaf are
+/// kept as hidden nodes.
+fn rebuild_spend_info(
+    internal_key: XOnlyPublicKey,
+    merkle_root: Option<TapNodeHash>,
+    tap_scripts: &BTreeMap<ControlBlock, (ScriptBuf, LeafVersion)>,
+) -> Result<TaprootSpendInfo, BridgeError> {
+    let spend_info = if tap_scripts.is_empty() {
+        TaprootSpendInfo::new_key_spend(&utils::SECP, internal_key, merkle_root)
+    } else {
+        let leaves = tap_scripts
+            .iter()
+            .map(|(control_block, (script, leaf_version))| {
+                (
+                    script.clone(),
+                    *leaf_version,
+                    control_block.merkle_branch.as_slice().to_vec(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        TaprootSpendInfo::from_node_info(&utils::SECP, internal_key, build_node(leaves)?)
+    };
+
+    if spend_info.merkle_root() != merkle_root {
+        return Err(BridgeError::TaprootBuilderError);
+    }
+    for (control_block, (script, _)) in tap_scripts {
+        if !control_block.verify_taproot_commitment(
+            &utils::SECP,
+            spend_info.output_key().to_inner(),
+            script,
+        ) {
+            return Err(BridgeError::ControlBlockError);
+        }
+    }
+
+    Ok(spend_info)
+}
+
+/// Builds the node of a subtree from its leaves and their remaining merkle
+/// branches. Last element of a branch is the sibling at the top of the
+/// subtree. Leaves on the same side share it and their other side is either
+/// built from the other leaves or hidden.
+fn build_node(
+    mut leaves: Vec<(ScriptBuf, LeafVersion, Vec<TapNodeHash>)>,
+) -> Result<NodeInfo, BridgeError> {
+    if leaves.iter().all(|(_, _, branch)| branch.is_empty()) {
+        return match leaves.as_slice() {
+            [(script, leaf_version, _)] => {
+                Ok(NodeInfo::new_leaf_with_ver(script.clone(), *leaf_version))
+            }
+            _ => Err(BridgeError::TaprootBuilderError),
+        };
+    }
+
+    let mut sides: BTreeMap<TapNodeHash, Vec<_>> = BTreeMap::new();
+    for (script, leaf_version, mut branch) in leaves.drain(..) {
+        let sibling = branch.pop().ok_or(BridgeError::TaprootBuilderError)?;
+        sides
+            .entry(sibling)
+            .or_default()
+            .push((script, leaf_version, branch));
+    }
+
+    let mut sides = sides.into_iter();
+    let node = match (sides.next(), sides.next(), sides.next()) {
+        (Some((sibling, leaves)), None, None) => {
+            NodeInfo::combine(build_node(leaves)?, NodeInfo::new_hidden_node(sibling))
+        }
+        (Some((_, left_leaves)), Some((_, right_leaves)), None) => {
+            NodeInfo::combine(build_node(left_leaves)?, build_node(right_leaves)?)
+        }
+        _ => return Err(BridgeError::TaprootBuilderError),
+    };
+
+    node.map_err(|_| BridgeError::TaprootBuilderError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::script_keys;
+    use crate::actor::Actor;
+    use crate::builder::{self, transaction::TxHandler};
+    use crate::errors::BridgeError;
+    use crate::utils::{handle_taproot_witness_new, SECP};
+    use crate::{EVMAddress, UTXO};
+    use bitcoin::opcodes::all::{OP_CHECKSIG, OP_EQUALVERIFY, OP_SHA256};
+    use bitcoin::psbt::Psbt;
+    use bitcoin::script::Builder;
+    use bitcoin::taproot::{LeafVersion, Signature, TapLeafHash};
+    use bitcoin::{
+        absolute, hashes::Hash, transaction, Address, Amount, FeeRate, OutPoint, TapSighash,
+        Transaction, TxIn, TxOut, Txid, XOnlyPublicKey,
+    };
+    use secp256k1::{rand, schnorr, Keypair, SecretKey};
+
+    fn random_actor() -> Actor {
+        Actor::new(
+            SecretKey::new(&mut rand::thread_rng()),
+            bitcoin::Network::Regtest,
+        )
+    }
+
+    fn random_xonly_pk() -> XOnlyPublicKey {
+        let secret_key = SecretKey::new(&mut rand::thread_rng());
+
+        XOnlyPublicKey::from_keypair(&Keypair::from_secret_key(&SECP, &secret_key)).0
+    }
+
+    fn signature(signature: schnorr::Signature) -> Signature {
+        Signature {
+            signature,
+            sighash_type: bitcoin::TapSighashType::Default,
+        }
+    }
+
+    fn create_operator_takes_tx(
+        operator_xonly_pk: XOnlyPublicKey,
+        nofn_xonly_pk: XOnlyPublicKey,
+    ) -> TxHandler {
+        let network = bitcoin::Network::Regtest;
+        let operator_takes_after = 5;
+
+        let relative_timelock_script = builder::script::generate_relative_timelock_script(
+            operator_xonly_pk,
+            operator_takes_after,
+        );
+        let (slash_or_take_address, _) = builder::address::create_taproot_address(
+            &[relative_timelock_script],
+            Some(nofn_xonly_pk),
+            network,
+        )
+        .unwrap();
+        let slash_or_take_utxo = UTXO {
+            outpoint: OutPoint {
+                txid: Txid::all_zeros(),
+                vout: 0,
+            },
+            txout: TxOut {
+                value: Amount::from_sat(0x1F45),
+                script_pubkey: slash_or_take_address.script_pubkey(),
+            },
+        };
+        let operator_wallet_address = Address::p2tr(&SECP, operator_xonly_pk, None, network)
+            .as_unchecked()
+            .clone();
+
+        let bridge_fund_utxo = UTXO {
+            outpoint: OutPoint {
+                txid: Txid::all_zeros(),
+                vout: 1,
+            },
+            txout: TxOut {
+                value: Amount::from_sat(100_000_000),
+                script_pubkey: builder::address::create_musig2_address(nofn_xonly_pk, network)
+                    .unwrap()
+                    .0
+                    .script_pubkey(),
+            },
+        };
+
+        builder::transaction::create_operator_takes_tx(
+            bridge_fund_utxo,
+            slash_or_take_utxo,
+            operator_xonly_pk,
+            nofn_xonly_pk,
+            network,
+            operator_takes_after,
+            operator_wallet_address,
+            FeeRate::from_sat_per_vb(1).unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn assert_same_handler(tx_handler: &TxHandler, imported: &TxHandler) {
+        assert_eq!(imported.tx, tx_handler.tx);
+        assert_eq!(imported.prevouts, tx_handler.prevouts);
+        assert_eq!(imported.scripts, tx_handler.scripts);
+        assert_eq!(
+            imported.taproot_spend_infos.len(),
+            tx_handler.taproot_spend_infos.len()
+        );
+
+        for (idx, (imported_info, info)) in imported
+            .taproot_spend_infos
+            .iter()
+            .zip(tx_handler.taproot_spend_infos.iter())
+            .enumerate()
+        {
+            assert_eq!(imported_info.output_key(), info.output_key());
+            assert_eq!(imported_info.merkle_root(), info.merkle_root());
+
+            for script in tx_handler.scripts[idx].iter() {
+                let leaf = (script.clone(), LeafVersion::TapScript);
+                assert_eq!(
+                    imported_info.control_block(&leaf),
+                    info.control_block(&leaf)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn psbt_round_trip() {
+        let tx_handler = create_operator_takes_tx(random_xonly_pk(), random_xonly_pk());
+
+        let psbt = tx_handler.to_psbt().unwrap();
+        assert_eq!(psbt.inputs[1].tap_scripts.len(), 1);
+        assert_eq!(
+            psbt.inputs[1].tap_internal_key,
+            Some(tx_handler.taproot_spend_infos[1].internal_key())
+        );
+
+        // PSBT is serialized as in BIP-174.
+        let psbt = Psbt::deserialize(&psbt.serialize()).unwrap();
+
+        let imported = TxHandler::from_psbt(&psbt).unwrap();
+        assert_same_handler(&tx_handler, &imported);
+    }
+
+    #[test]
+    fn psbt_round_trip_with_hidden_leaves() {
+        // Deposit address has a leaf for the user's recovery too, which is not
+        // spent by the move_tx.
+        let nofn_xonly_pk = random_xonly_pk();
+        let recovery_taproot_address =
+            Address::p2tr(&SECP, random_xonly_pk(), None, bitcoin::Network::Regtest);
+        let tx_handler = builder::transaction::create_move_tx_handler(
+            OutPoint {
+                txid: Txid::all_zeros(),
+                vout: 0x45,
+            },
+            EVMAddress([0x45u8; 20]),
+            recovery_taproot_address.as_unchecked(),
+            nofn_xonly_pk,
+            bitcoin::Network::Regtest,
+            200,
+            Amount::from_sat(100_000_000),
+            FeeRate::from_sat_per_vb(1).unwrap(),
+        )
+        .unwrap();
+
+        let imported = TxHandler::from_psbt(&tx_handler.to_psbt().unwrap()).unwrap();
+        assert_same_handler(&tx_handler, &imported);
+    }
+
+    #[test]
+    fn psbt_scripts_keep_their_indexes() {
+        let scripts = (0..3)
+            .map(|i| builder::script::generate_relative_timelock_script(random_xonly_pk(), i))
+            .collect::<Vec<_>>();
+        let (address, spend_info) =
+            builder::address::create_taproot_address(&scripts, None, bitcoin::Network::Regtest)
+                .unwrap();
+        let tx_handler = TxHandler {
+            tx: Transaction {
+                version: transaction::Version::TWO,
+                lock_time: absolute::LockTime::ZERO,
+                input: vec![TxIn::default()],
+                output: vec![builder::script::anyone_can_spend_txout()],
+            },
+            prevouts: vec![TxOut {
+                value: Amount::from_sat(0x1F45),
+                script_pubkey: address.script_pubkey(),
+            }],
+            scripts: vec![scripts],
+            taproot_spend_infos: vec![spend_info],
+        };
+
+        let mut psbt = tx_handler.to_psbt().unwrap();
+        let imported = TxHandler::from_psbt(&psbt).unwrap();
+        assert_same_handler(&tx_handler, &imported);
+
+        // Order of multiple scripts can't be known without their indexes.
+        psbt.inputs[0].proprietary.clear();
+        assert!(matches!(
+            TxHandler::from_psbt(&psbt),
+            Err(BridgeError::PsbtScriptOrderNotFound(0))
+        ));
+    }
+
+    #[test]
+    fn psbt_input_count_mismatch() {
+        let tx_handler = create_operator_takes_tx(random_xonly_pk(), random_xonly_pk());
+
+        let mut psbt = tx_handler.to_psbt().unwrap();
+        psbt.inputs.pop();
+        assert!(matches!(
+            TxHandler::from_psbt(&psbt),
+            Err(BridgeError::PsbtInputCountMismatch(1, 2))
+        ));
+        assert!(matches!(
+            tx_handler.clone().apply_psbt_signatures(&psbt),
+            Err(BridgeError::PsbtInputCountMismatch(1, 2))
+        ));
+    }
+
+    #[test]
+    fn script_keys_are_checked_keys() {
+        let hash = random_xonly_pk().serialize();
+        let xonly_pk = random_xonly_pk();
+        let hashlock_script = Builder::new()
+            .push_opcode(OP_SHA256)
+            .push_slice(hash)
+            .push_opcode(OP_EQUALVERIFY)
+            .push_x_only_key(&xonly_pk)
+            .push_opcode(OP_CHECKSIG)
+            .into_script();
+        assert_eq!(script_keys(&hashlock_script), vec![xonly_pk]);
+
+        let nofn_xonly_pk = random_xonly_pk();
+        let multisig_script =
+            builder::script::create_musig2_and_operator_multisig_script(nofn_xonly_pk, xonly_pk);
+        assert_eq!(script_keys(&multisig_script), vec![nofn_xonly_pk, xonly_pk]);
+    }
+
+    #[test]
+    fn psbt_signatures_are_applied() {
+        let operator = random_actor();
+        let nofn = random_actor();
+        let mut tx_handler =
+            create_operator_takes_tx(operator.xonly_public_key, nofn.xonly_public_key);
+        let mut expected = tx_handler.clone();
+
+        let mut psbt = tx_handler.to_psbt().unwrap();
+        assert!(tx_handler.apply_psbt_signatures(&psbt).unwrap().is_empty());
+
+        // Bridge fund input is spent with the N-of-N key path and the
+        // slash_or_take input with the operator's timelock script.
+        let key_sig = signature(
+            nofn.sign_with_tweak(
+                Actor::convert_tx_to_sighash_pubkey_spend(&mut tx_handler, 0).unwrap(),
+                None,
+            )
+            .unwrap(),
+        );
+        let script_sig = signature(
+            operator
+                .sign(Actor::convert_tx_to_sighash_script_spend(&mut tx_handler, 1, 0).unwrap()),
+        );
+        let leaf_hash = TapLeafHash::from_script(&tx_handler.scripts[1][0], LeafVersion::TapScript);
+        psbt.inputs[0].tap_key_sig = Some(key_sig);
+        psbt.inputs[1]
+            .tap_script_sigs
+            .insert((operator.xonly_public_key, leaf_hash), script_sig);
+
+        assert_eq!(tx_handler.apply_psbt_signatures(&psbt).unwrap(), vec![0, 1]);
+
+        handle_taproot_witness_new(&mut expected, &[key_sig.to_vec()], 0, None).unwrap();
+        handle_taproot_witness_new(&mut expected, &[script_sig.to_vec()], 1, Some(0)).unwrap();
+        assert_eq!(tx_handler.tx, expected.tx);
+
+        // Signed inputs are exported as final witnesses.
+        let imported = TxHandler::from_psbt(&tx_handler.to_psbt().unwrap()).unwrap();
+        assert_eq!(imported.tx, expected.tx);
+    }
+
+    #[test]
+    fn psbt_invalid_signatures_are_rejected() {
+        let operator = random_actor();
+        let nofn = random_actor();
+        let mut tx_handler =
+            create_operator_takes_tx(operator.xonly_public_key, nofn.xonly_public_key);
+        let leaf_hash = TapLeafHash::from_script(&tx_handler.scripts[1][0], LeafVersion::TapScript);
+
+        // Key path signature without the taproot tweak.
+        let mut psbt = tx_handler.to_psbt().unwrap();
+        psbt.inputs[0].tap_key_sig = Some(signature(
+            nofn.sign(Actor::convert_tx_to_sighash_pubkey_spend(&mut tx_handler, 0).unwrap()),
+        ));
+        assert!(matches!(
+            tx_handler.apply_psbt_signatures(&psbt),
+            Err(BridgeError::PsbtInvalidSignature(0))
+        ));
+
+        // Script path signature of another sighash.
+        let mut psbt = tx_handler.to_psbt().unwrap();
+        psbt.inputs[1].tap_script_sigs.insert(
+            (operator.xonly_public_key, leaf_hash),
+            signature(operator.sign(TapSighash::all_zeros())),
+        );
+        assert!(matches!(
+            tx_handler.apply_psbt_signatures(&psbt),
+            Err(BridgeError::PsbtInvalidSignature(1))
+        ));
+
+        // Nothing is applied.
+        assert!(tx_handler
+            .tx
+            .input
+            .iter()
+            .all(|txin| txin.witness.is_empty()));
+    }
+}
@@ -13,9 +13,14 @@ use bitcoin::{
 };
 use secp256k1::XOnlyPublicKey;
 
+/// Witness script of the anchor outputs, which anyone can spend for fee
+/// bumping.
+pub fn anyone_can_spend_script() -> ScriptBuf {
+    Builder::new().push_opcode(OP_PUSHNUM_1).into_script()
+}
+
 pub fn anyone_can_spend_txout() -> TxOut {
-    let script = Builder::new().push_opcode(OP_PUSHNUM_1).into_script();
-    let script_pubkey = script.to_p2wsh();
+    let script_pubkey = anyone_can_spend_script().to_p2wsh();
     let value = script_pubkey.minimal_non_dust();
 
     TxOut {
@@ -4,15 +4,19 @@
 //! transactions.
 
 use crate::builder;
+use crate::errors::BridgeError;
+use crate::fee::{self, SpendPath};
 use crate::{utils, EVMAddress, UTXO};
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::hashes::Hash;
 use bitcoin::script::PushBytesBuf;
 use bitcoin::Transaction;
 use bitcoin::{
-    absolute, taproot::TaprootSpendInfo, Address, Amount, OutPoint, ScriptBuf, TxIn, TxOut, Witness,
+    absolute, taproot::TaprootSpendInfo, Address, Amount, FeeRate, OutPoint, ScriptBuf, TxIn,
+    TxOut, Txid, Witness,
 };
 use secp256k1::XOnlyPublicKey;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone)]
 pub struct TxHandler {
@@ -22,11 +26,19 @@ pub struct TxHandler {
     pub taproot_spend_infos: Vec<TaprootSpendInfo>,
 }
 
-// TODO: Move these constants to the config file
-pub const MOVE_TX_MIN_RELAY_FEE: Amount = Amount::from_sat(190);
-pub const SLASH_OR_TAKE_TX_MIN_RELAY_FEE: Amount = Amount::from_sat(240);
-pub const OPERATOR_TAKES_TX_MIN_RELAY_FEE: Amount = Amount::from_sat(230);
+/// A deposit that is moved to the bridge by a move_tx.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveTxDeposit {
+    pub deposit_outpoint: OutPoint,
+    pub recovery_taproot_address: Address<NetworkUnchecked>,
+    pub evm_address: EVMAddress,
+    pub bridge_amount_sats: Amount,
+}
+
 pub const KICKOFF_UTXO_AMOUNT_SATS: Amount = Amount::from_sat(100_000);
+/// Upper bound of a CPFP child tx's virtual size: 11 vB overhead, 42 vB anchor
+/// input, 68 vB P2WPKH wallet input and 43 vB P2TR change output.
+pub const CPFP_CHILD_TX_MAX_VSIZE: u64 = 164;
 
 // Transaction Builders --------------------------------------------------------
 
@@ -36,18 +48,152 @@ pub fn create_move_tx(
     nofn_xonly_pk: XOnlyPublicKey,
     bridge_amount_sats: Amount,
     network: bitcoin::Network,
-) -> Transaction {
-    let (musig2_address, _) = builder::address::create_musig2_address(nofn_xonly_pk, network);
+    fee_rate: FeeRate,
+) -> Result<Transaction, BridgeError> {
+    create_batch_move_tx(
+        &[(deposit_outpoint, bridge_amount_sats)],
+        nofn_xonly_pk,
+        network,
+        fee_rate,
+    )
+}
+
+/// Creates a move_tx that moves several deposits at once. Deposit `i` is
+/// spent by input `i` and its bridge funds are locked in output `i`, followed
+/// by a single anchor output. Fee and anchor value are shared equally by the
+/// deposits. A batch of one deposit is the same as [`create_move_tx`].
+///
+/// # Errors
+///
+/// Returns [`BridgeError::EmptyDepositBatch`] if `deposits` is empty and
+/// [`BridgeError::InvalidBridgeAmount`] if a deposit can't pay its share of
+/// the fee.
+pub fn create_batch_move_tx(
+    deposits: &[(OutPoint, Amount)],
+    nofn_xonly_pk: XOnlyPublicKey,
+    network: bitcoin::Network,
+    fee_rate: FeeRate,
+) -> Result<Transaction, BridgeError> {
+    if deposits.is_empty() {
+        return Err(BridgeError::EmptyDepositBatch);
+    }
+    let (musig2_address, _) = builder::address::create_musig2_address(nofn_xonly_pk, network)?;
 
-    let tx_ins = create_tx_ins(vec![deposit_outpoint]);
+    let tx_ins = create_tx_ins(deposits.iter().map(|(outpoint, _)| *outpoint).collect());
 
     let anyone_can_spend_txout = builder::script::anyone_can_spend_txout();
-    let move_txout = TxOut {
-        value: bridge_amount_sats - MOVE_TX_MIN_RELAY_FEE - anyone_can_spend_txout.value,
-        script_pubkey: musig2_address.script_pubkey(),
+    let bridge_amounts_sats = deposits
+        .iter()
+        .map(|(_, amount)| *amount)
+        .collect::<Vec<_>>();
+    let move_tx_cost = anyone_can_spend_txout.value
+        + move_tx_fee(nofn_xonly_pk, &bridge_amounts_sats, network, fee_rate)?;
+    let cost_per_deposit = Amount::from_sat(move_tx_cost.to_sat().div_ceil(deposits.len() as u64));
+
+    let mut tx_outs = bridge_amounts_sats
+        .iter()
+        .map(|bridge_amount_sats| {
+            Ok(TxOut {
+                value: bridge_amount_sats
+                    .checked_sub(cost_per_deposit)
+                    .ok_or(BridgeError::InvalidBridgeAmount(*bridge_amount_sats))?,
+                script_pubkey: musig2_address.script_pubkey(),
+            })
+        })
+        .collect::<Result<Vec<_>, BridgeError>>()?;
+    tx_outs.push(anyone_can_spend_txout);
+
+    Ok(create_btc_tx(tx_ins, tx_outs))
+}
+
+/// Computes the fee of a move_tx that moves deposits of the given amounts.
+/// Size of the move_tx's witnesses doesn't depend on the EVM and recovery
+/// addresses of the deposits, so template deposits are used for the
+/// estimation.
+pub(crate) fn move_tx_fee(
+    nofn_xonly_pk: XOnlyPublicKey,
+    bridge_amounts_sats: &[Amount],
+    network: bitcoin::Network,
+    fee_rate: FeeRate,
+) -> Result<Amount, BridgeError> {
+    let (musig2_address, _) = builder::address::create_musig2_address(nofn_xonly_pk, network)?;
+    let template_evm_address = EVMAddress([0u8; 20]);
+    let template_recovery_address = Address::p2tr(&utils::SECP, nofn_xonly_pk, None, network)
+        .as_unchecked()
+        .clone();
+
+    let mut prevouts = Vec::with_capacity(bridge_amounts_sats.len());
+    let mut scripts = Vec::with_capacity(bridge_amounts_sats.len());
+    let mut taproot_spend_infos = Vec::with_capacity(bridge_amounts_sats.len());
+    let mut tx_outs = Vec::with_capacity(bridge_amounts_sats.len() + 1);
+    for bridge_amount_sats in bridge_amounts_sats {
+        let (deposit_address, deposit_taproot_spend_info) =
+            builder::address::generate_deposit_address(
+                nofn_xonly_pk,
+                &template_recovery_address,
+                template_evm_address,
+                *bridge_amount_sats,
+                network,
+                0,
+            )?;
+
+        prevouts.push(TxOut {
+            script_pubkey: deposit_address.script_pubkey(),
+            value: *bridge_amount_sats,
+        });
+        scripts.push(vec![builder::script::create_deposit_script(
+            nofn_xonly_pk,
+            template_evm_address,
+            *bridge_amount_sats,
+        )]);
+        taproot_spend_infos.push(deposit_taproot_spend_info);
+        tx_outs.push(TxOut {
+            value: *bridge_amount_sats,
+            script_pubkey: musig2_address.script_pubkey(),
+        });
+    }
+    tx_outs.push(builder::script::anyone_can_spend_txout());
+
+    let tx = create_btc_tx(
+        create_tx_ins(vec![OutPoint::null(); bridge_amounts_sats.len()]),
+        tx_outs,
+    );
+    let tx_handler = TxHandler {
+        tx,
+        prevouts,
+        scripts,
+        taproot_spend_infos,
     };
 
-    create_btc_tx(tx_ins, vec![move_txout, anyone_can_spend_txout])
+    fee::estimate_fee(
+        &tx_handler,
+        &vec![SpendPath::ScriptPath(0); bridge_amounts_sats.len()],
+        fee_rate,
+    )
+}
+
+/// Deducts the fee of the transaction from the output at `fee_payer_vout`.
+///
+/// # Errors
+///
+/// Returns [`BridgeError::NotEnoughFundsForFee`] if the output can't pay the
+/// fee or what is left of it is below the dust limit.
+fn deduct_fee(
+    tx_handler: &mut TxHandler,
+    spend_paths: &[SpendPath],
+    fee_rate: FeeRate,
+    fee_payer_vout: usize,
+) -> Result<(), BridgeError> {
+    let fee = fee::estimate_fee(tx_handler, spend_paths, fee_rate)?;
+
+    let fee_payer = &mut tx_handler.tx.output[fee_payer_vout];
+    fee_payer.value = fee_payer
+        .value
+        .checked_sub(fee)
+        .filter(|value| *value >= fee_payer.script_pubkey.minimal_non_dust())
+        .ok_or(BridgeError::NotEnoughFundsForFee)?;
+
+    Ok(())
 }
 
 /// Creates an [`TxHandler`] that includes move_tx to move the deposit.
@@ -59,9 +205,99 @@ pub fn create_move_tx_handler(
     network: bitcoin::Network,
     user_takes_after: u32,
     bridge_amount_sats: Amount,
-) -> TxHandler {
-    let move_tx = create_move_tx(deposit_outpoint, nofn_xonly_pk, bridge_amount_sats, network);
+    fee_rate: FeeRate,
+) -> Result<TxHandler, BridgeError> {
+    create_batch_move_tx_handler(
+        &[MoveTxDeposit {
+            deposit_outpoint,
+            recovery_taproot_address: recovery_taproot_address.clone(),
+            evm_address,
+            bridge_amount_sats,
+        }],
+        nofn_xonly_pk,
+        network,
+        user_takes_after,
+        fee_rate,
+    )
+}
 
+/// Creates an [`TxHandler`] that includes a move_tx that moves every given
+/// deposit, as in [`create_batch_move_tx`]. Every input is spent with its
+/// deposit script.
+///
+/// # Errors
+///
+/// See [`create_batch_move_tx`].
+pub fn create_batch_move_tx_handler(
+    deposits: &[MoveTxDeposit],
+    nofn_xonly_pk: XOnlyPublicKey,
+    network: bitcoin::Network,
+    user_takes_after: u32,
+    fee_rate: FeeRate,
+) -> Result<TxHandler, BridgeError> {
+    let move_tx = create_batch_move_tx(
+        &deposits
+            .iter()
+            .map(|deposit| (deposit.deposit_outpoint, deposit.bridge_amount_sats))
+            .collect::<Vec<_>>(),
+        nofn_xonly_pk,
+        network,
+        fee_rate,
+    )?;
+
+    let mut prevouts = Vec::with_capacity(deposits.len());
+    let mut scripts = Vec::with_capacity(deposits.len());
+    let mut taproot_spend_infos = Vec::with_capacity(deposits.len());
+    for deposit in deposits {
+        let (deposit_address, deposit_taproot_spend_info) =
+            builder::address::generate_deposit_address(
+                nofn_xonly_pk,
+                &deposit.recovery_taproot_address,
+                deposit.evm_address,
+                deposit.bridge_amount_sats,
+                network,
+                user_takes_after,
+            )?;
+
+        prevouts.push(TxOut {
+            script_pubkey: deposit_address.script_pubkey(),
+            value: deposit.bridge_amount_sats,
+        });
+        scripts.push(vec![builder::script::create_deposit_script(
+            nofn_xonly_pk,
+            deposit.evm_address,
+            deposit.bridge_amount_sats,
+        )]);
+        taproot_spend_infos.push(deposit_taproot_spend_info);
+    }
+
+    Ok(TxHandler {
+        tx: move_tx,
+        prevouts,
+        scripts,
+        taproot_spend_infos,
+    })
+}
+
+/// Creates the recovery tx of a deposit, which spends the deposit with the
+/// user's timelock script after `user_takes_after` blocks and sends it to
+/// `destination`. Fee is paid from the deposit.
+///
+/// # Errors
+///
+/// Returns [`BridgeError::InvalidRelativeTimelock`] if `user_takes_after`
+/// doesn't fit in a relative timelock.
+pub fn create_deposit_recovery_tx(
+    deposit_outpoint: OutPoint,
+    evm_address: EVMAddress,
+    recovery_taproot_address: &Address<NetworkUnchecked>,
+    nofn_xonly_pk: XOnlyPublicKey,
+    network: bitcoin::Network,
+    user_takes_after: u32,
+    bridge_amount_sats: Amount,
+    destination: &Address,
+    fee_rate: FeeRate,
+) -> Result<TxHandler, BridgeError> {
     let (deposit_address, deposit_taproot_spend_info) = builder::address::generate_deposit_address(
         nofn_xonly_pk,
         recovery_taproot_address,
@@ -69,61 +305,69 @@ pub fn create_move_tx_handler(
         bridge_amount_sats,
         network,
         user_takes_after,
-    );
+    )?;
+
+    let recovery_xonly_pk = builder::address::extract_taproot_xonly_pk(recovery_taproot_address)?;
+    let scripts = vec![vec![
+        builder::script::create_deposit_script(nofn_xonly_pk, evm_address, bridge_amount_sats),
+        builder::script::generate_relative_timelock_script(recovery_xonly_pk, user_takes_after),
+    ]];
 
+    let ins = create_tx_ins_with_sequence(
+        vec![deposit_outpoint],
+        relative_timelock_height(user_takes_after)?,
+    );
+    let outs = vec![TxOut {
+        value: bridge_amount_sats,
+        script_pubkey: destination.script_pubkey(),
+    }];
+    let tx = create_btc_tx(ins, outs);
     let prevouts = vec![TxOut {
         script_pubkey: deposit_address.script_pubkey(),
         value: bridge_amount_sats,
     }];
-
-    let deposit_script = vec![builder::script::create_deposit_script(
-        nofn_xonly_pk,
-        evm_address,
-        bridge_amount_sats,
-    )];
-
-    TxHandler {
-        tx: move_tx,
+    let mut tx_handler = TxHandler {
+        tx,
         prevouts,
-        scripts: vec![deposit_script],
+        scripts,
         taproot_spend_infos: vec![deposit_taproot_spend_info],
-    }
+    };
+    deduct_fee(&mut tx_handler, &[SpendPath::ScriptPath(1)], fee_rate, 0)?;
+
+    Ok(tx_handler)
 }
 
-/// Creates the kickoff_tx for the operator. It also returns the change utxo
+/// Creates the kickoff_tx for the operator. Change output is at index
+/// `num_kickoff_utxos_per_tx`.
+///
+/// # Errors
+///
+/// Returns [`BridgeError::NotEnoughFundsForFee`] if the funding UTXO can't pay
+/// the kickoff UTXOs and the fee.
 pub fn create_kickoff_utxo_tx(
     funding_utxo: &UTXO, // Make sure this comes from the operator's address.
     nofn_xonly_pk: XOnlyPublicKey,
     operator_xonly_pk: XOnlyPublicKey,
     network: bitcoin::Network,
     num_kickoff_utxos_per_tx: usize,
-) -> TxHandler {
-    // Here, we are calculating the minimum relay fee for the kickoff tx based on the number of kickoff utxos per tx.
-    // The formula is: 154 + 43 * num_kickoff_utxos_per_tx where
-    // 154 = (Signature as witness, 66 bytes + 2 bytes from flags) / 4
-    // + 43 * 2 from change and anyone can spend txouts
-    // + 41 from the single input (32 + 8 + 1)
-    // 4 + 4 + 1 + 1 from locktime, version, and VarInt bases of
-    // the number of inputs and outputs.
-    let kickoff_tx_min_relay_fee = match num_kickoff_utxos_per_tx {
-        0..=250 => 154 + 43 * num_kickoff_utxos_per_tx, // Handles all values from 0 to 250
-        _ => 156 + 43 * num_kickoff_utxos_per_tx,       // Handles all other values
-    };
-
-    //  = 154 + 43 * num_kickoff_utxos_per_tx;
+    fee_rate: FeeRate,
+) -> Result<TxHandler, BridgeError> {
     let tx_ins = create_tx_ins(vec![funding_utxo.outpoint]);
     let musig2_and_operator_script = builder::script::create_musig2_and_operator_multisig_script(
         nofn_xonly_pk,
         operator_xonly_pk,
     );
     let (musig2_and_operator_address, _) =
-        builder::address::create_taproot_address(&[musig2_and_operator_script], None, network);
+        builder::address::create_taproot_address(&[musig2_and_operator_script], None, network)?;
     let operator_address = Address::p2tr(&utils::SECP, operator_xonly_pk, None, network);
-    let change_amount = funding_utxo.txout.value
-        - Amount::from_sat(KICKOFF_UTXO_AMOUNT_SATS.to_sat() * num_kickoff_utxos_per_tx as u64)
-        - builder::script::anyone_can_spend_txout().value
-        - Amount::from_sat(kickoff_tx_min_relay_fee as u64);
-    tracing::debug!("Change amount: {:?}", change_amount);
+    let change_amount = funding_utxo
+        .txout
+        .value
+        .checked_sub(
+            Amount::from_sat(KICKOFF_UTXO_AMOUNT_SATS.to_sat() * num_kickoff_utxos_per_tx as u64)
+                + builder::script::anyone_can_spend_txout().value,
+        )
+        .ok_or(BridgeError::NotEnoughFundsForFee)?;
     let mut tx_outs_raw = vec![
         (
             KICKOFF_UTXO_AMOUNT_SATS,
@@ -142,16 +386,35 @@ pub fn create_kickoff_utxo_tx(
     let prevouts = vec![funding_utxo.txout.clone()];
     let scripts = vec![vec![]];
     let taproot_spend_infos = vec![];
-    TxHandler {
+    let mut tx_handler = TxHandler {
         tx,
         prevouts,
         scripts,
         taproot_spend_infos,
-    }
+    };
+    deduct_fee(
+        &mut tx_handler,
+        &[SpendPath::KeyPath],
+        fee_rate,
+        num_kickoff_utxos_per_tx,
+    )?;
+    tracing::debug!(
+        "Change amount: {:?}",
+        tx_handler.tx.output[num_kickoff_utxos_per_tx].value
+    );
+
+    Ok(tx_handler)
 }
 
+/// Creates the slash_or_take_tx of an operator for the deposit that is moved
+/// by the move_tx with `move_txid`.
+///
+/// # Errors
+///
+/// Returns [`BridgeError::InvalidKickoffUtxo`] if `kickoff_utxo` is not locked
+/// to the operator's kickoff address or can't pay the anchor output.
 pub fn create_slash_or_take_tx(
-    deposit_outpoint: OutPoint,
+    move_txid: Txid,
     kickoff_utxo: UTXO,
     operator_xonly_pk: XOnlyPublicKey,
     operator_idx: usize,
@@ -159,14 +422,10 @@ pub fn create_slash_or_take_tx(
     network: bitcoin::Network,
     _user_takes_after: u32,
     operator_takes_after: u32,
-    bridge_amount_sats: Amount,
-) -> TxHandler {
-    // First recreate the move_tx and move_txid. We can give dummy values for some of the parameters since we are only interested in txid.
-    let move_tx = create_move_tx(deposit_outpoint, nofn_xonly_pk, bridge_amount_sats, network);
-    let move_txid = move_tx.compute_txid();
-
+    fee_rate: FeeRate,
+) -> Result<TxHandler, BridgeError> {
     let (kickoff_utxo_address, kickoff_utxo_spend_info) =
-        builder::address::create_kickoff_address(nofn_xonly_pk, operator_xonly_pk, network);
+        builder::address::create_kickoff_address(nofn_xonly_pk, operator_xonly_pk, network)?;
     // tracing::debug!(
     //     "kickoff_utxo_script_pubkey: {:?}",
     //     kickoff_utxo_address.script_pubkey()
@@ -188,8 +447,10 @@ pub fn create_slash_or_take_tx(
     );
     tracing::debug!("Operator index: {:?}", operator_idx);
     tracing::debug!("Operator xonly pk: {:?}", operator_xonly_pk);
-    tracing::debug!("Deposit OutPoint: {:?}", deposit_outpoint);
-    assert!(kickoff_utxo_address.script_pubkey() == kickoff_utxo.txout.script_pubkey);
+    tracing::debug!("Move txid: {:?}", move_txid);
+    if kickoff_utxo_address.script_pubkey() != kickoff_utxo.txout.script_pubkey {
+        return Err(BridgeError::InvalidKickoffUtxo);
+    }
     let ins = create_tx_ins(vec![kickoff_utxo.outpoint]);
     let relative_timelock_script =
         builder::script::generate_relative_timelock_script(operator_xonly_pk, operator_takes_after);
@@ -197,17 +458,19 @@ pub fn create_slash_or_take_tx(
         &[relative_timelock_script.clone()],
         Some(nofn_xonly_pk),
         network,
-    );
+    )?;
     let mut op_return_script = move_txid.to_byte_array().to_vec();
     op_return_script.extend(utils::usize_to_var_len_bytes(operator_idx));
     let mut push_bytes = PushBytesBuf::new();
-    push_bytes.extend_from_slice(&op_return_script).unwrap();
+    push_bytes.extend_from_slice(&op_return_script)?;
     let op_return_txout = builder::script::op_return_txout(push_bytes);
     let outs = vec![
         TxOut {
-            value: kickoff_utxo.txout.value
-                - Amount::from_sat(330)
-                - SLASH_OR_TAKE_TX_MIN_RELAY_FEE,
+            value: kickoff_utxo
+                .txout
+                .value
+                .checked_sub(builder::script::anyone_can_spend_txout().value)
+                .ok_or(BridgeError::InvalidKickoffUtxo)?,
             script_pubkey: slash_or_take_address.script_pubkey(),
         },
         builder::script::anyone_can_spend_txout(),
@@ -216,34 +479,45 @@ pub fn create_slash_or_take_tx(
     let tx = create_btc_tx(ins, outs);
     let prevouts = vec![kickoff_utxo.txout.clone()];
     let scripts = vec![vec![musig2_and_operator_script]];
-    tracing::debug!("slash_or_take_tx weight: {:?}", tx.weight());
-    TxHandler {
+    let mut tx_handler = TxHandler {
         tx,
         prevouts,
         scripts,
         taproot_spend_infos: vec![kickoff_utxo_spend_info],
-    }
+    };
+    deduct_fee(&mut tx_handler, &[SpendPath::ScriptPath(0)], fee_rate, 0)?;
+    tracing::debug!("slash_or_take_tx weight: {:?}", tx_handler.tx.weight());
+
+    Ok(tx_handler)
 }
 
+/// Creates the operator_takes_tx, which spends the deposit's bridge fund
+/// output of the move_tx and the slash_or_take_tx output of the operator.
+///
+/// # Errors
+///
+/// Returns [`BridgeError::InvalidSlashOrTakeUtxo`] if `slash_or_take_utxo` is
+/// not locked to the operator's slash_or_take address and
+/// [`BridgeError::BitcoinAddressParseError`] if the operator's wallet address
+/// is not on `network`.
 pub fn create_operator_takes_tx(
-    bridge_fund_outpoint: OutPoint,
+    bridge_fund_utxo: UTXO,
     slash_or_take_utxo: UTXO,
     operator_xonly_pk: XOnlyPublicKey,
     nofn_xonly_pk: XOnlyPublicKey,
     network: bitcoin::Network,
     operator_takes_after: u32,
-    bridge_amount_sats: Amount,
     operator_wallet_address: Address<NetworkUnchecked>,
-) -> TxHandler {
-    let operator_wallet_address_checked = operator_wallet_address.require_network(network).unwrap();
-    let mut ins = create_tx_ins(vec![bridge_fund_outpoint]);
+    fee_rate: FeeRate,
+) -> Result<TxHandler, BridgeError> {
+    let operator_wallet_address_checked = operator_wallet_address.require_network(network)?;
+    let mut ins = create_tx_ins(vec![bridge_fund_utxo.outpoint]);
     ins.extend(create_tx_ins_with_sequence(
         vec![slash_or_take_utxo.outpoint],
-        operator_takes_after as u16,
+        relative_timelock_height(operator_takes_after)?,
     ));
 
-    let (musig2_address, musig2_spend_info) =
-        builder::address::create_musig2_address(nofn_xonly_pk, network);
+    let (_, musig2_spend_info) = builder::address::create_musig2_address(nofn_xonly_pk, network)?;
 
     let relative_timelock_script =
         builder::script::generate_relative_timelock_script(operator_xonly_pk, operator_takes_after);
@@ -252,40 +526,236 @@ pub fn create_operator_takes_tx(
             &[relative_timelock_script.clone()],
             Some(nofn_xonly_pk),
             network,
-        );
+        )?;
+
+    if slash_or_take_address.script_pubkey() != slash_or_take_utxo.txout.script_pubkey {
+        return Err(BridgeError::InvalidSlashOrTakeUtxo);
+    }
 
-    // Sanity check TODO: No asserts outside of tests
-    assert!(slash_or_take_address.script_pubkey() == slash_or_take_utxo.txout.script_pubkey);
+    let bridge_fund_txout = bridge_fund_utxo.txout;
 
     let outs = vec![
         TxOut {
-            value: slash_or_take_utxo.txout.value + bridge_amount_sats
-                - MOVE_TX_MIN_RELAY_FEE
-                - OPERATOR_TAKES_TX_MIN_RELAY_FEE
-                - builder::script::anyone_can_spend_txout().value
-                - builder::script::anyone_can_spend_txout().value,
+            value: slash_or_take_utxo
+                .txout
+                .value
+                .checked_add(bridge_fund_txout.value)
+                .and_then(|value| {
+                    value.checked_sub(builder::script::anyone_can_spend_txout().value)
+                })
+                .ok_or(BridgeError::InvalidSlashOrTakeUtxo)?,
             script_pubkey: operator_wallet_address_checked.script_pubkey(),
         },
         builder::script::anyone_can_spend_txout(),
     ];
     let tx = create_btc_tx(ins, outs);
-    let prevouts = vec![
+    let prevouts = vec![bridge_fund_txout, slash_or_take_utxo.txout];
+    let scripts = vec![vec![], vec![relative_timelock_script]];
+    let taproot_spend_infos = vec![musig2_spend_info, slash_or_take_spend_info];
+    let mut tx_handler = TxHandler {
+        tx,
+        prevouts,
+        scripts,
+        taproot_spend_infos,
+    };
+    deduct_fee(
+        &mut tx_handler,
+        &[SpendPath::KeyPath, SpendPath::ScriptPath(0)],
+        fee_rate,
+        0,
+    )?;
+
+    Ok(tx_handler)
+}
+
+/// Creates the burn_tx, which spends the slash_or_take_tx output with the N-of-N
+/// key before the operator's timelock expires. Output value is sent to an
+/// `OP_RETURN`, so the operator can't take the deposit after a dishonest kickoff.
+///
+/// # Errors
+///
+/// Returns [`BridgeError::InvalidSlashOrTakeUtxo`] if `slash_or_take_utxo` is
+/// not locked to the operator's slash_or_take address.
+pub fn create_burn_tx(
+    slash_or_take_utxo: UTXO,
+    operator_xonly_pk: XOnlyPublicKey,
+    nofn_xonly_pk: XOnlyPublicKey,
+    network: bitcoin::Network,
+    operator_takes_after: u32,
+    fee_rate: FeeRate,
+) -> Result<TxHandler, BridgeError> {
+    let ins = create_tx_ins(vec![slash_or_take_utxo.outpoint]);
+
+    let relative_timelock_script =
+        builder::script::generate_relative_timelock_script(operator_xonly_pk, operator_takes_after);
+    let (slash_or_take_address, slash_or_take_spend_info) =
+        builder::address::create_taproot_address(
+            &[relative_timelock_script],
+            Some(nofn_xonly_pk),
+            network,
+        )?;
+
+    if slash_or_take_address.script_pubkey() != slash_or_take_utxo.txout.script_pubkey {
+        return Err(BridgeError::InvalidSlashOrTakeUtxo);
+    }
+
+    let outs = vec![
         TxOut {
-            script_pubkey: musig2_address.script_pubkey(),
-            value: bridge_amount_sats
-                - MOVE_TX_MIN_RELAY_FEE
-                - builder::script::anyone_can_spend_txout().value,
+            value: slash_or_take_utxo
+                .txout
+                .value
+                .checked_sub(builder::script::anyone_can_spend_txout().value)
+                .ok_or(BridgeError::InvalidSlashOrTakeUtxo)?,
+            script_pubkey: builder::script::op_return_txout(PushBytesBuf::new()).script_pubkey,
         },
-        slash_or_take_utxo.txout,
+        builder::script::anyone_can_spend_txout(),
     ];
-    let scripts = vec![vec![], vec![relative_timelock_script]];
-    let taproot_spend_infos = vec![musig2_spend_info, slash_or_take_spend_info];
-    TxHandler {
+    let tx = create_btc_tx(ins, outs);
+    let prevouts = vec![slash_or_take_utxo.txout];
+    let scripts = vec![vec![]];
+    let taproot_spend_infos = vec![slash_or_take_spend_info];
+    let mut tx_handler = TxHandler {
         tx,
         prevouts,
         scripts,
         taproot_spend_infos,
+    };
+    deduct_fee(&mut tx_handler, &[SpendPath::KeyPath], fee_rate, 0)?;
+
+    Ok(tx_handler)
+}
+
+/// Creates the sweep_tx, which moves bridge funds from the N-of-N key of an
+/// old verifier epoch to the N-of-N key of a new one. Bridge fund `i` is spent
+/// by input `i` with the old N-of-N key and locked to the new N-of-N key in
+/// output `i`, followed by a single anchor output. Fee and anchor value are
+/// shared equally by the bridge funds, as in [`create_batch_move_tx`].
+///
+/// # Errors
+///
+/// Returns [`BridgeError::EmptySweep`] if `bridge_fund_utxos` is empty and
+/// [`BridgeError::InvalidBridgeFundUtxo`] if a bridge fund UTXO is given
+/// twice, is not locked to the old N-of-N key or can't pay its share of the
+/// fee.
+pub fn create_sweep_tx_handler(
+    bridge_fund_utxos: &[UTXO],
+    old_nofn_xonly_pk: XOnlyPublicKey,
+    new_nofn_xonly_pk: XOnlyPublicKey,
+    network: bitcoin::Network,
+    fee_rate: FeeRate,
+) -> Result<TxHandler, BridgeError> {
+    if bridge_fund_utxos.is_empty() {
+        return Err(BridgeError::EmptySweep);
+    }
+    let (old_musig2_address, old_musig2_spend_info) =
+        builder::address::create_musig2_address(old_nofn_xonly_pk, network)?;
+    let (new_musig2_address, _) =
+        builder::address::create_musig2_address(new_nofn_xonly_pk, network)?;
+
+    for (i, utxo) in bridge_fund_utxos.iter().enumerate() {
+        if utxo.txout.script_pubkey != old_musig2_address.script_pubkey()
+            || bridge_fund_utxos[..i]
+                .iter()
+                .any(|other| other.outpoint == utxo.outpoint)
+        {
+            return Err(BridgeError::InvalidBridgeFundUtxo(utxo.outpoint));
+        }
+    }
+
+    let ins = create_tx_ins(bridge_fund_utxos.iter().map(|utxo| utxo.outpoint).collect());
+    let anyone_can_spend_txout = builder::script::anyone_can_spend_txout();
+    let mut outs = bridge_fund_utxos
+        .iter()
+        .map(|utxo| TxOut {
+            value: utxo.txout.value,
+            script_pubkey: new_musig2_address.script_pubkey(),
+        })
+        .collect::<Vec<_>>();
+    outs.push(anyone_can_spend_txout.clone());
+
+    let mut tx_handler = TxHandler {
+        tx: create_btc_tx(ins, outs),
+        prevouts: bridge_fund_utxos
+            .iter()
+            .map(|utxo| utxo.txout.clone())
+            .collect(),
+        scripts: vec![vec![]; bridge_fund_utxos.len()],
+        taproot_spend_infos: vec![old_musig2_spend_info; bridge_fund_utxos.len()],
+    };
+
+    let sweep_tx_cost = anyone_can_spend_txout.value
+        + fee::estimate_fee(
+            &tx_handler,
+            &vec![SpendPath::KeyPath; bridge_fund_utxos.len()],
+            fee_rate,
+        )?;
+    let cost_per_bridge_fund = Amount::from_sat(
+        sweep_tx_cost
+            .to_sat()
+            .div_ceil(bridge_fund_utxos.len() as u64),
+    );
+    for (txout, utxo) in tx_handler.tx.output.iter_mut().zip(bridge_fund_utxos) {
+        txout.value = utxo
+            .txout
+            .value
+            .checked_sub(cost_per_bridge_fund)
+            .filter(|value| *value >= txout.script_pubkey.minimal_non_dust())
+            .ok_or(BridgeError::InvalidBridgeFundUtxo(utxo.outpoint))?;
     }
+
+    Ok(tx_handler)
+}
+
+/// Creates a child tx that spends the anchor output of the `parent_tx` and a
+/// wallet UTXO, so that parent and child together pay `fee_rate`. Anchor input
+/// is ready to be broadcasted but wallet input must be signed by the wallet.
+pub fn create_cpfp_child_tx(
+    parent_tx: &Transaction,
+    parent_fee: Amount,
+    wallet_utxo: UTXO,
+    change_script_pubkey: ScriptBuf,
+    fee_rate: FeeRate,
+) -> Result<Transaction, BridgeError> {
+    let anchor_txout = builder::script::anyone_can_spend_txout();
+    let anchor_vout = parent_tx
+        .output
+        .iter()
+        .position(|txout| *txout == anchor_txout)
+        .ok_or(BridgeError::AnchorOutputNotFound)?;
+
+    let package_fee = fee_rate
+        .fee_vb(parent_tx.vsize() as u64 + CPFP_CHILD_TX_MAX_VSIZE)
+        .ok_or(BridgeError::NotEnoughFundsForCpfp)?;
+    let child_min_fee = fee_rate
+        .fee_vb(CPFP_CHILD_TX_MAX_VSIZE)
+        .ok_or(BridgeError::NotEnoughFundsForCpfp)?;
+    let child_fee = package_fee
+        .checked_sub(parent_fee)
+        .unwrap_or(Amount::ZERO)
+        .max(child_min_fee);
+
+    let change_value = (anchor_txout.value + wallet_utxo.txout.value)
+        .checked_sub(child_fee)
+        .filter(|value| *value >= change_script_pubkey.minimal_non_dust())
+        .ok_or(BridgeError::NotEnoughFundsForCpfp)?;
+
+    let ins = create_tx_ins(vec![
+        OutPoint {
+            txid: parent_tx.compute_txid(),
+            vout: anchor_vout as u32,
+        },
+        wallet_utxo.outpoint,
+    ]);
+    let outs = vec![TxOut {
+        value: change_value,
+        script_pubkey: change_script_pubkey,
+    }];
+    let mut tx = create_btc_tx(ins, outs);
+    tx.input[0]
+        .witness
+        .push(builder::script::anyone_can_spend_script().as_bytes());
+
+    Ok(tx)
 }
 
 pub fn create_btc_tx(tx_ins: Vec<TxIn>, tx_outs: Vec<TxOut>) -> bitcoin::Transaction {
@@ -312,6 +782,12 @@ pub fn create_tx_ins(outpoints: Vec<OutPoint>) -> Vec<TxIn> {
     tx_ins
 }
 
+/// Returns the height of a relative timelock of `blocks` blocks. Relative
+/// timelocks have 16 bits, so bigger values would lock for another height.
+pub fn relative_timelock_height(blocks: u32) -> Result<u16, BridgeError> {
+    u16::try_from(blocks).map_err(|_| BridgeError::InvalidRelativeTimelock(blocks))
+}
+
 pub fn create_tx_ins_with_sequence(utxos: Vec<OutPoint>, height: u16) -> Vec<TxIn> {
     let mut tx_ins = Vec::new();
 
@@ -342,8 +818,9 @@ pub fn create_tx_outs(pairs: Vec<(Amount, ScriptBuf)>) -> Vec<TxOut> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{builder, utils::SECP};
-    use bitcoin::{hashes::Hash, Amount, OutPoint, Txid, XOnlyPublicKey};
+    use crate::utils::{handle_taproot_witness_new, SECP};
+    use crate::{builder, errors::BridgeError, EVMAddress, UTXO};
+    use bitcoin::{hashes::Hash, Address, Amount, FeeRate, OutPoint, TxOut, Txid, XOnlyPublicKey};
     use secp256k1::{rand, Keypair, SecretKey};
 
     #[test]
@@ -357,9 +834,16 @@ mod tests {
             XOnlyPublicKey::from_keypair(&Keypair::from_secret_key(&SECP, &secret_key)).0;
         let bridge_amount_sats = Amount::from_sat(0x1F45);
         let network = bitcoin::Network::Regtest;
+        let fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
 
-        let move_tx =
-            super::create_move_tx(deposit_outpoint, nofn_xonly_pk, bridge_amount_sats, network);
+        let move_tx = super::create_move_tx(
+            deposit_outpoint,
+            nofn_xonly_pk,
+            bridge_amount_sats,
+            network,
+            fee_rate,
+        )
+        .unwrap();
 
         assert_eq!(
             move_tx.input.first().unwrap().previous_output,
@@ -368,6 +852,7 @@ mod tests {
         assert_eq!(
             move_tx.output.first().unwrap().script_pubkey,
             builder::address::create_musig2_address(nofn_xonly_pk, network)
+                .unwrap()
                 .0
                 .script_pubkey()
         );
@@ -376,4 +861,589 @@ mod tests {
             builder::script::anyone_can_spend_txout()
         );
     }
+
+    #[test]
+    fn move_tx_fee_matches_signed_move_tx() {
+        let deposit_outpoint = OutPoint {
+            txid: Txid::all_zeros(),
+            vout: 0x45,
+        };
+        let secret_key = SecretKey::new(&mut rand::thread_rng());
+        let nofn_xonly_pk =
+            XOnlyPublicKey::from_keypair(&Keypair::from_secret_key(&SECP, &secret_key)).0;
+        let secret_key = SecretKey::new(&mut rand::thread_rng());
+        let recovery_taproot_address = Address::p2tr(
+            &SECP,
+            XOnlyPublicKey::from_keypair(&Keypair::from_secret_key(&SECP, &secret_key)).0,
+            None,
+            bitcoin::Network::Regtest,
+        );
+        let bridge_amount_sats = Amount::from_sat(100_000_000);
+        let fee_rate = FeeRate::from_sat_per_vb(3).unwrap();
+
+        let mut move_tx_handler = super::create_move_tx_handler(
+            deposit_outpoint,
+            EVMAddress([0x45u8; 20]),
+            recovery_taproot_address.as_unchecked(),
+            nofn_xonly_pk,
+            bitcoin::Network::Regtest,
+            200,
+            bridge_amount_sats,
+            fee_rate,
+        )
+        .unwrap();
+        handle_taproot_witness_new(&mut move_tx_handler, &[[1u8; 64]], 0, Some(0)).unwrap();
+
+        let output_value = move_tx_handler
+            .tx
+            .output
+            .iter()
+            .map(|txout| txout.value)
+            .sum::<Amount>();
+        assert_eq!(
+            bridge_amount_sats - output_value,
+            fee_rate.fee_vb(move_tx_handler.tx.vsize() as u64).unwrap()
+        );
+    }
+
+    #[test]
+    fn batch_move_tx() {
+        let secret_key = SecretKey::new(&mut rand::thread_rng());
+        let nofn_xonly_pk =
+            XOnlyPublicKey::from_keypair(&Keypair::from_secret_key(&SECP, &secret_key)).0;
+        let network = bitcoin::Network::Regtest;
+        let fee_rate = FeeRate::from_sat_per_vb(3).unwrap();
+
+        let deposits = [100_000_000, 10_000_000, 100_000_000]
+            .into_iter()
+            .enumerate()
+            .map(|(i, amount)| {
+                let secret_key = SecretKey::new(&mut rand::thread_rng());
+                let recovery_taproot_address = Address::p2tr(
+                    &SECP,
+                    XOnlyPublicKey::from_keypair(&Keypair::from_secret_key(&SECP, &secret_key)).0,
+                    None,
+                    network,
+                );
+
+                super::MoveTxDeposit {
+                    deposit_outpoint: OutPoint {
+                        txid: Txid::all_zeros(),
+                        vout: i as u32,
+                    },
+                    recovery_taproot_address: recovery_taproot_address.as_unchecked().clone(),
+                    evm_address: EVMAddress([i as u8; 20]),
+                    bridge_amount_sats: Amount::from_sat(amount),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // A batch of one deposit is a regular move_tx.
+        let single_move_tx_handler = super::create_move_tx_handler(
+            deposits[0].deposit_outpoint,
+            deposits[0].evm_address,
+            &deposits[0].recovery_taproot_address,
+            nofn_xonly_pk,
+            network,
+            200,
+            deposits[0].bridge_amount_sats,
+            fee_rate,
+        )
+        .unwrap();
+        let batch_of_one = super::create_batch_move_tx_handler(
+            &deposits[..1],
+            nofn_xonly_pk,
+            network,
+            200,
+            fee_rate,
+        )
+        .unwrap();
+        assert_eq!(batch_of_one.tx, single_move_tx_handler.tx);
+        assert_eq!(batch_of_one.prevouts, single_move_tx_handler.prevouts);
+
+        let mut move_tx_handler =
+            super::create_batch_move_tx_handler(&deposits, nofn_xonly_pk, network, 200, fee_rate)
+                .unwrap();
+        assert_eq!(move_tx_handler.tx.input.len(), deposits.len());
+        assert_eq!(move_tx_handler.tx.output.len(), deposits.len() + 1);
+        assert_eq!(
+            *move_tx_handler.tx.output.last().unwrap(),
+            builder::script::anyone_can_spend_txout()
+        );
+
+        let musig2_script_pubkey = builder::address::create_musig2_address(nofn_xonly_pk, network)
+            .unwrap()
+            .0
+            .script_pubkey();
+        let cost_per_deposit = deposits[0].bridge_amount_sats - move_tx_handler.tx.output[0].value;
+        for (i, deposit) in deposits.iter().enumerate() {
+            assert_eq!(
+                move_tx_handler.tx.input[i].previous_output,
+                deposit.deposit_outpoint
+            );
+            assert_eq!(
+                move_tx_handler.tx.output[i].script_pubkey,
+                musig2_script_pubkey
+            );
+            assert_eq!(
+                deposit.bridge_amount_sats - move_tx_handler.tx.output[i].value,
+                cost_per_deposit
+            );
+        }
+
+        // Every deposit pays less than it would pay with its own move_tx.
+        assert!(
+            cost_per_deposit
+                < deposits[0].bridge_amount_sats - single_move_tx_handler.tx.output[0].value
+        );
+
+        for i in 0..deposits.len() {
+            handle_taproot_witness_new(&mut move_tx_handler, &[[1u8; 64]], i, Some(0)).unwrap();
+        }
+        let input_value = deposits
+            .iter()
+            .map(|deposit| deposit.bridge_amount_sats)
+            .sum::<Amount>();
+        let output_value = move_tx_handler
+            .tx
+            .output
+            .iter()
+            .map(|txout| txout.value)
+            .sum::<Amount>();
+        let fee = fee_rate.fee_vb(move_tx_handler.tx.vsize() as u64).unwrap();
+        // Shares are rounded up, so the deposits can overpay a few satoshis.
+        assert!(input_value - output_value >= fee);
+        assert!(input_value - output_value < fee + Amount::from_sat(deposits.len() as u64));
+    }
+
+    #[test]
+    fn create_deposit_recovery_tx() {
+        let deposit_outpoint = OutPoint {
+            txid: Txid::all_zeros(),
+            vout: 0x45,
+        };
+        let secret_key = SecretKey::new(&mut rand::thread_rng());
+        let nofn_xonly_pk =
+            XOnlyPublicKey::from_keypair(&Keypair::from_secret_key(&SECP, &secret_key)).0;
+        let secret_key = SecretKey::new(&mut rand::thread_rng());
+        let recovery_taproot_address = Address::p2tr(
+            &SECP,
+            XOnlyPublicKey::from_keypair(&Keypair::from_secret_key(&SECP, &secret_key)).0,
+            None,
+            bitcoin::Network::Regtest,
+        );
+        let evm_address = EVMAddress([0x45u8; 20]);
+        let bridge_amount_sats = Amount::from_sat(100_000_000);
+        let user_takes_after = 200;
+        let fee_rate = FeeRate::from_sat_per_vb(3).unwrap();
+
+        let mut tx_handler = super::create_deposit_recovery_tx(
+            deposit_outpoint,
+            evm_address,
+            recovery_taproot_address.as_unchecked(),
+            nofn_xonly_pk,
+            bitcoin::Network::Regtest,
+            user_takes_after,
+            bridge_amount_sats,
+            &recovery_taproot_address,
+            fee_rate,
+        )
+        .unwrap();
+
+        let (deposit_address, _) = builder::address::generate_deposit_address(
+            nofn_xonly_pk,
+            recovery_taproot_address.as_unchecked(),
+            evm_address,
+            bridge_amount_sats,
+            bitcoin::Network::Regtest,
+            user_takes_after,
+        )
+        .unwrap();
+        assert_eq!(
+            tx_handler.prevouts[0].script_pubkey,
+            deposit_address.script_pubkey()
+        );
+        assert_eq!(tx_handler.tx.input[0].previous_output, deposit_outpoint);
+        assert_eq!(
+            tx_handler.tx.input[0].sequence,
+            bitcoin::Sequence::from_height(u16::try_from(user_takes_after).unwrap())
+        );
+
+        handle_taproot_witness_new(&mut tx_handler, &[[1u8; 64]], 0, Some(1)).unwrap();
+        assert_eq!(
+            bridge_amount_sats - tx_handler.tx.output[0].value,
+            fee_rate.fee_vb(tx_handler.tx.vsize() as u64).unwrap()
+        );
+
+        // Timelock doesn't fit in the 16 bits of a sequence.
+        assert!(matches!(
+            super::create_deposit_recovery_tx(
+                deposit_outpoint,
+                evm_address,
+                recovery_taproot_address.as_unchecked(),
+                nofn_xonly_pk,
+                bitcoin::Network::Regtest,
+                u32::from(u16::MAX) + 1,
+                bridge_amount_sats,
+                &recovery_taproot_address,
+                fee_rate,
+            ),
+            Err(BridgeError::InvalidRelativeTimelock(0x10000))
+        ));
+    }
+
+    #[test]
+    fn create_burn_tx() {
+        let secret_key = SecretKey::new(&mut rand::thread_rng());
+        let nofn_xonly_pk =
+            XOnlyPublicKey::from_keypair(&Keypair::from_secret_key(&SECP, &secret_key)).0;
+        let secret_key = SecretKey::new(&mut rand::thread_rng());
+        let operator_xonly_pk =
+            XOnlyPublicKey::from_keypair(&Keypair::from_secret_key(&SECP, &secret_key)).0;
+        let network = bitcoin::Network::Regtest;
+        let operator_takes_after = 5;
+
+        let relative_timelock_script = builder::script::generate_relative_timelock_script(
+            operator_xonly_pk,
+            operator_takes_after,
+        );
+        let (slash_or_take_address, _) = builder::address::create_taproot_address(
+            &[relative_timelock_script],
+            Some(nofn_xonly_pk),
+            network,
+        )
+        .unwrap();
+        let slash_or_take_utxo = UTXO {
+            outpoint: OutPoint {
+                txid: Txid::all_zeros(),
+                vout: 0,
+            },
+            txout: TxOut {
+                value: Amount::from_sat(0x1F45),
+                script_pubkey: slash_or_take_address.script_pubkey(),
+            },
+        };
+
+        let burn_tx = super::create_burn_tx(
+            slash_or_take_utxo.clone(),
+            operator_xonly_pk,
+            nofn_xonly_pk,
+            network,
+            operator_takes_after,
+            FeeRate::from_sat_per_vb(1).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            burn_tx.tx.input.first().unwrap().previous_output,
+            slash_or_take_utxo.outpoint
+        );
+        assert!(burn_tx
+            .tx
+            .output
+            .first()
+            .unwrap()
+            .script_pubkey
+            .is_op_return());
+        assert_eq!(
+            *burn_tx.tx.output.get(1).unwrap(),
+            builder::script::anyone_can_spend_txout()
+        );
+        assert_eq!(burn_tx.prevouts, vec![slash_or_take_utxo.txout]);
+    }
+
+    #[test]
+    fn create_sweep_tx_handler() {
+        let secret_key = SecretKey::new(&mut rand::thread_rng());
+        let old_nofn_xonly_pk =
+            XOnlyPublicKey::from_keypair(&Keypair::from_secret_key(&SECP, &secret_key)).0;
+        let secret_key = SecretKey::new(&mut rand::thread_rng());
+        let new_nofn_xonly_pk =
+            XOnlyPublicKey::from_keypair(&Keypair::from_secret_key(&SECP, &secret_key)).0;
+        let network = bitcoin::Network::Regtest;
+        let fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
+
+        let old_musig2_address =
+            builder::address::create_musig2_address(old_nofn_xonly_pk, network)
+                .unwrap()
+                .0;
+        let bridge_fund_utxos = (0..3)
+            .map(|vout| UTXO {
+                outpoint: OutPoint {
+                    txid: Txid::all_zeros(),
+                    vout,
+                },
+                txout: TxOut {
+                    value: Amount::from_sat(100_000_000),
+                    script_pubkey: old_musig2_address.script_pubkey(),
+                },
+            })
+            .collect::<Vec<_>>();
+
+        let mut sweep_tx = super::create_sweep_tx_handler(
+            &bridge_fund_utxos,
+            old_nofn_xonly_pk,
+            new_nofn_xonly_pk,
+            network,
+            fee_rate,
+        )
+        .unwrap();
+
+        let new_musig2_address =
+            builder::address::create_musig2_address(new_nofn_xonly_pk, network)
+                .unwrap()
+                .0;
+        assert_eq!(sweep_tx.tx.input.len(), 3);
+        assert_eq!(sweep_tx.tx.output.len(), 4);
+        for (i, utxo) in bridge_fund_utxos.iter().enumerate() {
+            assert_eq!(sweep_tx.tx.input[i].previous_output, utxo.outpoint);
+            assert_eq!(
+                sweep_tx.tx.output[i].script_pubkey,
+                new_musig2_address.script_pubkey()
+            );
+            assert_eq!(sweep_tx.tx.output[i].value, sweep_tx.tx.output[0].value);
+        }
+        assert_eq!(
+            sweep_tx.tx.output[3],
+            builder::script::anyone_can_spend_txout()
+        );
+
+        // Bridge funds pay at least the fee of the signed sweep_tx.
+        for i in 0..3 {
+            handle_taproot_witness_new(&mut sweep_tx, &[[1u8; 64]], i, None).unwrap();
+        }
+        let input_value = bridge_fund_utxos
+            .iter()
+            .map(|utxo| utxo.txout.value)
+            .sum::<Amount>();
+        let output_value = sweep_tx
+            .tx
+            .output
+            .iter()
+            .map(|txout| txout.value)
+            .sum::<Amount>();
+        assert!(input_value - output_value >= fee_rate.fee_vb(sweep_tx.tx.vsize() as u64).unwrap());
+
+        assert!(matches!(
+            super::create_sweep_tx_handler(
+                &[],
+                old_nofn_xonly_pk,
+                new_nofn_xonly_pk,
+                network,
+                fee_rate,
+            ),
+            Err(BridgeError::EmptySweep)
+        ));
+        // Bridge funds of the new epoch can't be swept with the old key.
+        assert!(matches!(
+            super::create_sweep_tx_handler(
+                &bridge_fund_utxos,
+                new_nofn_xonly_pk,
+                old_nofn_xonly_pk,
+                network,
+                fee_rate,
+            ),
+            Err(BridgeError::InvalidBridgeFundUtxo(outpoint)) if outpoint == bridge_fund_utxos[0].outpoint
+        ));
+    }
+
+    #[test]
+    fn invalid_inputs_return_errors() {
+        let secret_key = SecretKey::new(&mut rand::thread_rng());
+        let nofn_xonly_pk =
+            XOnlyPublicKey::from_keypair(&Keypair::from_secret_key(&SECP, &secret_key)).0;
+        let secret_key = SecretKey::new(&mut rand::thread_rng());
+        let operator_xonly_pk =
+            XOnlyPublicKey::from_keypair(&Keypair::from_secret_key(&SECP, &secret_key)).0;
+        let network = bitcoin::Network::Regtest;
+        let fee_rate = FeeRate::from_sat_per_vb(1).unwrap();
+
+        assert!(matches!(
+            super::create_batch_move_tx(&[], nofn_xonly_pk, network, fee_rate),
+            Err(BridgeError::EmptyDepositBatch)
+        ));
+
+        // A UTXO that is locked to the N-of-N key is neither a kickoff UTXO
+        // nor a slash_or_take UTXO.
+        let musig2_utxo = UTXO {
+            outpoint: OutPoint {
+                txid: Txid::all_zeros(),
+                vout: 0,
+            },
+            txout: TxOut {
+                value: Amount::from_sat(0x1F45),
+                script_pubkey: builder::address::create_musig2_address(nofn_xonly_pk, network)
+                    .unwrap()
+                    .0
+                    .script_pubkey(),
+            },
+        };
+
+        assert!(matches!(
+            super::create_slash_or_take_tx(
+                Txid::all_zeros(),
+                musig2_utxo.clone(),
+                operator_xonly_pk,
+                0,
+                nofn_xonly_pk,
+                network,
+                200,
+                5,
+                fee_rate,
+            ),
+            Err(BridgeError::InvalidKickoffUtxo)
+        ));
+        assert!(matches!(
+            super::create_burn_tx(
+                musig2_utxo.clone(),
+                operator_xonly_pk,
+                nofn_xonly_pk,
+                network,
+                5,
+                fee_rate,
+            ),
+            Err(BridgeError::InvalidSlashOrTakeUtxo)
+        ));
+        assert!(matches!(
+            super::create_operator_takes_tx(
+                musig2_utxo.clone(),
+                musig2_utxo,
+                operator_xonly_pk,
+                nofn_xonly_pk,
+                network,
+                5,
+                Address::p2tr(&SECP, operator_xonly_pk, None, network)
+                    .as_unchecked()
+                    .clone(),
+                fee_rate,
+            ),
+            Err(BridgeError::InvalidSlashOrTakeUtxo)
+        ));
+
+        // Fee paying outputs can't end up below the dust limit.
+        let operator_address = Address::p2tr(&SECP, operator_xonly_pk, None, network);
+        assert!(matches!(
+            super::create_deposit_recovery_tx(
+                musig2_utxo.outpoint,
+                EVMAddress([0x45u8; 20]),
+                operator_address.as_unchecked(),
+                nofn_xonly_pk,
+                network,
+                200,
+                Amount::from_sat(400),
+                &operator_address,
+                fee_rate,
+            ),
+            Err(BridgeError::NotEnoughFundsForFee)
+        ));
+        let funding_utxo = UTXO {
+            outpoint: musig2_utxo.outpoint,
+            txout: TxOut {
+                value: super::KICKOFF_UTXO_AMOUNT_SATS,
+                script_pubkey: operator_address.script_pubkey(),
+            },
+        };
+        assert!(matches!(
+            super::create_kickoff_utxo_tx(
+                &funding_utxo,
+                nofn_xonly_pk,
+                operator_xonly_pk,
+                network,
+                1,
+                fee_rate,
+            ),
+            Err(BridgeError::NotEnoughFundsForFee)
+        ));
+    }
+
+    #[test]
+    fn create_cpfp_child_tx() {
+        let deposit_outpoint = OutPoint {
+            txid: Txid::all_zeros(),
+            vout: 0x45,
+        };
+        let secret_key = SecretKey::new(&mut rand::thread_rng());
+        let nofn_xonly_pk =
+            XOnlyPublicKey::from_keypair(&Keypair::from_secret_key(&SECP, &secret_key)).0;
+        let network = bitcoin::Network::Regtest;
+        let bridge_amount_sats = Amount::from_sat(100_000_000);
+        let parent_tx = super::create_move_tx(
+            deposit_outpoint,
+            nofn_xonly_pk,
+            bridge_amount_sats,
+            network,
+            FeeRate::from_sat_per_vb(1).unwrap(),
+        )
+        .unwrap();
+        let parent_fee = bridge_amount_sats
+            - parent_tx
+                .output
+                .iter()
+                .map(|txout| txout.value)
+                .sum::<Amount>();
+
+        let change_script_pubkey = builder::address::create_musig2_address(nofn_xonly_pk, network)
+            .unwrap()
+            .0
+            .script_pubkey();
+        let wallet_utxo = UTXO {
+            outpoint: OutPoint {
+                txid: Txid::from_byte_array([1u8; 32]),
+                vout: 0,
+            },
+            txout: TxOut {
+                value: Amount::from_sat(100_000),
+                script_pubkey: change_script_pubkey.clone(),
+            },
+        };
+        let fee_rate = FeeRate::from_sat_per_vb(20).unwrap();
+
+        let child_tx = super::create_cpfp_child_tx(
+            &parent_tx,
+            parent_fee,
+            wallet_utxo.clone(),
+            change_script_pubkey.clone(),
+            fee_rate,
+        )
+        .unwrap();
+
+        assert_eq!(
+            child_tx.input[0].previous_output,
+            OutPoint {
+                txid: parent_tx.compute_txid(),
+                vout: 1,
+            }
+        );
+        assert_eq!(child_tx.input[1].previous_output, wallet_utxo.outpoint);
+        assert_eq!(child_tx.output[0].script_pubkey, change_script_pubkey);
+
+        // Package must pay at least the target fee rate.
+        let child_fee = builder::script::anyone_can_spend_txout().value + wallet_utxo.txout.value
+            - child_tx.output[0].value;
+        assert!(
+            parent_fee + child_fee
+                >= fee_rate
+                    .fee_vb(parent_tx.vsize() as u64 + super::CPFP_CHILD_TX_MAX_VSIZE)
+                    .unwrap()
+        );
+
+        // Wallet UTXO can't pay the fee.
+        let poor_wallet_utxo = UTXO {
+            txout: TxOut {
+                value: Amount::from_sat(1_000),
+                ..wallet_utxo.txout
+            },
+            ..wallet_utxo
+        };
+        assert!(matches!(
+            super::create_cpfp_child_tx(
+                &parent_tx,
+                parent_fee,
+                poor_wallet_utxo,
+                change_script_pubkey,
+                fee_rate,
+            ),
+            Err(BridgeError::NotEnoughFundsForCpfp)
+        ));
+    }
 }
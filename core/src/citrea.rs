@@ -0,0 +1,367 @@
+//! # Citrea
+//!
+//! Typed client for the bridge contract on Citrea. Calls are ABI encoded and
+//! sent to the configured contract address with `eth_call`, deposits are
+//! submitted with `eth_sendTransaction`.
+//!
+//! See: https://gist.github.com/okkothejawa/a9379b02a16dada07a2b85cbbd3c1e80
+
+use crate::config::BridgeConfig;
+use crate::errors::BridgeError;
+use crate::EVMAddress;
+use bitcoin::consensus::encode::serialize;
+use bitcoin::hashes::Hash;
+use bitcoin::{OutPoint, Transaction, Txid};
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use jsonrpsee::rpc_params;
+use serde_json::json;
+
+/// Size of an ABI word.
+pub(crate) const WORD_SIZE: usize = 32;
+
+/// `withdrawalUTXOs(uint256)`
+pub const WITHDRAWAL_UTXOS_SELECTOR: [u8; 4] = [0x47, 0x1b, 0xa1, 0xe3];
+/// `withdrawFillers(uint256)`
+pub const WITHDRAW_FILLERS_SELECTOR: [u8; 4] = [0xc0, 0x45, 0x57, 0x7b];
+/// `utxoToDepositId(bytes32,bytes4)`
+pub const UTXO_TO_DEPOSIT_ID_SELECTOR: [u8; 4] = [0x46, 0xa0, 0x68, 0x05];
+/// `deposit((bytes4,bytes2,bytes,bytes,bytes,bytes4,bytes,uint256,uint256))`
+pub const DEPOSIT_SELECTOR: [u8; 4] = [0xdd, 0x95, 0xc7, 0xc6];
+
+/// Move transaction and its inclusion proof, split into the fields of the
+/// bridge contract's `TransactionParams`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepositTransactionParams {
+    pub version: [u8; 4],
+    pub flag: [u8; 2],
+    pub vin: Vec<u8>,
+    pub vout: Vec<u8>,
+    pub witness: Vec<u8>,
+    pub locktime: [u8; 4],
+    pub intermediate_nodes: Vec<u8>,
+    pub block_height: u64,
+    pub index: u64,
+}
+
+impl DepositTransactionParams {
+    /// Splits the consensus encoding of the move_tx into the contract's
+    /// fields. `intermediate_nodes` is the concatenation of the merkle proof
+    /// hashes of the move_tx at `index` of the block at `block_height`.
+    pub fn new(
+        move_tx: &Transaction,
+        intermediate_nodes: Vec<u8>,
+        block_height: u64,
+        index: u64,
+    ) -> Self {
+        let witness = move_tx
+            .input
+            .iter()
+            .flat_map(|txin| serialize(&txin.witness))
+            .collect();
+
+        DepositTransactionParams {
+            version: move_tx.version.0.to_le_bytes(),
+            flag: [0x00, 0x01],
+            vin: serialize(&move_tx.input),
+            vout: serialize(&move_tx.output),
+            witness,
+            locktime: move_tx.lock_time.to_consensus_u32().to_le_bytes(),
+            intermediate_nodes,
+            block_height,
+            index,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CitreaClient {
+    client: HttpClient,
+    bridge_contract_address: String,
+}
+
+impl CitreaClient {
+    pub fn new(config: &BridgeConfig) -> Result<Self, BridgeError> {
+        let client = HttpClientBuilder::default().build(config.citrea_rpc_url.clone())?;
+
+        Ok(CitreaClient {
+            client,
+            bridge_contract_address: format!(
+                "0x{}",
+                config.bridge_contract_address.trim_start_matches("0x")
+            ),
+        })
+    }
+
+    /// Returns the UTXO that user locked for the withdrawal.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn withdrawal_utxo(&self, withdrawal_idx: u32) -> Result<OutPoint, BridgeError> {
+        let words = self
+            .call(encode_call(
+                WITHDRAWAL_UTXOS_SELECTOR,
+                &[encode_uint(withdrawal_idx as u64)],
+            ))
+            .await?;
+        let [txid, output_id, ..] = words.as_slice() else {
+            return Err(BridgeError::InvalidCitreaResponse(format!("{:?}", words)));
+        };
+
+        // TODO: we should need to reverse this, test this with declareWithdrawalFiller
+        let txid = Txid::from_slice(txid)?;
+        let vout = u32::from_le_bytes([output_id[0], output_id[1], output_id[2], output_id[3]]);
+
+        Ok(OutPoint { txid, vout })
+    }
+
+    /// Returns the index of the operator that filled the withdrawal, or `None`
+    /// if it is not filled yet.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn withdrawal_filler(
+        &self,
+        withdrawal_idx: u32,
+    ) -> Result<Option<usize>, BridgeError> {
+        let words = self
+            .call(encode_call(
+                WITHDRAW_FILLERS_SELECTOR,
+                &[encode_uint(withdrawal_idx as u64)],
+            ))
+            .await?;
+
+        // Fillers are registered with 1 based indexes.
+        Ok(decode_u32(&words)?
+            .checked_sub(1)
+            .map(|operator_idx| operator_idx as usize))
+    }
+
+    /// Returns the index of the deposit whose bridge funds are held by the
+    /// move_tx output at `bridge_fund_outpoint`, or `None` if it is not
+    /// registered on Citrea. Every output of a batched move_tx is registered
+    /// as its own deposit. Withdrawals use the same index as their deposits.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn deposit_idx(
+        &self,
+        bridge_fund_outpoint: OutPoint,
+    ) -> Result<Option<u32>, BridgeError> {
+        let words = self
+            .call(encode_call(
+                UTXO_TO_DEPOSIT_ID_SELECTOR,
+                &[
+                    bridge_fund_outpoint.txid.to_byte_array(),
+                    encode_fixed_bytes(&bridge_fund_outpoint.vout.to_le_bytes()),
+                ],
+            ))
+            .await?;
+
+        // Deposits are registered with 1 based indexes.
+        Ok(decode_u32(&words)?.checked_sub(1))
+    }
+
+    /// Submits the deposit to the bridge contract from the given account.
+    ///
+    /// # Returns
+    ///
+    /// Hash of the Citrea transaction.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn submit_deposit(
+        &self,
+        from: EVMAddress,
+        deposit: &DepositTransactionParams,
+    ) -> Result<String, BridgeError> {
+        let params = rpc_params![json!({
+            "from": format!("0x{}", hex::encode(from.0)),
+            "to": self.bridge_contract_address,
+            "data": format!("0x{}", hex::encode(encode_deposit_call(deposit))),
+        })];
+
+        Ok(self.client.request("eth_sendTransaction", params).await?)
+    }
+
+    async fn call(&self, data: Vec<u8>) -> Result<Vec<[u8; WORD_SIZE]>, BridgeError> {
+        let params = rpc_params![
+            json!({
+                "to": self.bridge_contract_address,
+                "data": format!("0x{}", hex::encode(data)),
+            }),
+            "latest"
+        ];
+        let response: String = self.client.request("eth_call", params).await?;
+
+        decode_words(&response)
+    }
+}
+
+/// ABI encoding of a tuple element.
+enum Token<'a> {
+    Word([u8; WORD_SIZE]),
+    Bytes(&'a [u8]),
+}
+
+pub(crate) fn encode_uint(value: u64) -> [u8; WORD_SIZE] {
+    let mut word = [0u8; WORD_SIZE];
+    word[WORD_SIZE - 8..].copy_from_slice(&value.to_be_bytes());
+
+    word
+}
+
+/// Encodes a `bytesN`, which is left aligned unlike integers.
+pub(crate) fn encode_fixed_bytes(bytes: &[u8]) -> [u8; WORD_SIZE] {
+    let mut word = [0u8; WORD_SIZE];
+    word[..bytes.len()].copy_from_slice(bytes);
+
+    word
+}
+
+fn encode_call(selector: [u8; 4], words: &[[u8; WORD_SIZE]]) -> Vec<u8> {
+    let mut data = selector.to_vec();
+    words.iter().for_each(|word| data.extend_from_slice(word));
+
+    data
+}
+
+/// Encodes a tuple: static elements are in the head, dynamic elements are
+/// appended to the tail and the head holds their offsets.
+fn encode_tuple(tokens: &[Token]) -> Vec<u8> {
+    let mut head = Vec::new();
+    let mut tail = Vec::new();
+
+    for token in tokens {
+        match token {
+            Token::Word(word) => head.extend_from_slice(word),
+            Token::Bytes(bytes) => {
+                head.extend_from_slice(&encode_uint(
+                    (tokens.len() * WORD_SIZE + tail.len()) as u64,
+                ));
+                tail.extend_from_slice(&encode_uint(bytes.len() as u64));
+                tail.extend_from_slice(bytes);
+                tail.resize(tail.len().next_multiple_of(WORD_SIZE), 0);
+            }
+        }
+    }
+
+    head.extend(tail);
+    head
+}
+
+fn encode_deposit_call(deposit: &DepositTransactionParams) -> Vec<u8> {
+    let tuple = encode_tuple(&[
+        Token::Word(encode_fixed_bytes(&deposit.version)),
+        Token::Word(encode_fixed_bytes(&deposit.flag)),
+        Token::Bytes(&deposit.vin),
+        Token::Bytes(&deposit.vout),
+        Token::Bytes(&deposit.witness),
+        Token::Word(encode_fixed_bytes(&deposit.locktime)),
+        Token::Bytes(&deposit.intermediate_nodes),
+        Token::Word(encode_uint(deposit.block_height)),
+        Token::Word(encode_uint(deposit.index)),
+    ]);
+
+    // Tuple is dynamic, so the only argument is its offset.
+    let mut data = encode_call(DEPOSIT_SELECTOR, &[encode_uint(WORD_SIZE as u64)]);
+    data.extend(tuple);
+
+    data
+}
+
+/// Splits a hex encoded `eth_call` response into ABI words.
+fn decode_words(response: &str) -> Result<Vec<[u8; WORD_SIZE]>, BridgeError> {
+    let invalid_response = || BridgeError::InvalidCitreaResponse(response.to_string());
+
+    let bytes = hex::decode(response.trim_start_matches("0x")).map_err(|_| invalid_response())?;
+    if bytes.is_empty() || bytes.len() % WORD_SIZE != 0 {
+        return Err(invalid_response());
+    }
+
+    Ok(bytes
+        .chunks_exact(WORD_SIZE)
+        .map(|chunk| chunk.try_into().expect("Chunks are word sized"))
+        .collect())
+}
+
+/// Reads the first word of a response as a `u32`.
+fn decode_u32(words: &[[u8; WORD_SIZE]]) -> Result<u32, BridgeError> {
+    let word = words
+        .first()
+        .ok_or(BridgeError::InvalidCitreaResponse(format!("{:?}", words)))?;
+    if word[..WORD_SIZE - 4].iter().any(|byte| *byte != 0) {
+        return Err(BridgeError::InvalidCitreaResponse(hex::encode(word)));
+    }
+
+    Ok(u32::from_be_bytes(
+        word[WORD_SIZE - 4..].try_into().expect("Slice is 4 bytes"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode_u32, decode_words, encode_call, encode_deposit_call, encode_uint,
+        DepositTransactionParams, WITHDRAW_FILLERS_SELECTOR,
+    };
+    use crate::errors::BridgeError;
+
+    #[test]
+    fn decode_u32_reads_last_four_bytes() {
+        let response = format!("0x{}{}", "00".repeat(28), "0000002a");
+
+        assert_eq!(decode_u32(&decode_words(&response).unwrap()).unwrap(), 42);
+    }
+
+    #[test]
+    fn decode_rejects_invalid_responses() {
+        assert!(matches!(
+            decode_words("0x2a"),
+            Err(BridgeError::InvalidCitreaResponse(_))
+        ));
+        assert!(matches!(
+            decode_words("0xzz"),
+            Err(BridgeError::InvalidCitreaResponse(_))
+        ));
+
+        let overflowing_response = format!("0x01{}", "00".repeat(31));
+        assert!(matches!(
+            decode_u32(&decode_words(&overflowing_response).unwrap()),
+            Err(BridgeError::InvalidCitreaResponse(_))
+        ));
+    }
+
+    #[test]
+    fn encode_call_pads_arguments() {
+        let data = encode_call(WITHDRAW_FILLERS_SELECTOR, &[encode_uint(5)]);
+
+        assert_eq!(
+            hex::encode(data),
+            "c045577b0000000000000000000000000000000000000000000000000000000000000005"
+        );
+    }
+
+    #[test]
+    fn encode_deposit_call_offsets_dynamic_fields() {
+        let deposit = DepositTransactionParams {
+            version: [2, 0, 0, 0],
+            flag: [0, 1],
+            vin: vec![0x11; 33],
+            vout: vec![0x22; 2],
+            witness: vec![],
+            locktime: [0, 0, 0, 0],
+            intermediate_nodes: vec![0x33; 32],
+            block_height: 100,
+            index: 3,
+        };
+
+        let data = encode_deposit_call(&deposit);
+        let words = decode_words(&hex::encode(&data[4..])).unwrap();
+
+        // Tuple offset, 9 head elements, vin (3 words), vout (2 words),
+        // witness (1 word) and intermediate nodes (2 words).
+        assert_eq!(words.len(), 1 + 9 + 3 + 2 + 1 + 2);
+        assert_eq!(words[0], encode_uint(32));
+        assert_eq!(words[3], encode_uint(9 * 32));
+        assert_eq!(words[4], encode_uint(12 * 32));
+        assert_eq!(words[5], encode_uint(14 * 32));
+        assert_eq!(words[7], encode_uint(15 * 32));
+        assert_eq!(words[8], encode_uint(100));
+        assert_eq!(words[9], encode_uint(3));
+        assert_eq!(words[10], encode_uint(33));
+        assert_eq!(words[15], encode_uint(0));
+    }
+}
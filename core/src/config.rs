@@ -31,6 +31,20 @@ pub struct BridgeConfig {
     pub verifiers_public_keys: Vec<secp256k1::PublicKey>,
     /// Number of verifiers.
     pub num_verifiers: usize,
+    /// Epoch of `verifiers_public_keys`. Every change of the verifier set or
+    /// its N-of-N key, e.g. setting `frost_group_public_key`, starts a new
+    /// epoch.
+    pub verifier_epoch: u32,
+    /// Verifier set of the next epoch. If set, bridge funds of this epoch can
+    /// be swept to the next epoch's N-of-N MuSig2 key.
+    pub next_verifiers_public_keys: Option<Vec<secp256k1::PublicKey>>,
+    /// Threshold of the verifiers' FROST key. If set, verifiers sign with a
+    /// FROST key instead of the N-of-N MuSig2 key, so that any
+    /// `frost_threshold` of them can sign a deposit.
+    pub frost_threshold: Option<u16>,
+    /// Group key of the verifiers' FROST key, which is generated with the
+    /// `frost_dkg` ceremony. If set, it replaces the N-of-N MuSig2 key.
+    pub frost_group_public_key: Option<secp256k1::XOnlyPublicKey>,
     /// Operators x-only public keys.
     pub operators_xonly_pks: Vec<secp256k1::XOnlyPublicKey>,
     /// Operators wallet addresses.
@@ -43,10 +57,23 @@ pub struct BridgeConfig {
     pub user_takes_after: u32,
     /// Number of blocks after which operator can take reimburse the bridge fund if they are honest.
     pub operator_takes_after: u32,
-    /// Bridge amount in satoshis.
-    pub bridge_amount_sats: Amount,
+    /// Allowed bridge amounts (deposit denominations), in satoshis.
+    pub bridge_amounts_sats: Vec<Amount>,
+    /// Fee rate of the pre-signed transactions, in sat/vB. Every party must
+    /// use the same value to sign the same transactions.
+    pub presigned_tx_fee_rate_sat_vb: u64,
+    /// Lower bound of the estimated fee rates, in sat/vB.
+    pub min_fee_rate_sat_vb: u64,
+    /// Upper bound of the estimated fee rates, in sat/vB.
+    pub max_fee_rate_sat_vb: u64,
+    /// Confirmation target of the fee rate estimations, in blocks.
+    pub fee_estimation_conf_target: u16,
     /// Operator: number of kickoff UTXOs per funding transaction.
     pub operator_num_kickoff_utxos_per_tx: usize,
+    /// Verifier: number of MuSig2 nonce pairs that are pre-generated for new
+    /// deposits. If the pool is empty, a deposit's nonces are generated when
+    /// it is requested.
+    pub verifier_nonce_pool_size: usize,
     /// Threshold for confirmation.
     pub confirmation_threshold: u32,
     /// Bitcoin remote procedure call URL.
@@ -55,12 +82,24 @@ pub struct BridgeConfig {
     pub bitcoin_rpc_user: String,
     /// Bitcoin RPC user password.
     pub bitcoin_rpc_password: String,
+    /// Bitcoin RPC URLs that are used in order when the current one is
+    /// unreachable.
+    pub bitcoin_rpc_fallback_urls: Vec<String>,
+    /// Number of times a read-only Bitcoin RPC call is retried after a
+    /// transient failure. Calls that broadcast transactions or mine blocks are
+    /// not retried.
+    pub bitcoin_rpc_max_retries: u32,
+    /// Wait time before the first Bitcoin RPC retry, in milliseconds. It is
+    /// doubled after every retry.
+    pub bitcoin_rpc_retry_backoff_ms: u64,
     /// All Secret keys. Just for testing purposes.
     pub all_verifiers_secret_keys: Option<Vec<secp256k1::SecretKey>>,
     /// All Secret keys. Just for testing purposes.
     pub all_operators_secret_keys: Option<Vec<secp256k1::SecretKey>>,
     /// Verifier endpoints.
     pub verifier_endpoints: Option<Vec<String>>,
+    /// Operator endpoints.
+    pub operator_endpoints: Option<Vec<String>>,
     /// PostgreSQL database host address.
     pub db_host: String,
     /// PostgreSQL database port.
@@ -121,22 +160,35 @@ impl Default for BridgeConfig {
             secret_key: secp256k1::SecretKey::new(&mut secp256k1::rand::thread_rng()),
             verifiers_public_keys: vec![],
             num_verifiers: 7,
+            verifier_epoch: 0,
+            next_verifiers_public_keys: None,
+            frost_threshold: None,
+            frost_group_public_key: None,
             operators_xonly_pks: vec![],
             operator_wallet_addresses: vec![],
             num_operators: 3,
             operator_withdrawal_fee_sats: None,
             user_takes_after: 5,
             operator_takes_after: 5,
-            bridge_amount_sats: Amount::from_sat(100_000_000),
+            bridge_amounts_sats: vec![Amount::from_sat(100_000_000)],
+            presigned_tx_fee_rate_sat_vb: 1,
+            min_fee_rate_sat_vb: 1,
+            max_fee_rate_sat_vb: 100,
+            fee_estimation_conf_target: 6,
             operator_num_kickoff_utxos_per_tx: 10,
+            verifier_nonce_pool_size: 100,
             confirmation_threshold: 1,
             network: Network::Regtest,
             bitcoin_rpc_url: "http://127.0.0.1:18443".to_string(),
             bitcoin_rpc_user: "admin".to_string(),
             bitcoin_rpc_password: "admin".to_string(),
+            bitcoin_rpc_fallback_urls: vec![],
+            bitcoin_rpc_max_retries: 5,
+            bitcoin_rpc_retry_backoff_ms: 200,
             all_verifiers_secret_keys: None,
             all_operators_secret_keys: None,
             verifier_endpoints: None,
+            operator_endpoints: None,
             db_host: "127.0.0.1".to_string(),
             db_port: 5432,
             db_user: "postgres".to_string(),
@@ -4,15 +4,26 @@
 //! directly talks with PostgreSQL. It is expected that PostgreSQL is properly
 //! installed and configured.
 
-use super::wrapper::{AddressDB, EVMAddressDB, OutPointDB, SignatureDB, TxOutDB, TxidDB, Utxodb};
+use super::wrapper::{
+    AddressDB, BlockHashDB, EVMAddressDB, OutPointDB, SignatureDB, TxOutDB, TxidDB, Utxodb,
+};
 use super::Database;
+use crate::aggregator::DepositSession;
+use crate::builder::transaction::MoveTxDeposit;
 use crate::errors::BridgeError;
+use crate::frost::{FrostCommitment, FrostNonce, FrostSignerId};
+use crate::header_indexer::IndexedHeader;
 use crate::musig2::{MuSigAggNonce, MuSigPubNonce, MuSigSecNonce, MuSigSigHash};
 use crate::{EVMAddress, UTXO};
 use bitcoin::address::NetworkUnchecked;
-use bitcoin::{Address, OutPoint, Txid};
-use secp256k1::schnorr;
+use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::{Address, Amount, BlockHash, OutPoint, Txid, Work};
+use frost_secp256k1_tr::keys::{KeyPackage, PublicKeyPackage};
+use secp256k1::{schnorr, PublicKey, XOnlyPublicKey};
 use sqlx::{Postgres, QueryBuilder};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::time::Duration;
 
 impl Database {
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
@@ -160,15 +171,68 @@ impl Database {
         tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
         deposit_outpoint: OutPoint,
         kickoff_utxo: UTXO,
+        amount: Amount,
     ) -> Result<(), BridgeError> {
         let query = sqlx::query(
-            "INSERT INTO operators_kickoff_utxo (deposit_outpoint, kickoff_utxo) VALUES ($1, $2);",
+            "INSERT INTO operators_kickoff_utxo (deposit_outpoint, kickoff_utxo, amount) VALUES ($1, $2, $3);",
         )
         .bind(OutPointDB(deposit_outpoint))
         .bind(sqlx::types::Json(Utxodb {
             outpoint_db: OutPointDB(kickoff_utxo.outpoint),
             txout_db: TxOutDB(kickoff_utxo.txout),
-        }));
+        }))
+        .bind(amount.to_sat() as i64);
+
+        match tx {
+            Some(tx) => query.execute(&mut **tx).await?,
+            None => query.execute(&self.connection).await?,
+        };
+
+        Ok(())
+    }
+
+    /// Operator: Gets the amount of a deposit that the operator has a kickoff
+    /// UTXO for.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_operator_deposit_amount(
+        &self,
+        deposit_outpoint: OutPoint,
+    ) -> Result<Option<Amount>, BridgeError> {
+        let qr: Option<(i64,)> = sqlx::query_as(
+            "SELECT amount FROM operators_kickoff_utxo WHERE deposit_outpoint = $1;",
+        )
+        .bind(OutPointDB(deposit_outpoint))
+        .fetch_optional(&self.connection)
+        .await?;
+
+        Ok(qr.map(|(amount,)| Amount::from_sat(amount as u64)))
+    }
+
+    /// Operator: Saves the deposits of a batched move_tx, in the order of the
+    /// move_tx inputs.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn save_operator_deposit_batch(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoints: &[OutPoint],
+    ) -> Result<(), BridgeError> {
+        let Some(batch_outpoint) = deposit_outpoints.first() else {
+            return Ok(());
+        };
+
+        let mut query = QueryBuilder::new(
+            "INSERT INTO operator_deposit_batches (deposit_outpoint, batch_outpoint, batch_idx) ",
+        );
+        query.push_values(
+            deposit_outpoints.iter().enumerate(),
+            |mut builder, (batch_idx, deposit_outpoint)| {
+                builder
+                    .push_bind(OutPointDB(*deposit_outpoint))
+                    .push_bind(OutPointDB(*batch_outpoint))
+                    .push_bind(batch_idx as i32);
+            },
+        );
+        let query = query.build();
 
         match tx {
             Some(tx) => query.execute(&mut **tx).await?,
@@ -178,6 +242,36 @@ impl Database {
         Ok(())
     }
 
+    /// Operator: Gets the deposits of the batched move_tx that moves the
+    /// deposit, in the order of the move_tx inputs. Returns `None` if the
+    /// deposit is not batched.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_operator_deposit_batch(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+    ) -> Result<Option<Vec<OutPoint>>, BridgeError> {
+        let query = sqlx::query_as(
+            "SELECT deposit_outpoint FROM operator_deposit_batches
+             WHERE batch_outpoint = (SELECT batch_outpoint FROM operator_deposit_batches WHERE deposit_outpoint = $1)
+             ORDER BY batch_idx ASC;",
+        )
+        .bind(OutPointDB(deposit_outpoint));
+
+        let result: Vec<(OutPointDB,)> = match tx {
+            Some(tx) => query.fetch_all(&mut **tx).await?,
+            None => query.fetch_all(&self.connection).await?,
+        };
+
+        if result.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(
+                result.into_iter().map(|(outpoint,)| outpoint.0).collect(),
+            ))
+        }
+    }
+
     /// Operator: Save the signed kickoff UTXO generator tx.
     ///  Txid is the txid of the signed tx.
     /// funding_txid is the txid of the input[0].
@@ -209,14 +303,18 @@ impl Database {
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
     pub async fn get_kickoff_utxos(
         &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
         deposit_outpoint: OutPoint,
     ) -> Result<Option<Vec<UTXO>>, BridgeError> {
-        let qr: Vec<(sqlx::types::Json<Utxodb>,)> = sqlx::query_as(
+        let query = sqlx::query_as(
             "SELECT kickoff_utxo FROM deposit_kickoff_utxos WHERE deposit_outpoint = $1 ORDER BY operator_idx ASC;",
         )
-        .bind(OutPointDB(deposit_outpoint))
-        .fetch_all(&self.connection)
-        .await?;
+        .bind(OutPointDB(deposit_outpoint));
+
+        let qr: Vec<(sqlx::types::Json<Utxodb>,)> = match tx {
+            Some(tx) => query.fetch_all(&mut **tx).await?,
+            None => query.fetch_all(&self.connection).await?,
+        };
         if qr.is_empty() {
             Ok(None)
         } else {
@@ -327,19 +425,37 @@ impl Database {
         Ok(())
     }
 
-    /// Verifier: Save the deposit info to use later
+    /// Verifier: Returns true if a sighash is saved for any nonce of the
+    /// deposit, which means the nonce is signed with.
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub async fn save_deposit_info(
+    pub async fn has_signed_nonces(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+    ) -> Result<bool, BridgeError> {
+        let query = sqlx::query_as(
+            "SELECT EXISTS (SELECT 1 FROM nonces WHERE deposit_outpoint = $1 AND sighash IS NOT NULL);",
+        )
+        .bind(OutPointDB(deposit_outpoint));
+
+        let (has_signed_nonces,): (bool,) = match tx {
+            Some(tx) => query.fetch_one(&mut **tx).await?,
+            None => query.fetch_one(&self.connection).await?,
+        };
+
+        Ok(has_signed_nonces)
+    }
+
+    /// Verifier: Deletes the nonces of a deposit, so that they can be replaced
+    /// with fresh ones.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn delete_nonces(
         &self,
         tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
         deposit_outpoint: OutPoint,
-        recovery_taproot_address: Address<NetworkUnchecked>,
-        evm_address: EVMAddress,
     ) -> Result<(), BridgeError> {
-        let query = sqlx::query("INSERT INTO deposit_infos (deposit_outpoint, recovery_taproot_address, evm_address) VALUES ($1, $2, $3);")
-        .bind(OutPointDB(deposit_outpoint))
-        .bind(AddressDB(recovery_taproot_address))
-        .bind(EVMAddressDB(evm_address));
+        let query = sqlx::query("DELETE FROM nonces WHERE deposit_outpoint = $1;")
+            .bind(OutPointDB(deposit_outpoint));
 
         match tx {
             Some(tx) => query.execute(&mut **tx).await?,
@@ -349,166 +465,881 @@ impl Database {
         Ok(())
     }
 
-    /// Verifier: Get the deposit info to use later
+    /// Verifier: Adds pre-generated nonce pairs to the nonce pool. They are
+    /// not assigned to any deposit until [`Self::assign_pool_nonces`].
+    #[tracing::instrument(skip(self, nonces), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn save_pool_nonces(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        nonces: &[(MuSigSecNonce, MuSigPubNonce)],
+    ) -> Result<(), BridgeError> {
+        if nonces.is_empty() {
+            return Ok(());
+        }
+
+        let mut query = QueryBuilder::new("INSERT INTO nonces (sec_nonce, pub_nonce) ");
+        query.push_values(nonces, |mut builder, (sec_nonce, pub_nonce)| {
+            builder.push_bind(sec_nonce).push_bind(pub_nonce);
+        });
+        let query = query.build();
+
+        match tx {
+            Some(tx) => query.execute(&mut **tx).await?,
+            None => query.execute(&self.connection).await?,
+        };
+
+        Ok(())
+    }
+
+    /// Verifier: Gets the number of nonce pairs in the nonce pool.
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub async fn get_deposit_info(
+    pub async fn get_nonce_pool_size(
         &self,
-        deposit_outpoint: OutPoint,
-    ) -> Result<Option<(Address<NetworkUnchecked>, EVMAddress)>, BridgeError> {
-        let qr: (AddressDB, EVMAddressDB) = sqlx::query_as("SELECT recovery_taproot_address, evm_address FROM deposit_infos WHERE deposit_outpoint = $1;")
-            .bind(OutPointDB(deposit_outpoint))
-            .fetch_one(&self.connection)
-            .await?;
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+    ) -> Result<usize, BridgeError> {
+        let query = sqlx::query_as("SELECT COUNT(*) FROM nonces WHERE deposit_outpoint IS NULL;");
+
+        let result: (i64,) = match tx {
+            Some(tx) => query.fetch_one(&mut **tx).await?,
+            None => query.fetch_one(&self.connection).await?,
+        };
 
-        Ok(Some((qr.0 .0, qr.1 .0)))
+        Ok(result.0 as usize)
     }
 
-    /// Verifier: saves the sighash and returns sec and agg nonces, if the sighash is already there and different, returns error
+    /// Verifier: Assigns `num_nonces` nonce pairs of the nonce pool to the
+    /// deposit, oldest first, and returns their public nonces in index order.
+    /// Returns `None` and assigns nothing if the pool doesn't have enough
+    /// nonces. Nonces that are being assigned by another transaction are
+    /// skipped, so a nonce is never assigned twice.
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub async fn save_sighashes_and_get_nonces(
+    pub async fn assign_pool_nonces(
         &self,
         tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
         deposit_outpoint: OutPoint,
-        index: usize,
-        sighashes: &[MuSigSigHash],
-    ) -> Result<Option<Vec<(MuSigSecNonce, MuSigAggNonce)>>, BridgeError> {
-        // Update the sighashes
-        let mut query = QueryBuilder::new(
-            "WITH updated AS (
+        num_nonces: usize,
+    ) -> Result<Option<Vec<MuSigPubNonce>>, BridgeError> {
+        let query = sqlx::query_as(
+            "WITH pool AS (
+                SELECT id FROM nonces
+                WHERE deposit_outpoint IS NULL
+                ORDER BY id
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            ), assigned AS (
+                SELECT id, (row_number() OVER (ORDER BY id) - 1)::int AS internal_idx
+                FROM pool
+            ), updated AS (
                 UPDATE nonces
-                SET sighash = batch.sighash
-                FROM (",
-        );
-        let query = query.push_values(sighashes.iter().enumerate(), |mut builder, (i, sighash)| {
-            builder.push_bind((index + i) as i32).push_bind(sighash);
-        });
-
-        let query = query
-            .push(
-                ") AS batch (internal_idx, sighash)
-                WHERE nonces.internal_idx = batch.internal_idx AND nonces.deposit_outpoint = ",
+                SET deposit_outpoint = $1, internal_idx = assigned.internal_idx
+                FROM assigned
+                WHERE nonces.id = assigned.id AND (SELECT COUNT(*) FROM assigned) = $2
+                RETURNING nonces.internal_idx, nonces.pub_nonce
             )
-            .push_bind(OutPointDB(deposit_outpoint))
-            .push(
-                " RETURNING nonces.internal_idx, sec_nonce, agg_nonce)
-            SELECT updated.sec_nonce, updated.agg_nonce 
-            FROM updated 
+            SELECT updated.pub_nonce
+            FROM updated
             ORDER BY updated.internal_idx;",
-            )
-            .build_query_as();
+        )
+        .bind(OutPointDB(deposit_outpoint))
+        .bind(num_nonces as i64);
 
-        let result: Result<Vec<(MuSigSecNonce, MuSigAggNonce)>, sqlx::Error> = match tx {
-            Some(tx) => query.fetch_all(&mut **tx).await,
-            None => query.fetch_all(&self.connection).await,
+        let result: Vec<(MuSigPubNonce,)> = match tx {
+            Some(tx) => query.fetch_all(&mut **tx).await?,
+            None => query.fetch_all(&self.connection).await?,
         };
 
-        match result {
-            Ok(nonces) => Ok(Some(nonces)),
-            Err(sqlx::Error::RowNotFound) => Ok(None),
-            Err(e) => Err(BridgeError::DatabaseError(e)),
+        if result.len() != num_nonces {
+            return Ok(None);
         }
+
+        Ok(Some(result.into_iter().map(|(x,)| x).collect()))
     }
 
-    /// Verifier: Save the agg nonces for signing
-    #[tracing::instrument(skip(self, agg_nonces), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub async fn save_agg_nonces(
+    /// Saves the verifier set of an epoch and its N-of-N key.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn save_verifier_epoch(
         &self,
         tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
-        deposit_outpoint: OutPoint,
-        agg_nonces: impl IntoIterator<Item = &MuSigAggNonce>,
+        epoch: u32,
+        verifiers_public_keys: &[PublicKey],
+        nofn_xonly_pk: XOnlyPublicKey,
     ) -> Result<(), BridgeError> {
-        let mut query = QueryBuilder::new(
-            "UPDATE nonces
-             SET agg_nonce = batch.agg_nonce
-             FROM (",
-        );
-
-        let query = query.push_values(
-            agg_nonces.into_iter().enumerate(),
-            |mut builder, (i, agg_nonce)| {
-                builder.push_bind(i as i32).push_bind(agg_nonce);
-            },
-        );
-
-        let query = query
-            .push(
-                ") AS batch (internal_idx, agg_nonce)
-             WHERE nonces.internal_idx = batch.internal_idx AND nonces.deposit_outpoint = ",
-            )
-            .push_bind(OutPointDB(deposit_outpoint))
-            .build();
+        let query = sqlx::query(
+            "INSERT INTO verifier_epochs (epoch, verifiers_public_keys, nofn_xonly_pk) VALUES ($1, $2, $3);",
+        )
+        .bind(epoch as i32)
+        .bind(sqlx::types::Json(verifiers_public_keys))
+        .bind(nofn_xonly_pk.to_string());
 
         match tx {
             Some(tx) => query.execute(&mut **tx).await?,
             None => query.execute(&self.connection).await?,
         };
+
         Ok(())
     }
 
-    #[tracing::instrument(skip(self, slash_or_take_sigs), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub async fn save_slash_or_take_sigs(
+    /// Gets the verifier set of an epoch and its N-of-N key, if the epoch is
+    /// saved.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_verifier_epoch(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        epoch: u32,
+    ) -> Result<Option<(Vec<PublicKey>, XOnlyPublicKey)>, BridgeError> {
+        let query = sqlx::query_as(
+            "SELECT verifiers_public_keys, nofn_xonly_pk FROM verifier_epochs WHERE epoch = $1;",
+        )
+        .bind(epoch as i32);
+
+        let result: Option<(sqlx::types::Json<Vec<PublicKey>>, String)> = match tx {
+            Some(tx) => query.fetch_optional(&mut **tx).await?,
+            None => query.fetch_optional(&self.connection).await?,
+        };
+
+        match result {
+            Some((verifiers_public_keys, nofn_xonly_pk)) => Ok(Some((
+                verifiers_public_keys.0,
+                XOnlyPublicKey::from_str(&nofn_xonly_pk)?,
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    /// Verifier: Save the deposit info to use later, with the hash of the
+    /// block the deposit is confirmed in and the verifier epoch it is signed
+    /// under.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn save_deposit_info(
         &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
         deposit_outpoint: OutPoint,
-        slash_or_take_sigs: impl IntoIterator<Item = schnorr::Signature>,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        amount: Amount,
+        block_hash: BlockHash,
+        epoch: u32,
     ) -> Result<(), BridgeError> {
-        QueryBuilder::new(
-            "UPDATE deposit_kickoff_utxos
-             SET slash_or_take_sig = batch.sig
-             FROM (",
-        )
-        .push_values(
-            slash_or_take_sigs.into_iter().enumerate(),
-            |mut builder, (i, slash_or_take_sig)| {
-                builder
-                    .push_bind(i as i32)
-                    .push_bind(SignatureDB(slash_or_take_sig));
-            },
-        )
-        .push(
-            ") AS batch (operator_idx, sig)
-             WHERE deposit_kickoff_utxos.deposit_outpoint = ",
-        )
-        .push_bind(OutPointDB(deposit_outpoint))
-        .push(" AND deposit_kickoff_utxos.operator_idx = batch.operator_idx;")
-        .build()
-        .execute(&self.connection)
-        .await?;
+        let query = sqlx::query("INSERT INTO deposit_infos (deposit_outpoint, recovery_taproot_address, evm_address, amount, block_hash, epoch) VALUES ($1, $2, $3, $4, $5, $6);")
+        .bind(OutPointDB(deposit_outpoint))
+        .bind(AddressDB(recovery_taproot_address))
+        .bind(EVMAddressDB(evm_address))
+        .bind(amount.to_sat() as i64)
+        .bind(BlockHashDB(block_hash))
+        .bind(epoch as i32);
+
+        match tx {
+            Some(tx) => query.execute(&mut **tx).await?,
+            None => query.execute(&self.connection).await?,
+        };
 
         Ok(())
     }
 
+    /// Verifier: Get the deposit info to use later
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub async fn get_slash_or_take_sig(
+    pub async fn get_deposit_info(
         &self,
         deposit_outpoint: OutPoint,
-        kickoff_utxo: UTXO,
-    ) -> Result<Option<schnorr::Signature>, BridgeError> {
-        let qr: Option<(SignatureDB,)> = sqlx::query_as(
-            "SELECT slash_or_take_sig
-             FROM deposit_kickoff_utxos
-             WHERE deposit_outpoint = $1 AND kickoff_utxo = $2;",
-        )
-        .bind(OutPointDB(deposit_outpoint))
-        .bind(sqlx::types::Json(Utxodb {
-            outpoint_db: OutPointDB(kickoff_utxo.outpoint),
-            txout_db: TxOutDB(kickoff_utxo.txout),
-        }))
-        .fetch_optional(&self.connection)
-        .await?;
+    ) -> Result<Option<(Address<NetworkUnchecked>, EVMAddress, Amount)>, BridgeError> {
+        let qr: (AddressDB, EVMAddressDB, i64) = sqlx::query_as("SELECT recovery_taproot_address, evm_address, amount FROM deposit_infos WHERE deposit_outpoint = $1;")
+            .bind(OutPointDB(deposit_outpoint))
+            .fetch_one(&self.connection)
+            .await?;
 
-        match qr {
-            Some(sig) => Ok(Some(sig.0 .0)),
-            None => Ok(None),
-        }
+        Ok(Some((qr.0 .0, qr.1 .0, Amount::from_sat(qr.2 as u64))))
     }
 
-    #[tracing::instrument(skip(self, kickoff_utxos_and_sigs), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub async fn save_operator_take_sigs(
+    /// Verifier: Gets the verifier epoch a deposit is signed under, if the
+    /// deposit is saved.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_deposit_epoch(
         &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
         deposit_outpoint: OutPoint,
-        kickoff_utxos_and_sigs: impl IntoIterator<Item = (UTXO, schnorr::Signature)>,
-    ) -> Result<(), BridgeError> {
-        QueryBuilder::new(
+    ) -> Result<Option<u32>, BridgeError> {
+        let query = sqlx::query_as("SELECT epoch FROM deposit_infos WHERE deposit_outpoint = $1;")
+            .bind(OutPointDB(deposit_outpoint));
+
+        let result: Option<(i32,)> = match tx {
+            Some(tx) => query.fetch_optional(&mut **tx).await?,
+            None => query.fetch_optional(&self.connection).await?,
+        };
+
+        Ok(result.map(|(epoch,)| epoch as u32))
+    }
+
+    /// Verifier: Gets every deposit with the hash of the block it is confirmed
+    /// in and whether it is invalidated by a reorg.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_deposit_confirmations(
+        &self,
+    ) -> Result<Vec<(OutPoint, BlockHash, bool)>, BridgeError> {
+        let qr: Vec<(OutPointDB, BlockHashDB, bool)> =
+            sqlx::query_as("SELECT deposit_outpoint, block_hash, invalidated FROM deposit_infos;")
+                .fetch_all(&self.connection)
+                .await?;
+
+        Ok(qr
+            .into_iter()
+            .map(|(outpoint, block_hash, invalidated)| (outpoint.0, block_hash.0, invalidated))
+            .collect())
+    }
+
+    /// Verifier: Marks a deposit as invalidated, because the block it is
+    /// confirmed in is reorged out.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn set_deposit_invalidated(
+        &self,
+        deposit_outpoint: OutPoint,
+    ) -> Result<(), BridgeError> {
+        sqlx::query("UPDATE deposit_infos SET invalidated = true WHERE deposit_outpoint = $1;")
+            .bind(OutPointDB(deposit_outpoint))
+            .execute(&self.connection)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Verifier: Saves the block a deposit is confirmed in and clears its
+    /// invalidation.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn set_deposit_confirmed(
+        &self,
+        deposit_outpoint: OutPoint,
+        block_hash: BlockHash,
+    ) -> Result<(), BridgeError> {
+        sqlx::query(
+            "UPDATE deposit_infos
+             SET block_hash = $2, invalidated = false
+             WHERE deposit_outpoint = $1;",
+        )
+        .bind(OutPointDB(deposit_outpoint))
+        .bind(BlockHashDB(block_hash))
+        .execute(&self.connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Verifier: Returns true if the deposit is invalidated by a reorg and not
+    /// confirmed again yet. Deposit infos row is locked until the end of the
+    /// transaction, so that the signing rounds of a deposit that change its
+    /// state are run one at a time.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn is_deposit_invalidated(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+    ) -> Result<bool, BridgeError> {
+        let query = sqlx::query_as(
+            "SELECT invalidated FROM deposit_infos WHERE deposit_outpoint = $1 FOR UPDATE;",
+        )
+        .bind(OutPointDB(deposit_outpoint));
+
+        let qr: Option<(bool,)> = match tx {
+            Some(tx) => query.fetch_optional(&mut **tx).await?,
+            None => query.fetch_optional(&self.connection).await?,
+        };
+
+        match qr {
+            Some((invalidated,)) => Ok(invalidated),
+            None => Err(BridgeError::DepositInfoNotFound),
+        }
+    }
+
+    /// Verifier: Saves the deposits of a batched move_tx, in the order of the
+    /// move_tx inputs.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn save_deposit_batch(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoints: &[OutPoint],
+    ) -> Result<(), BridgeError> {
+        let Some(batch_outpoint) = deposit_outpoints.first() else {
+            return Ok(());
+        };
+
+        let mut query = QueryBuilder::new(
+            "INSERT INTO deposit_batches (deposit_outpoint, batch_outpoint, batch_idx) ",
+        );
+        query.push_values(
+            deposit_outpoints.iter().enumerate(),
+            |mut builder, (batch_idx, deposit_outpoint)| {
+                builder
+                    .push_bind(OutPointDB(*deposit_outpoint))
+                    .push_bind(OutPointDB(*batch_outpoint))
+                    .push_bind(batch_idx as i32);
+            },
+        );
+        let query = query.build();
+
+        match tx {
+            Some(tx) => query.execute(&mut **tx).await?,
+            None => query.execute(&self.connection).await?,
+        };
+
+        Ok(())
+    }
+
+    /// Verifier: Gets the deposits of the batched move_tx that moves the
+    /// deposit, in the order of the move_tx inputs. Returns `None` if the
+    /// deposit is not batched.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_deposit_batch(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+    ) -> Result<Option<Vec<OutPoint>>, BridgeError> {
+        let query = sqlx::query_as(
+            "SELECT deposit_outpoint FROM deposit_batches
+             WHERE batch_outpoint = (SELECT batch_outpoint FROM deposit_batches WHERE deposit_outpoint = $1)
+             ORDER BY batch_idx ASC;",
+        )
+        .bind(OutPointDB(deposit_outpoint));
+
+        let result: Vec<(OutPointDB,)> = match tx {
+            Some(tx) => query.fetch_all(&mut **tx).await?,
+            None => query.fetch_all(&self.connection).await?,
+        };
+
+        if result.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(
+                result.into_iter().map(|(outpoint,)| outpoint.0).collect(),
+            ))
+        }
+    }
+
+    /// Verifier: Gets the deposits that are moved by the move_tx of the
+    /// deposit, in the order of the move_tx inputs. A deposit that is not
+    /// batched is the only deposit of its move_tx.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_move_tx_deposits(
+        &self,
+        deposit_outpoint: OutPoint,
+    ) -> Result<Vec<MoveTxDeposit>, BridgeError> {
+        let deposit_outpoints = self
+            .get_deposit_batch(None, deposit_outpoint)
+            .await?
+            .unwrap_or_else(|| vec![deposit_outpoint]);
+
+        let mut deposits = Vec::with_capacity(deposit_outpoints.len());
+        for deposit_outpoint in deposit_outpoints {
+            let (recovery_taproot_address, evm_address, bridge_amount_sats) = self
+                .get_deposit_info(deposit_outpoint)
+                .await?
+                .ok_or(BridgeError::DepositInfoNotFound)?;
+
+            deposits.push(MoveTxDeposit {
+                deposit_outpoint,
+                recovery_taproot_address,
+                evm_address,
+                bridge_amount_sats,
+            });
+        }
+
+        Ok(deposits)
+    }
+
+    /// Verifier: saves the sighash and returns sec and agg nonces, if the sighash is already there and different, returns error
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn save_sighashes_and_get_nonces(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+        index: usize,
+        sighashes: &[MuSigSigHash],
+    ) -> Result<Option<Vec<(MuSigSecNonce, MuSigAggNonce)>>, BridgeError> {
+        // Update the sighashes
+        let mut query = QueryBuilder::new(
+            "WITH updated AS (
+                UPDATE nonces
+                SET sighash = batch.sighash
+                FROM (",
+        );
+        let query = query.push_values(sighashes.iter().enumerate(), |mut builder, (i, sighash)| {
+            builder.push_bind((index + i) as i32).push_bind(sighash);
+        });
+
+        let query = query
+            .push(
+                ") AS batch (internal_idx, sighash)
+                WHERE nonces.internal_idx = batch.internal_idx AND nonces.deposit_outpoint = ",
+            )
+            .push_bind(OutPointDB(deposit_outpoint))
+            .push(
+                " RETURNING nonces.internal_idx, sec_nonce, agg_nonce)
+            SELECT updated.sec_nonce, updated.agg_nonce 
+            FROM updated 
+            ORDER BY updated.internal_idx;",
+            )
+            .build_query_as();
+
+        let result: Result<Vec<(MuSigSecNonce, MuSigAggNonce)>, sqlx::Error> = match tx {
+            Some(tx) => query.fetch_all(&mut **tx).await,
+            None => query.fetch_all(&self.connection).await,
+        };
+
+        match result {
+            Ok(nonces) => Ok(Some(nonces)),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(BridgeError::DatabaseError(e)),
+        }
+    }
+
+    /// Verifier: Save the agg nonces for signing
+    #[tracing::instrument(skip(self, agg_nonces), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn save_agg_nonces(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+        agg_nonces: impl IntoIterator<Item = &MuSigAggNonce>,
+    ) -> Result<(), BridgeError> {
+        let mut query = QueryBuilder::new(
+            "UPDATE nonces
+             SET agg_nonce = batch.agg_nonce
+             FROM (",
+        );
+
+        let query = query.push_values(
+            agg_nonces.into_iter().enumerate(),
+            |mut builder, (i, agg_nonce)| {
+                builder.push_bind(i as i32).push_bind(agg_nonce);
+            },
+        );
+
+        let query = query
+            .push(
+                ") AS batch (internal_idx, agg_nonce)
+             WHERE nonces.internal_idx = batch.internal_idx AND nonces.deposit_outpoint = ",
+            )
+            .push_bind(OutPointDB(deposit_outpoint))
+            .build();
+
+        match tx {
+            Some(tx) => query.execute(&mut **tx).await?,
+            None => query.execute(&self.connection).await?,
+        };
+        Ok(())
+    }
+
+    /// Verifier: Gets the public nonce that signs the deposit's bridge fund
+    /// input of the sweep_tx to `new_epoch`, if there is one.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_sweep_pub_nonce(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+        new_epoch: u32,
+    ) -> Result<Option<MuSigPubNonce>, BridgeError> {
+        let query = sqlx::query_as(
+            "SELECT pub_nonce FROM sweep_nonces WHERE deposit_outpoint = $1 AND new_epoch = $2;",
+        )
+        .bind(OutPointDB(deposit_outpoint))
+        .bind(new_epoch as i32);
+
+        let result: Option<(MuSigPubNonce,)> = match tx {
+            Some(tx) => query.fetch_optional(&mut **tx).await?,
+            None => query.fetch_optional(&self.connection).await?,
+        };
+
+        Ok(result.map(|(pub_nonce,)| pub_nonce))
+    }
+
+    /// Verifier: Saves the nonce that signs the deposit's bridge fund input of
+    /// the sweep_tx to `new_epoch`.
+    #[tracing::instrument(skip(self, sec_nonce), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn save_sweep_nonce(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+        new_epoch: u32,
+        sec_nonce: MuSigSecNonce,
+        pub_nonce: MuSigPubNonce,
+    ) -> Result<(), BridgeError> {
+        let query = sqlx::query(
+            "INSERT INTO sweep_nonces (deposit_outpoint, new_epoch, sec_nonce, pub_nonce) VALUES ($1, $2, $3, $4);",
+        )
+        .bind(OutPointDB(deposit_outpoint))
+        .bind(new_epoch as i32)
+        .bind(sec_nonce)
+        .bind(pub_nonce);
+
+        match tx {
+            Some(tx) => query.execute(&mut **tx).await?,
+            None => query.execute(&self.connection).await?,
+        };
+
+        Ok(())
+    }
+
+    /// Verifier: Saves the agg nonce and the sighash of the deposit's bridge
+    /// fund input of the sweep_tx to `new_epoch` and returns the sec nonce.
+    /// Returns `None` if there is no nonce, or it is saved with another agg
+    /// nonce or sighash.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR))]
+    pub async fn save_sweep_sighash_and_get_sec_nonce(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+        new_epoch: u32,
+        agg_nonce: MuSigAggNonce,
+        sighash: MuSigSigHash,
+    ) -> Result<Option<MuSigSecNonce>, BridgeError> {
+        let query = sqlx::query_as(
+            "UPDATE sweep_nonces
+             SET agg_nonce = $3, sighash = $4
+             WHERE deposit_outpoint = $1 AND new_epoch = $2
+                AND (agg_nonce IS NULL OR agg_nonce = $3)
+                AND (sighash IS NULL OR sighash = $4)
+             RETURNING sec_nonce;",
+        )
+        .bind(OutPointDB(deposit_outpoint))
+        .bind(new_epoch as i32)
+        .bind(agg_nonce)
+        .bind(sighash);
+
+        let result: Option<(MuSigSecNonce,)> = match tx {
+            Some(tx) => query.fetch_optional(&mut **tx).await?,
+            None => query.fetch_optional(&self.connection).await?,
+        };
+
+        Ok(result.map(|(sec_nonce,)| sec_nonce))
+    }
+
+    /// Verifier: saves the sighashes and returns the sec nonces of a deposit
+    /// that is signed with the FROST key. FROST nonces don't have agg nonces,
+    /// otherwise it is the same as [`Self::save_sighashes_and_get_nonces`].
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR))]
+    pub async fn save_sighashes_and_get_sec_nonces(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+        index: usize,
+        sighashes: &[MuSigSigHash],
+    ) -> Result<Option<Vec<FrostNonce>>, BridgeError> {
+        let mut query = QueryBuilder::new(
+            "WITH updated AS (
+                UPDATE nonces
+                SET sighash = batch.sighash
+                FROM (",
+        );
+        let query = query.push_values(sighashes.iter().enumerate(), |mut builder, (i, sighash)| {
+            builder.push_bind((index + i) as i32).push_bind(sighash);
+        });
+
+        let query = query
+            .push(
+                ") AS batch (internal_idx, sighash)
+                WHERE nonces.internal_idx = batch.internal_idx AND nonces.deposit_outpoint = ",
+            )
+            .push_bind(OutPointDB(deposit_outpoint))
+            .push(
+                " RETURNING nonces.internal_idx, sec_nonce)
+            SELECT updated.sec_nonce
+            FROM updated
+            ORDER BY updated.internal_idx;",
+            )
+            .build_query_as();
+
+        let result: Result<Vec<(FrostNonce,)>, sqlx::Error> = match tx {
+            Some(tx) => query.fetch_all(&mut **tx).await,
+            None => query.fetch_all(&self.connection).await,
+        };
+
+        match result {
+            Ok(nonces) => Ok(Some(nonces.into_iter().map(|(x,)| x).collect())),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(BridgeError::DatabaseError(e)),
+        }
+    }
+
+    /// Verifier: Saves the verifiers that sign a deposit with the FROST key
+    /// and their commitments, in nonce order.
+    #[tracing::instrument(skip(self, signing_commitments), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn save_frost_signing_commitments(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+        signing_commitments: &BTreeMap<FrostSignerId, Vec<FrostCommitment>>,
+    ) -> Result<(), BridgeError> {
+        let mut query = QueryBuilder::new(
+            "INSERT INTO frost_signing_commitments (deposit_outpoint, signer_id, commitments) ",
+        );
+        query.push_values(
+            signing_commitments.iter(),
+            |mut builder, (signer_id, commitments)| {
+                builder
+                    .push_bind(OutPointDB(deposit_outpoint))
+                    .push_bind(i32::from(*signer_id))
+                    .push_bind(sqlx::types::Json(commitments.clone()));
+            },
+        );
+        let query = query.build();
+
+        match tx {
+            Some(tx) => query.execute(&mut **tx).await?,
+            None => query.execute(&self.connection).await?,
+        };
+
+        Ok(())
+    }
+
+    /// Verifier: Gets the verifiers that sign a deposit with the FROST key and
+    /// their commitments, if they are saved.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_frost_signing_commitments(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+    ) -> Result<Option<BTreeMap<FrostSignerId, Vec<FrostCommitment>>>, BridgeError> {
+        let query = sqlx::query_as(
+            "SELECT signer_id, commitments FROM frost_signing_commitments WHERE deposit_outpoint = $1;",
+        )
+        .bind(OutPointDB(deposit_outpoint));
+
+        let result: Vec<(i32, sqlx::types::Json<Vec<FrostCommitment>>)> = match tx {
+            Some(tx) => query.fetch_all(&mut **tx).await?,
+            None => query.fetch_all(&self.connection).await?,
+        };
+        if result.is_empty() {
+            return Ok(None);
+        }
+
+        result
+            .into_iter()
+            .map(|(signer_id, commitments)| {
+                let signer_id = FrostSignerId::try_from(signer_id)
+                    .map_err(|_| BridgeError::InvalidFrostSigningSet)?;
+
+                Ok((signer_id, commitments.0))
+            })
+            .collect::<Result<BTreeMap<_, _>, BridgeError>>()
+            .map(Some)
+    }
+
+    /// Verifier: Saves the verifier's key package and the group's public key
+    /// package, which are generated by the DKG ceremony.
+    #[tracing::instrument(skip(self, key_package, public_key_package), err(level = tracing::Level::ERROR))]
+    pub async fn save_frost_key_package(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        key_package: &KeyPackage,
+        public_key_package: &PublicKeyPackage,
+    ) -> Result<(), BridgeError> {
+        let query = sqlx::query(
+            "INSERT INTO frost_key_packages (key_package, public_key_package) VALUES ($1, $2);",
+        )
+        .bind(key_package.serialize()?)
+        .bind(public_key_package.serialize()?);
+
+        match tx {
+            Some(tx) => query.execute(&mut **tx).await?,
+            None => query.execute(&self.connection).await?,
+        };
+
+        Ok(())
+    }
+
+    /// Verifier: Gets the verifier's key package and the group's public key
+    /// package, if the DKG ceremony is completed.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR))]
+    pub async fn get_frost_key_package(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+    ) -> Result<Option<(KeyPackage, PublicKeyPackage)>, BridgeError> {
+        let query =
+            sqlx::query_as("SELECT key_package, public_key_package FROM frost_key_packages;");
+
+        let result: Option<(Vec<u8>, Vec<u8>)> = match tx {
+            Some(tx) => query.fetch_optional(&mut **tx).await?,
+            None => query.fetch_optional(&self.connection).await?,
+        };
+
+        result
+            .map(|(key_package, public_key_package)| {
+                Ok((
+                    KeyPackage::deserialize(&key_package)?,
+                    PublicKeyPackage::deserialize(&public_key_package)?,
+                ))
+            })
+            .transpose()
+    }
+
+    #[tracing::instrument(skip(self, slash_or_take_sigs), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn save_slash_or_take_sigs(
+        &self,
+        deposit_outpoint: OutPoint,
+        slash_or_take_sigs: impl IntoIterator<Item = schnorr::Signature>,
+    ) -> Result<(), BridgeError> {
+        QueryBuilder::new(
+            "UPDATE deposit_kickoff_utxos
+             SET slash_or_take_sig = batch.sig
+             FROM (",
+        )
+        .push_values(
+            slash_or_take_sigs.into_iter().enumerate(),
+            |mut builder, (i, slash_or_take_sig)| {
+                builder
+                    .push_bind(i as i32)
+                    .push_bind(SignatureDB(slash_or_take_sig));
+            },
+        )
+        .push(
+            ") AS batch (operator_idx, sig)
+             WHERE deposit_kickoff_utxos.deposit_outpoint = ",
+        )
+        .push_bind(OutPointDB(deposit_outpoint))
+        .push(" AND deposit_kickoff_utxos.operator_idx = batch.operator_idx;")
+        .build()
+        .execute(&self.connection)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_slash_or_take_sig(
+        &self,
+        deposit_outpoint: OutPoint,
+        kickoff_utxo: UTXO,
+    ) -> Result<Option<schnorr::Signature>, BridgeError> {
+        let qr: Option<(SignatureDB,)> = sqlx::query_as(
+            "SELECT slash_or_take_sig
+             FROM deposit_kickoff_utxos
+             WHERE deposit_outpoint = $1 AND kickoff_utxo = $2;",
+        )
+        .bind(OutPointDB(deposit_outpoint))
+        .bind(sqlx::types::Json(Utxodb {
+            outpoint_db: OutPointDB(kickoff_utxo.outpoint),
+            txout_db: TxOutDB(kickoff_utxo.txout),
+        }))
+        .fetch_optional(&self.connection)
+        .await?;
+
+        match qr {
+            Some(sig) => Ok(Some(sig.0 .0)),
+            None => Ok(None),
+        }
+    }
+
+    #[tracing::instrument(skip(self, burn_sigs), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn save_burn_sigs(
+        &self,
+        deposit_outpoint: OutPoint,
+        burn_sigs: impl IntoIterator<Item = schnorr::Signature>,
+    ) -> Result<(), BridgeError> {
+        QueryBuilder::new(
+            "UPDATE deposit_kickoff_utxos
+             SET burn_sig = batch.sig
+             FROM (",
+        )
+        .push_values(
+            burn_sigs.into_iter().enumerate(),
+            |mut builder, (i, burn_sig)| {
+                builder.push_bind(i as i32).push_bind(SignatureDB(burn_sig));
+            },
+        )
+        .push(
+            ") AS batch (operator_idx, sig)
+             WHERE deposit_kickoff_utxos.deposit_outpoint = ",
+        )
+        .push_bind(OutPointDB(deposit_outpoint))
+        .push(" AND deposit_kickoff_utxos.operator_idx = batch.operator_idx;")
+        .build()
+        .execute(&self.connection)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_burn_sig(
+        &self,
+        deposit_outpoint: OutPoint,
+        kickoff_utxo: UTXO,
+    ) -> Result<Option<schnorr::Signature>, BridgeError> {
+        let qr: Option<(SignatureDB,)> = sqlx::query_as(
+            "SELECT burn_sig
+             FROM deposit_kickoff_utxos
+             WHERE deposit_outpoint = $1 AND kickoff_utxo = $2;",
+        )
+        .bind(OutPointDB(deposit_outpoint))
+        .bind(sqlx::types::Json(Utxodb {
+            outpoint_db: OutPointDB(kickoff_utxo.outpoint),
+            txout_db: TxOutDB(kickoff_utxo.txout),
+        }))
+        .fetch_optional(&self.connection)
+        .await?;
+
+        match qr {
+            Some(sig) => Ok(Some(sig.0 .0)),
+            None => Ok(None),
+        }
+    }
+
+    /// Verifier: Get the kickoff UTXOs that have a burn signature but are not
+    /// burned yet, alongside their deposit outpoint and operator index.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_unburned_kickoff_utxos(
+        &self,
+    ) -> Result<Vec<(OutPoint, usize, UTXO, schnorr::Signature)>, BridgeError> {
+        let qr: Vec<(OutPointDB, i32, sqlx::types::Json<Utxodb>, SignatureDB)> = sqlx::query_as(
+            "SELECT deposit_outpoint, operator_idx, kickoff_utxo, burn_sig
+             FROM deposit_kickoff_utxos
+             WHERE burn_sig IS NOT NULL AND burn_txid IS NULL
+             ORDER BY created_at ASC, operator_idx ASC;",
+        )
+        .fetch_all(&self.connection)
+        .await?;
+
+        Ok(qr
+            .into_iter()
+            .map(|(deposit_outpoint, operator_idx, utxo_db, burn_sig)| {
+                (
+                    deposit_outpoint.0,
+                    operator_idx as usize,
+                    UTXO {
+                        outpoint: utxo_db.outpoint_db.0,
+                        txout: utxo_db.txout_db.0.clone(),
+                    },
+                    burn_sig.0,
+                )
+            })
+            .collect())
+    }
+
+    /// Verifier: Save the txid of the broadcasted burn tx of a kickoff UTXO.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn save_burn_txid(
+        &self,
+        deposit_outpoint: OutPoint,
+        operator_idx: usize,
+        burn_txid: Txid,
+    ) -> Result<(), BridgeError> {
+        sqlx::query(
+            "UPDATE deposit_kickoff_utxos
+             SET burn_txid = $3
+             WHERE deposit_outpoint = $1 AND operator_idx = $2;",
+        )
+        .bind(OutPointDB(deposit_outpoint))
+        .bind(operator_idx as i32)
+        .bind(TxidDB(burn_txid))
+        .execute(&self.connection)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self, kickoff_utxos_and_sigs), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn save_operator_take_sigs(
+        &self,
+        deposit_outpoint: OutPoint,
+        kickoff_utxos_and_sigs: impl IntoIterator<Item = (UTXO, schnorr::Signature)>,
+    ) -> Result<(), BridgeError> {
+        QueryBuilder::new(
             "UPDATE deposit_kickoff_utxos
              SET operator_take_sig = batch.sig
              FROM (",
@@ -528,76 +1359,559 @@ impl Database {
             ") AS batch (kickoff_utxo, sig)
              WHERE deposit_kickoff_utxos.deposit_outpoint = ",
         )
-        .push_bind(OutPointDB(deposit_outpoint))
-        .push(" AND deposit_kickoff_utxos.kickoff_utxo = batch.kickoff_utxo;")
-        .build()
+        .push_bind(OutPointDB(deposit_outpoint))
+        .push(" AND deposit_kickoff_utxos.kickoff_utxo = batch.kickoff_utxo;")
+        .build()
+        .execute(&self.connection)
+        .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_operator_take_sig(
+        &self,
+        deposit_outpoint: OutPoint,
+        kickoff_utxo: UTXO,
+    ) -> Result<Option<schnorr::Signature>, BridgeError> {
+        let qr: Option<(SignatureDB,)> = sqlx::query_as(
+            "SELECT operator_take_sig
+             FROM deposit_kickoff_utxos
+             WHERE deposit_outpoint = $1 AND kickoff_utxo = $2;",
+        )
+        .bind(OutPointDB(deposit_outpoint))
+        .bind(sqlx::types::Json(Utxodb {
+            outpoint_db: OutPointDB(kickoff_utxo.outpoint),
+            txout_db: TxOutDB(kickoff_utxo.txout),
+        }))
+        .fetch_optional(&self.connection)
+        .await?;
+
+        match qr {
+            Some(sig) => Ok(Some(sig.0 .0)),
+            None => Ok(None),
+        }
+    }
+
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_deposit_kickoff_generator_tx(
+        &self,
+        txid: Txid,
+    ) -> Result<Option<(String, usize, usize, Txid)>, BridgeError> {
+        let qr: Option<(String, i32, i32, TxidDB)> = sqlx::query_as("SELECT raw_signed_tx, num_kickoffs, cur_unused_kickoff_index, funding_txid FROM deposit_kickoff_generator_txs WHERE txid = $1;")
+            .bind(TxidDB(txid))
+            .fetch_optional(&self.connection)
+            .await?;
+
+        match qr {
+            Some((raw_hex, num_kickoffs, cur_unused_kickoff_index, funding_txid)) => Ok(Some((
+                raw_hex,
+                num_kickoffs as usize,
+                cur_unused_kickoff_index as usize,
+                funding_txid.0,
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    /// Aggregator: Creates a signing session for the deposit under the given
+    /// verifier epoch, if there isn't one already.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn save_deposit_session(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        amount: Amount,
+        epoch: u32,
+    ) -> Result<(), BridgeError> {
+        let query = sqlx::query(
+            "INSERT INTO aggregator_deposit_sessions (deposit_outpoint, recovery_taproot_address, evm_address, amount, session, epoch)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT (deposit_outpoint) DO NOTHING;",
+        )
+        .bind(OutPointDB(deposit_outpoint))
+        .bind(AddressDB(recovery_taproot_address))
+        .bind(EVMAddressDB(evm_address))
+        .bind(amount.to_sat() as i64)
+        .bind(sqlx::types::Json(DepositSession::default()))
+        .bind(epoch as i32);
+
+        match tx {
+            Some(tx) => query.execute(&mut **tx).await?,
+            None => query.execute(&self.connection).await?,
+        };
+
+        Ok(())
+    }
+
+    /// Aggregator: Claims the lease of a deposit's signing session for
+    /// `lease_owner` and returns the session. Returns `None` if the lease is
+    /// held by another owner and hasn't expired yet.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn claim_deposit_session(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+        lease_owner: &str,
+        lease: Duration,
+    ) -> Result<Option<DepositSession>, BridgeError> {
+        let query = sqlx::query_as(
+            "UPDATE aggregator_deposit_sessions
+             SET lease_owner = $2, lease_expires_at = now() + make_interval(secs => $3)
+             WHERE deposit_outpoint = $1
+             AND (lease_owner IS NULL OR lease_owner = $2 OR lease_expires_at < now())
+             RETURNING session;",
+        )
+        .bind(OutPointDB(deposit_outpoint))
+        .bind(lease_owner)
+        .bind(lease.as_secs_f64());
+
+        let result: Option<(sqlx::types::Json<DepositSession>,)> = match tx {
+            Some(tx) => query.fetch_optional(&mut **tx).await?,
+            None => query.fetch_optional(&self.connection).await?,
+        };
+
+        Ok(result.map(|(session,)| session.0))
+    }
+
+    /// Aggregator: Releases the lease of a deposit's signing session, if it is
+    /// still held by `lease_owner`.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn release_deposit_session(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+        lease_owner: &str,
+    ) -> Result<(), BridgeError> {
+        let query = sqlx::query(
+            "UPDATE aggregator_deposit_sessions
+             SET lease_owner = NULL, lease_expires_at = NULL
+             WHERE deposit_outpoint = $1 AND lease_owner = $2;",
+        )
+        .bind(OutPointDB(deposit_outpoint))
+        .bind(lease_owner);
+
+        match tx {
+            Some(tx) => query.execute(&mut **tx).await?,
+            None => query.execute(&self.connection).await?,
+        };
+
+        Ok(())
+    }
+
+    /// Aggregator: Records the progress of a deposit signing session.
+    #[tracing::instrument(skip(self, session), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn update_deposit_session(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+        session: &DepositSession,
+    ) -> Result<(), BridgeError> {
+        let query = sqlx::query(
+            "UPDATE aggregator_deposit_sessions
+             SET session = $2, completed = $3, updated_at = now()
+             WHERE deposit_outpoint = $1;",
+        )
+        .bind(OutPointDB(deposit_outpoint))
+        .bind(sqlx::types::Json(session))
+        .bind(session.is_completed());
+
+        match tx {
+            Some(tx) => query.execute(&mut **tx).await?,
+            None => query.execute(&self.connection).await?,
+        };
+
+        Ok(())
+    }
+
+    /// Aggregator: Gets the signing session of a deposit, if there is any.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_deposit_session(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+    ) -> Result<Option<DepositSession>, BridgeError> {
+        let query = sqlx::query_as(
+            "SELECT session FROM aggregator_deposit_sessions WHERE deposit_outpoint = $1;",
+        )
+        .bind(OutPointDB(deposit_outpoint));
+
+        let result: Option<(sqlx::types::Json<DepositSession>,)> = match tx {
+            Some(tx) => query.fetch_optional(&mut **tx).await?,
+            None => query.fetch_optional(&self.connection).await?,
+        };
+
+        Ok(result.map(|(session,)| session.0))
+    }
+
+    /// Aggregator: Gets the deposit of a signing session and the verifier
+    /// epoch it is signed under, if there is a session.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_deposit_session_info(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+    ) -> Result<Option<(MoveTxDeposit, u32)>, BridgeError> {
+        let query = sqlx::query_as(
+            "SELECT recovery_taproot_address, evm_address, amount, epoch
+             FROM aggregator_deposit_sessions
+             WHERE deposit_outpoint = $1;",
+        )
+        .bind(OutPointDB(deposit_outpoint));
+
+        let result: Option<(AddressDB, EVMAddressDB, i64, i32)> = match tx {
+            Some(tx) => query.fetch_optional(&mut **tx).await?,
+            None => query.fetch_optional(&self.connection).await?,
+        };
+
+        Ok(result.map(|(address, evm_address, amount, epoch)| {
+            (
+                MoveTxDeposit {
+                    deposit_outpoint,
+                    recovery_taproot_address: address.0,
+                    evm_address: evm_address.0,
+                    bridge_amount_sats: Amount::from_sat(amount as u64),
+                },
+                epoch as u32,
+            )
+        }))
+    }
+
+    /// Aggregator: Gets the deposits of the given verifier epoch whose signing
+    /// sessions are not completed yet, oldest first.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_unfinished_deposit_sessions(
+        &self,
+        epoch: u32,
+    ) -> Result<Vec<(OutPoint, Address<NetworkUnchecked>, EVMAddress, Amount)>, BridgeError> {
+        let qr: Vec<(OutPointDB, AddressDB, EVMAddressDB, i64)> = sqlx::query_as(
+            "SELECT deposit_outpoint, recovery_taproot_address, evm_address, amount
+             FROM aggregator_deposit_sessions
+             WHERE completed = false AND epoch = $1
+             ORDER BY created_at ASC;",
+        )
+        .bind(epoch as i32)
+        .fetch_all(&self.connection)
+        .await?;
+
+        Ok(qr
+            .into_iter()
+            .map(|(outpoint, address, evm_address, amount)| {
+                (
+                    outpoint.0,
+                    address.0,
+                    evm_address.0,
+                    Amount::from_sat(amount as u64),
+                )
+            })
+            .collect())
+    }
+
+    /// Aggregator: Saves the public key package of the verifiers' FROST key.
+    #[tracing::instrument(skip(self, public_key_package), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn save_frost_public_key_package(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        public_key_package: &PublicKeyPackage,
+    ) -> Result<(), BridgeError> {
+        let query = sqlx::query(
+            "INSERT INTO aggregator_frost_public_key_packages (public_key_package) VALUES ($1);",
+        )
+        .bind(public_key_package.serialize()?);
+
+        match tx {
+            Some(tx) => query.execute(&mut **tx).await?,
+            None => query.execute(&self.connection).await?,
+        };
+
+        Ok(())
+    }
+
+    /// Aggregator: Gets the public key package of the verifiers' FROST key, if
+    /// there is one.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_frost_public_key_package(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+    ) -> Result<Option<PublicKeyPackage>, BridgeError> {
+        let query =
+            sqlx::query_as("SELECT public_key_package FROM aggregator_frost_public_key_packages;");
+
+        let result: Option<(Vec<u8>,)> = match tx {
+            Some(tx) => query.fetch_optional(&mut **tx).await?,
+            None => query.fetch_optional(&self.connection).await?,
+        };
+
+        result
+            .map(|(public_key_package,)| Ok(PublicKeyPackage::deserialize(&public_key_package)?))
+            .transpose()
+    }
+
+    /// Operator: Creates a reimbursement job for a paid withdrawal with its
+    /// transactions in broadcast order.
+    #[tracing::instrument(skip(self, txs), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn save_reimbursement_job(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+        withdrawal_idx: u32,
+        txs: &[(Txid, String)],
+    ) -> Result<(), BridgeError> {
+        let job_query = sqlx::query(
+            "INSERT INTO operator_reimbursements (deposit_outpoint, withdrawal_idx) VALUES ($1, $2);",
+        )
+        .bind(OutPointDB(deposit_outpoint))
+        .bind(withdrawal_idx as i32);
+
+        let mut txs_query_builder = QueryBuilder::new(
+            "INSERT INTO operator_reimbursement_txs (deposit_outpoint, tx_idx, txid, raw_signed_tx) ",
+        );
+        txs_query_builder.push_values(
+            txs.iter().enumerate(),
+            |mut builder, (tx_idx, (txid, raw_signed_tx))| {
+                builder
+                    .push_bind(OutPointDB(deposit_outpoint))
+                    .push_bind(tx_idx as i32)
+                    .push_bind(TxidDB(*txid))
+                    .push_bind(raw_signed_tx.clone());
+            },
+        );
+        let txs_query = txs_query_builder.build();
+
+        match tx {
+            Some(tx) => {
+                job_query.execute(&mut **tx).await?;
+                txs_query.execute(&mut **tx).await?;
+            }
+            None => {
+                let mut tx = self.begin_transaction().await?;
+                job_query.execute(&mut *tx).await?;
+                txs_query.execute(&mut *tx).await?;
+                tx.commit().await?;
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Operator: Gets the transactions of a reimbursement job in broadcast
+    /// order, alongside their broadcasted and confirmed flags.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_reimbursement_txs(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        deposit_outpoint: OutPoint,
+    ) -> Result<Vec<(Txid, String, bool, bool)>, BridgeError> {
+        let query = sqlx::query_as(
+            "SELECT txid, raw_signed_tx, broadcasted, confirmed
+             FROM operator_reimbursement_txs
+             WHERE deposit_outpoint = $1
+             ORDER BY tx_idx ASC;",
+        )
+        .bind(OutPointDB(deposit_outpoint));
+
+        let qr: Vec<(TxidDB, String, bool, bool)> = match tx {
+            Some(tx) => query.fetch_all(&mut **tx).await?,
+            None => query.fetch_all(&self.connection).await?,
+        };
+
+        Ok(qr
+            .into_iter()
+            .map(|(txid, raw_signed_tx, broadcasted, confirmed)| {
+                (txid.0, raw_signed_tx, broadcasted, confirmed)
+            })
+            .collect())
+    }
+
+    /// Operator: Records the broadcast and confirmation status of a
+    /// reimbursement job transaction.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn update_reimbursement_tx_status(
+        &self,
+        deposit_outpoint: OutPoint,
+        tx_idx: usize,
+        broadcasted: bool,
+        confirmed: bool,
+    ) -> Result<(), BridgeError> {
+        sqlx::query(
+            "UPDATE operator_reimbursement_txs
+             SET broadcasted = $3, confirmed = $4
+             WHERE deposit_outpoint = $1 AND tx_idx = $2;",
+        )
+        .bind(OutPointDB(deposit_outpoint))
+        .bind(tx_idx as i32)
+        .bind(broadcasted)
+        .bind(confirmed)
+        .execute(&self.connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Operator: Marks a reimbursement job as completed.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn set_reimbursement_completed(
+        &self,
+        deposit_outpoint: OutPoint,
+    ) -> Result<(), BridgeError> {
+        sqlx::query(
+            "UPDATE operator_reimbursements SET completed = true WHERE deposit_outpoint = $1;",
+        )
+        .bind(OutPointDB(deposit_outpoint))
         .execute(&self.connection)
         .await?;
 
         Ok(())
     }
 
+    /// Operator: Gets the deposits whose reimbursement jobs are not completed
+    /// yet, oldest first.
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub async fn get_operator_take_sig(
+    pub async fn get_unfinished_reimbursements(&self) -> Result<Vec<OutPoint>, BridgeError> {
+        let qr: Vec<(OutPointDB,)> = sqlx::query_as(
+            "SELECT deposit_outpoint
+             FROM operator_reimbursements
+             WHERE completed = false
+             ORDER BY created_at ASC;",
+        )
+        .fetch_all(&self.connection)
+        .await?;
+
+        Ok(qr.into_iter().map(|(outpoint,)| outpoint.0).collect())
+    }
+
+    /// Header indexer: Saves headers of the best chain.
+    #[tracing::instrument(skip(self, headers), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn save_block_headers(
         &self,
-        deposit_outpoint: OutPoint,
-        kickoff_utxo: UTXO,
-    ) -> Result<Option<schnorr::Signature>, BridgeError> {
-        let qr: Option<(SignatureDB,)> = sqlx::query_as(
-            "SELECT operator_take_sig
-             FROM deposit_kickoff_utxos
-             WHERE deposit_outpoint = $1 AND kickoff_utxo = $2;",
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        headers: &[IndexedHeader],
+    ) -> Result<(), BridgeError> {
+        if headers.is_empty() {
+            return Ok(());
+        }
+
+        let mut query = QueryBuilder::new(
+            "INSERT INTO block_headers (height, block_hash, prev_block_hash, header, chainwork) ",
+        );
+        query.push_values(headers, |mut builder, header| {
+            builder
+                .push_bind(header.height as i32)
+                .push_bind(BlockHashDB(header.header.block_hash()))
+                .push_bind(BlockHashDB(header.header.prev_blockhash))
+                .push_bind(serialize(&header.header))
+                .push_bind(header.chainwork.to_be_bytes().to_vec());
+        });
+        let query = query.build();
+
+        match tx {
+            Some(tx) => query.execute(&mut **tx).await?,
+            None => query.execute(&self.connection).await?,
+        };
+
+        Ok(())
+    }
+
+    /// Header indexer: Deletes the headers after the given height, which are
+    /// reorged out.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn delete_block_headers_after(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, Postgres>>,
+        height: Option<u32>,
+    ) -> Result<(), BridgeError> {
+        let query = sqlx::query("DELETE FROM block_headers WHERE height > $1;")
+            .bind(height.map_or(-1, |height| height as i32));
+
+        match tx {
+            Some(tx) => query.execute(&mut **tx).await?,
+            None => query.execute(&self.connection).await?,
+        };
+
+        Ok(())
+    }
+
+    /// Header indexer: Gets the header of the best chain at the given height.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_block_header_by_height(
+        &self,
+        height: u32,
+    ) -> Result<Option<IndexedHeader>, BridgeError> {
+        let qr: Option<(i32, Vec<u8>, Vec<u8>)> = sqlx::query_as(
+            "SELECT height, header, chainwork FROM block_headers WHERE height = $1;",
         )
-        .bind(OutPointDB(deposit_outpoint))
-        .bind(sqlx::types::Json(Utxodb {
-            outpoint_db: OutPointDB(kickoff_utxo.outpoint),
-            txout_db: TxOutDB(kickoff_utxo.txout),
-        }))
+        .bind(height as i32)
         .fetch_optional(&self.connection)
         .await?;
 
-        match qr {
-            Some(sig) => Ok(Some(sig.0 .0)),
-            None => Ok(None),
-        }
+        qr.map(decode_indexed_header).transpose()
     }
 
+    /// Header indexer: Gets the header with the given hash, if it is in the
+    /// best chain.
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub async fn get_deposit_kickoff_generator_tx(
+    pub async fn get_block_header_by_hash(
         &self,
-        txid: Txid,
-    ) -> Result<Option<(String, usize, usize, Txid)>, BridgeError> {
-        let qr: Option<(String, i32, i32, TxidDB)> = sqlx::query_as("SELECT raw_signed_tx, num_kickoffs, cur_unused_kickoff_index, funding_txid FROM deposit_kickoff_generator_txs WHERE txid = $1;")
-            .bind(TxidDB(txid))
-            .fetch_optional(&self.connection)
-            .await?;
+        block_hash: BlockHash,
+    ) -> Result<Option<IndexedHeader>, BridgeError> {
+        let qr: Option<(i32, Vec<u8>, Vec<u8>)> = sqlx::query_as(
+            "SELECT height, header, chainwork FROM block_headers WHERE block_hash = $1;",
+        )
+        .bind(BlockHashDB(block_hash))
+        .fetch_optional(&self.connection)
+        .await?;
 
-        match qr {
-            Some((raw_hex, num_kickoffs, cur_unused_kickoff_index, funding_txid)) => Ok(Some((
-                raw_hex,
-                num_kickoffs as usize,
-                cur_unused_kickoff_index as usize,
-                funding_txid.0,
-            ))),
-            None => Ok(None),
-        }
+        qr.map(decode_indexed_header).transpose()
+    }
+
+    /// Header indexer: Gets the header at the tip of the indexed chain.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_block_header_tip(&self) -> Result<Option<IndexedHeader>, BridgeError> {
+        let qr: Option<(i32, Vec<u8>, Vec<u8>)> = sqlx::query_as(
+            "SELECT height, header, chainwork FROM block_headers ORDER BY height DESC LIMIT 1;",
+        )
+        .fetch_optional(&self.connection)
+        .await?;
+
+        qr.map(decode_indexed_header).transpose()
     }
 }
 
+/// Decodes a `block_headers` row of height, header and chainwork.
+fn decode_indexed_header(
+    (height, header, chainwork): (i32, Vec<u8>, Vec<u8>),
+) -> Result<IndexedHeader, BridgeError> {
+    let chainwork: [u8; 32] = chainwork
+        .try_into()
+        .map_err(|_| BridgeError::TryFromSliceError)?;
+
+    Ok(IndexedHeader {
+        height: height as u32,
+        header: deserialize(&header)?,
+        chainwork: Work::from_be_bytes(chainwork),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::Database;
     use crate::{
+        aggregator::DepositSession,
+        frost::{self, DkgParticipant},
         mock::database::create_test_config_with_thread_name,
         musig2::{nonce_pair, MuSigAggNonce, MuSigPubNonce, MuSigSecNonce},
-        ByteArray32, EVMAddress, UTXO,
+        utils, ByteArray32, ByteArray66, EVMAddress, UTXO,
     };
     use bitcoin::{
-        hashes::Hash, Address, Amount, OutPoint, ScriptBuf, TxOut, Txid, XOnlyPublicKey,
+        hashes::Hash, Address, Amount, BlockHash, OutPoint, ScriptBuf, TxOut, Txid, XOnlyPublicKey,
     };
     use crypto_bigint::rand_core::OsRng;
     use secp256k1::constants::SCHNORR_SIGNATURE_SIZE;
     use secp256k1::{schnorr, Secp256k1};
+    use std::collections::BTreeMap;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn test_database_gets_previously_saved_operator_take_signature() {
@@ -637,37 +1951,352 @@ mod tests {
         assert_eq!(actual_sig, expected_sig);
     }
 
-    #[tokio::test]
-    async fn test_save_and_get_deposit_info() {
-        let config = create_test_config_with_thread_name("test_config.toml", None).await;
-        let database = Database::new(&config).await.unwrap();
+    #[tokio::test]
+    async fn test_database_gets_previously_saved_burn_signature() {
+        let config = create_test_config_with_thread_name("test_config.toml", None).await;
+        let database = Database::new(&config).await.unwrap();
+
+        let deposit_outpoint = OutPoint::null();
+        let outpoint = OutPoint {
+            txid: Txid::from_byte_array([1u8; 32]),
+            vout: 1,
+        };
+        let kickoff_utxo = UTXO {
+            outpoint,
+            txout: TxOut {
+                value: Amount::from_sat(100),
+                script_pubkey: ScriptBuf::from(vec![1u8]),
+            },
+        };
+        let signature = schnorr::Signature::from_slice(&[0u8; SCHNORR_SIGNATURE_SIZE]).unwrap();
+
+        database
+            .save_kickoff_utxos(None, deposit_outpoint, &[kickoff_utxo.clone()])
+            .await
+            .unwrap();
+
+        database
+            .save_burn_sigs(deposit_outpoint, [signature])
+            .await
+            .unwrap();
+
+        let actual_sig = database
+            .get_burn_sig(deposit_outpoint, kickoff_utxo)
+            .await
+            .unwrap();
+        let expected_sig = Some(signature);
+
+        assert_eq!(actual_sig, expected_sig);
+    }
+
+    #[tokio::test]
+    async fn test_unburned_kickoff_utxos() {
+        let config = create_test_config_with_thread_name("test_config.toml", None).await;
+        let database = Database::new(&config).await.unwrap();
+
+        let deposit_outpoint = OutPoint::null();
+        let kickoff_utxos = (0..2u8)
+            .map(|i| UTXO {
+                outpoint: OutPoint {
+                    txid: Txid::from_byte_array([i; 32]),
+                    vout: 1,
+                },
+                txout: TxOut {
+                    value: Amount::from_sat(100),
+                    script_pubkey: ScriptBuf::from(vec![i]),
+                },
+            })
+            .collect::<Vec<_>>();
+        let signature = schnorr::Signature::from_slice(&[0u8; SCHNORR_SIGNATURE_SIZE]).unwrap();
+
+        database
+            .save_kickoff_utxos(None, deposit_outpoint, &kickoff_utxos)
+            .await
+            .unwrap();
+        assert!(database
+            .get_unburned_kickoff_utxos()
+            .await
+            .unwrap()
+            .is_empty());
+
+        database
+            .save_burn_sigs(deposit_outpoint, [signature, signature])
+            .await
+            .unwrap();
+        let unburned = database.get_unburned_kickoff_utxos().await.unwrap();
+        assert_eq!(
+            unburned,
+            vec![
+                (deposit_outpoint, 0, kickoff_utxos[0].clone(), signature),
+                (deposit_outpoint, 1, kickoff_utxos[1].clone(), signature)
+            ]
+        );
+
+        database
+            .save_burn_txid(deposit_outpoint, 0, Txid::from_byte_array([2u8; 32]))
+            .await
+            .unwrap();
+        let unburned = database.get_unburned_kickoff_utxos().await.unwrap();
+        assert_eq!(
+            unburned,
+            vec![(deposit_outpoint, 1, kickoff_utxos[1].clone(), signature)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reimbursement_job() {
+        let config = create_test_config_with_thread_name("test_config.toml", None).await;
+        let database = Database::new(&config).await.unwrap();
+
+        let deposit_outpoint = OutPoint::null();
+        let txs = (0..3u8)
+            .map(|i| (Txid::from_byte_array([i; 32]), hex::encode([i; 8])))
+            .collect::<Vec<_>>();
+
+        assert!(database
+            .get_unfinished_reimbursements()
+            .await
+            .unwrap()
+            .is_empty());
+
+        database
+            .save_reimbursement_job(None, deposit_outpoint, 0, &txs)
+            .await
+            .unwrap();
+        assert_eq!(
+            database.get_unfinished_reimbursements().await.unwrap(),
+            vec![deposit_outpoint]
+        );
+        assert_eq!(
+            database
+                .get_reimbursement_txs(None, deposit_outpoint)
+                .await
+                .unwrap(),
+            txs.iter()
+                .map(|(txid, raw_tx)| (*txid, raw_tx.clone(), false, false))
+                .collect::<Vec<_>>()
+        );
+
+        database
+            .update_reimbursement_tx_status(deposit_outpoint, 1, true, false)
+            .await
+            .unwrap();
+        let reimbursement_txs = database
+            .get_reimbursement_txs(None, deposit_outpoint)
+            .await
+            .unwrap();
+        assert_eq!(
+            reimbursement_txs[1],
+            (txs[1].0, txs[1].1.clone(), true, false)
+        );
+
+        database
+            .set_reimbursement_completed(deposit_outpoint)
+            .await
+            .unwrap();
+        assert!(database
+            .get_unfinished_reimbursements()
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_save_and_get_deposit_info() {
+        let config = create_test_config_with_thread_name("test_config.toml", None).await;
+        let database = Database::new(&config).await.unwrap();
+
+        let secp = Secp256k1::new();
+        let xonly_public_key = XOnlyPublicKey::from_slice(&[
+            0x78u8, 0x19u8, 0x90u8, 0xd7u8, 0xe2u8, 0x11u8, 0x8cu8, 0xc3u8, 0x61u8, 0xa9u8, 0x3au8,
+            0x6fu8, 0xccu8, 0x54u8, 0xceu8, 0x61u8, 0x1du8, 0x6du8, 0xf3u8, 0x81u8, 0x68u8, 0xd6u8,
+            0xb1u8, 0xedu8, 0xfbu8, 0x55u8, 0x65u8, 0x35u8, 0xf2u8, 0x20u8, 0x0cu8, 0x4b,
+        ])
+        .unwrap();
+        let outpoint = OutPoint::null();
+        let taproot_address = Address::p2tr(&secp, xonly_public_key, None, config.network);
+        let evm_address = EVMAddress([1u8; 20]);
+        database
+            .save_deposit_info(
+                None,
+                outpoint,
+                taproot_address.as_unchecked().clone(),
+                evm_address,
+                Amount::from_sat(100_000_000),
+                BlockHash::all_zeros(),
+                0,
+            )
+            .await
+            .unwrap();
+
+        let (db_taproot_address, db_evm_address, db_amount) =
+            database.get_deposit_info(outpoint).await.unwrap().unwrap();
+
+        // Sanity checks
+        assert_eq!(taproot_address, db_taproot_address.assume_checked());
+        assert_eq!(evm_address, db_evm_address);
+        assert_eq!(Amount::from_sat(100_000_000), db_amount);
+    }
+
+    #[tokio::test]
+    async fn test_deposit_invalidation() {
+        let config = create_test_config_with_thread_name("test_config.toml", None).await;
+        let database = Database::new(&config).await.unwrap();
+
+        let outpoint = OutPoint::null();
+        let taproot_address = Address::p2tr(
+            &Secp256k1::new(),
+            *utils::UNSPENDABLE_XONLY_PUBKEY,
+            None,
+            config.network,
+        );
+        database
+            .save_deposit_info(
+                None,
+                outpoint,
+                taproot_address.as_unchecked().clone(),
+                EVMAddress([1u8; 20]),
+                Amount::from_sat(100_000_000),
+                BlockHash::all_zeros(),
+                0,
+            )
+            .await
+            .unwrap();
+        assert!(!database
+            .is_deposit_invalidated(None, outpoint)
+            .await
+            .unwrap());
+
+        database.set_deposit_invalidated(outpoint).await.unwrap();
+        assert!(database
+            .is_deposit_invalidated(None, outpoint)
+            .await
+            .unwrap());
+        assert_eq!(
+            database.get_deposit_confirmations().await.unwrap(),
+            vec![(outpoint, BlockHash::all_zeros(), true)]
+        );
+
+        let new_block_hash = BlockHash::from_byte_array([1u8; 32]);
+        database
+            .set_deposit_confirmed(outpoint, new_block_hash)
+            .await
+            .unwrap();
+        assert_eq!(
+            database.get_deposit_confirmations().await.unwrap(),
+            vec![(outpoint, new_block_hash, false)]
+        );
+
+        // Deposit is locked until the transaction that checks it ends.
+        let mut dbtx = database.begin_transaction().await.unwrap();
+        assert!(!database
+            .is_deposit_invalidated(Some(&mut dbtx), outpoint)
+            .await
+            .unwrap());
+        assert!(tokio::time::timeout(
+            std::time::Duration::from_millis(100),
+            database.is_deposit_invalidated(None, outpoint)
+        )
+        .await
+        .is_err());
+        dbtx.commit().await.unwrap();
+        assert!(!database
+            .is_deposit_invalidated(None, outpoint)
+            .await
+            .unwrap());
+
+        assert!(matches!(
+            database
+                .is_deposit_invalidated(
+                    None,
+                    OutPoint {
+                        txid: Txid::all_zeros(),
+                        vout: 1,
+                    }
+                )
+                .await,
+            Err(crate::errors::BridgeError::DepositInfoNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_deposit_batch() {
+        let config = create_test_config_with_thread_name("test_config.toml", None).await;
+        let database = Database::new(&config).await.unwrap();
+
+        let taproot_address = Address::p2tr(
+            &Secp256k1::new(),
+            *utils::UNSPENDABLE_XONLY_PUBKEY,
+            None,
+            config.network,
+        );
+        let deposit_outpoints = (0..3)
+            .map(|vout| OutPoint {
+                txid: Txid::all_zeros(),
+                vout,
+            })
+            .collect::<Vec<_>>();
+        for (i, deposit_outpoint) in deposit_outpoints.iter().enumerate() {
+            database
+                .save_deposit_info(
+                    None,
+                    *deposit_outpoint,
+                    taproot_address.as_unchecked().clone(),
+                    EVMAddress([i as u8; 20]),
+                    Amount::from_sat(100_000_000),
+                    BlockHash::all_zeros(),
+                    0,
+                )
+                .await
+                .unwrap();
+        }
+
+        // Last deposit is not batched.
+        let batch = vec![deposit_outpoints[1], deposit_outpoints[0]];
+        database.save_deposit_batch(None, &batch).await.unwrap();
+
+        for deposit_outpoint in &batch {
+            assert_eq!(
+                database
+                    .get_deposit_batch(None, *deposit_outpoint)
+                    .await
+                    .unwrap(),
+                Some(batch.clone())
+            );
+
+            let move_tx_deposits = database
+                .get_move_tx_deposits(*deposit_outpoint)
+                .await
+                .unwrap();
+            assert_eq!(
+                move_tx_deposits
+                    .iter()
+                    .map(|deposit| deposit.deposit_outpoint)
+                    .collect::<Vec<_>>(),
+                batch
+            );
+            assert_eq!(move_tx_deposits[0].evm_address, EVMAddress([1u8; 20]));
+        }
 
-        let secp = Secp256k1::new();
-        let xonly_public_key = XOnlyPublicKey::from_slice(&[
-            0x78u8, 0x19u8, 0x90u8, 0xd7u8, 0xe2u8, 0x11u8, 0x8cu8, 0xc3u8, 0x61u8, 0xa9u8, 0x3au8,
-            0x6fu8, 0xccu8, 0x54u8, 0xceu8, 0x61u8, 0x1du8, 0x6du8, 0xf3u8, 0x81u8, 0x68u8, 0xd6u8,
-            0xb1u8, 0xedu8, 0xfbu8, 0x55u8, 0x65u8, 0x35u8, 0xf2u8, 0x20u8, 0x0cu8, 0x4b,
-        ])
-        .unwrap();
-        let outpoint = OutPoint::null();
-        let taproot_address = Address::p2tr(&secp, xonly_public_key, None, config.network);
-        let evm_address = EVMAddress([1u8; 20]);
-        database
-            .save_deposit_info(
-                None,
-                outpoint,
-                taproot_address.as_unchecked().clone(),
-                evm_address,
-            )
+        assert_eq!(
+            database
+                .get_deposit_batch(None, deposit_outpoints[2])
+                .await
+                .unwrap(),
+            None
+        );
+        let move_tx_deposits = database
+            .get_move_tx_deposits(deposit_outpoints[2])
             .await
             .unwrap();
+        assert_eq!(move_tx_deposits.len(), 1);
+        assert_eq!(move_tx_deposits[0].deposit_outpoint, deposit_outpoints[2]);
 
-        let (db_taproot_address, db_evm_address) =
-            database.get_deposit_info(outpoint).await.unwrap().unwrap();
-
-        // Sanity checks
-        assert_eq!(taproot_address, db_taproot_address.assume_checked());
-        assert_eq!(evm_address, db_evm_address);
+        // A deposit can't be in two batches.
+        assert!(database
+            .save_deposit_batch(None, &[deposit_outpoints[2], deposit_outpoints[0]])
+            .await
+            .is_err());
     }
 
     #[tokio::test]
@@ -693,7 +2322,7 @@ mod tests {
             .collect();
         let nonce_pairs: Vec<(MuSigSecNonce, MuSigPubNonce)> = keypairs
             .into_iter()
-            .map(|kp| nonce_pair(&kp, &mut OsRng))
+            .map(|kp| nonce_pair(&kp, &mut OsRng).unwrap())
             .collect();
         let agg_nonces: Vec<MuSigAggNonce> = nonce_pairs
             .iter()
@@ -735,7 +2364,7 @@ mod tests {
             .collect();
         let nonce_pairs: Vec<(MuSigSecNonce, MuSigPubNonce)> = keypairs
             .into_iter()
-            .map(|kp| nonce_pair(&kp, &mut OsRng))
+            .map(|kp| nonce_pair(&kp, &mut OsRng).unwrap())
             .collect();
         let agg_nonces: Vec<MuSigAggNonce> = nonce_pairs
             .iter()
@@ -782,7 +2411,7 @@ mod tests {
             .collect();
         let nonce_pairs: Vec<(MuSigSecNonce, MuSigPubNonce)> = keypairs
             .into_iter()
-            .map(|kp| nonce_pair(&kp, &mut OsRng))
+            .map(|kp| nonce_pair(&kp, &mut OsRng).unwrap())
             .collect();
         let agg_nonces: Vec<MuSigAggNonce> = nonce_pairs
             .iter()
@@ -828,7 +2457,7 @@ mod tests {
             .collect();
         let nonce_pairs: Vec<(MuSigSecNonce, MuSigPubNonce)> = keypairs
             .into_iter()
-            .map(|kp| nonce_pair(&kp, &mut OsRng))
+            .map(|kp| nonce_pair(&kp, &mut OsRng).unwrap())
             .collect();
         db.save_nonces(None, outpoint, &nonce_pairs).await.unwrap();
         let pub_nonces = db.get_pub_nonces(None, outpoint).await.unwrap().unwrap();
@@ -852,6 +2481,265 @@ mod tests {
         assert!(pub_nonces.is_none());
     }
 
+    #[tokio::test]
+    async fn test_nonce_pool() {
+        let config = create_test_config_with_thread_name("test_config.toml", None).await;
+        let db = Database::new(&config).await.unwrap();
+        let secp = Secp256k1::new();
+
+        let keypair = secp256k1::Keypair::from_secret_key(
+            &secp,
+            &secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap(),
+        );
+        let nonce_pairs: Vec<(MuSigSecNonce, MuSigPubNonce)> = (0..5)
+            .map(|_| nonce_pair(&keypair, &mut OsRng).unwrap())
+            .collect();
+        db.save_pool_nonces(None, &nonce_pairs).await.unwrap();
+        db.save_pool_nonces(None, &[]).await.unwrap();
+        assert_eq!(db.get_nonce_pool_size(None).await.unwrap(), 5);
+
+        let outpoint = OutPoint {
+            txid: Txid::from_byte_array([1u8; 32]),
+            vout: 1,
+        };
+        let pub_nonces = db
+            .assign_pool_nonces(None, outpoint, 3)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            pub_nonces,
+            nonce_pairs[..3]
+                .iter()
+                .map(|(_, pub_nonce)| *pub_nonce)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(db.get_nonce_pool_size(None).await.unwrap(), 2);
+        assert_eq!(
+            db.get_pub_nonces(None, outpoint).await.unwrap(),
+            Some(pub_nonces)
+        );
+
+        // Assigned nonces are signed like generated ones.
+        let agg_nonces = vec![ByteArray66([2u8; 66]); 3];
+        db.save_agg_nonces(None, outpoint, &agg_nonces)
+            .await
+            .unwrap();
+        let nonces = db
+            .save_sighashes_and_get_nonces(None, outpoint, 1, &[ByteArray32([3u8; 32])])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(nonces, vec![(nonce_pairs[1].0, agg_nonces[1])]);
+
+        // Pool doesn't have enough nonces, so none of them is assigned.
+        let outpoint = OutPoint {
+            txid: Txid::from_byte_array([1u8; 32]),
+            vout: 2,
+        };
+        assert!(db
+            .assign_pool_nonces(None, outpoint, 3)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(db.get_nonce_pool_size(None).await.unwrap(), 2);
+        assert!(db.get_pub_nonces(None, outpoint).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_frost_nonces_and_keys() {
+        let config = create_test_config_with_thread_name("test_config.toml", None).await;
+        let db = Database::new(&config).await.unwrap();
+
+        let outpoint = OutPoint {
+            txid: Txid::from_byte_array([1u8; 32]),
+            vout: 1,
+        };
+        assert!(db
+            .get_frost_signing_commitments(None, outpoint)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(db.get_frost_key_package(None).await.unwrap().is_none());
+
+        // Keys of a 2-of-2 group.
+        let sks = [
+            secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap(),
+            secp256k1::SecretKey::from_slice(&[2u8; 32]).unwrap(),
+        ];
+        let pks = sks
+            .iter()
+            .map(|sk| sk.public_key(&utils::SECP))
+            .collect::<Vec<_>>();
+        let mut participants = sks
+            .iter()
+            .map(|sk| DkgParticipant::new(*sk, pks.clone(), 2).unwrap())
+            .collect::<Vec<_>>();
+        let round1_packages = participants
+            .iter_mut()
+            .map(|participant| (participant.id(), participant.round1(&mut OsRng).unwrap()))
+            .collect::<BTreeMap<_, _>>();
+        let round2_packages = participants
+            .iter_mut()
+            .map(|participant| {
+                participant
+                    .round2(round1_packages.clone(), &mut OsRng)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let (key_package, public_key_package) = participants[0]
+            .finalize(BTreeMap::from([(2, round2_packages[1][&1].clone())]))
+            .unwrap();
+
+        db.save_frost_key_package(None, &key_package, &public_key_package)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_frost_key_package(None).await.unwrap().unwrap(),
+            (key_package.clone(), public_key_package.clone())
+        );
+        // There is a single key.
+        assert!(db
+            .save_frost_key_package(None, &key_package, &public_key_package)
+            .await
+            .is_err());
+
+        db.save_frost_public_key_package(None, &public_key_package)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_frost_public_key_package(None)
+                .await
+                .unwrap()
+                .unwrap(),
+            public_key_package
+        );
+
+        let nonce_pairs = (0..3)
+            .map(|_| frost::nonce_pair(&key_package, &mut OsRng).unwrap())
+            .collect::<Vec<_>>();
+        db.save_nonces(None, outpoint, &nonce_pairs).await.unwrap();
+        assert!(!db.has_signed_nonces(None, outpoint).await.unwrap());
+        // Unsigned nonces can be replaced.
+        db.delete_nonces(None, outpoint).await.unwrap();
+        assert!(db.get_pub_nonces(None, outpoint).await.unwrap().is_none());
+        db.save_nonces(None, outpoint, &nonce_pairs).await.unwrap();
+        let signing_commitments = BTreeMap::from([
+            (
+                1,
+                nonce_pairs
+                    .iter()
+                    .map(|(_, commitment)| *commitment)
+                    .collect::<Vec<_>>(),
+            ),
+            (2, vec![ByteArray66([2u8; 66]); 3]),
+        ]);
+        db.save_frost_signing_commitments(None, outpoint, &signing_commitments)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_frost_signing_commitments(None, outpoint)
+                .await
+                .unwrap()
+                .unwrap(),
+            signing_commitments
+        );
+
+        let sec_nonces = db
+            .save_sighashes_and_get_sec_nonces(
+                None,
+                outpoint,
+                1,
+                &[ByteArray32([1u8; 32]), ByteArray32([2u8; 32])],
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sec_nonces, vec![nonce_pairs[1].0, nonce_pairs[2].0]);
+        assert!(db.has_signed_nonces(None, outpoint).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_sweep_nonces() {
+        let config = create_test_config_with_thread_name("test_config.toml", None).await;
+        let db = Database::new(&config).await.unwrap();
+        let secp = Secp256k1::new();
+
+        let outpoint = OutPoint {
+            txid: Txid::from_byte_array([1u8; 32]),
+            vout: 1,
+        };
+        let keypair = secp256k1::Keypair::from_secret_key(
+            &secp,
+            &secp256k1::SecretKey::from_slice(&[1u8; 32]).unwrap(),
+        );
+        let (sec_nonce, pub_nonce) = nonce_pair(&keypair, &mut OsRng).unwrap();
+
+        assert!(db
+            .get_sweep_pub_nonce(None, outpoint, 1)
+            .await
+            .unwrap()
+            .is_none());
+        db.save_sweep_nonce(None, outpoint, 1, sec_nonce, pub_nonce)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_sweep_pub_nonce(None, outpoint, 1).await.unwrap(),
+            Some(pub_nonce)
+        );
+        // Nonces of another epoch are separate.
+        assert!(db
+            .get_sweep_pub_nonce(None, outpoint, 2)
+            .await
+            .unwrap()
+            .is_none());
+
+        let agg_nonce = ByteArray66([2u8; 66]);
+        let sighash = ByteArray32([3u8; 32]);
+        assert_eq!(
+            db.save_sweep_sighash_and_get_sec_nonce(None, outpoint, 1, agg_nonce, sighash)
+                .await
+                .unwrap(),
+            Some(sec_nonce)
+        );
+        // Same sweep can be signed again.
+        assert_eq!(
+            db.save_sweep_sighash_and_get_sec_nonce(None, outpoint, 1, agg_nonce, sighash)
+                .await
+                .unwrap(),
+            Some(sec_nonce)
+        );
+
+        // Nonce can't sign another sweep.
+        assert!(db
+            .save_sweep_sighash_and_get_sec_nonce(
+                None,
+                outpoint,
+                1,
+                agg_nonce,
+                ByteArray32([4u8; 32])
+            )
+            .await
+            .unwrap()
+            .is_none());
+        assert!(db
+            .save_sweep_sighash_and_get_sec_nonce(
+                None,
+                outpoint,
+                1,
+                ByteArray66([5u8; 66]),
+                sighash
+            )
+            .await
+            .unwrap()
+            .is_none());
+        assert!(db
+            .save_sweep_sighash_and_get_sec_nonce(None, outpoint, 2, agg_nonce, sighash)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
     #[tokio::test]
     async fn test_operators_kickoff_utxo_1() {
         let config = create_test_config_with_thread_name("test_config.toml", None).await;
@@ -868,13 +2756,84 @@ mod tests {
                 script_pubkey: ScriptBuf::from(vec![1u8]),
             },
         };
-        db.save_kickoff_utxo(None, outpoint, kickoff_utxo.clone())
-            .await
-            .unwrap();
+        db.save_kickoff_utxo(
+            None,
+            outpoint,
+            kickoff_utxo.clone(),
+            Amount::from_sat(100_000_000),
+        )
+        .await
+        .unwrap();
         let db_kickoff_utxo = db.get_kickoff_utxo(None, outpoint).await.unwrap().unwrap();
 
         // Sanity check
         assert_eq!(db_kickoff_utxo, kickoff_utxo);
+        assert_eq!(
+            db.get_operator_deposit_amount(outpoint).await.unwrap(),
+            Some(Amount::from_sat(100_000_000))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_operator_deposit_batch() {
+        let config = create_test_config_with_thread_name("test_config.toml", None).await;
+        let db = Database::new(&config).await.unwrap();
+
+        let deposit_outpoints = (0..3)
+            .map(|i| OutPoint {
+                txid: Txid::from_byte_array([i as u8; 32]),
+                vout: i,
+            })
+            .collect::<Vec<_>>();
+        for deposit_outpoint in &deposit_outpoints {
+            db.save_kickoff_utxo(
+                None,
+                *deposit_outpoint,
+                UTXO {
+                    outpoint: *deposit_outpoint,
+                    txout: TxOut {
+                        value: Amount::from_sat(100),
+                        script_pubkey: ScriptBuf::from(vec![1u8]),
+                    },
+                },
+                Amount::from_sat(100_000_000),
+            )
+            .await
+            .unwrap();
+        }
+        assert!(db
+            .get_operator_deposit_batch(None, deposit_outpoints[0])
+            .await
+            .unwrap()
+            .is_none());
+        assert!(db
+            .get_operator_deposit_amount(OutPoint::null())
+            .await
+            .unwrap()
+            .is_none());
+
+        db.save_operator_deposit_batch(None, &deposit_outpoints[1..])
+            .await
+            .unwrap();
+        for deposit_outpoint in &deposit_outpoints[1..] {
+            assert_eq!(
+                db.get_operator_deposit_batch(None, *deposit_outpoint)
+                    .await
+                    .unwrap(),
+                Some(deposit_outpoints[1..].to_vec())
+            );
+        }
+        assert!(db
+            .get_operator_deposit_batch(None, deposit_outpoints[0])
+            .await
+            .unwrap()
+            .is_none());
+
+        // A deposit can't be in two batches.
+        assert!(db
+            .save_operator_deposit_batch(None, &deposit_outpoints[..2])
+            .await
+            .is_err());
     }
 
     #[tokio::test]
@@ -918,7 +2877,7 @@ mod tests {
         db.save_kickoff_utxos(None, outpoint, &kickoff_utxos)
             .await
             .unwrap();
-        let db_kickoff_utxos = db.get_kickoff_utxos(outpoint).await.unwrap().unwrap();
+        let db_kickoff_utxos = db.get_kickoff_utxos(None, outpoint).await.unwrap().unwrap();
 
         // Sanity checks
         assert_eq!(db_kickoff_utxos.len(), kickoff_utxos.len());
@@ -936,7 +2895,7 @@ mod tests {
             txid: Txid::from_byte_array([1u8; 32]),
             vout: 1,
         };
-        let res = db.get_kickoff_utxos(outpoint).await.unwrap();
+        let res = db.get_kickoff_utxos(None, outpoint).await.unwrap();
         assert!(res.is_none());
     }
 
@@ -1087,4 +3046,158 @@ mod tests {
         let res = db.get_deposit_kickoff_generator_tx(txid).await.unwrap();
         assert!(res.is_none());
     }
+
+    #[tokio::test]
+    async fn test_deposit_session() {
+        let config = create_test_config_with_thread_name("test_config.toml", None).await;
+        let db = Database::new(&config).await.unwrap();
+        let secp = Secp256k1::new();
+
+        let outpoint = OutPoint {
+            txid: Txid::from_byte_array([1u8; 32]),
+            vout: 1,
+        };
+        let xonly_public_key = XOnlyPublicKey::from_slice(&[
+            0x78u8, 0x19u8, 0x90u8, 0xd7u8, 0xe2u8, 0x11u8, 0x8cu8, 0xc3u8, 0x61u8, 0xa9u8, 0x3au8,
+            0x6fu8, 0xccu8, 0x54u8, 0xceu8, 0x61u8, 0x1du8, 0x6du8, 0xf3u8, 0x81u8, 0x68u8, 0xd6u8,
+            0xb1u8, 0xedu8, 0xfbu8, 0x55u8, 0x65u8, 0x35u8, 0xf2u8, 0x20u8, 0x0cu8, 0x4b,
+        ])
+        .unwrap();
+        let taproot_address = Address::p2tr(&secp, xonly_public_key, None, config.network);
+        let evm_address = EVMAddress([1u8; 20]);
+
+        assert!(db
+            .get_deposit_session(None, outpoint)
+            .await
+            .unwrap()
+            .is_none());
+
+        db.save_deposit_session(
+            None,
+            outpoint,
+            taproot_address.as_unchecked().clone(),
+            evm_address,
+            Amount::from_sat(100_000_000),
+            0,
+        )
+        .await
+        .unwrap();
+        let session = db
+            .get_deposit_session(None, outpoint)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(session, DepositSession::default());
+
+        let unfinished = db.get_unfinished_deposit_sessions(0).await.unwrap();
+        assert_eq!(unfinished.len(), 1);
+        assert_eq!(unfinished[0].0, outpoint);
+        assert_eq!(unfinished[0].1.clone().assume_checked(), taproot_address);
+        assert_eq!(unfinished[0].2, evm_address);
+        assert_eq!(unfinished[0].3, Amount::from_sat(100_000_000));
+        // Sessions of other epochs are not resumed.
+        assert!(db
+            .get_unfinished_deposit_sessions(1)
+            .await
+            .unwrap()
+            .is_empty());
+        let (deposit, epoch) = db
+            .get_deposit_session_info(None, outpoint)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(deposit.deposit_outpoint, outpoint);
+        assert_eq!(deposit.evm_address, evm_address);
+        assert_eq!(deposit.bridge_amount_sats, Amount::from_sat(100_000_000));
+        assert_eq!(epoch, 0);
+
+        // Progress is kept between calls.
+        let session = DepositSession {
+            agg_nonces: Some(vec![ByteArray66([1u8; 66])]),
+            ..Default::default()
+        };
+        db.update_deposit_session(None, outpoint, &session)
+            .await
+            .unwrap();
+        let db_session = db
+            .get_deposit_session(None, outpoint)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(db_session, session);
+
+        // Session can be run by a single call at a time.
+        let lease = Duration::from_secs(60);
+        assert_eq!(
+            db.claim_deposit_session(None, outpoint, "owner", lease)
+                .await
+                .unwrap(),
+            Some(session.clone())
+        );
+        assert!(db
+            .claim_deposit_session(None, outpoint, "other owner", lease)
+            .await
+            .unwrap()
+            .is_none());
+        db.release_deposit_session(None, outpoint, "other owner")
+            .await
+            .unwrap();
+        assert!(db
+            .claim_deposit_session(None, outpoint, "other owner", lease)
+            .await
+            .unwrap()
+            .is_none());
+        db.release_deposit_session(None, outpoint, "owner")
+            .await
+            .unwrap();
+        assert!(db
+            .claim_deposit_session(None, outpoint, "other owner", lease)
+            .await
+            .unwrap()
+            .is_some());
+        // Expired leases can be claimed by others.
+        db.claim_deposit_session(None, outpoint, "other owner", Duration::ZERO)
+            .await
+            .unwrap();
+        assert!(db
+            .claim_deposit_session(None, outpoint, "owner", lease)
+            .await
+            .unwrap()
+            .is_some());
+        db.release_deposit_session(None, outpoint, "owner")
+            .await
+            .unwrap();
+
+        // Saving the session again must not reset the progress.
+        db.save_deposit_session(
+            None,
+            outpoint,
+            taproot_address.as_unchecked().clone(),
+            evm_address,
+            Amount::from_sat(100_000_000),
+            0,
+        )
+        .await
+        .unwrap();
+        let db_session = db
+            .get_deposit_session(None, outpoint)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(db_session, session);
+
+        // Completed sessions are not resumed.
+        let session = DepositSession {
+            move_tx: Some(("00".to_string(), Txid::from_byte_array([2u8; 32]))),
+            ..session
+        };
+        db.update_deposit_session(None, outpoint, &session)
+            .await
+            .unwrap();
+        assert!(db
+            .get_unfinished_deposit_sessions(0)
+            .await
+            .unwrap()
+            .is_empty());
+    }
 }
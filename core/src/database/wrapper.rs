@@ -1,5 +1,5 @@
 use crate::EVMAddress;
-use bitcoin::{address::NetworkUnchecked, Address, OutPoint, TxOut, Txid};
+use bitcoin::{address::NetworkUnchecked, Address, BlockHash, OutPoint, TxOut, Txid};
 use serde::{Deserialize, Serialize};
 use sqlx::{
     postgres::{PgArgumentBuffer, PgValueRef},
@@ -22,6 +22,9 @@ pub struct EVMAddressDB(pub EVMAddress);
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TxidDB(pub Txid);
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BlockHashDB(pub BlockHash);
+
 #[derive(Serialize, Deserialize, sqlx::FromRow, Debug, Clone)]
 pub struct SignatureDB(pub secp256k1::schnorr::Signature);
 
@@ -116,6 +119,27 @@ impl<'r> Decode<'r, Postgres> for TxidDB {
     }
 }
 
+impl sqlx::Type<sqlx::Postgres> for BlockHashDB {
+    fn type_info() -> sqlx::postgres::PgTypeInfo {
+        sqlx::postgres::PgTypeInfo::with_name("TEXT")
+    }
+}
+
+impl<'q> Encode<'q, Postgres> for BlockHashDB {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> sqlx::encode::IsNull {
+        let s = bitcoin::consensus::encode::serialize_hex(&self.0);
+        <&str as Encode<Postgres>>::encode_by_ref(&s.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for BlockHashDB {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as Decode<Postgres>>::decode(value)?;
+        let x: BlockHash = bitcoin::consensus::encode::deserialize_hex(s)?;
+        Ok(BlockHashDB(x))
+    }
+}
+
 impl sqlx::Type<sqlx::Postgres> for TxOutDB {
     fn type_info() -> sqlx::postgres::PgTypeInfo {
         sqlx::postgres::PgTypeInfo::with_name("TEXT")
@@ -162,10 +186,10 @@ impl<'r> Decode<'r, Postgres> for SignatureDB {
 mod tests {
     use super::OutPointDB;
     use crate::{
-        database::wrapper::{AddressDB, EVMAddressDB, SignatureDB, TxOutDB, TxidDB},
+        database::wrapper::{AddressDB, BlockHashDB, EVMAddressDB, SignatureDB, TxOutDB, TxidDB},
         utils, EVMAddress,
     };
-    use bitcoin::{hashes::Hash, Amount, OutPoint, ScriptBuf, TxOut, Txid};
+    use bitcoin::{hashes::Hash, Amount, BlockHash, OutPoint, ScriptBuf, TxOut, Txid};
     use secp256k1::schnorr::Signature;
     use sqlx::{encode::IsNull, postgres::PgArgumentBuffer, Encode, Type};
 
@@ -261,6 +285,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn blockhashdb() {
+        assert_eq!(
+            BlockHashDB::type_info(),
+            sqlx::postgres::PgTypeInfo::with_name("TEXT")
+        );
+
+        let block_hash = BlockHash::all_zeros();
+        let blockhashdb = BlockHashDB(block_hash);
+
+        let mut hex: PgArgumentBuffer = PgArgumentBuffer::default();
+        if let IsNull::Yes = blockhashdb.clone().encode(&mut hex) {
+            panic!("Couldn't write {:?} to the buffer!", blockhashdb);
+        }
+    }
+
     #[test]
     fn signaturedb() {
         assert_eq!(
@@ -0,0 +1,260 @@
+//! # Deposit Tracker
+//!
+//! Deposit tracker follows the Bitcoin chain on behalf of a verifier. Every
+//! deposit is saved with the hash of the block it is confirmed in. When that
+//! block is reorged out, the deposit is invalidated and verifier refuses its
+//! signing rounds until it is confirmed again.
+
+use crate::config::BridgeConfig;
+use crate::database::Database;
+use crate::errors::BridgeError;
+use crate::extended_rpc::{self, ExtendedRpc};
+use bitcoin::OutPoint;
+use bitcoin_mock_rpc::RpcApiWrapper;
+use std::time::Duration;
+
+/// Time to wait between two checks for a new block.
+pub const DEPOSIT_TRACKER_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Change in the confirmation of a deposit, found by a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepositStatus {
+    /// Deposit's block is reorged out and it is not confirmed enough anymore.
+    Invalidated,
+    /// Deposit is confirmed enough in a new block, after a reorg.
+    Reconfirmed,
+}
+
+#[derive(Debug, Clone)]
+pub struct DepositTracker<R>
+where
+    R: RpcApiWrapper,
+{
+    rpc: ExtendedRpc<R>,
+    db: Database,
+    config: BridgeConfig,
+}
+
+impl<R> DepositTracker<R>
+where
+    R: RpcApiWrapper,
+{
+    pub async fn new(rpc: ExtendedRpc<R>, config: BridgeConfig) -> Result<Self, BridgeError> {
+        let db = Database::new(&config).await?;
+
+        Ok(DepositTracker { rpc, db, config })
+    }
+
+    /// Follows the chain forever and checks the deposits every time a new
+    /// block is found. Errors are logged, so that a temporary failure doesn't
+    /// stop the tracker.
+    pub async fn run(&self, poll_interval: Duration) {
+        let mut last_block_count = None;
+
+        loop {
+            match self.rpc.get_block_count().await {
+                Ok(block_count) if last_block_count != Some(block_count) => {
+                    match self.check_deposits().await {
+                        Ok(_) => last_block_count = Some(block_count),
+                        Err(e) => tracing::error!("Deposit tracker can't check deposits: {}", e),
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("Deposit tracker can't get block count: {}", e),
+            }
+
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Checks every deposit against the current chain. Deposits whose block
+    /// is reorged out are invalidated and invalidated deposits that are
+    /// confirmed enough again are revalidated. A deposit that can't be
+    /// checked is logged and checked again in the next call.
+    ///
+    /// # Returns
+    ///
+    /// Deposits whose status changed, with their new status.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn check_deposits(&self) -> Result<Vec<(OutPoint, DepositStatus)>, BridgeError> {
+        let mut changes = Vec::new();
+
+        for (deposit_outpoint, block_hash, invalidated) in
+            self.db.get_deposit_confirmations().await?
+        {
+            let confirmation = match self
+                .rpc
+                .confirmation_block_hash(&deposit_outpoint.txid)
+                .await
+            {
+                Ok((new_block_hash, confirmations))
+                    if confirmations >= self.config.confirmation_threshold =>
+                {
+                    Some(new_block_hash)
+                }
+                Ok(_) | Err(BridgeError::NoConfirmationData) => None,
+                // Deposit tx is dropped with its block and it is not in the
+                // mempool either.
+                Err(e) if extended_rpc::is_tx_not_found(&e) => None,
+                Err(e) => {
+                    tracing::error!("Deposit {} can't be checked: {}", deposit_outpoint, e);
+                    continue;
+                }
+            };
+
+            match confirmation {
+                Some(new_block_hash) if new_block_hash == block_hash && !invalidated => {}
+                Some(new_block_hash) => {
+                    tracing::info!(
+                        "Deposit {} is confirmed in block {}",
+                        deposit_outpoint,
+                        new_block_hash
+                    );
+                    self.db
+                        .set_deposit_confirmed(deposit_outpoint, new_block_hash)
+                        .await?;
+
+                    if invalidated {
+                        changes.push((deposit_outpoint, DepositStatus::Reconfirmed));
+                    }
+                }
+                None if !invalidated => {
+                    tracing::warn!(
+                        "Block {} of deposit {} is reorged out",
+                        block_hash,
+                        deposit_outpoint
+                    );
+                    self.db.set_deposit_invalidated(deposit_outpoint).await?;
+                    changes.push((deposit_outpoint, DepositStatus::Invalidated));
+                }
+                None => {}
+            }
+        }
+
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DepositStatus, DepositTracker};
+    use crate::actor::Actor;
+    use crate::extended_rpc::ExtendedRpc;
+    use crate::EVMAddress;
+    use crate::{create_extended_rpc, mock::database::create_test_config};
+    use bitcoin::hashes::Hash;
+    use bitcoin::{BlockHash, OutPoint, Txid};
+
+    #[tokio::test]
+    async fn deposits_are_invalidated_and_reconfirmed() {
+        let mut config = create_test_config(
+            "deposits_are_invalidated_and_reconfirmed",
+            "test_config.toml",
+        )
+        .await;
+        let rpc = create_extended_rpc!(config);
+        let tracker = DepositTracker::new(rpc.clone(), config.clone())
+            .await
+            .unwrap();
+
+        let address = Actor::new(config.secret_key, config.network).address;
+        let evm_address = EVMAddress([1u8; 20]);
+
+        // Deposit is confirmed in another block than the saved one, as if it
+        // is reorged out and mined again.
+        let deposit_outpoint = rpc
+            .send_to_address(&address, config.bridge_amounts_sats[0])
+            .await
+            .unwrap();
+        rpc.mine_blocks((config.confirmation_threshold + 2).into())
+            .await
+            .unwrap();
+        tracker
+            .db
+            .save_deposit_info(
+                None,
+                deposit_outpoint,
+                address.as_unchecked().clone(),
+                evm_address,
+                config.bridge_amounts_sats[0],
+                BlockHash::all_zeros(),
+                0,
+            )
+            .await
+            .unwrap();
+        tracker
+            .db
+            .set_deposit_invalidated(deposit_outpoint)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            tracker.check_deposits().await.unwrap(),
+            vec![(deposit_outpoint, DepositStatus::Reconfirmed)]
+        );
+        let (block_hash, _) = rpc
+            .confirmation_block_hash(&deposit_outpoint.txid)
+            .await
+            .unwrap();
+        assert_eq!(
+            tracker.db.get_deposit_confirmations().await.unwrap(),
+            vec![(deposit_outpoint, block_hash, false)]
+        );
+
+        // Deposit is not in a block anymore.
+        let unconfirmed_deposit_outpoint = rpc
+            .send_to_address(&address, config.bridge_amounts_sats[0])
+            .await
+            .unwrap();
+        tracker
+            .db
+            .save_deposit_info(
+                None,
+                unconfirmed_deposit_outpoint,
+                address.as_unchecked().clone(),
+                evm_address,
+                config.bridge_amounts_sats[0],
+                BlockHash::all_zeros(),
+                0,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            tracker.check_deposits().await.unwrap(),
+            vec![(unconfirmed_deposit_outpoint, DepositStatus::Invalidated)]
+        );
+        assert!(tracker
+            .db
+            .is_deposit_invalidated(None, unconfirmed_deposit_outpoint)
+            .await
+            .unwrap());
+
+        // Nothing changes until a new block.
+        assert!(tracker.check_deposits().await.unwrap().is_empty());
+
+        // Deposit tx is not known by the node at all.
+        let unknown_deposit_outpoint = OutPoint {
+            txid: Txid::from_byte_array([0x45; 32]),
+            vout: 0,
+        };
+        tracker
+            .db
+            .save_deposit_info(
+                None,
+                unknown_deposit_outpoint,
+                address.as_unchecked().clone(),
+                evm_address,
+                config.bridge_amounts_sats[0],
+                BlockHash::all_zeros(),
+                0,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            tracker.check_deposits().await.unwrap(),
+            vec![(unknown_deposit_outpoint, DepositStatus::Invalidated)]
+        );
+    }
+}
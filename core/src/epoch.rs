@@ -0,0 +1,683 @@
+//! # Verifier Epochs
+//!
+//! Verifier set of the bridge is versioned with epochs. Every epoch has its own
+//! verifier set and N-of-N key, which are saved to the database, and deposits
+//! record the epoch they are signed under. Therefore, the verifier set can
+//! change without orphaning the deposits of the old epoch.
+//!
+//! Bridge funds of a deposit are locked to the N-of-N key of its epoch. When the
+//! verifier set changes, verifiers of the old epoch sign a sweep_tx that moves
+//! the bridge funds to the N-of-N MuSig2 key of the next epoch.
+//!
+//! A sweep invalidates the operator_takes_txs of the swept deposits, as they
+//! spend the same bridge fund outputs. Therefore, a deposit is only swept if no
+//! operator has kicked off or paid a withdrawal of it, and after the next
+//! epoch's verifiers sign a new graph on its swept output.
+
+use crate::builder::{
+    self,
+    deposit_graph::{DepositGraph, GraphSighash},
+    transaction::{MoveTxDeposit, TxHandler},
+};
+use crate::config::BridgeConfig;
+use crate::database::Database;
+use crate::errors::BridgeError;
+use crate::musig2::NofnContext;
+use crate::utils::SECP;
+use crate::{actor::Actor, fee, ByteArray32, UTXO};
+use bitcoin::hashes::Hash;
+use bitcoin::OutPoint;
+use secp256k1::{schnorr, Message, PublicKey, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+
+/// Verifier set of an epoch and its N-of-N key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifierEpoch {
+    pub epoch: u32,
+    pub verifiers_public_keys: Vec<PublicKey>,
+    pub nofn_xonly_pk: XOnlyPublicKey,
+}
+
+impl VerifierEpoch {
+    /// Returns the current epoch of the config, whose N-of-N key is `nofn`.
+    pub fn current(config: &BridgeConfig, nofn: &NofnContext) -> Self {
+        VerifierEpoch {
+            epoch: config.verifier_epoch,
+            verifiers_public_keys: config.verifiers_public_keys.clone(),
+            nofn_xonly_pk: nofn.xonly_pk(),
+        }
+    }
+
+    /// Returns the next epoch of the config, whose N-of-N key is the MuSig2
+    /// key of `next_verifiers_public_keys`, if it is set.
+    pub fn next(config: &BridgeConfig) -> Result<Option<Self>, BridgeError> {
+        let Some(next_verifiers_public_keys) = config.next_verifiers_public_keys.clone() else {
+            return Ok(None);
+        };
+        let epoch = config
+            .verifier_epoch
+            .checked_add(1)
+            .ok_or(BridgeError::VerifierEpochNotFound(config.verifier_epoch))?;
+        let nofn_xonly_pk = NofnContext::new(next_verifiers_public_keys.clone())?.xonly_pk();
+
+        Ok(Some(VerifierEpoch {
+            epoch,
+            verifiers_public_keys: next_verifiers_public_keys,
+            nofn_xonly_pk,
+        }))
+    }
+
+    /// Returns the saved epoch.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BridgeError::VerifierEpochNotFound`] if the epoch is not
+    /// saved.
+    pub async fn load(db: &Database, epoch: u32) -> Result<Self, BridgeError> {
+        let (verifiers_public_keys, nofn_xonly_pk) = db
+            .get_verifier_epoch(None, epoch)
+            .await?
+            .ok_or(BridgeError::VerifierEpochNotFound(epoch))?;
+
+        Ok(VerifierEpoch {
+            epoch,
+            verifiers_public_keys,
+            nofn_xonly_pk,
+        })
+    }
+
+    /// Saves the epoch, if it is not saved already.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BridgeError::VerifierEpochMismatch`] if the epoch is saved
+    /// with another verifier set or N-of-N key.
+    pub async fn save(&self, db: &Database) -> Result<(), BridgeError> {
+        let mut dbtx = db.begin_transaction().await?;
+
+        match db.get_verifier_epoch(Some(&mut dbtx), self.epoch).await? {
+            Some((verifiers_public_keys, nofn_xonly_pk)) => {
+                if verifiers_public_keys != self.verifiers_public_keys
+                    || nofn_xonly_pk != self.nofn_xonly_pk
+                {
+                    return Err(BridgeError::VerifierEpochMismatch(self.epoch));
+                }
+            }
+            None => {
+                db.save_verifier_epoch(
+                    Some(&mut dbtx),
+                    self.epoch,
+                    &self.verifiers_public_keys,
+                    self.nofn_xonly_pk,
+                )
+                .await?
+            }
+        }
+
+        dbtx.commit().await?;
+
+        Ok(())
+    }
+}
+
+/// Saves the current and next epochs of the config. Verifiers and aggregator
+/// save them when they start, so that a changed verifier set can't reuse an
+/// epoch.
+pub async fn save_config_epochs(
+    db: &Database,
+    config: &BridgeConfig,
+    nofn: &NofnContext,
+) -> Result<(), BridgeError> {
+    VerifierEpoch::current(config, nofn).save(db).await?;
+
+    if let Some(next_epoch) = VerifierEpoch::next(config)? {
+        next_epoch.save(db).await?;
+    }
+
+    Ok(())
+}
+
+/// Returns the UTXO that holds the bridge funds of the deposit, which is moved
+/// by the move_tx of `move_tx_deposits` to `nofn_xonly_pk`.
+///
+/// # Errors
+///
+/// Returns [`BridgeError::InvalidDepositBatch`] if the deposit is not one of
+/// `move_tx_deposits`.
+pub fn bridge_fund_utxo(
+    move_tx_deposits: &[MoveTxDeposit],
+    deposit_outpoint: OutPoint,
+    nofn_xonly_pk: XOnlyPublicKey,
+    config: &BridgeConfig,
+) -> Result<UTXO, BridgeError> {
+    let deposit_idx = move_tx_deposits
+        .iter()
+        .position(|deposit| deposit.deposit_outpoint == deposit_outpoint)
+        .ok_or(BridgeError::InvalidDepositBatch(deposit_outpoint))?;
+
+    let move_tx = builder::transaction::create_batch_move_tx(
+        &move_tx_deposits
+            .iter()
+            .map(|deposit| (deposit.deposit_outpoint, deposit.bridge_amount_sats))
+            .collect::<Vec<_>>(),
+        nofn_xonly_pk,
+        config.network,
+        fee::presigned_fee_rate(config)?,
+    )?;
+
+    Ok(UTXO {
+        outpoint: OutPoint {
+            txid: move_tx.compute_txid(),
+            vout: deposit_idx as u32,
+        },
+        txout: move_tx.output[deposit_idx].clone(),
+    })
+}
+
+/// Graph of a swept deposit under the next verifier epoch, which spends the
+/// deposit's sweep_tx output. Kickoff UTXOs are locked to the next epoch's
+/// N-of-N key and signatures are aggregated by the next epoch's verifiers, in
+/// the order of `config.operators_xonly_pks`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SweptDepositGraph {
+    pub kickoff_utxos: Vec<UTXO>,
+    pub slash_or_take_sigs: Vec<schnorr::Signature>,
+    pub burn_sigs: Vec<schnorr::Signature>,
+    pub operator_takes_sigs: Vec<schnorr::Signature>,
+}
+
+/// Sweep of deposits' bridge funds from an epoch to the next one, with the
+/// sighashes that verifiers of the old epoch sign. Every actor builds the
+/// sweep from the same deposits, so they agree on the sweep_tx.
+#[derive(Debug, Clone)]
+pub struct EpochSweep {
+    deposit_outpoints: Vec<OutPoint>,
+    sweep_tx: TxHandler,
+    sighashes: Vec<GraphSighash>,
+}
+
+impl EpochSweep {
+    /// Builds the sweep of the deposits, in the given order. Every deposit is
+    /// given with the deposits of its move_tx, which is signed under
+    /// `old_epoch`.
+    ///
+    /// # Errors
+    ///
+    /// See [`builder::transaction::create_sweep_tx_handler`].
+    pub fn new(
+        deposits: &[(OutPoint, Vec<MoveTxDeposit>)],
+        old_epoch: &VerifierEpoch,
+        new_epoch: &VerifierEpoch,
+        config: &BridgeConfig,
+    ) -> Result<Self, BridgeError> {
+        let bridge_fund_utxos = deposits
+            .iter()
+            .map(|(deposit_outpoint, move_tx_deposits)| {
+                bridge_fund_utxo(
+                    move_tx_deposits,
+                    *deposit_outpoint,
+                    old_epoch.nofn_xonly_pk,
+                    config,
+                )
+            })
+            .collect::<Result<Vec<_>, BridgeError>>()?;
+
+        let mut sweep_tx = builder::transaction::create_sweep_tx_handler(
+            &bridge_fund_utxos,
+            old_epoch.nofn_xonly_pk,
+            new_epoch.nofn_xonly_pk,
+            config.network,
+            fee::presigned_fee_rate(config)?,
+        )?;
+
+        // Bridge funds are spent with the key path of the N-of-N key.
+        let sighashes = (0..deposits.len())
+            .map(|txin_index| {
+                Ok(GraphSighash {
+                    sighash: ByteArray32(
+                        Actor::convert_tx_to_sighash_pubkey_spend(&mut sweep_tx, txin_index)?
+                            .to_byte_array(),
+                    ),
+                    tweak: None,
+                    tweak_flag: true,
+                })
+            })
+            .collect::<Result<Vec<_>, BridgeError>>()?;
+
+        Ok(EpochSweep {
+            deposit_outpoints: deposits
+                .iter()
+                .map(|(deposit_outpoint, _)| *deposit_outpoint)
+                .collect(),
+            sweep_tx,
+            sighashes,
+        })
+    }
+
+    /// Deposits of the sweep, in the order of the sweep_tx inputs.
+    pub fn deposit_outpoints(&self) -> &[OutPoint] {
+        &self.deposit_outpoints
+    }
+
+    pub fn sweep_tx(&self) -> &TxHandler {
+        &self.sweep_tx
+    }
+
+    /// Sighashes of the sweep_tx inputs, in input order.
+    pub fn sighashes(&self) -> &[GraphSighash] {
+        &self.sighashes
+    }
+
+    /// Builds the next epoch's graph of the deposit at `deposit_idx` of the
+    /// sweep, which spends its swept output.
+    pub fn deposit_graph(
+        &self,
+        deposit_idx: usize,
+        kickoff_utxos: Vec<UTXO>,
+        new_epoch: &VerifierEpoch,
+        config: &BridgeConfig,
+    ) -> Result<DepositGraph, BridgeError> {
+        DepositGraph::new_swept(
+            self.sweep_tx.clone(),
+            deposit_idx,
+            self.sighashes[deposit_idx],
+            kickoff_utxos,
+            new_epoch.nofn_xonly_pk,
+            config,
+        )
+    }
+
+    /// Checks that every deposit of the sweep has a graph under `new_epoch`,
+    /// in the order of the sweep's deposits, whose slash_or_take_txs,
+    /// burn_txs and operator_takes_txs are signed with the new N-of-N key.
+    /// Otherwise, operators couldn't be reimbursed from the swept bridge
+    /// funds.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BridgeError::SweptDepositGraphNotFound`] if a deposit has no
+    /// graph and an invalid signature error if a signature of a graph can't
+    /// be verified.
+    pub fn check_swept_graphs(
+        &self,
+        swept_graphs: &[SweptDepositGraph],
+        new_epoch: &VerifierEpoch,
+        config: &BridgeConfig,
+    ) -> Result<(), BridgeError> {
+        if let Some(deposit_outpoint) = self.deposit_outpoints.get(swept_graphs.len()) {
+            return Err(BridgeError::SweptDepositGraphNotFound(*deposit_outpoint));
+        }
+
+        for (deposit_idx, swept_graph) in swept_graphs
+            .iter()
+            .take(self.deposit_outpoints.len())
+            .enumerate()
+        {
+            let graph = self.deposit_graph(
+                deposit_idx,
+                swept_graph.kickoff_utxos.clone(),
+                new_epoch,
+                config,
+            )?;
+            let num_operators = graph.kickoff_utxos().len();
+
+            if swept_graph.slash_or_take_sigs.len() != num_operators {
+                return Err(BridgeError::InvalidSlashOrTakeSignatures);
+            }
+            for (sig, sighash) in swept_graph
+                .slash_or_take_sigs
+                .iter()
+                .zip(graph.slash_or_take_sighashes())
+            {
+                SECP.verify_schnorr(
+                    sig,
+                    &Message::from_digest(sighash.sighash.0),
+                    &new_epoch.nofn_xonly_pk,
+                )
+                .map_err(|_| BridgeError::InvalidSlashOrTakeSignatures)?;
+            }
+
+            if swept_graph.burn_sigs.len() != num_operators {
+                return Err(BridgeError::InvalidBurnSignatures);
+            }
+            for ((sig, sighash), burn_tx) in swept_graph
+                .burn_sigs
+                .iter()
+                .zip(graph.burn_sighashes())
+                .zip(graph.burn_txs())
+            {
+                SECP.verify_schnorr(
+                    sig,
+                    &Message::from_digest(sighash.sighash.0),
+                    &burn_tx.taproot_spend_infos[0].output_key().to_inner(),
+                )
+                .map_err(|_| BridgeError::InvalidBurnSignatures)?;
+            }
+
+            if swept_graph.operator_takes_sigs.len() != num_operators {
+                return Err(BridgeError::InvalidOperatorTakeSignatures);
+            }
+            for ((sig, sighash), operator_takes_tx) in swept_graph
+                .operator_takes_sigs
+                .iter()
+                .zip(graph.operator_takes_sighashes())
+                .zip(graph.operator_takes_txs())
+            {
+                SECP.verify_schnorr(
+                    sig,
+                    &Message::from_digest(sighash.sighash.0),
+                    &operator_takes_tx.taproot_spend_infos[0]
+                        .output_key()
+                        .to_inner(),
+                )
+                .map_err(|_| BridgeError::InvalidOperatorTakeSignatures)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EpochSweep, SweptDepositGraph, VerifierEpoch};
+    use crate::builder::{
+        deposit_graph::{DepositGraph, GraphSighash},
+        transaction::MoveTxDeposit,
+    };
+    use crate::config::BridgeConfig;
+    use crate::database::Database;
+    use crate::errors::BridgeError;
+    use crate::mock::{common, database::create_test_config_with_thread_name};
+    use crate::musig2::{self, NofnContext};
+    use crate::utils::{self, SECP};
+    use crate::{builder, EVMAddress, UTXO};
+    use bitcoin::{hashes::Hash, Address, OutPoint, TxOut, Txid};
+    use secp256k1::{rand, schnorr, Keypair, Message, XOnlyPublicKey};
+
+    fn create_move_tx_deposit(vout: u32, config: &crate::config::BridgeConfig) -> MoveTxDeposit {
+        MoveTxDeposit {
+            deposit_outpoint: OutPoint {
+                txid: Txid::all_zeros(),
+                vout,
+            },
+            recovery_taproot_address: Address::p2tr(
+                &SECP,
+                config.operators_xonly_pks[0],
+                None,
+                config.network,
+            )
+            .as_unchecked()
+            .clone(),
+            evm_address: EVMAddress([vout as u8; 20]),
+            bridge_amount_sats: config.bridge_amounts_sats[0],
+        }
+    }
+
+    fn create_kickoff_utxos(nofn_xonly_pk: XOnlyPublicKey, config: &BridgeConfig) -> Vec<UTXO> {
+        config
+            .operators_xonly_pks
+            .iter()
+            .enumerate()
+            .map(|(i, operator_xonly_pk)| UTXO {
+                outpoint: OutPoint {
+                    txid: Txid::all_zeros(),
+                    vout: 100 + i as u32,
+                },
+                txout: TxOut {
+                    value: builder::transaction::KICKOFF_UTXO_AMOUNT_SATS,
+                    script_pubkey: builder::address::create_kickoff_address(
+                        nofn_xonly_pk,
+                        *operator_xonly_pk,
+                        config.network,
+                    )
+                    .unwrap()
+                    .0
+                    .script_pubkey(),
+                },
+            })
+            .collect()
+    }
+
+    /// Signs the sighash with MuSig2 by every verifier of `nofn`.
+    fn sign(
+        nofn: &NofnContext,
+        keypairs: &[Keypair],
+        sighash: &GraphSighash,
+    ) -> schnorr::Signature {
+        let nonces = keypairs
+            .iter()
+            .map(|keypair| musig2::nonce_pair(keypair, &mut rand::rngs::OsRng).unwrap())
+            .collect::<Vec<_>>();
+        let agg_nonce =
+            musig2::aggregate_nonces(nonces.iter().map(|(_, pub_nonce)| *pub_nonce).collect())
+                .unwrap();
+        let partial_sigs = keypairs
+            .iter()
+            .zip(&nonces)
+            .map(|(keypair, (sec_nonce, _))| {
+                nofn.partial_sign(
+                    sighash.tweak,
+                    sighash.tweak_flag,
+                    *sec_nonce,
+                    agg_nonce,
+                    keypair,
+                    sighash.sighash,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let sig = nofn
+            .aggregate_partial_signatures(
+                sighash.tweak,
+                sighash.tweak_flag,
+                &agg_nonce,
+                partial_sigs,
+                sighash.sighash,
+            )
+            .unwrap();
+
+        schnorr::Signature::from_slice(&sig).unwrap()
+    }
+
+    #[tokio::test]
+    async fn save_epochs() {
+        let mut config = create_test_config_with_thread_name("test_config.toml", None).await;
+        let db = Database::new(&config).await.unwrap();
+        let nofn = NofnContext::from_config(&config).unwrap();
+
+        let next_verifiers_public_keys = config.verifiers_public_keys[1..].to_vec();
+        config.next_verifiers_public_keys = Some(next_verifiers_public_keys.clone());
+        super::save_config_epochs(&db, &config, &nofn)
+            .await
+            .unwrap();
+        // Saving the same epochs again is a no-op.
+        super::save_config_epochs(&db, &config, &nofn)
+            .await
+            .unwrap();
+
+        let current_epoch = VerifierEpoch::load(&db, config.verifier_epoch)
+            .await
+            .unwrap();
+        assert_eq!(current_epoch, VerifierEpoch::current(&config, &nofn));
+        let next_epoch = VerifierEpoch::load(&db, config.verifier_epoch + 1)
+            .await
+            .unwrap();
+        assert_eq!(next_epoch.verifiers_public_keys, next_verifiers_public_keys);
+        assert_eq!(
+            next_epoch.nofn_xonly_pk,
+            NofnContext::new(next_verifiers_public_keys)
+                .unwrap()
+                .xonly_pk()
+        );
+        assert!(matches!(
+            VerifierEpoch::load(&db, config.verifier_epoch + 2).await,
+            Err(BridgeError::VerifierEpochNotFound(_))
+        ));
+
+        // Verifier set of an epoch can't change.
+        config.next_verifiers_public_keys = Some(config.verifiers_public_keys[2..].to_vec());
+        assert!(matches!(
+            super::save_config_epochs(&db, &config, &nofn).await,
+            Err(BridgeError::VerifierEpochMismatch(epoch)) if epoch == config.verifier_epoch + 1
+        ));
+    }
+
+    #[test]
+    fn sweep_is_signed_by_old_epoch() {
+        let mut config = common::get_test_config("test_config.toml").unwrap();
+        let secret_keys = config.all_verifiers_secret_keys.clone().unwrap();
+        let nofn = NofnContext::from_config(&config).unwrap();
+        config.next_verifiers_public_keys = Some(config.verifiers_public_keys[1..].to_vec());
+        let old_epoch = VerifierEpoch::current(&config, &nofn);
+        let new_epoch = VerifierEpoch::next(&config).unwrap().unwrap();
+
+        // A batched deposit and a deposit with its own move_tx.
+        let batch = vec![
+            create_move_tx_deposit(0, &config),
+            create_move_tx_deposit(1, &config),
+        ];
+        let deposit = create_move_tx_deposit(2, &config);
+        let deposits = vec![
+            (batch[1].deposit_outpoint, batch.clone()),
+            (deposit.deposit_outpoint, vec![deposit.clone()]),
+        ];
+        let sweep = EpochSweep::new(&deposits, &old_epoch, &new_epoch, &config).unwrap();
+        assert_eq!(
+            sweep.deposit_outpoints(),
+            [batch[1].deposit_outpoint, deposit.deposit_outpoint]
+        );
+
+        // Sweep spends the bridge fund outputs of the deposit graphs.
+        let kickoff_utxos = create_kickoff_utxos(nofn.xonly_pk(), &config);
+        for (txin, (deposit_outpoint, move_tx_deposits)) in
+            sweep.sweep_tx().tx.input.iter().zip(&deposits)
+        {
+            let graph = DepositGraph::new(
+                move_tx_deposits,
+                *deposit_outpoint,
+                kickoff_utxos.clone(),
+                &nofn,
+                &config,
+            )
+            .unwrap();
+            assert_eq!(txin.previous_output, graph.bridge_fund_outpoint());
+        }
+
+        // Verifiers of the old epoch sign every input of the sweep_tx.
+        let keypairs = secret_keys
+            .iter()
+            .map(|secret_key| Keypair::from_secret_key(&SECP, secret_key))
+            .collect::<Vec<_>>();
+        let mut sweep_tx = sweep.sweep_tx().clone();
+        for (txin_index, sighash) in sweep.sighashes().iter().enumerate() {
+            let sig = sign(&nofn, &keypairs, sighash);
+
+            SECP.verify_schnorr(
+                &sig,
+                &Message::from_digest(sighash.sighash.0),
+                &sweep_tx.taproot_spend_infos[txin_index]
+                    .output_key()
+                    .to_inner(),
+            )
+            .unwrap();
+            utils::handle_taproot_witness_new(&mut sweep_tx, &[sig.serialize()], txin_index, None)
+                .unwrap();
+        }
+
+        // Bridge funds are locked to the new epoch's N-of-N key.
+        let (new_musig2_address, _) =
+            builder::address::create_musig2_address(new_epoch.nofn_xonly_pk, config.network)
+                .unwrap();
+        for (txout, prevout) in sweep_tx.tx.output.iter().zip(&sweep_tx.prevouts) {
+            assert_eq!(txout.script_pubkey, new_musig2_address.script_pubkey());
+            assert!(txout.value < prevout.value);
+        }
+    }
+
+    #[test]
+    fn sweep_needs_graphs_of_new_epoch() {
+        let mut config = common::get_test_config("test_config.toml").unwrap();
+        let secret_keys = config.all_verifiers_secret_keys.clone().unwrap();
+        let nofn = NofnContext::from_config(&config).unwrap();
+        let next_verifiers_public_keys = config.verifiers_public_keys[1..].to_vec();
+        config.next_verifiers_public_keys = Some(next_verifiers_public_keys.clone());
+        let old_epoch = VerifierEpoch::current(&config, &nofn);
+        let new_epoch = VerifierEpoch::next(&config).unwrap().unwrap();
+        let new_nofn = NofnContext::new(next_verifiers_public_keys).unwrap();
+        let old_keypairs = secret_keys
+            .iter()
+            .map(|secret_key| Keypair::from_secret_key(&SECP, secret_key))
+            .collect::<Vec<_>>();
+        let new_keypairs = old_keypairs[1..].to_vec();
+
+        let deposit = create_move_tx_deposit(0, &config);
+        let deposits = vec![(deposit.deposit_outpoint, vec![deposit.clone()])];
+        let sweep = EpochSweep::new(&deposits, &old_epoch, &new_epoch, &config).unwrap();
+
+        // New graph spends the swept output with the new epoch's key.
+        let kickoff_utxos = create_kickoff_utxos(new_epoch.nofn_xonly_pk, &config);
+        let graph = sweep
+            .deposit_graph(0, kickoff_utxos.clone(), &new_epoch, &config)
+            .unwrap();
+        assert_eq!(
+            graph.bridge_fund_outpoint(),
+            OutPoint {
+                txid: sweep.sweep_tx().tx.compute_txid(),
+                vout: 0,
+            }
+        );
+        for operator_takes_tx in graph.operator_takes_txs() {
+            assert_eq!(
+                operator_takes_tx.tx.input[0].previous_output,
+                graph.bridge_fund_outpoint()
+            );
+        }
+
+        let sign_all = |nofn: &NofnContext, keypairs: &[Keypair], sighashes: &[GraphSighash]| {
+            sighashes
+                .iter()
+                .map(|sighash| sign(nofn, keypairs, sighash))
+                .collect::<Vec<_>>()
+        };
+        let swept_graph = SweptDepositGraph {
+            kickoff_utxos: kickoff_utxos.clone(),
+            slash_or_take_sigs: sign_all(&new_nofn, &new_keypairs, graph.slash_or_take_sighashes()),
+            burn_sigs: sign_all(&new_nofn, &new_keypairs, graph.burn_sighashes()),
+            operator_takes_sigs: sign_all(
+                &new_nofn,
+                &new_keypairs,
+                graph.operator_takes_sighashes(),
+            ),
+        };
+        sweep
+            .check_swept_graphs(&[swept_graph.clone()], &new_epoch, &config)
+            .unwrap();
+
+        assert!(matches!(
+            sweep.check_swept_graphs(&[], &new_epoch, &config),
+            Err(BridgeError::SweptDepositGraphNotFound(deposit_outpoint))
+                if deposit_outpoint == deposit.deposit_outpoint
+        ));
+
+        // Signatures of the old epoch can't spend the swept output.
+        let old_epoch_graph = SweptDepositGraph {
+            operator_takes_sigs: sign_all(&nofn, &old_keypairs, graph.operator_takes_sighashes()),
+            ..swept_graph.clone()
+        };
+        assert!(matches!(
+            sweep.check_swept_graphs(&[old_epoch_graph], &new_epoch, &config),
+            Err(BridgeError::InvalidOperatorTakeSignatures)
+        ));
+
+        // Kickoff UTXOs must be locked to the new epoch's key.
+        let old_kickoffs_graph = SweptDepositGraph {
+            kickoff_utxos: create_kickoff_utxos(nofn.xonly_pk(), &config),
+            ..swept_graph
+        };
+        assert!(matches!(
+            sweep.check_swept_graphs(&[old_kickoffs_graph], &new_epoch, &config),
+            Err(BridgeError::InvalidKickoffUtxo)
+        ));
+    }
+}
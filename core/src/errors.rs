@@ -2,7 +2,9 @@
 //!
 //! This module defines errors, returned by the library.
 
-use bitcoin::{consensus::encode::FromHexError, merkle_tree::MerkleBlockError, Txid};
+use bitcoin::{
+    consensus::encode::FromHexError, merkle_tree::MerkleBlockError, Amount, OutPoint, Txid,
+};
 use core::fmt::Debug;
 use jsonrpsee::types::ErrorObject;
 use musig2::secp::errors::InvalidScalarBytes;
@@ -24,6 +26,21 @@ pub enum BridgeError {
     /// Returned when an invalid deposit UTXO is found
     #[error("InvalidDepositUTXO")]
     InvalidDepositUTXO,
+    /// Returned when the amount of a deposit is not one of the bridge amounts
+    #[error("InvalidBridgeAmount: {0}")]
+    InvalidBridgeAmount(Amount),
+    /// Returned when the block of a deposit is reorged out and the deposit is
+    /// not confirmed again yet
+    #[error("DepositReorged: {0}")]
+    DepositReorged(OutPoint),
+    /// Returned when a deposit is not a part of the move_tx batch it is
+    /// signed with, or it is already signed with another move_tx
+    #[error("InvalidDepositBatch: {0}")]
+    InvalidDepositBatch(OutPoint),
+    /// Returned when the depositor of a batched deposit can take it back
+    /// before the move_tx of the batch is confirmed
+    #[error("DepositBatchRecoveryTooClose: {0}")]
+    DepositBatchRecoveryTooClose(OutPoint),
     /// Returned when a UTXO is already spent
     #[error("UTXOSpent")]
     UTXOSpent,
@@ -123,6 +140,29 @@ pub enum BridgeError {
     /// InvalidKickoffUtxo is returned when the kickoff utxo is invalid
     #[error("InvalidKickoffUtxo")]
     InvalidKickoffUtxo,
+    /// InvalidSlashOrTakeUtxo is returned when the slash or take utxo is not
+    /// locked to the expected script
+    #[error("InvalidSlashOrTakeUtxo")]
+    InvalidSlashOrTakeUtxo,
+    /// InvalidRecoveryAddress is returned when the recovery address of a
+    /// deposit is not a taproot address
+    #[error("InvalidRecoveryAddress")]
+    InvalidRecoveryAddress,
+    /// EmptyDepositBatch is returned when a move tx is requested for no deposits
+    #[error("EmptyDepositBatch")]
+    EmptyDepositBatch,
+    /// PushBytesError is returned when data can't be pushed to a script
+    #[error("PushBytesError: {0}")]
+    PushBytesError(#[from] bitcoin::script::PushBytesError),
+    /// InvalidBurnSignatures is returned when burn tx signatures are missing or invalid
+    #[error("InvalidBurnSignatures")]
+    InvalidBurnSignatures,
+    /// InvalidSlashOrTakeSignatures is returned when slash_or_take tx signatures are missing or invalid
+    #[error("InvalidSlashOrTakeSignatures")]
+    InvalidSlashOrTakeSignatures,
+    /// InvalidOperatorTakeSignatures is returned when operator_takes tx signatures are missing or invalid
+    #[error("InvalidOperatorTakeSignatures")]
+    InvalidOperatorTakeSignatures,
 
     #[error("KeyAggContextError: {0}")]
     KeyAggContextError(#[from] musig2::errors::KeyAggError),
@@ -139,6 +179,158 @@ pub enum BridgeError {
     #[error("MuSig2VerifyError: {0}")]
     MuSig2VerifyError(#[from] musig2::errors::VerifyError),
 
+    #[error("InvalidPartialSignatures: partial signatures of verifiers {0:?} are invalid")]
+    InvalidPartialSignatures(Vec<usize>),
+
+    #[error("MuSig2SigningError: {0}")]
+    MuSig2SigningError(#[from] musig2::errors::SigningError),
+
+    /// InvalidMuSigPublicKey is returned with the index of a public key that
+    /// can't be used in MuSig2 key aggregation
+    #[error("InvalidMuSigPublicKey: public key {0} is invalid")]
+    InvalidMuSigPublicKey(usize),
+
+    /// InvalidMuSigPubNonce is returned with the index of a public nonce that
+    /// is not a pair of valid curve points
+    #[error("InvalidMuSigPubNonce: public nonce {0} is invalid")]
+    InvalidMuSigPubNonce(usize),
+
+    /// InvalidMuSigPartialSignature is returned with the index of a partial
+    /// signature that is not a valid scalar
+    #[error("InvalidMuSigPartialSignature: partial signature {0} is invalid")]
+    InvalidMuSigPartialSignature(usize),
+
+    #[error("InvalidMuSigAggNonce")]
+    InvalidMuSigAggNonce,
+
+    #[error("InvalidMuSigSecNonce")]
+    InvalidMuSigSecNonce,
+
+    /// MuSigTweakWithoutTweakFlag is returned when a tweak is given for an
+    /// untweaked key aggregation context
+    #[error("MuSigTweakWithoutTweakFlag")]
+    MuSigTweakWithoutTweakFlag,
+
+    /// MuSigWithThresholdKey is returned when MuSig2 is used to sign with a
+    /// FROST group key
+    #[error("MuSigWithThresholdKey")]
+    MuSigWithThresholdKey,
+
+    #[error("FrostError: {0}")]
+    FrostError(#[from] frost_secp256k1_tr::Error),
+
+    /// InvalidFrostThreshold is returned when the threshold of a FROST group
+    /// is less than 2 or more than the number of verifiers
+    #[error("InvalidFrostThreshold: threshold {0} is invalid for {1} verifiers")]
+    InvalidFrostThreshold(u16, usize),
+
+    #[error("InvalidFrostNonce")]
+    InvalidFrostNonce,
+
+    /// InvalidFrostCommitment is returned with the identifier of a signer
+    /// whose commitment is not a pair of valid curve points
+    #[error("InvalidFrostCommitment: commitment of signer {0} is invalid")]
+    InvalidFrostCommitment(u16),
+
+    /// InvalidFrostSignatureShare is returned with the identifier of a signer
+    /// whose signature share is not a valid scalar
+    #[error("InvalidFrostSignatureShare: signature share of signer {0} is invalid")]
+    InvalidFrostSignatureShare(u16),
+
+    /// InvalidFrostDkgPackage is returned with the identifier of the verifier
+    /// whose DKG package is missing or can't be decrypted
+    #[error("InvalidFrostDkgPackage: package of verifier {0} is invalid")]
+    InvalidFrostDkgPackage(u16),
+
+    /// FrostDkgNotStarted is returned when a round of the DKG ceremony is run
+    /// before its previous round
+    #[error("FrostDkgNotStarted")]
+    FrostDkgNotStarted,
+
+    /// FrostNotEnabled is returned when FROST is used without a configured
+    /// threshold
+    #[error("FrostNotEnabled")]
+    FrostNotEnabled,
+
+    /// FrostKeyNotFound is returned when FROST is used before the DKG
+    /// ceremony
+    #[error("FrostKeyNotFound")]
+    FrostKeyNotFound,
+
+    /// FrostKeyAlreadyGenerated is returned when the DKG ceremony is started
+    /// again after a key is generated
+    #[error("FrostKeyAlreadyGenerated")]
+    FrostKeyAlreadyGenerated,
+
+    /// FrostGroupKeyMismatch is returned when the generated FROST group key is
+    /// not the configured one, or verifiers generated different keys
+    #[error("FrostGroupKeyMismatch")]
+    FrostGroupKeyMismatch,
+
+    /// InvalidFrostSigningSet is returned when the signers of a deposit are
+    /// not a valid signing set or differ from the saved ones
+    #[error("InvalidFrostSigningSet")]
+    InvalidFrostSigningSet,
+
+    /// NotEnoughFrostSigners is returned when less verifiers than the
+    /// threshold can sign a deposit
+    #[error("NotEnoughFrostSigners: {0} verifiers can sign, threshold is {1}")]
+    NotEnoughFrostSigners(usize, u16),
+
+    /// FrostNoncesAlreadySigned is returned when the FROST nonces of a deposit
+    /// are renewed after something is signed with them
+    #[error("FrostNoncesAlreadySigned: {0}")]
+    FrostNoncesAlreadySigned(OutPoint),
+
+    /// VerifierEpochMismatch is returned when the verifier set of an epoch is
+    /// different than the one saved for it
+    #[error("VerifierEpochMismatch: verifier set of epoch {0} is different")]
+    VerifierEpochMismatch(u32),
+
+    /// VerifierEpochNotFound is returned when an epoch's verifier set is not
+    /// known
+    #[error("VerifierEpochNotFound: {0}")]
+    VerifierEpochNotFound(u32),
+
+    /// InvalidDepositEpoch is returned when a deposit is not signed under the
+    /// expected verifier epoch
+    #[error("InvalidDepositEpoch: {0}")]
+    InvalidDepositEpoch(OutPoint),
+
+    /// DepositSessionMismatch is returned when a deposit is requested with
+    /// other deposit infos than its signing session is created with
+    #[error("DepositSessionMismatch: {0}")]
+    DepositSessionMismatch(OutPoint),
+
+    /// DepositSessionInProgress is returned when the signing session of a
+    /// deposit is already run by another call
+    #[error("DepositSessionInProgress: {0}")]
+    DepositSessionInProgress(OutPoint),
+
+    /// EmptySweep is returned when a sweep tx is requested for no deposits
+    #[error("EmptySweep")]
+    EmptySweep,
+
+    /// InvalidBridgeFundUtxo is returned when a bridge fund UTXO is not locked
+    /// to the N-of-N key, or it can't pay its share of the sweep tx's fee
+    #[error("InvalidBridgeFundUtxo: {0}")]
+    InvalidBridgeFundUtxo(OutPoint),
+
+    /// SweepNonceAlreadyUsed is returned when the sweep nonce of a deposit is
+    /// requested for another sweep tx
+    #[error("SweepNonceAlreadyUsed: {0}")]
+    SweepNonceAlreadyUsed(OutPoint),
+
+    /// DepositWithdrawalInProgress is returned when a deposit can't be swept,
+    /// because an operator has kicked off or paid a withdrawal of it
+    #[error("DepositWithdrawalInProgress: {0}")]
+    DepositWithdrawalInProgress(OutPoint),
+
+    /// SweptDepositGraphNotFound is returned when a deposit is swept without a
+    /// signed graph of the next epoch for its swept output
+    #[error("SweptDepositGraphNotFound: {0}")]
+    SweptDepositGraphNotFound(OutPoint),
+
     #[error("KickoffOutpointsNotFound")]
     KickoffOutpointsNotFound,
     #[error("DepositInfoNotFound")]
@@ -174,6 +366,70 @@ pub enum BridgeError {
     #[error("OperatorTakesSigNotFound")]
     OperatorTakesSigNotFound,
 
+    /// AnchorOutputNotFound is returned when a transaction has no anchor output for fee bumping
+    #[error("AnchorOutputNotFound")]
+    AnchorOutputNotFound,
+    /// NotEnoughFundsForCpfp is returned when wallet can't pay the fee of a CPFP child transaction
+    #[error("NotEnoughFundsForCpfp")]
+    NotEnoughFundsForCpfp,
+    /// NotEnoughFundsForFee is returned when the fee paying output of a transaction can't pay its fee
+    #[error("NotEnoughFundsForFee")]
+    NotEnoughFundsForFee,
+    /// FeeOverflow is returned when a fee doesn't fit in an amount
+    #[error("FeeOverflow")]
+    FeeOverflow,
+    /// PackageSubmissionError is returned when Bitcoin node rejects a transaction package
+    #[error("PackageSubmissionError: {0}")]
+    PackageSubmissionError(String),
+    /// PsbtError is returned when a PSBT can't be created
+    #[error("PsbtError: {0}")]
+    PsbtError(#[from] bitcoin::psbt::Error),
+    /// PsbtWitnessUtxoNotFound is returned when a PSBT input doesn't have its witness UTXO
+    #[error("PsbtWitnessUtxoNotFound: {0}")]
+    PsbtWitnessUtxoNotFound(usize),
+    /// PsbtTaprootInfoNotFound is returned when a PSBT input before a taproot input doesn't have its internal key
+    #[error("PsbtTaprootInfoNotFound: {0}")]
+    PsbtTaprootInfoNotFound(usize),
+    /// PsbtInputCountMismatch is returned when a PSBT doesn't have an input for every transaction input
+    #[error("PsbtInputCountMismatch: PSBT has {0} inputs, transaction has {1}")]
+    PsbtInputCountMismatch(usize, usize),
+    /// PsbtScriptOrderNotFound is returned when the script indexes of a PSBT input are missing or invalid
+    #[error("PsbtScriptOrderNotFound: {0}")]
+    PsbtScriptOrderNotFound(usize),
+    /// PsbtInvalidSignature is returned when a signature of a PSBT input doesn't verify against its sighash and key
+    #[error("PsbtInvalidSignature: {0}")]
+    PsbtInvalidSignature(usize),
+    /// RpcTimeout is returned when a Bitcoin RPC call doesn't return in time
+    #[error("RpcTimeout: {0:?}")]
+    RpcTimeout(std::time::Duration),
+    /// JoinError is returned when a blocking task panics or is cancelled
+    #[error("JoinError: {0}")]
+    JoinError(#[from] tokio::task::JoinError),
+
+    #[error("InvalidCitreaResponse: {0}")]
+    InvalidCitreaResponse(String),
+
+    #[error("WithdrawalNotFilled: {0}")]
+    WithdrawalNotFilled(u32),
+
+    #[error("DepositNotFoundOnCitrea: {0}")]
+    DepositNotFoundOnCitrea(OutPoint),
+
+    /// DepositTimelockNotMatured is returned when a deposit is recovered
+    /// before its timelock, with the number of blocks left
+    #[error("DepositTimelockNotMatured: {0} needs {1} more blocks")]
+    DepositTimelockNotMatured(OutPoint, u32),
+
+    /// InvalidRelativeTimelock is returned when a timelock doesn't fit in the
+    /// 16 bits of a relative timelock
+    #[error("InvalidRelativeTimelock: {0} blocks")]
+    InvalidRelativeTimelock(u32),
+
+    /// KickoffChecksFailed is returned when some kickoffs can't be checked by
+    /// the watchtower, with their number
+    #[error("KickoffChecksFailed: {0} kickoffs can't be checked")]
+    KickoffChecksFailed(usize),
+
     #[error("Musig2 error: {0}")]
     Musig2Error(#[from] musig2::secp256k1::Error),
 }
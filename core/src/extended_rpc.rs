@@ -3,67 +3,335 @@
 //! This module provides helpful functions for Bitcoin RPC.
 
 use crate::builder;
+use crate::config::BridgeConfig;
 use crate::errors::BridgeError;
-use crate::EVMAddress;
+use crate::{EVMAddress, UTXO};
 use bitcoin::address::NetworkUnchecked;
+use bitcoin::block::Header;
+use bitcoin::consensus::deserialize;
 use bitcoin::Address;
 use bitcoin::Amount;
+use bitcoin::BlockHash;
+use bitcoin::FeeRate;
 use bitcoin::OutPoint;
 use bitcoin::ScriptBuf;
 use bitcoin::Transaction;
 use bitcoin::TxOut;
+use bitcoin::Txid;
 use bitcoin::XOnlyPublicKey;
 use bitcoin_mock_rpc::RpcApiWrapper;
+use bitcoincore_rpc::json::SignRawTransactionInput;
 use bitcoincore_rpc::Auth;
+use bitcoincore_rpc::RawTx;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// Maximum number of Bitcoin RPC calls that can run at the same time.
+pub const MAX_CONCURRENT_RPC_CALLS: usize = 16;
+/// Time limit of a single Bitcoin RPC call.
+pub const RPC_CALL_TIMEOUT: Duration = Duration::from_secs(30);
+/// Error code of Bitcoin RPC while the node is starting.
+const RPC_IN_WARMUP: i32 = -28;
+/// Error code of Bitcoin RPC when a transaction is not in the mempool or the
+/// chain.
+const RPC_INVALID_ADDRESS_OR_KEY: i32 = -5;
+/// Error code of Bitcoin RPC when a transaction is rejected by the mempool
+/// policy or the consensus rules.
+const RPC_VERIFY_REJECTED: i32 = -26;
+/// Reject reasons of Bitcoin Core for transactions that pay too little fee.
+const FEE_REJECT_REASONS: [&str; 3] = [
+    "min relay fee not met",
+    "mempool min fee not met",
+    "insufficient fee",
+];
+
+/// Limits and retry behaviour of the Bitcoin RPC calls.
+#[derive(Debug, Clone, Copy)]
+pub struct RpcOptions {
+    /// Maximum number of calls that can run at the same time.
+    pub max_concurrent_calls: usize,
+    /// Time limit of a single call.
+    pub call_timeout: Duration,
+    /// Number of times a read-only or idempotent call is retried after a
+    /// transient failure.
+    pub max_retries: u32,
+    /// Wait time before the first retry. It is doubled after every retry.
+    pub retry_backoff: Duration,
+}
+
+impl RpcOptions {
+    pub fn from_config(config: &BridgeConfig) -> Self {
+        RpcOptions {
+            max_retries: config.bitcoin_rpc_max_retries,
+            retry_backoff: Duration::from_millis(config.bitcoin_rpc_retry_backoff_ms),
+            ..Default::default()
+        }
+    }
+}
 
+impl Default for RpcOptions {
+    fn default() -> Self {
+        RpcOptions {
+            max_concurrent_calls: MAX_CONCURRENT_RPC_CALLS,
+            call_timeout: RPC_CALL_TIMEOUT,
+            max_retries: 0,
+            retry_backoff: Duration::ZERO,
+        }
+    }
+}
+
+/// Bitcoin RPC clients that are not in use. A client is created when every
+/// existing client is busy, until the concurrency limit is reached. Clients
+/// connect to the active endpoint, which changes when it is unreachable.
 #[derive(Debug)]
-pub struct ExtendedRpc<R> {
-    url: String,
+struct ClientPool<R> {
+    urls: Vec<String>,
     auth: Auth,
-    pub client: R,
+    active_url: AtomicUsize,
+    idle_clients: Mutex<Vec<(usize, R)>>,
+    permits: Arc<Semaphore>,
+}
+
+impl<R> ClientPool<R>
+where
+    R: RpcApiWrapper,
+{
+    fn take(&self, url_idx: usize) -> Result<R, bitcoincore_rpc::Error> {
+        let mut idle_clients = self.idle_clients.lock().unwrap();
+        // Clients of the previous endpoints are not used anymore.
+        idle_clients.retain(|(client_url_idx, _)| *client_url_idx == url_idx);
+
+        match idle_clients.pop() {
+            Some((_, client)) => Ok(client),
+            None => R::new(&self.urls[url_idx], self.auth.clone()),
+        }
+    }
+
+    fn put(&self, url_idx: usize, client: R) {
+        if url_idx == self.active_url.load(Ordering::Acquire) {
+            self.idle_clients.lock().unwrap().push((url_idx, client));
+        }
+    }
+
+    /// Switches to the next endpoint, unless another call already switched
+    /// away from the failed one.
+    fn fail_over(&self, failed_url_idx: usize) {
+        let next_url_idx = (failed_url_idx + 1) % self.urls.len();
+
+        if next_url_idx != failed_url_idx
+            && self
+                .active_url
+                .compare_exchange(
+                    failed_url_idx,
+                    next_url_idx,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                )
+                .is_ok()
+        {
+            tracing::warn!(
+                "Bitcoin RPC {} is unreachable, failing over to {}",
+                self.urls[failed_url_idx],
+                self.urls[next_url_idx]
+            );
+        }
+    }
+}
+
+/// Async Bitcoin RPC. Blocking RPC calls are run on tokio's blocking threads,
+/// so that they don't block the async servers. Calls are limited and retried
+/// as described in [`RpcOptions`].
+#[derive(Debug)]
+pub struct ExtendedRpc<R> {
+    pool: Arc<ClientPool<R>>,
+    options: RpcOptions,
 }
 
 impl<R> ExtendedRpc<R>
 where
     R: RpcApiWrapper,
 {
-    /// Connects to Bitcoin RPC and returns a new `ExtendedRpc`.
-    ///
-    /// # Panics
-    ///
-    /// Panics if it cannot connect to Bitcoin RPC.
-    pub fn new(url: String, user: String, password: String) -> Self {
+    /// Returns a new `ExtendedRpc` for a single Bitcoin RPC endpoint, without
+    /// retries.
+    pub fn new(url: String, user: String, password: String) -> Result<Self, BridgeError> {
+        Self::with_endpoints(vec![url], user, password, RpcOptions::default())
+    }
+
+    /// Returns a new `ExtendedRpc` for `bitcoin_rpc_url`, which fails over to
+    /// `bitcoin_rpc_fallback_urls`.
+    pub fn from_config(config: &BridgeConfig) -> Result<Self, BridgeError> {
+        let urls = std::iter::once(config.bitcoin_rpc_url.clone())
+            .chain(config.bitcoin_rpc_fallback_urls.iter().cloned())
+            .collect();
+
+        Self::with_endpoints(
+            urls,
+            config.bitcoin_rpc_user.clone(),
+            config.bitcoin_rpc_password.clone(),
+            RpcOptions::from_config(config),
+        )
+    }
+
+    /// Returns a new `ExtendedRpc` that uses the first endpoint a client can be
+    /// created for and fails over to the next ones in order.
+    pub fn with_endpoints(
+        urls: Vec<String>,
+        user: String,
+        password: String,
+        options: RpcOptions,
+    ) -> Result<Self, BridgeError> {
         let auth = Auth::UserPass(user, password);
 
-        let rpc = R::new(&url, auth.clone())
-            .unwrap_or_else(|e| panic!("Failed to connect to Bitcoin RPC: {}", e));
+        let mut last_error = None;
+        for (url_idx, url) in urls.iter().enumerate() {
+            match R::new(url, auth.clone()) {
+                Ok(client) => {
+                    return Ok(Self {
+                        pool: Arc::new(ClientPool {
+                            urls,
+                            auth,
+                            active_url: AtomicUsize::new(url_idx),
+                            idle_clients: Mutex::new(vec![(url_idx, client)]),
+                            permits: Arc::new(Semaphore::new(options.max_concurrent_calls)),
+                        }),
+                        options,
+                    });
+                }
+                Err(e) => {
+                    tracing::warn!("Can't create Bitcoin RPC client for {}: {}", url, e);
+                    last_error = Some(e);
+                }
+            }
+        }
 
-        Self {
-            url,
-            auth,
-            client: rpc,
+        match last_error {
+            Some(e) => Err(e.into()),
+            None => Err(BridgeError::ConfigError(
+                "No Bitcoin RPC endpoint is given".to_string(),
+            )),
+        }
+    }
+
+    /// Runs a blocking RPC call on a blocking thread. If the call times out,
+    /// its client and concurrency slot are released after it returns.
+    /// Transient failures are retried with backoff, on the next endpoint.
+    ///
+    /// A failed call might still have reached the node, so this is only for
+    /// read-only or idempotent calls. Others should use
+    /// [`ExtendedRpc::call_once`].
+    async fn call<T, F>(&self, f: F) -> Result<T, BridgeError>
+    where
+        T: Send + 'static,
+        F: Fn(&R) -> Result<T, bitcoincore_rpc::Error> + Send + Sync + 'static,
+    {
+        self.call_with_retries(f, self.options.max_retries).await
+    }
+
+    /// Runs a blocking RPC call like [`ExtendedRpc::call`], but surfaces
+    /// every failure instead of retrying it. Used for calls that change the
+    /// state of the node or its wallet, like broadcasting transactions or
+    /// mining blocks.
+    async fn call_once<T, F>(&self, f: F) -> Result<T, BridgeError>
+    where
+        T: Send + 'static,
+        F: Fn(&R) -> Result<T, bitcoincore_rpc::Error> + Send + Sync + 'static,
+    {
+        self.call_with_retries(f, 0).await
+    }
+
+    async fn call_with_retries<T, F>(&self, f: F, max_retries: u32) -> Result<T, BridgeError>
+    where
+        T: Send + 'static,
+        F: Fn(&R) -> Result<T, bitcoincore_rpc::Error> + Send + Sync + 'static,
+    {
+        let f = Arc::new(f);
+        let mut backoff = self.options.retry_backoff;
+        let mut retries = 0;
+
+        loop {
+            let permit = Arc::clone(&self.pool.permits)
+                .acquire_owned()
+                .await
+                .expect("Semaphore is never closed");
+            let pool = Arc::clone(&self.pool);
+            let f = Arc::clone(&f);
+
+            let task = tokio::task::spawn_blocking(move || {
+                let url_idx = pool.active_url.load(Ordering::Acquire);
+                let result = pool.take(url_idx).and_then(|client| {
+                    let result = f(&client);
+                    pool.put(url_idx, client);
+
+                    result
+                });
+                drop(permit);
+
+                (url_idx, result)
+            });
+
+            let (url_idx, result) =
+                match tokio::time::timeout(self.options.call_timeout, task).await {
+                    Ok(joined) => joined?,
+                    Err(_) => return Err(BridgeError::RpcTimeout(self.options.call_timeout)),
+                };
+
+            match result {
+                Err(e) if retries < max_retries && is_transient(&e) => {
+                    tracing::warn!("Bitcoin RPC call failed: {}, retrying in {:?}", e, backoff);
+                    self.pool.fail_over(url_idx);
+
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    retries += 1;
+                }
+                result => return Ok(result?),
+            }
         }
     }
 
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub fn confirmation_blocks(&self, txid: &bitcoin::Txid) -> Result<u32, BridgeError> {
-        let raw_transaction_results = self.client.get_raw_transaction_info(txid, None)?;
+    pub async fn confirmation_blocks(&self, txid: &bitcoin::Txid) -> Result<u32, BridgeError> {
+        let txid = *txid;
+        let raw_transaction_results = self
+            .call(move |client| client.get_raw_transaction_info(&txid, None))
+            .await?;
 
         raw_transaction_results
             .confirmations
             .ok_or(BridgeError::NoConfirmationData)
     }
 
+    /// Returns the hash of the block that the transaction is confirmed in and
+    /// its number of confirmations.
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub fn check_utxo_address_and_amount(
+    pub async fn confirmation_block_hash(
+        &self,
+        txid: &bitcoin::Txid,
+    ) -> Result<(BlockHash, u32), BridgeError> {
+        let txid = *txid;
+        let raw_transaction_results = self
+            .call(move |client| client.get_raw_transaction_info(&txid, None))
+            .await?;
+
+        match (
+            raw_transaction_results.blockhash,
+            raw_transaction_results.confirmations,
+        ) {
+            (Some(block_hash), Some(confirmations)) => Ok((block_hash, confirmations)),
+            _ => Err(BridgeError::NoConfirmationData),
+        }
+    }
+
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn check_utxo_address_and_amount(
         &self,
         outpoint: &OutPoint,
         address: &ScriptBuf,
         amount_sats: Amount,
     ) -> Result<bool, BridgeError> {
-        let tx = self.client.get_raw_transaction(&outpoint.txid, None)?;
-
-        let current_output = tx.output[outpoint.vout as usize].clone();
+        let current_output = self.get_txout_from_outpoint(outpoint).await?;
 
         let expected_output = TxOut {
             script_pubkey: address.clone(),
@@ -73,110 +341,304 @@ where
         Ok(expected_output == current_output)
     }
 
+    /// Returns true if the transaction is in the mempool or the chain.
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub fn is_utxo_spent(&self, outpoint: &OutPoint) -> Result<bool, BridgeError> {
+    pub async fn is_tx_known(&self, txid: &Txid) -> Result<bool, BridgeError> {
+        match self.get_raw_transaction(txid, None).await {
+            Ok(_) => Ok(true),
+            Err(e) if is_tx_not_found(&e) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn is_utxo_spent(&self, outpoint: &OutPoint) -> Result<bool, BridgeError> {
+        let outpoint = *outpoint;
         let res = self
-            .client
-            .get_tx_out(&outpoint.txid, outpoint.vout, Some(true))?;
+            .call(move |client| client.get_tx_out(&outpoint.txid, outpoint.vout, Some(true)))
+            .await?;
 
         Ok(res.is_none())
     }
 
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub fn mine_blocks(&self, block_num: u64) -> Result<(), BridgeError> {
-        let new_address = self.client.get_new_address(None, None)?.assume_checked();
+    pub async fn mine_blocks(&self, block_num: u64) -> Result<(), BridgeError> {
+        self.call_once(move |client| {
+            let new_address = client.get_new_address(None, None)?.assume_checked();
 
-        self.client.generate_to_address(block_num, &new_address)?;
+            client.generate_to_address(block_num, &new_address)
+        })
+        .await?;
 
         Ok(())
     }
 
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub fn send_to_address(
+    pub async fn send_to_address(
         &self,
         address: &Address,
         amount_sats: Amount,
     ) -> Result<OutPoint, BridgeError> {
-        let txid = self.client.send_to_address(
-            address,
-            amount_sats,
-            None,
-            None,
-            None,
-            None,
-            None,
-            None,
-        )?;
-
-        let tx_result = self.client.get_transaction(&txid, None)?;
-        let vout = tx_result.details[0].vout;
-
-        Ok(OutPoint { txid, vout })
+        let address = address.clone();
+
+        self.call_once(move |client| {
+            let txid = client.send_to_address(
+                &address,
+                amount_sats,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )?;
+
+            let tx_result = client.get_transaction(&txid, None)?;
+            let vout = tx_result.details[0].vout;
+
+            Ok(OutPoint { txid, vout })
+        })
+        .await
     }
 
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub fn get_txout_from_outpoint(&self, outpoint: &OutPoint) -> Result<TxOut, BridgeError> {
-        let tx = self.client.get_raw_transaction(&outpoint.txid, None)?;
+    pub async fn get_txout_from_outpoint(&self, outpoint: &OutPoint) -> Result<TxOut, BridgeError> {
+        let tx = self.get_raw_transaction(&outpoint.txid, None).await?;
         let txout = tx.output[outpoint.vout as usize].clone();
 
         Ok(txout)
     }
 
+    /// Submits transactions to the mempool as a package, parents first. It is
+    /// not retried, since a package that timed out might still be accepted.
+    #[tracing::instrument(skip(self, txs), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn submit_package(&self, txs: &[Transaction]) -> Result<(), BridgeError> {
+        let raw_txs = txs.iter().map(|tx| tx.raw_hex()).collect::<Vec<_>>();
+
+        let result: serde_json::Value = self
+            .call_once(move |client| client.call("submitpackage", &[serde_json::json!(raw_txs)]))
+            .await?;
+
+        check_package_result(&result)
+    }
+
+    /// Bumps the fee of a pre-signed transaction with a child transaction that
+    /// spends its anchor output and a wallet UTXO. Parent and child are
+    /// submitted together as a package.
+    ///
+    /// # Returns
+    ///
+    /// Txid of the child transaction.
+    #[tracing::instrument(skip(self, parent_tx), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn bump_fee_with_cpfp(
+        &self,
+        parent_tx: &Transaction,
+        fee_rate: FeeRate,
+    ) -> Result<Txid, BridgeError> {
+        let mut input_value = Amount::ZERO;
+        for txin in parent_tx.input.iter() {
+            input_value += self
+                .get_txout_from_outpoint(&txin.previous_output)
+                .await?
+                .value;
+        }
+        let output_value = parent_tx
+            .output
+            .iter()
+            .map(|txout| txout.value)
+            .sum::<Amount>();
+        let parent_fee = input_value
+            .checked_sub(output_value)
+            .unwrap_or(Amount::ZERO);
+
+        let wallet_utxo = self
+            .call(|client| client.list_unspent(Some(1), None, None, Some(false), None))
+            .await?
+            .into_iter()
+            .max_by_key(|utxo| utxo.amount)
+            .map(|utxo| UTXO {
+                outpoint: OutPoint {
+                    txid: utxo.txid,
+                    vout: utxo.vout,
+                },
+                txout: TxOut {
+                    value: utxo.amount,
+                    script_pubkey: utxo.script_pub_key,
+                },
+            })
+            .ok_or(BridgeError::NotEnoughFundsForCpfp)?;
+        let change_address = self
+            .call(|client| client.get_raw_change_address(None))
+            .await?
+            .assume_checked();
+
+        let child_tx = builder::transaction::create_cpfp_child_tx(
+            parent_tx,
+            parent_fee,
+            wallet_utxo,
+            change_address.script_pubkey(),
+            fee_rate,
+        )?;
+
+        // Wallet can't know the anchor output before the parent is in the
+        // mempool.
+        let anchor_txout = builder::script::anyone_can_spend_txout();
+        let anchor_input = SignRawTransactionInput {
+            txid: child_tx.input[0].previous_output.txid,
+            vout: child_tx.input[0].previous_output.vout,
+            script_pub_key: anchor_txout.script_pubkey,
+            redeem_script: None,
+            amount: Some(anchor_txout.value),
+        };
+        let mut signed_child_tx: Transaction = deserialize(
+            &self
+                .sign_raw_transaction_with_wallet(&child_tx, Some(vec![anchor_input]), None)
+                .await?
+                .hex,
+        )?;
+        signed_child_tx.input[0].witness = child_tx.input[0].witness.clone();
+
+        self.submit_package(&[parent_tx.clone(), signed_child_tx.clone()])
+            .await?;
+
+        Ok(signed_child_tx.compute_txid())
+    }
+
+    /// Broadcasts a pre-signed transaction. If it is rejected because its fixed
+    /// fee is below the mempool minimum, it is broadcasted again with a CPFP
+    /// child that pays `fee_rate`. Other rejections are returned as errors.
+    #[tracing::instrument(skip(self, tx), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn send_or_bump_transaction(
+        &self,
+        tx: &Transaction,
+        fee_rate: FeeRate,
+    ) -> Result<Txid, BridgeError> {
+        match self.send_raw_transaction(tx).await {
+            Ok(_) => {}
+            Err(e) if is_fee_rejection(&e) => {
+                tracing::warn!(
+                    "Transaction {} is rejected: {}, bumping its fee with CPFP",
+                    tx.compute_txid(),
+                    e
+                );
+                self.bump_fee_with_cpfp(tx, fee_rate).await?;
+            }
+            Err(e) => return Err(e),
+        }
+
+        Ok(tx.compute_txid())
+    }
+
     // Following methods are just wrappers around the bitcoincore_rpc::Client methods
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub fn fund_raw_transaction(
+    pub async fn fund_raw_transaction(
         &self,
         tx: &Transaction,
         options: Option<&bitcoincore_rpc::json::FundRawTransactionOptions>,
         is_witness: Option<bool>,
-    ) -> Result<bitcoincore_rpc::json::FundRawTransactionResult, bitcoincore_rpc::Error> {
-        self.client.fund_raw_transaction(tx, options, is_witness)
+    ) -> Result<bitcoincore_rpc::json::FundRawTransactionResult, BridgeError> {
+        let raw_tx = tx.raw_hex();
+        let options = options.cloned();
+
+        self.call(move |client| {
+            client.fund_raw_transaction(raw_tx.as_str(), options.as_ref(), is_witness)
+        })
+        .await
     }
 
     #[tracing::instrument(skip(self, tx, sighash_type), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub fn sign_raw_transaction_with_wallet<T: bitcoincore_rpc::RawTx>(
+    pub async fn sign_raw_transaction_with_wallet<T: bitcoincore_rpc::RawTx>(
         &self,
         tx: T,
-        utxos: Option<&[bitcoincore_rpc::json::SignRawTransactionInput]>,
+        utxos: Option<Vec<bitcoincore_rpc::json::SignRawTransactionInput>>,
         sighash_type: Option<bitcoincore_rpc::json::SigHashType>,
-    ) -> Result<bitcoincore_rpc::json::SignRawTransactionResult, bitcoincore_rpc::Error> {
-        self.client
-            .sign_raw_transaction_with_wallet(tx, utxos, sighash_type)
+    ) -> Result<bitcoincore_rpc::json::SignRawTransactionResult, BridgeError> {
+        let raw_tx = tx.raw_hex();
+
+        self.call(move |client| {
+            client.sign_raw_transaction_with_wallet(raw_tx.as_str(), utxos.as_deref(), sighash_type)
+        })
+        .await
     }
 
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub fn get_raw_transaction(
+    pub async fn get_raw_transaction(
         &self,
         txid: &bitcoin::Txid,
         block_hash: Option<&bitcoin::BlockHash>,
-    ) -> Result<bitcoin::Transaction, bitcoincore_rpc::Error> {
-        self.client.get_raw_transaction(txid, block_hash)
+    ) -> Result<bitcoin::Transaction, BridgeError> {
+        let txid = *txid;
+        let block_hash = block_hash.copied();
+
+        self.call(move |client| client.get_raw_transaction(&txid, block_hash.as_ref()))
+            .await
     }
 
     #[tracing::instrument(skip(self, tx), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub fn send_raw_transaction<T: bitcoincore_rpc::RawTx>(
+    pub async fn send_raw_transaction<T: bitcoincore_rpc::RawTx>(
         &self,
         tx: T,
-    ) -> Result<bitcoin::Txid, bitcoincore_rpc::Error> {
-        self.client.send_raw_transaction(tx)
+    ) -> Result<bitcoin::Txid, BridgeError> {
+        let raw_tx = tx.raw_hex();
+
+        self.call_once(move |client| client.send_raw_transaction(raw_tx.as_str()))
+            .await
     }
 
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub fn check_deposit_utxo(
+    pub async fn get_block_count(&self) -> Result<u64, BridgeError> {
+        self.call(|client| client.get_block_count()).await
+    }
+
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_block_hash(&self, height: u64) -> Result<BlockHash, BridgeError> {
+        self.call(move |client| client.get_block_hash(height)).await
+    }
+
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn get_block_header(&self, block_hash: &BlockHash) -> Result<Header, BridgeError> {
+        let block_hash = *block_hash;
+
+        self.call(move |client| client.get_block_header(&block_hash))
+            .await
+    }
+
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn estimate_smart_fee(
+        &self,
+        conf_target: u16,
+    ) -> Result<bitcoincore_rpc::json::EstimateSmartFeeResult, BridgeError> {
+        self.call(move |client| client.estimate_smart_fee(conf_target, None))
+            .await
+    }
+
+    /// Checks that the deposit UTXO is finalized, unspent and sent to the
+    /// deposit address with one of the bridge amounts. Returns the hash of the
+    /// block it is confirmed in and the amount of the deposit.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn check_deposit_utxo(
         &self,
         nofn_xonly_pk: XOnlyPublicKey,
         deposit_outpoint: &OutPoint,
         recovery_taproot_address: &Address<NetworkUnchecked>,
         evm_address: EVMAddress,
-        amount_sats: Amount,
+        bridge_amounts_sats: &[Amount],
         confirmation_block_count: u32,
         network: bitcoin::Network,
         user_takes_after: u32,
-    ) -> Result<(), BridgeError> {
-        if self.confirmation_blocks(&deposit_outpoint.txid)? < confirmation_block_count {
+    ) -> Result<(BlockHash, Amount), BridgeError> {
+        let (block_hash, confirmations) =
+            self.confirmation_block_hash(&deposit_outpoint.txid).await?;
+        if confirmations < confirmation_block_count {
             return Err(BridgeError::DepositNotFinalized);
         }
 
+        let deposit_txout = self.get_txout_from_outpoint(deposit_outpoint).await?;
+        let amount_sats = deposit_txout.value;
+        if !bridge_amounts_sats.contains(&amount_sats) {
+            return Err(BridgeError::InvalidBridgeAmount(amount_sats));
+        }
+
         let (deposit_address, _) = builder::address::generate_deposit_address(
             nofn_xonly_pk,
             recovery_taproot_address,
@@ -184,36 +646,257 @@ where
             amount_sats,
             network,
             user_takes_after,
-        );
+        )?;
 
-        if !self.check_utxo_address_and_amount(
-            deposit_outpoint,
-            &deposit_address.script_pubkey(),
-            amount_sats,
-        )? {
+        if deposit_txout.script_pubkey != deposit_address.script_pubkey() {
             return Err(BridgeError::InvalidDepositUTXO);
         }
 
-        if self.is_utxo_spent(deposit_outpoint)? {
+        if self.is_utxo_spent(deposit_outpoint).await? {
             return Err(BridgeError::UTXOSpent);
         }
 
-        Ok(())
+        Ok((block_hash, amount_sats))
     }
 }
 
-impl<R> Clone for ExtendedRpc<R>
-where
-    R: RpcApiWrapper,
-{
-    fn clone(&self) -> Self {
-        let new_client = R::new(&self.url, self.auth.clone())
-            .unwrap_or_else(|e| panic!("Failed to clone Bitcoin RPC client: {}", e));
+/// Returns true if the call can succeed when it is retried: the node is
+/// unreachable or it is still starting.
+fn is_transient(error: &bitcoincore_rpc::Error) -> bool {
+    match error {
+        bitcoincore_rpc::Error::JsonRpc(bitcoincore_rpc::jsonrpc::Error::Transport(_)) => true,
+        bitcoincore_rpc::Error::JsonRpc(bitcoincore_rpc::jsonrpc::Error::Rpc(rpc_error)) => {
+            rpc_error.code == RPC_IN_WARMUP
+        }
+        _ => false,
+    }
+}
+
+/// Returns true if the error is returned for a transaction that the node
+/// doesn't know.
+pub fn is_tx_not_found(error: &BridgeError) -> bool {
+    matches!(
+        error,
+        BridgeError::BitcoinRpcError(bitcoincore_rpc::Error::JsonRpc(
+            bitcoincore_rpc::jsonrpc::Error::Rpc(rpc_error)
+        )) if rpc_error.code == RPC_INVALID_ADDRESS_OR_KEY
+    )
+}
+
+/// Returns true if the error is returned for a transaction that is rejected
+/// only because of its fee.
+pub fn is_fee_rejection(error: &BridgeError) -> bool {
+    matches!(
+        error,
+        BridgeError::BitcoinRpcError(bitcoincore_rpc::Error::JsonRpc(
+            bitcoincore_rpc::jsonrpc::Error::Rpc(rpc_error)
+        )) if rpc_error.code == RPC_VERIFY_REJECTED
+            && FEE_REJECT_REASONS
+                .iter()
+                .any(|reason| rpc_error.message.contains(reason))
+    )
+}
 
+/// Checks the result of `submitpackage`. Package is accepted only if the node
+/// reports success and none of its transactions has an error.
+fn check_package_result(result: &serde_json::Value) -> Result<(), BridgeError> {
+    match result.get("package_msg").and_then(|msg| msg.as_str()) {
+        Some("success") => {}
+        Some(msg) => return Err(BridgeError::PackageSubmissionError(msg.to_string())),
+        None => {
+            return Err(BridgeError::PackageSubmissionError(
+                "Missing package_msg".to_string(),
+            ))
+        }
+    }
+
+    let tx_results = result
+        .get("tx-results")
+        .and_then(|tx_results| tx_results.as_object())
+        .ok_or(BridgeError::PackageSubmissionError(
+            "Missing tx-results".to_string(),
+        ))?;
+    for (wtxid, tx_result) in tx_results {
+        if let Some(error) = tx_result.get("error") {
+            return Err(BridgeError::PackageSubmissionError(format!(
+                "{}: {}",
+                wtxid, error
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+impl<R> Clone for ExtendedRpc<R> {
+    fn clone(&self) -> Self {
         Self {
-            url: self.url.clone(),
-            auth: self.auth.clone(),
-            client: new_client,
+            pool: Arc::clone(&self.pool),
+            options: self.options,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_package_result, is_fee_rejection, is_transient, is_tx_not_found,
+        MAX_CONCURRENT_RPC_CALLS, RPC_INVALID_ADDRESS_OR_KEY, RPC_IN_WARMUP, RPC_VERIFY_REJECTED,
+    };
+    use crate::errors::BridgeError;
+    use crate::{
+        create_extended_rpc, extended_rpc::ExtendedRpc, mock::database::create_test_config,
+    };
+    use bitcoincore_rpc::jsonrpc;
+    use std::sync::atomic::Ordering;
+
+    #[tokio::test]
+    async fn concurrent_calls_are_bounded() {
+        let mut config =
+            create_test_config("concurrent_calls_are_bounded", "test_config.toml").await;
+        let rpc = create_extended_rpc!(config);
+
+        let calls = (0..2 * MAX_CONCURRENT_RPC_CALLS).map(|_| rpc.get_block_count());
+        futures::future::try_join_all(calls).await.unwrap();
+
+        // Every call returned its client and no more clients are created than
+        // the concurrency limit.
+        let idle_clients = rpc.pool.idle_clients.lock().unwrap().len();
+        assert!((1..=MAX_CONCURRENT_RPC_CALLS).contains(&idle_clients));
+        assert_eq!(
+            rpc.pool.permits.available_permits(),
+            MAX_CONCURRENT_RPC_CALLS
+        );
+    }
+
+    #[tokio::test]
+    async fn fail_over_switches_endpoint() {
+        let mut config =
+            create_test_config("fail_over_switches_endpoint", "test_config.toml").await;
+        config.bitcoin_rpc_fallback_urls = vec!["http://127.0.0.1:18444".to_string()];
+        let rpc = create_extended_rpc!(config);
+        rpc.get_block_count().await.unwrap();
+
+        rpc.pool.fail_over(0);
+        assert_eq!(rpc.pool.active_url.load(Ordering::Acquire), 1);
+
+        // A late failure of the previous endpoint doesn't switch back.
+        rpc.pool.fail_over(0);
+        assert_eq!(rpc.pool.active_url.load(Ordering::Acquire), 1);
+
+        // Clients of the previous endpoint are dropped.
+        rpc.pool.take(1).unwrap();
+        assert!(rpc.pool.idle_clients.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn transient_errors() {
+        let warmup = jsonrpc::error::RpcError {
+            code: RPC_IN_WARMUP,
+            message: "Loading block index...".to_string(),
+            data: None,
+        };
+        assert!(is_transient(&bitcoincore_rpc::Error::JsonRpc(
+            jsonrpc::Error::Rpc(warmup)
+        )));
+
+        let connection_refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+        assert!(is_transient(&bitcoincore_rpc::Error::JsonRpc(
+            jsonrpc::Error::Transport(Box::new(connection_refused))
+        )));
+
+        let invalid_parameter = jsonrpc::error::RpcError {
+            code: -8,
+            message: "Invalid parameter".to_string(),
+            data: None,
+        };
+        assert!(!is_transient(&bitcoincore_rpc::Error::JsonRpc(
+            jsonrpc::Error::Rpc(invalid_parameter)
+        )));
+        assert!(!is_transient(&bitcoincore_rpc::Error::ReturnedError(
+            "Transaction already in block chain".to_string()
+        )));
+    }
+
+    #[test]
+    fn tx_not_found_errors() {
+        let not_found = jsonrpc::error::RpcError {
+            code: RPC_INVALID_ADDRESS_OR_KEY,
+            message: "No such mempool or blockchain transaction".to_string(),
+            data: None,
+        };
+        assert!(is_tx_not_found(&BridgeError::BitcoinRpcError(
+            bitcoincore_rpc::Error::JsonRpc(jsonrpc::Error::Rpc(not_found))
+        )));
+
+        let warmup = jsonrpc::error::RpcError {
+            code: RPC_IN_WARMUP,
+            message: "Loading block index...".to_string(),
+            data: None,
+        };
+        assert!(!is_tx_not_found(&BridgeError::BitcoinRpcError(
+            bitcoincore_rpc::Error::JsonRpc(jsonrpc::Error::Rpc(warmup))
+        )));
+        assert!(!is_tx_not_found(&BridgeError::NoConfirmationData));
+    }
+
+    #[test]
+    fn fee_rejection_errors() {
+        let min_fee = jsonrpc::error::RpcError {
+            code: RPC_VERIFY_REJECTED,
+            message: "min relay fee not met, 0 < 110".to_string(),
+            data: None,
+        };
+        assert!(is_fee_rejection(&BridgeError::BitcoinRpcError(
+            bitcoincore_rpc::Error::JsonRpc(jsonrpc::Error::Rpc(min_fee))
+        )));
+
+        let invalid = jsonrpc::error::RpcError {
+            code: RPC_VERIFY_REJECTED,
+            message: "mandatory-script-verify-flag-failed".to_string(),
+            data: None,
+        };
+        assert!(!is_fee_rejection(&BridgeError::BitcoinRpcError(
+            bitcoincore_rpc::Error::JsonRpc(jsonrpc::Error::Rpc(invalid))
+        )));
+        assert!(!is_fee_rejection(&BridgeError::NoConfirmationData));
+    }
+
+    #[test]
+    fn package_results() {
+        assert!(check_package_result(&serde_json::json!({
+            "package_msg": "success",
+            "tx-results": {
+                "00": { "txid": "00" },
+                "01": { "txid": "01" },
+            },
+        }))
+        .is_ok());
+
+        assert!(matches!(
+            check_package_result(&serde_json::json!({})),
+            Err(BridgeError::PackageSubmissionError(_))
+        ));
+        assert!(matches!(
+            check_package_result(&serde_json::json!({ "package_msg": "success" })),
+            Err(BridgeError::PackageSubmissionError(_))
+        ));
+        assert!(matches!(
+            check_package_result(&serde_json::json!({
+                "package_msg": "transaction failed",
+                "tx-results": {},
+            })),
+            Err(BridgeError::PackageSubmissionError(_))
+        ));
+        assert!(matches!(
+            check_package_result(&serde_json::json!({
+                "package_msg": "success",
+                "tx-results": {
+                    "00": { "txid": "00" },
+                    "01": { "txid": "01", "error": "bad-txns-inputs-missingorspent" },
+                },
+            })),
+            Err(BridgeError::PackageSubmissionError(_))
+        ));
+    }
+}
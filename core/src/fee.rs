@@ -0,0 +1,228 @@
+//! # Fee Policy
+//!
+//! Fee policy decides the fee rates of the transactions and computes their
+//! fees from their virtual sizes.
+//!
+//! Pre-signed transactions must be identical for every party. Therefore, their
+//! fee rate is agreed in [`BridgeConfig`] instead of being estimated. Other
+//! transactions use `estimatesmartfee`, bounded by the configured floor and
+//! ceiling.
+
+use crate::builder::transaction::TxHandler;
+use crate::config::BridgeConfig;
+use crate::errors::BridgeError;
+use crate::extended_rpc::ExtendedRpc;
+use bitcoin::opcodes::all::{OP_CHECKSIG, OP_CHECKSIGADD, OP_CHECKSIGVERIFY};
+use bitcoin::script::Instruction;
+use bitcoin::taproot::LeafVersion;
+use bitcoin::{Amount, FeeRate, ScriptBuf, Witness};
+use bitcoin_mock_rpc::RpcApiWrapper;
+
+/// Size of a Schnorr signature with the default sighash type.
+const SCHNORR_SIGNATURE_SIZE: usize = 64;
+
+/// How an input of a [`TxHandler`] is going to be spent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpendPath {
+    /// Key path spend with a single signature.
+    KeyPath,
+    /// Script path spend with the script at the given index of the input's
+    /// scripts.
+    ScriptPath(usize),
+}
+
+/// Computes the virtual size of the transaction after its inputs are signed.
+/// Witnesses are filled with dummy elements of the expected sizes: a signature
+/// for every signature check in the spent script, the script itself and the
+/// control block.
+pub fn estimate_vsize(
+    tx_handler: &TxHandler,
+    spend_paths: &[SpendPath],
+) -> Result<u64, BridgeError> {
+    if spend_paths.len() != tx_handler.tx.input.len() {
+        return Err(BridgeError::TxInputNotFound);
+    }
+
+    let mut tx = tx_handler.tx.clone();
+    for (txin_index, (txin, spend_path)) in tx.input.iter_mut().zip(spend_paths).enumerate() {
+        let mut witness = Witness::new();
+
+        match spend_path {
+            SpendPath::KeyPath => witness.push([0u8; SCHNORR_SIGNATURE_SIZE]),
+            SpendPath::ScriptPath(script_index) => {
+                let script = tx_handler
+                    .scripts
+                    .get(txin_index)
+                    .and_then(|scripts| scripts.get(*script_index))
+                    .ok_or(BridgeError::ControlBlockError)?;
+                let control_block = tx_handler
+                    .taproot_spend_infos
+                    .get(txin_index)
+                    .and_then(|spend_info| {
+                        spend_info.control_block(&(script.clone(), LeafVersion::TapScript))
+                    })
+                    .ok_or(BridgeError::ControlBlockError)?;
+
+                for _ in 0..count_signature_checks(script) {
+                    witness.push([0u8; SCHNORR_SIGNATURE_SIZE]);
+                }
+                witness.push(script);
+                witness.push(control_block.serialize());
+            }
+        }
+
+        txin.witness = witness;
+    }
+
+    Ok(tx.vsize() as u64)
+}
+
+/// Computes the fee of the transaction for the given fee rate.
+pub fn estimate_fee(
+    tx_handler: &TxHandler,
+    spend_paths: &[SpendPath],
+    fee_rate: FeeRate,
+) -> Result<Amount, BridgeError> {
+    fee_rate
+        .fee_vb(estimate_vsize(tx_handler, spend_paths)?)
+        .ok_or(BridgeError::FeeOverflow)
+}
+
+/// Returns the agreed fee rate of the pre-signed transactions.
+pub fn presigned_fee_rate(config: &BridgeConfig) -> Result<FeeRate, BridgeError> {
+    fee_rate_from_sat_vb(config.presigned_tx_fee_rate_sat_vb)
+}
+
+/// Estimates a fee rate with `estimatesmartfee` for the transactions that are
+/// not pre-signed. Estimation is bounded by the configured floor and ceiling.
+/// If node can't estimate a fee rate, e.g. there isn't enough data on
+/// regtest, floor is used.
+pub async fn estimate_fee_rate<R>(
+    rpc: &ExtendedRpc<R>,
+    config: &BridgeConfig,
+) -> Result<FeeRate, BridgeError>
+where
+    R: RpcApiWrapper,
+{
+    let min_fee_rate = fee_rate_from_sat_vb(config.min_fee_rate_sat_vb)?;
+    let max_fee_rate = fee_rate_from_sat_vb(config.max_fee_rate_sat_vb)?;
+    if min_fee_rate > max_fee_rate {
+        return Err(BridgeError::ConfigError(
+            "Minimum fee rate is bigger than maximum fee rate".to_string(),
+        ));
+    }
+
+    let estimated_fee_rate = match rpc
+        .estimate_smart_fee(config.fee_estimation_conf_target)
+        .await
+    {
+        // Estimation is in BTC/kvB, where 1 kvB is 4 kwu.
+        Ok(estimation) => estimation
+            .fee_rate
+            .map(|fee_rate| FeeRate::from_sat_per_kwu(fee_rate.to_sat() / 4)),
+        Err(e) => {
+            tracing::warn!("Can't estimate fee rate: {}", e);
+            None
+        }
+    };
+
+    Ok(estimated_fee_rate
+        .unwrap_or(min_fee_rate)
+        .clamp(min_fee_rate, max_fee_rate))
+}
+
+fn fee_rate_from_sat_vb(sat_vb: u64) -> Result<FeeRate, BridgeError> {
+    FeeRate::from_sat_per_vb(sat_vb).ok_or(BridgeError::ConfigError(format!(
+        "Fee rate {} sat/vB is too big",
+        sat_vb
+    )))
+}
+
+fn count_signature_checks(script: &ScriptBuf) -> usize {
+    script
+        .instructions()
+        .filter(|instruction| {
+            matches!(
+                instruction,
+                Ok(Instruction::Op(OP_CHECKSIG))
+                    | Ok(Instruction::Op(OP_CHECKSIGVERIFY))
+                    | Ok(Instruction::Op(OP_CHECKSIGADD))
+            )
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate_fee, estimate_vsize, SpendPath};
+    use crate::builder::{self, transaction::TxHandler};
+    use crate::utils::{handle_taproot_witness_new, SECP};
+    use bitcoin::{hashes::Hash, Amount, FeeRate, OutPoint, TxOut, Txid, XOnlyPublicKey};
+    use secp256k1::{rand, Keypair, SecretKey};
+
+    fn create_test_tx_handler() -> TxHandler {
+        let secret_key = SecretKey::new(&mut rand::thread_rng());
+        let keypair = Keypair::from_secret_key(&SECP, &secret_key);
+        let xonly_pk = XOnlyPublicKey::from_keypair(&keypair).0;
+        let network = bitcoin::Network::Regtest;
+
+        let script = builder::script::generate_relative_timelock_script(xonly_pk, 5);
+        let (address, spend_info) =
+            builder::address::create_taproot_address(&[script.clone()], Some(xonly_pk), network)
+                .unwrap();
+        let prevout = TxOut {
+            value: Amount::from_sat(100_000),
+            script_pubkey: address.script_pubkey(),
+        };
+        let ins = builder::transaction::create_tx_ins(vec![
+            OutPoint {
+                txid: Txid::all_zeros(),
+                vout: 0,
+            },
+            OutPoint {
+                txid: Txid::all_zeros(),
+                vout: 1,
+            },
+        ]);
+        let outs = vec![TxOut {
+            value: Amount::from_sat(150_000),
+            script_pubkey: address.script_pubkey(),
+        }];
+
+        TxHandler {
+            tx: builder::transaction::create_btc_tx(ins, outs),
+            prevouts: vec![prevout.clone(), prevout],
+            scripts: vec![vec![], vec![script]],
+            taproot_spend_infos: vec![spend_info.clone(), spend_info],
+        }
+    }
+
+    #[test]
+    fn estimate_vsize_matches_signed_tx() {
+        let mut tx_handler = create_test_tx_handler();
+        let spend_paths = [SpendPath::KeyPath, SpendPath::ScriptPath(0)];
+
+        let vsize = estimate_vsize(&tx_handler, &spend_paths).unwrap();
+
+        let signature = [1u8; 64];
+        handle_taproot_witness_new(&mut tx_handler, &[signature], 0, None).unwrap();
+        handle_taproot_witness_new(&mut tx_handler, &[signature], 1, Some(0)).unwrap();
+        assert_eq!(vsize, tx_handler.tx.vsize() as u64);
+
+        let fee_rate = FeeRate::from_sat_per_vb(3).unwrap();
+        assert_eq!(
+            estimate_fee(&tx_handler, &spend_paths, fee_rate).unwrap(),
+            Amount::from_sat(3 * vsize)
+        );
+    }
+
+    #[test]
+    fn estimate_vsize_rejects_invalid_spend_paths() {
+        let tx_handler = create_test_tx_handler();
+
+        assert!(estimate_vsize(&tx_handler, &[SpendPath::KeyPath]).is_err());
+        assert!(
+            estimate_vsize(&tx_handler, &[SpendPath::KeyPath, SpendPath::ScriptPath(1)]).is_err()
+        );
+    }
+}
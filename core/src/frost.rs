@@ -0,0 +1,709 @@
+//! # FROST
+//!
+//! Threshold Schnorr signatures of the verifiers, as an alternative to the
+//! N-of-N MuSig2 key. Any `threshold` of the verifiers can sign with the FROST
+//! group key, which is a single taproot key on chain like the MuSig2 key.
+//!
+//! The group key is generated with a distributed key generation (DKG)
+//! ceremony, which is relayed by the aggregator. Round 2 packages of the
+//! ceremony are secret shares, so they are encrypted to their recipients with
+//! the verifiers' keys and bound to the round 1 packages their sender has seen.
+
+use crate::{
+    errors::BridgeError, musig2::MuSigSigHash, utils, ByteArray32, ByteArray64, ByteArray66,
+};
+use bitcoin::hashes::Hash;
+use bitcoin::TapNodeHash;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key};
+use frost::keys::{dkg, KeyPackage, PublicKeyPackage};
+use frost::{round1, round2, Identifier, Secp256K1Sha256TR, SigningPackage};
+use frost_secp256k1_tr as frost;
+use secp256k1::rand::{CryptoRng, RngCore};
+use secp256k1::{ecdh::SharedSecret, schnorr, PublicKey, SecretKey, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+// FrostSignerId is the identifier of a verifier in the FROST group, which is its index in the verifiers' public keys plus one.
+pub type FrostSignerId = u16;
+// FrostNonce consists of the hiding and binding nonces, which are scalars, so it's 64 bytes.
+pub type FrostNonce = ByteArray64;
+// FrostCommitment consists of the commitments of the hiding and binding nonces, which are curve points, so it's 66 bytes (compressed).
+pub type FrostCommitment = ByteArray66;
+// FrostSignatureShare is a scalar, so it's 32 bytes.
+pub type FrostSignatureShare = ByteArray32;
+pub type FrostNoncePair = (FrostNonce, FrostCommitment);
+
+// Signing nonce of the ciphersuite, which isn't aliased by the ciphersuite crate.
+type SigningNonce = frost::frost::round1::Nonce<Secp256K1Sha256TR>;
+
+// Length of the nonce that prefixes an encrypted round 2 package.
+const ROUND2_NONCE_LEN: usize = 12;
+
+/// Round 2 package of the DKG ceremony, encrypted to its recipient.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EncryptedRound2Package(#[serde(with = "hex::serde")] pub Vec<u8>);
+
+// Returns the identifier of the verifier with the given index.
+pub fn signer_id(verifier_idx: usize) -> Result<FrostSignerId, BridgeError> {
+    FrostSignerId::try_from(verifier_idx + 1)
+        .map_err(|_| BridgeError::ConfigError("Too many verifiers for FROST".to_string()))
+}
+
+// Converts the identifier of a verifier to the frost crate's type.
+fn to_identifier(id: FrostSignerId) -> Result<Identifier, BridgeError> {
+    Ok(Identifier::try_from(id)?)
+}
+
+// Checks that `threshold` of `num_signers` verifiers is a valid FROST group.
+pub fn check_threshold(threshold: u16, num_signers: usize) -> Result<(), BridgeError> {
+    if threshold < 2 || usize::from(threshold) > num_signers || num_signers > usize::from(u16::MAX)
+    {
+        return Err(BridgeError::InvalidFrostThreshold(threshold, num_signers));
+    }
+
+    Ok(())
+}
+
+// Returns the x-only group key of the public key package, which is the N-of-N key in scripts.
+pub fn group_xonly_pk(
+    public_key_package: &PublicKeyPackage,
+) -> Result<XOnlyPublicKey, BridgeError> {
+    let verifying_key = public_key_package.verifying_key().serialize()?;
+
+    Ok(PublicKey::from_slice(&verifying_key)?.x_only_public_key().0)
+}
+
+// Generates a nonce pair for the key package. Commitment is sent to the aggregator and the nonce is kept secret.
+pub fn nonce_pair<R: RngCore + CryptoRng>(
+    key_package: &KeyPackage,
+    rng: &mut R,
+) -> Result<FrostNoncePair, BridgeError> {
+    let (nonces, commitments) = round1::commit(key_package.signing_share(), rng);
+
+    let nonce = [nonces.hiding().serialize(), nonces.binding().serialize()].concat();
+    let commitment = [
+        commitments.hiding().serialize()?,
+        commitments.binding().serialize()?,
+    ]
+    .concat();
+
+    Ok((
+        ByteArray64(
+            nonce
+                .try_into()
+                .map_err(|_| frost::Error::SerializationError)?,
+        ),
+        ByteArray66(
+            commitment
+                .try_into()
+                .map_err(|_| frost::Error::SerializationError)?,
+        ),
+    ))
+}
+
+// Decodes the secret nonces of a nonce pair.
+fn to_signing_nonces(nonce: &FrostNonce) -> Result<round1::SigningNonces, BridgeError> {
+    let hiding =
+        SigningNonce::deserialize(&nonce.0[..32]).map_err(|_| BridgeError::InvalidFrostNonce)?;
+    let binding =
+        SigningNonce::deserialize(&nonce.0[32..]).map_err(|_| BridgeError::InvalidFrostNonce)?;
+
+    Ok(round1::SigningNonces::from_nonces(hiding, binding))
+}
+
+// Creates the signing package of a sighash with the commitments of the signers. Returns the identifier of the first
+// signer whose commitment is not a pair of valid curve points.
+fn create_signing_package(
+    commitments: &BTreeMap<FrostSignerId, FrostCommitment>,
+    sighash: MuSigSigHash,
+) -> Result<SigningPackage, BridgeError> {
+    let signing_commitments = commitments
+        .iter()
+        .map(|(id, commitment)| {
+            let hiding = round1::NonceCommitment::deserialize(&commitment.0[..33])
+                .map_err(|_| BridgeError::InvalidFrostCommitment(*id))?;
+            let binding = round1::NonceCommitment::deserialize(&commitment.0[33..])
+                .map_err(|_| BridgeError::InvalidFrostCommitment(*id))?;
+
+            Ok((
+                to_identifier(*id)?,
+                round1::SigningCommitments::new(hiding, binding),
+            ))
+        })
+        .collect::<Result<BTreeMap<_, _>, BridgeError>>()?;
+
+    Ok(SigningPackage::new(signing_commitments, &sighash.0))
+}
+
+// Returns the merkle root to tweak the group key with, like the MuSig2 key aggregation context. If the tweak flag is
+// set without a tweak, the key is tweaked with an empty script tree.
+fn to_merkle_root(
+    tweak: &Option<TapNodeHash>,
+    tweak_flag: bool,
+) -> Result<Option<Option<&[u8]>>, BridgeError> {
+    match (tweak, tweak_flag) {
+        (None, false) => Ok(None),
+        (tweak, true) => Ok(Some(
+            tweak.as_ref().map(|tweak| tweak.as_byte_array().as_slice()),
+        )),
+        (Some(_), false) => Err(BridgeError::MuSigTweakWithoutTweakFlag),
+    }
+}
+
+// Signs the sighash with the key package and the nonce, for the signers of the commitments. Commitments must be the
+// same for every signer.
+#[tracing::instrument(skip(key_package, nonce), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+pub fn sign(
+    key_package: &KeyPackage,
+    tweak: Option<TapNodeHash>,
+    tweak_flag: bool,
+    nonce: FrostNonce,
+    commitments: &BTreeMap<FrostSignerId, FrostCommitment>,
+    sighash: MuSigSigHash,
+) -> Result<FrostSignatureShare, BridgeError> {
+    let signing_package = create_signing_package(commitments, sighash)?;
+    let nonces = to_signing_nonces(&nonce)?;
+
+    let signature_share = match to_merkle_root(&tweak, tweak_flag)? {
+        None => round2::sign(&signing_package, &nonces, key_package)?,
+        Some(merkle_root) => {
+            round2::sign_with_tweak(&signing_package, &nonces, key_package, merkle_root)?
+        }
+    };
+
+    Ok(ByteArray32(
+        signature_share
+            .serialize()
+            .try_into()
+            .map_err(|_| frost::Error::SerializationError)?,
+    ))
+}
+
+// Verifies the signature shares of the signers and aggregates them into a single signature of the group key. Returns
+// the identifier of the first signature share that is not a valid scalar.
+#[tracing::instrument(skip(public_key_package), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+pub fn aggregate(
+    public_key_package: &PublicKeyPackage,
+    tweak: Option<TapNodeHash>,
+    tweak_flag: bool,
+    commitments: &BTreeMap<FrostSignerId, FrostCommitment>,
+    signature_shares: &BTreeMap<FrostSignerId, FrostSignatureShare>,
+    sighash: MuSigSigHash,
+) -> Result<schnorr::Signature, BridgeError> {
+    let signing_package = create_signing_package(commitments, sighash)?;
+    let signature_shares = signature_shares
+        .iter()
+        .map(|(id, signature_share)| {
+            Ok((
+                to_identifier(*id)?,
+                round2::SignatureShare::deserialize(&signature_share.0)
+                    .map_err(|_| BridgeError::InvalidFrostSignatureShare(*id))?,
+            ))
+        })
+        .collect::<Result<BTreeMap<_, _>, BridgeError>>()?;
+
+    let signature = match to_merkle_root(&tweak, tweak_flag)? {
+        None => frost::aggregate(&signing_package, &signature_shares, public_key_package)?,
+        Some(merkle_root) => frost::aggregate_with_tweak(
+            &signing_package,
+            &signature_shares,
+            public_key_package,
+            merkle_root,
+        )?,
+    };
+
+    Ok(schnorr::Signature::from_slice(&signature.serialize()?)?)
+}
+
+// Hashes every verifier's round 1 package. Round 2 packages are encrypted with this hash as associated data, so they
+// can only be decrypted by a verifier that has seen the same round 1 packages as their sender.
+fn round1_transcript(
+    round1_packages: &BTreeMap<FrostSignerId, dkg::round1::Package>,
+) -> Result<[u8; 32], BridgeError> {
+    let mut hasher = Sha256::new();
+    for (id, package) in round1_packages {
+        let package = package.serialize()?;
+
+        hasher.update(id.to_be_bytes());
+        hasher.update((package.len() as u64).to_be_bytes());
+        hasher.update(package);
+    }
+
+    Ok(hasher.finalize().into())
+}
+
+// Returns the cipher of the round 2 packages from the sender to the recipient, whose key is derived from their ECDH
+// shared secret. Either of them can compute it with their secret key.
+fn round2_cipher(
+    secret_key: &SecretKey,
+    pk: &PublicKey,
+    sender: FrostSignerId,
+    recipient: FrostSignerId,
+) -> ChaCha20Poly1305 {
+    let shared_secret = SharedSecret::new(pk, secret_key);
+    let key = crate::sha256_hash!(
+        shared_secret.secret_bytes(),
+        sender.to_be_bytes(),
+        recipient.to_be_bytes()
+    );
+
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+/// A verifier's side of the DKG ceremony.
+///
+/// Round secrets are only kept in memory, so a ceremony that is interrupted,
+/// e.g. by a restart, must be started again from round 1.
+pub struct DkgParticipant {
+    id: FrostSignerId,
+    secret_key: SecretKey,
+    verifiers_public_keys: Vec<PublicKey>,
+    threshold: u16,
+    round1: Option<(dkg::round1::SecretPackage, dkg::round1::Package)>,
+    round2: Option<(
+        dkg::round2::SecretPackage,
+        BTreeMap<FrostSignerId, dkg::round1::Package>,
+    )>,
+}
+
+impl std::fmt::Debug for DkgParticipant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DkgParticipant")
+            .field("id", &self.id)
+            .field("threshold", &self.threshold)
+            .field("round1", &self.round1.is_some())
+            .field("round2", &self.round2.is_some())
+            .finish()
+    }
+}
+
+impl DkgParticipant {
+    /// Creates the participant of the verifier with the given secret key,
+    /// which must be one of the verifiers' keys.
+    pub fn new(
+        secret_key: SecretKey,
+        verifiers_public_keys: Vec<PublicKey>,
+        threshold: u16,
+    ) -> Result<Self, BridgeError> {
+        check_threshold(threshold, verifiers_public_keys.len())?;
+
+        let pk = secret_key.public_key(&utils::SECP);
+        let verifier_idx = verifiers_public_keys
+            .iter()
+            .position(|verifier_pk| *verifier_pk == pk)
+            .ok_or(BridgeError::PublicKeyNotFound)?;
+
+        Ok(DkgParticipant {
+            id: signer_id(verifier_idx)?,
+            secret_key,
+            verifiers_public_keys,
+            threshold,
+            round1: None,
+            round2: None,
+        })
+    }
+
+    pub fn id(&self) -> FrostSignerId {
+        self.id
+    }
+
+    // Identifiers and public keys of the other verifiers.
+    fn others(&self) -> impl Iterator<Item = (FrostSignerId, &PublicKey)> {
+        let id = self.id;
+
+        self.verifiers_public_keys
+            .iter()
+            .enumerate()
+            .map(|(i, pk)| (i as FrostSignerId + 1, pk))
+            .filter(move |(other_id, _)| *other_id != id)
+    }
+
+    // Round 1 packages of the other verifiers, keyed with the frost crate's identifiers.
+    fn others_round1_packages(
+        &self,
+        round1_packages: &BTreeMap<FrostSignerId, dkg::round1::Package>,
+    ) -> Result<BTreeMap<Identifier, dkg::round1::Package>, BridgeError> {
+        self.others()
+            .map(|(id, _)| {
+                let package = round1_packages
+                    .get(&id)
+                    .ok_or(BridgeError::InvalidFrostDkgPackage(id))?;
+
+                Ok((to_identifier(id)?, package.clone()))
+            })
+            .collect()
+    }
+
+    /// Starts a new ceremony and returns the round 1 package, which is
+    /// broadcast to every verifier.
+    pub fn round1<R: RngCore + CryptoRng>(
+        &mut self,
+        rng: &mut R,
+    ) -> Result<dkg::round1::Package, BridgeError> {
+        let (secret_package, package) = dkg::part1(
+            to_identifier(self.id)?,
+            self.verifiers_public_keys.len() as u16,
+            self.threshold,
+            &mut *rng,
+        )?;
+
+        self.round1 = Some((secret_package, package.clone()));
+        self.round2 = None;
+
+        Ok(package)
+    }
+
+    /// Returns the round 2 packages of the other verifiers, encrypted to
+    /// them. `round1_packages` must have every verifier's round 1 package,
+    /// including this verifier's.
+    pub fn round2<R: RngCore + CryptoRng>(
+        &mut self,
+        round1_packages: BTreeMap<FrostSignerId, dkg::round1::Package>,
+        rng: &mut R,
+    ) -> Result<BTreeMap<FrostSignerId, EncryptedRound2Package>, BridgeError> {
+        let (secret_package, package) =
+            self.round1.take().ok_or(BridgeError::FrostDkgNotStarted)?;
+
+        if round1_packages.len() != self.verifiers_public_keys.len()
+            || round1_packages.get(&self.id) != Some(&package)
+        {
+            return Err(BridgeError::InvalidFrostDkgPackage(self.id));
+        }
+        let transcript = round1_transcript(&round1_packages)?;
+
+        let (round2_secret_package, round2_packages) = dkg::part2(
+            secret_package,
+            &self.others_round1_packages(&round1_packages)?,
+        )?;
+
+        let mut encrypted_packages = BTreeMap::new();
+        for (id, pk) in self.others() {
+            let package = round2_packages
+                .get(&to_identifier(id)?)
+                .ok_or(BridgeError::InvalidFrostDkgPackage(id))?;
+
+            let mut nonce = [0u8; ROUND2_NONCE_LEN];
+            rng.fill_bytes(&mut nonce);
+            let ciphertext = round2_cipher(&self.secret_key, pk, self.id, id)
+                .encrypt(
+                    chacha20poly1305::Nonce::from_slice(&nonce),
+                    Payload {
+                        msg: &package.serialize()?,
+                        aad: &transcript,
+                    },
+                )
+                .map_err(|_| BridgeError::InvalidFrostDkgPackage(id))?;
+
+            encrypted_packages.insert(
+                id,
+                EncryptedRound2Package([nonce.to_vec(), ciphertext].concat()),
+            );
+        }
+
+        self.round2 = Some((round2_secret_package, round1_packages));
+
+        Ok(encrypted_packages)
+    }
+
+    /// Finishes the ceremony with the round 2 packages that are sent to this
+    /// verifier, keyed with their senders. Returns the verifier's key package
+    /// and the public key package of the group.
+    pub fn finalize(
+        &mut self,
+        round2_packages: BTreeMap<FrostSignerId, EncryptedRound2Package>,
+    ) -> Result<(KeyPackage, PublicKeyPackage), BridgeError> {
+        let (secret_package, round1_packages) =
+            self.round2.take().ok_or(BridgeError::FrostDkgNotStarted)?;
+
+        if round2_packages.len() + 1 != self.verifiers_public_keys.len() {
+            return Err(BridgeError::InvalidFrostDkgPackage(self.id));
+        }
+        let transcript = round1_transcript(&round1_packages)?;
+
+        let mut decrypted_packages = BTreeMap::new();
+        for (id, pk) in self.others() {
+            let encrypted_package = round2_packages
+                .get(&id)
+                .filter(|package| package.0.len() > ROUND2_NONCE_LEN)
+                .ok_or(BridgeError::InvalidFrostDkgPackage(id))?;
+            let (nonce, ciphertext) = encrypted_package.0.split_at(ROUND2_NONCE_LEN);
+
+            let package = round2_cipher(&self.secret_key, pk, id, self.id)
+                .decrypt(
+                    chacha20poly1305::Nonce::from_slice(nonce),
+                    Payload {
+                        msg: ciphertext,
+                        aad: &transcript,
+                    },
+                )
+                .map_err(|_| BridgeError::InvalidFrostDkgPackage(id))?;
+
+            decrypted_packages.insert(
+                to_identifier(id)?,
+                dkg::round2::Package::deserialize(&package)
+                    .map_err(|_| BridgeError::InvalidFrostDkgPackage(id))?,
+            );
+        }
+
+        Ok(dkg::part3(
+            &secret_package,
+            &self.others_round1_packages(&round1_packages)?,
+            &decrypted_packages,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DkgParticipant, EncryptedRound2Package, FrostSignerId, ROUND2_NONCE_LEN};
+    use crate::{errors::BridgeError, utils, ByteArray32};
+    use bitcoin::{hashes::Hash, key::TapTweak, TapNodeHash};
+    use frost_secp256k1_tr::keys::{KeyPackage, PublicKeyPackage};
+    use secp256k1::{rand::Rng, Message, SecretKey};
+    use std::collections::BTreeMap;
+
+    // Runs the DKG ceremony of `num_signers` verifiers, relaying the packages like the aggregator.
+    fn run_dkg(num_signers: usize, threshold: u16) -> Vec<(KeyPackage, PublicKeyPackage)> {
+        let mut participants = create_participants(num_signers, threshold);
+        let round2_packages = run_dkg_round2(&mut participants);
+
+        participants
+            .iter_mut()
+            .map(|participant| {
+                participant
+                    .finalize(packages_to(&round2_packages, participant.id()))
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    fn create_participants(num_signers: usize, threshold: u16) -> Vec<DkgParticipant> {
+        let sks = (0..num_signers)
+            .map(|_| SecretKey::new(&mut secp256k1::rand::thread_rng()))
+            .collect::<Vec<_>>();
+        let pks = sks
+            .iter()
+            .map(|sk| sk.public_key(&utils::SECP))
+            .collect::<Vec<_>>();
+
+        sks.iter()
+            .map(|sk| DkgParticipant::new(*sk, pks.clone(), threshold).unwrap())
+            .collect()
+    }
+
+    fn run_dkg_round2(
+        participants: &mut [DkgParticipant],
+    ) -> BTreeMap<FrostSignerId, BTreeMap<FrostSignerId, EncryptedRound2Package>> {
+        let round1_packages = participants
+            .iter_mut()
+            .map(|participant| {
+                (
+                    participant.id(),
+                    participant
+                        .round1(&mut secp256k1::rand::thread_rng())
+                        .unwrap(),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        participants
+            .iter_mut()
+            .map(|participant| {
+                (
+                    participant.id(),
+                    participant
+                        .round2(round1_packages.clone(), &mut secp256k1::rand::thread_rng())
+                        .unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    // Round 2 packages that are sent to the recipient, keyed with their senders.
+    fn packages_to(
+        round2_packages: &BTreeMap<FrostSignerId, BTreeMap<FrostSignerId, EncryptedRound2Package>>,
+        recipient: FrostSignerId,
+    ) -> BTreeMap<FrostSignerId, EncryptedRound2Package> {
+        round2_packages
+            .iter()
+            .filter_map(|(sender, packages)| {
+                packages
+                    .get(&recipient)
+                    .map(|package| (*sender, package.clone()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_threshold_signing() {
+        let keys = run_dkg(5, 3);
+        let group_pk = super::group_xonly_pk(&keys[0].1).unwrap();
+        for (_, public_key_package) in &keys {
+            assert_eq!(public_key_package, &keys[0].1);
+        }
+
+        let message: [u8; 32] = secp256k1::rand::thread_rng().gen();
+        let tweak: [u8; 32] = secp256k1::rand::thread_rng().gen();
+        let tweak = TapNodeHash::from_slice(&tweak).unwrap();
+
+        for (tweak, tweak_flag, pk) in [
+            (None, false, group_pk),
+            (
+                None,
+                true,
+                group_pk.tap_tweak(&utils::SECP, None).0.to_inner(),
+            ),
+            (
+                Some(tweak),
+                true,
+                group_pk.tap_tweak(&utils::SECP, Some(tweak)).0.to_inner(),
+            ),
+        ] {
+            // Any 3 of the verifiers can sign.
+            for signers in [[1, 2, 3], [1, 3, 5], [2, 4, 5]] {
+                let nonce_pairs = signers
+                    .iter()
+                    .map(|id| {
+                        let key_package = &keys[usize::from(*id) - 1].0;
+                        (
+                            *id,
+                            super::nonce_pair(key_package, &mut secp256k1::rand::thread_rng())
+                                .unwrap(),
+                        )
+                    })
+                    .collect::<BTreeMap<FrostSignerId, _>>();
+                let commitments = nonce_pairs
+                    .iter()
+                    .map(|(id, (_, commitment))| (*id, *commitment))
+                    .collect::<BTreeMap<_, _>>();
+
+                let signature_shares = nonce_pairs
+                    .iter()
+                    .map(|(id, (nonce, _))| {
+                        let share = super::sign(
+                            &keys[usize::from(*id) - 1].0,
+                            tweak,
+                            tweak_flag,
+                            *nonce,
+                            &commitments,
+                            ByteArray32(message),
+                        )
+                        .unwrap();
+
+                        (*id, share)
+                    })
+                    .collect::<BTreeMap<_, _>>();
+
+                let signature = super::aggregate(
+                    &keys[0].1,
+                    tweak,
+                    tweak_flag,
+                    &commitments,
+                    &signature_shares,
+                    ByteArray32(message),
+                )
+                .unwrap();
+                utils::SECP
+                    .verify_schnorr(&signature, &Message::from_digest(message), &pk)
+                    .unwrap();
+
+                // Shares of less than the threshold can't be aggregated.
+                let mut signature_shares = signature_shares;
+                signature_shares.remove(&signers[0]);
+                assert!(super::aggregate(
+                    &keys[0].1,
+                    tweak,
+                    tweak_flag,
+                    &commitments,
+                    &signature_shares,
+                    ByteArray32(message),
+                )
+                .is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_dkg_invalid_packages() {
+        assert!(matches!(
+            DkgParticipant::new(
+                SecretKey::new(&mut secp256k1::rand::thread_rng()),
+                vec![],
+                2
+            ),
+            Err(BridgeError::InvalidFrostThreshold(2, 0))
+        ));
+
+        let mut participants = create_participants(3, 2);
+        assert!(matches!(
+            participants[0].finalize(BTreeMap::new()),
+            Err(BridgeError::FrostDkgNotStarted)
+        ));
+
+        // A round 2 package that is tampered with by the aggregator can't be
+        // decrypted by its recipient.
+        let round2_packages = run_dkg_round2(&mut participants);
+        let mut packages = packages_to(&round2_packages, 1);
+        packages.get_mut(&2).unwrap().0[ROUND2_NONCE_LEN] ^= 1;
+        assert!(matches!(
+            participants[0].finalize(packages),
+            Err(BridgeError::InvalidFrostDkgPackage(2))
+        ));
+
+        // Round 2 packages are bound to the round 1 packages of their sender.
+        let mut round1_packages = participants
+            .iter_mut()
+            .map(|participant| {
+                (
+                    participant.id(),
+                    participant
+                        .round1(&mut secp256k1::rand::thread_rng())
+                        .unwrap(),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+        let round2_packages_of_2 = participants[1]
+            .round2(round1_packages.clone(), &mut secp256k1::rand::thread_rng())
+            .unwrap();
+        let round2_packages_of_3 = participants[2]
+            .round2(round1_packages.clone(), &mut secp256k1::rand::thread_rng())
+            .unwrap();
+        // Round 1 package of the 3rd verifier is replaced for the 1st one.
+        let replaced_package = create_participants(3, 2)[2]
+            .round1(&mut secp256k1::rand::thread_rng())
+            .unwrap();
+        round1_packages.insert(3, replaced_package);
+        participants[0]
+            .round2(round1_packages, &mut secp256k1::rand::thread_rng())
+            .unwrap();
+        assert!(matches!(
+            participants[0].finalize(BTreeMap::from([
+                (2, round2_packages_of_2[&1].clone()),
+                (3, round2_packages_of_3[&1].clone()),
+            ])),
+            Err(BridgeError::InvalidFrostDkgPackage(2))
+        ));
+    }
+
+    #[test]
+    fn test_frost_tweak_without_tweak_flag() {
+        let keys = run_dkg(2, 2);
+        let tweak = Some(TapNodeHash::from_slice(&[1u8; 32]).unwrap());
+        let (nonce, commitment) =
+            super::nonce_pair(&keys[0].0, &mut secp256k1::rand::thread_rng()).unwrap();
+
+        assert!(matches!(
+            super::sign(
+                &keys[0].0,
+                tweak,
+                false,
+                nonce,
+                &BTreeMap::from([(1, commitment)]),
+                ByteArray32([1u8; 32]),
+            ),
+            Err(BridgeError::MuSigTweakWithoutTweakFlag)
+        ));
+    }
+}
@@ -0,0 +1,271 @@
+//! # Header Indexer
+//!
+//! Header indexer keeps the block headers of the Bitcoin node's best chain in
+//! the database, with their heights and chainworks, so that proof builders can
+//! look them up without asking the node. Headers that are reorged out are
+//! replaced with the new chain's.
+
+use crate::config::BridgeConfig;
+use crate::database::Database;
+use crate::errors::BridgeError;
+use crate::extended_rpc::ExtendedRpc;
+use bitcoin::block::Header;
+use bitcoin::{BlockHash, Work};
+use bitcoin_mock_rpc::RpcApiWrapper;
+use std::time::Duration;
+
+/// Time to wait between two checks for a new block.
+pub const HEADER_INDEXER_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Maximum number of headers that are saved in a single database transaction.
+pub const HEADER_SYNC_BATCH_SIZE: usize = 1000;
+
+/// Block header of the best chain, with its position in the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexedHeader {
+    pub height: u32,
+    pub header: Header,
+    /// Total work of the chain up to and including this block.
+    pub chainwork: Work,
+}
+
+#[derive(Debug, Clone)]
+pub struct HeaderIndexer<R>
+where
+    R: RpcApiWrapper,
+{
+    rpc: ExtendedRpc<R>,
+    db: Database,
+}
+
+impl<R> HeaderIndexer<R>
+where
+    R: RpcApiWrapper,
+{
+    pub async fn new(rpc: ExtendedRpc<R>, config: BridgeConfig) -> Result<Self, BridgeError> {
+        let db = Database::new(&config).await?;
+
+        Ok(HeaderIndexer { rpc, db })
+    }
+
+    /// Follows the chain forever and syncs the headers every time a new block
+    /// is found. Errors are logged, so that a temporary failure doesn't stop
+    /// the indexer.
+    pub async fn run(&self, poll_interval: Duration) {
+        let mut last_block_count = None;
+
+        loop {
+            match self.rpc.get_block_count().await {
+                Ok(block_count) if last_block_count != Some(block_count) => {
+                    match self.sync().await {
+                        Ok(_) => last_block_count = Some(block_count),
+                        Err(e) => tracing::error!("Header indexer can't sync headers: {}", e),
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("Header indexer can't get block count: {}", e),
+            }
+
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Syncs the indexed headers with the node's best chain.
+    ///
+    /// # Returns
+    ///
+    /// Header at the tip of the indexed chain.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn sync(&self) -> Result<Option<IndexedHeader>, BridgeError> {
+        loop {
+            let node_height = self.rpc.get_block_count().await? as u32;
+            let tip = self.sync_batch(node_height).await?;
+
+            if tip.is_some_and(|tip| tip.height >= node_height) {
+                return Ok(tip);
+            }
+        }
+    }
+
+    /// Replaces the headers after the fork point with the node's and saves at
+    /// most [`HEADER_SYNC_BATCH_SIZE`] new headers, in a single database
+    /// transaction. If the node's chain changes during the batch, the batch
+    /// ends early and the next one starts from the new fork point.
+    async fn sync_batch(&self, node_height: u32) -> Result<Option<IndexedHeader>, BridgeError> {
+        let fork_point = self.find_fork_point(node_height).await?;
+
+        let mut headers: Vec<IndexedHeader> = Vec::new();
+        let mut height = fork_point.map_or(0, |header| header.height + 1);
+        while height <= node_height && headers.len() < HEADER_SYNC_BATCH_SIZE {
+            let block_hash = self.rpc.get_block_hash(height as u64).await?;
+            let header = self.rpc.get_block_header(&block_hash).await?;
+
+            let prev = headers.last().or(fork_point.as_ref());
+            if prev.is_some_and(|prev| prev.header.block_hash() != header.prev_blockhash) {
+                break;
+            }
+
+            headers.push(IndexedHeader {
+                height,
+                header,
+                chainwork: prev.map_or(header.work(), |prev| prev.chainwork + header.work()),
+            });
+            height += 1;
+        }
+
+        let mut dbtx = self.db.begin_transaction().await?;
+        self.db
+            .delete_block_headers_after(
+                Some(&mut dbtx),
+                fork_point.map(|fork_point| fork_point.height),
+            )
+            .await?;
+        self.db
+            .save_block_headers(Some(&mut dbtx), &headers)
+            .await?;
+        dbtx.commit().await?;
+
+        Ok(headers.last().copied().or(fork_point))
+    }
+
+    /// Returns the last indexed header that is still in the node's best
+    /// chain, or `None` if none of them are.
+    async fn find_fork_point(
+        &self,
+        node_height: u32,
+    ) -> Result<Option<IndexedHeader>, BridgeError> {
+        let mut height = match self.db.get_block_header_tip().await? {
+            Some(tip) => tip.height.min(node_height),
+            None => return Ok(None),
+        };
+
+        loop {
+            let Some(header) = self.db.get_block_header_by_height(height).await? else {
+                return Ok(None);
+            };
+
+            if self.rpc.get_block_hash(height as u64).await? == header.header.block_hash() {
+                return Ok(Some(header));
+            }
+
+            tracing::warn!(
+                "Block {} at height {} is reorged out",
+                header.header.block_hash(),
+                height
+            );
+
+            if height == 0 {
+                return Ok(None);
+            }
+            height -= 1;
+        }
+    }
+
+    /// Returns the indexed header at the given height of the best chain.
+    pub async fn header_by_height(
+        &self,
+        height: u32,
+    ) -> Result<Option<IndexedHeader>, BridgeError> {
+        self.db.get_block_header_by_height(height).await
+    }
+
+    /// Returns the indexed header with the given hash, if it is in the best
+    /// chain.
+    pub async fn header_by_hash(
+        &self,
+        block_hash: BlockHash,
+    ) -> Result<Option<IndexedHeader>, BridgeError> {
+        self.db.get_block_header_by_hash(block_hash).await
+    }
+
+    /// Returns the header at the tip of the indexed chain.
+    pub async fn tip(&self) -> Result<Option<IndexedHeader>, BridgeError> {
+        self.db.get_block_header_tip().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeaderIndexer, IndexedHeader};
+    use crate::extended_rpc::ExtendedRpc;
+    use crate::{create_extended_rpc, mock::database::create_test_config};
+
+    #[tokio::test]
+    async fn headers_are_synced_and_reorgs_are_replaced() {
+        let mut config = create_test_config(
+            "headers_are_synced_and_reorgs_are_replaced",
+            "test_config.toml",
+        )
+        .await;
+        let rpc = create_extended_rpc!(config);
+        let indexer = HeaderIndexer::new(rpc.clone(), config.clone())
+            .await
+            .unwrap();
+
+        rpc.mine_blocks(5).await.unwrap();
+        let tip = indexer.sync().await.unwrap().unwrap();
+        assert_eq!(tip.height as u64, rpc.get_block_count().await.unwrap());
+        assert_eq!(
+            tip.header.block_hash(),
+            rpc.get_block_hash(tip.height as u64).await.unwrap()
+        );
+        assert_eq!(
+            indexer
+                .header_by_hash(tip.header.block_hash())
+                .await
+                .unwrap(),
+            Some(tip)
+        );
+
+        let prev = indexer
+            .header_by_height(tip.height - 1)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tip.header.prev_blockhash, prev.header.block_hash());
+        assert_eq!(tip.chainwork, prev.chainwork + tip.header.work());
+
+        // Replace the last two headers with a fork that the node doesn't know.
+        let fork_point = indexer
+            .header_by_height(tip.height - 2)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut fork = vec![];
+        let mut prev = fork_point;
+        for _ in 0..2 {
+            let mut header = prev.header;
+            header.prev_blockhash = prev.header.block_hash();
+            header.nonce = header.nonce.wrapping_add(1);
+
+            prev = IndexedHeader {
+                height: prev.height + 1,
+                header,
+                chainwork: prev.chainwork + header.work(),
+            };
+            fork.push(prev);
+        }
+        indexer
+            .db
+            .delete_block_headers_after(None, Some(fork_point.height))
+            .await
+            .unwrap();
+        indexer.db.save_block_headers(None, &fork).await.unwrap();
+
+        rpc.mine_blocks(3).await.unwrap();
+        let new_tip = indexer.sync().await.unwrap().unwrap();
+        assert_eq!(new_tip.height, tip.height + 3);
+        assert_eq!(
+            indexer.header_by_height(tip.height).await.unwrap(),
+            Some(tip)
+        );
+        for header in fork {
+            assert_eq!(
+                indexer
+                    .header_by_hash(header.header.block_hash())
+                    .await
+                    .unwrap(),
+                None
+            );
+        }
+    }
+}
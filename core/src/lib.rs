@@ -12,23 +12,31 @@ use serde::{Deserialize, Serialize};
 pub mod actor;
 pub mod aggregator;
 pub mod builder;
+pub mod citrea;
 pub mod cli;
 pub mod config;
 pub mod constants;
 pub mod database;
+pub mod deposit_tracker;
 pub mod env_writer;
+pub mod epoch;
 pub mod errors;
 pub mod extended_rpc;
+pub mod fee;
+pub mod frost;
 pub mod hashes;
+pub mod header_indexer;
 pub mod merkle;
 pub mod mock;
 pub mod musig2;
+pub mod nonce_pool;
 pub mod operator;
 pub mod servers;
 pub mod traits;
 pub mod user;
 pub mod utils;
 pub mod verifier;
+pub mod watchtower;
 
 pub type ConnectorUTXOTree = Vec<Vec<OutPoint>>;
 // pub type HashTree = Vec<Vec<HashType>>;
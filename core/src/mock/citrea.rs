@@ -0,0 +1,168 @@
+//! # Citrea Mock
+//!
+//! In-process Citrea JSON-RPC server for tests. It emulates the bridge
+//! contract's `eth_call` responses from an in-memory registry of deposits,
+//! withdrawals and withdrawal fillers.
+
+use crate::citrea::{
+    encode_fixed_bytes, encode_uint, UTXO_TO_DEPOSIT_ID_SELECTOR, WITHDRAWAL_UTXOS_SELECTOR,
+    WITHDRAW_FILLERS_SELECTOR, WORD_SIZE,
+};
+use crate::errors::BridgeError;
+use bitcoin::hashes::Hash;
+use bitcoin::{OutPoint, Txid};
+use jsonrpsee::core::{async_trait, RpcResult};
+use jsonrpsee::proc_macros::rpc;
+use jsonrpsee::server::{Server, ServerHandle};
+use jsonrpsee::types::ErrorObject;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Call object of an `eth_call` request. Only the call data is needed, as
+/// there is a single contract.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EthCall {
+    pub data: String,
+}
+
+#[rpc(server)]
+pub trait MockCitreaRpc {
+    #[method(name = "eth_call")]
+    async fn eth_call(&self, call: EthCall, block: Option<String>) -> RpcResult<String>;
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    /// Bridge fund outpoints, indexed by deposit index.
+    deposits: Vec<OutPoint>,
+    /// Withdrawal UTXOs, indexed by withdrawal index.
+    withdrawals: Vec<OutPoint>,
+    /// Operator indexes, keyed by withdrawal index.
+    fillers: HashMap<u32, usize>,
+}
+
+/// Bridge contract state that can be shared between a test and the mock
+/// server.
+#[derive(Debug, Clone, Default)]
+pub struct MockCitrea {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl MockCitrea {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the deposit of a move_tx output and returns its index.
+    pub fn add_deposit(&self, bridge_fund_outpoint: OutPoint) -> u32 {
+        let mut registry = self.registry.lock().unwrap();
+        registry.deposits.push(bridge_fund_outpoint);
+
+        registry.deposits.len() as u32 - 1
+    }
+
+    /// Registers a withdrawal and returns its index.
+    pub fn add_withdrawal(&self, withdrawal_utxo: OutPoint) -> u32 {
+        let mut registry = self.registry.lock().unwrap();
+        registry.withdrawals.push(withdrawal_utxo);
+
+        registry.withdrawals.len() as u32 - 1
+    }
+
+    /// Declares the operator as the filler of the withdrawal.
+    pub fn fill_withdrawal(&self, withdrawal_idx: u32, operator_idx: usize) {
+        self.registry
+            .lock()
+            .unwrap()
+            .fillers
+            .insert(withdrawal_idx, operator_idx);
+    }
+
+    /// Starts the server on a random local port.
+    ///
+    /// # Returns
+    ///
+    /// URL of the server, to be set as `citrea_rpc_url`, and its handle.
+    pub async fn start(&self) -> Result<(String, ServerHandle), BridgeError> {
+        let server = Server::builder()
+            .build("127.0.0.1:0")
+            .await
+            .map_err(BridgeError::ServerError)?;
+        let addr = server.local_addr().map_err(BridgeError::ServerError)?;
+        let handle = server.start(self.clone().into_rpc());
+
+        tracing::info!("Mock Citrea server started with address: {}", addr);
+
+        Ok((format!("http://{}:{}/", addr.ip(), addr.port()), handle))
+    }
+
+    /// Executes a bridge contract call and returns the ABI encoded result, or
+    /// `None` if the contract would revert.
+    fn call(&self, data: &[u8]) -> Option<Vec<u8>> {
+        let selector: [u8; 4] = data.get(..4)?.try_into().ok()?;
+        let argument: [u8; WORD_SIZE] = data.get(4..4 + WORD_SIZE)?.try_into().ok()?;
+        let registry = self.registry.lock().unwrap();
+
+        match selector {
+            WITHDRAWAL_UTXOS_SELECTOR => {
+                let withdrawal_utxo = registry.withdrawals.get(decode_index(&argument)?)?;
+
+                let mut response = withdrawal_utxo.txid.to_byte_array().to_vec();
+                response.extend(encode_fixed_bytes(&withdrawal_utxo.vout.to_le_bytes()));
+                Some(response)
+            }
+            WITHDRAW_FILLERS_SELECTOR => {
+                let withdrawal_idx = decode_index(&argument)? as u32;
+
+                // Fillers are registered with 1 based indexes.
+                let filler = registry
+                    .fillers
+                    .get(&withdrawal_idx)
+                    .map_or(0, |operator_idx| *operator_idx as u64 + 1);
+                Some(encode_uint(filler).to_vec())
+            }
+            UTXO_TO_DEPOSIT_ID_SELECTOR => {
+                let output_id: [u8; 4] = data
+                    .get(4 + WORD_SIZE..4 + WORD_SIZE + 4)?
+                    .try_into()
+                    .ok()?;
+                let bridge_fund_outpoint = OutPoint {
+                    txid: Txid::from_byte_array(argument),
+                    vout: u32::from_le_bytes(output_id),
+                };
+
+                // Deposits are registered with 1 based indexes.
+                let deposit_id = registry
+                    .deposits
+                    .iter()
+                    .position(|outpoint| *outpoint == bridge_fund_outpoint)
+                    .map_or(0, |deposit_idx| deposit_idx as u64 + 1);
+                Some(encode_uint(deposit_id).to_vec())
+            }
+            _ => None,
+        }
+    }
+}
+
+#[async_trait]
+impl MockCitreaRpcServer for MockCitrea {
+    async fn eth_call(&self, call: EthCall, _block: Option<String>) -> RpcResult<String> {
+        let data = hex::decode(call.data.trim_start_matches("0x"))
+            .map_err(|e| ErrorObject::owned(-32602, e.to_string(), None::<()>))?;
+
+        match self.call(&data) {
+            Some(response) => Ok(format!("0x{}", hex::encode(response))),
+            None => Err(ErrorObject::owned(3, "execution reverted", None::<()>)),
+        }
+    }
+}
+
+/// Reads a `uint256` argument that is used as an array index.
+fn decode_index(word: &[u8; WORD_SIZE]) -> Option<usize> {
+    if word[..WORD_SIZE - 4].iter().any(|byte| *byte != 0) {
+        return None;
+    }
+
+    Some(u32::from_be_bytes(word[WORD_SIZE - 4..].try_into().ok()?) as usize)
+}
@@ -3,6 +3,7 @@
 //! This module includes mock interfaces for tests. There are also some common
 //! elements for unit and integration tests.
 
+pub mod citrea;
 pub mod common;
 pub mod database;
 pub mod env;
@@ -10,6 +10,10 @@
 /// - `config`: Mutable `BridgeConfig` structure.
 /// - `db_name`: If mock is used, this will be it's database name. If not used
 ///    can be dummy value.
+///
+/// # Panics
+///
+/// Panics if a client can't be created for any of the configured endpoints.
 #[cfg(feature = "mock_rpc")]
 #[macro_export]
 macro_rules! create_extended_rpc {
@@ -25,11 +29,7 @@ macro_rules! create_extended_rpc {
 
         $config.bitcoin_rpc_url = handle.to_string();
 
-        ExtendedRpc::<bitcoin_mock_rpc::Client>::new(
-            $config.bitcoin_rpc_url.clone(),
-            $config.bitcoin_rpc_user.clone(),
-            $config.bitcoin_rpc_password.clone(),
-        )
+        ExtendedRpc::<bitcoin_mock_rpc::Client>::from_config(&$config).unwrap()
     }};
 }
 /// Creates an [`ExtendedRpc`] struct from either the real Bitcoin RPC or mock
@@ -40,6 +40,10 @@ macro_rules! create_extended_rpc {
 /// - `config`: Mutable `BridgeConfig` structure.
 /// - `db_name`: If mock is used, this will be it's database name. If not used
 ///   can be dummy value.
+///
+/// # Panics
+///
+/// Panics if a client can't be created for any of the configured endpoints.
 #[cfg(not(feature = "mock_rpc"))]
 #[macro_export]
 macro_rules! create_extended_rpc {
@@ -49,10 +53,6 @@ macro_rules! create_extended_rpc {
         // Mutation for consistency with above defined macro
         $config.bitcoin_rpc_url = $config.bitcoin_rpc_url.clone();
 
-        ExtendedRpc::<bitcoincore_rpc::Client>::new(
-            $config.bitcoin_rpc_url.clone(),
-            $config.bitcoin_rpc_user.clone(),
-            $config.bitcoin_rpc_password.clone(),
-        )
+        ExtendedRpc::<bitcoincore_rpc::Client>::from_config(&$config).unwrap()
     }};
 }
@@ -1,8 +1,12 @@
-use crate::{errors::BridgeError, ByteArray32, ByteArray64, ByteArray66};
+use crate::{
+    config::BridgeConfig, errors::BridgeError, utils, ByteArray32, ByteArray64, ByteArray66,
+};
 use bitcoin::hashes::Hash;
+use bitcoin::key::TapTweak;
 use bitcoin::TapNodeHash;
 use musig2::{sign_partial, AggNonce, KeyAggContext, SecNonce, SecNonceSpices};
-use secp256k1::{rand::Rng, PublicKey};
+use secp256k1::{rand::Rng, PublicKey, XOnlyPublicKey};
+use std::borrow::Cow;
 
 // We can directly use the musig2 crate for this
 // No need for extra types etc.
@@ -25,28 +29,47 @@ pub trait AggregateFromPublicKeys {
         pks: Vec<PublicKey>,
         tweak: Option<TapNodeHash>,
         tweak_flag: bool,
-    ) -> secp256k1::XOnlyPublicKey;
+    ) -> Result<secp256k1::XOnlyPublicKey, BridgeError>;
 }
 
 impl AggregateFromPublicKeys for secp256k1::XOnlyPublicKey {
-    #[tracing::instrument(ret(level = tracing::Level::TRACE))]
+    #[tracing::instrument(err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
     fn from_musig2_pks(
         pks: Vec<PublicKey>,
         tweak: Option<TapNodeHash>,
         tweak_flag: bool,
-    ) -> secp256k1::XOnlyPublicKey {
-        let key_agg_ctx = create_key_agg_ctx(pks, tweak, tweak_flag).unwrap();
-        let musig_agg_pubkey: musig2::secp256k1::PublicKey = if tweak_flag {
-            key_agg_ctx.aggregated_pubkey()
-        } else {
-            key_agg_ctx.aggregated_pubkey_untweaked()
-        };
-        // tracing::debug!("UNTWEAKED AGGREGATED PUBKEY: {:?}", musig_agg_pubkey);
-        let musig_agg_xonly_pubkey = musig_agg_pubkey.x_only_public_key().0;
-        secp256k1::XOnlyPublicKey::from_slice(&musig_agg_xonly_pubkey.serialize()).unwrap()
+    ) -> Result<secp256k1::XOnlyPublicKey, BridgeError> {
+        let key_agg_ctx = create_key_agg_ctx(pks, tweak, tweak_flag)?;
+
+        to_xonly_pk(&key_agg_ctx, tweak_flag)
     }
 }
 
+// Converts the public keys to the musig2 crate's type. Returns the index of the first invalid public key.
+fn to_musig_pks(pks: &[PublicKey]) -> Result<Vec<musig2::secp256k1::PublicKey>, BridgeError> {
+    pks.iter()
+        .enumerate()
+        .map(|(i, pk)| {
+            musig2::secp256k1::PublicKey::from_slice(&pk.serialize())
+                .map_err(|_| BridgeError::InvalidMuSigPublicKey(i))
+        })
+        .collect()
+}
+
+// Converts the secret key of the keypair to the musig2 crate's type.
+fn to_musig_seckey(
+    keypair: &secp256k1::Keypair,
+) -> Result<musig2::secp256k1::SecretKey, BridgeError> {
+    Ok(musig2::secp256k1::SecretKey::from_slice(
+        &keypair.secret_key().secret_bytes(),
+    )?)
+}
+
+// Decodes an aggregated nonce. Both of its points must be on the curve or at infinity.
+fn to_musig_agg_nonce(agg_nonce: &MuSigAggNonce) -> Result<AggNonce, BridgeError> {
+    AggNonce::from_bytes(&agg_nonce.0).map_err(|_| BridgeError::InvalidMuSigAggNonce)
+}
+
 // Creates the key aggregation context, with the public keys and the tweak (if any).
 // There are two functions to retrieve the aggregated public key, one with the tweak and one without.
 #[tracing::instrument(err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
@@ -55,15 +78,22 @@ pub fn create_key_agg_ctx(
     tweak: Option<TapNodeHash>,
     tweak_flag: bool,
 ) -> Result<KeyAggContext, BridgeError> {
-    let musig_pks: Vec<musig2::secp256k1::PublicKey> = pks
-        .iter()
-        .map(|pk| musig2::secp256k1::PublicKey::from_slice(&pk.serialize()).unwrap())
-        .collect::<Vec<musig2::secp256k1::PublicKey>>();
+    let musig_pks = to_musig_pks(&pks)?;
     let key_agg_ctx_raw = KeyAggContext::new(musig_pks)?;
     // tracing::debug!(
     //     "UNTWEAKED AGGREGATED PUBKEY: {:?}",
     //     key_agg_ctx_raw.aggregated_pubkey::<musig2::secp256k1::PublicKey>()
     // );
+    tweak_key_agg_ctx(key_agg_ctx_raw, tweak, tweak_flag)
+}
+
+// Adds the taproot tweak to an untweaked key aggregation context. If the tweak flag is set without a tweak,
+// the unspendable taproot tweak is used.
+fn tweak_key_agg_ctx(
+    key_agg_ctx_raw: KeyAggContext,
+    tweak: Option<TapNodeHash>,
+    tweak_flag: bool,
+) -> Result<KeyAggContext, BridgeError> {
     if tweak_flag {
         let key_agg_ctx = match tweak {
             Some(scalar) => key_agg_ctx_raw.with_taproot_tweak(&scalar.to_byte_array())?,
@@ -76,24 +106,209 @@ pub fn create_key_agg_ctx(
         Ok(key_agg_ctx)
     } else {
         if tweak.is_some() {
-            return Err(BridgeError::VecConversionError); // TODO: Change error handling.
+            return Err(BridgeError::MuSigTweakWithoutTweakFlag);
         }
         Ok(key_agg_ctx_raw)
     }
 }
 
+// Returns the x-only public key of a key aggregation context, tweaked or not.
+fn to_xonly_pk(
+    key_agg_ctx: &KeyAggContext,
+    tweak_flag: bool,
+) -> Result<XOnlyPublicKey, BridgeError> {
+    let musig_agg_pubkey: musig2::secp256k1::PublicKey = if tweak_flag {
+        key_agg_ctx.aggregated_pubkey()
+    } else {
+        key_agg_ctx.aggregated_pubkey_untweaked()
+    };
+    let musig_agg_xonly_pubkey = musig_agg_pubkey.x_only_public_key().0;
+
+    Ok(XOnlyPublicKey::from_slice(
+        &musig_agg_xonly_pubkey.serialize(),
+    )?)
+}
+
+/// Key aggregation of the verifiers' public keys, which is computed once at
+/// startup instead of for every signature.
+///
+/// Holds the untweaked context, which is tweaked with a script tree's merkle
+/// root when needed, and the context with the unspendable taproot tweak.
+///
+/// If the verifiers sign with a FROST key, its group key replaces the
+/// aggregated key and MuSig2 signing methods return an error.
+#[derive(Debug, Clone)]
+pub struct NofnContext {
+    pks: Vec<PublicKey>,
+    untweaked_key_agg_ctx: KeyAggContext,
+    tweaked_key_agg_ctx: KeyAggContext,
+    xonly_pk: XOnlyPublicKey,
+    tweaked_xonly_pk: XOnlyPublicKey,
+    threshold_key: bool,
+}
+
+impl NofnContext {
+    #[tracing::instrument(err(level = tracing::Level::ERROR))]
+    pub fn new(pks: Vec<PublicKey>) -> Result<Self, BridgeError> {
+        let untweaked_key_agg_ctx = create_key_agg_ctx(pks.clone(), None, false)?;
+        let tweaked_key_agg_ctx = tweak_key_agg_ctx(untweaked_key_agg_ctx.clone(), None, true)?;
+        let xonly_pk = to_xonly_pk(&untweaked_key_agg_ctx, false)?;
+        let tweaked_xonly_pk = to_xonly_pk(&tweaked_key_agg_ctx, true)?;
+
+        Ok(NofnContext {
+            pks,
+            untweaked_key_agg_ctx,
+            tweaked_key_agg_ctx,
+            xonly_pk,
+            tweaked_xonly_pk,
+            threshold_key: false,
+        })
+    }
+
+    /// Creates the context of the configured verifiers, with the FROST group
+    /// key if there is one.
+    pub fn from_config(config: &BridgeConfig) -> Result<Self, BridgeError> {
+        let nofn = NofnContext::new(config.verifiers_public_keys.clone())?;
+
+        Ok(match config.frost_group_public_key {
+            Some(group_pk) => nofn.with_threshold_key(group_pk),
+            None => nofn,
+        })
+    }
+
+    /// Replaces the aggregated key with the group key of a FROST key.
+    pub fn with_threshold_key(self, group_pk: XOnlyPublicKey) -> Self {
+        NofnContext {
+            xonly_pk: group_pk,
+            tweaked_xonly_pk: group_pk.tap_tweak(&utils::SECP, None).0.to_inner(),
+            threshold_key: true,
+            ..self
+        }
+    }
+
+    /// Returns true if the N-of-N key is the group key of a FROST key.
+    pub fn is_threshold_key(&self) -> bool {
+        self.threshold_key
+    }
+
+    /// Public keys of the verifiers, in signer order.
+    pub fn pks(&self) -> &[PublicKey] {
+        &self.pks
+    }
+
+    /// Untweaked aggregated x-only public key, which is the N-of-N key in
+    /// scripts and the internal key of the MuSig2 address.
+    pub fn xonly_pk(&self) -> XOnlyPublicKey {
+        self.xonly_pk
+    }
+
+    /// Aggregated x-only public key with the unspendable taproot tweak.
+    pub fn tweaked_xonly_pk(&self) -> XOnlyPublicKey {
+        self.tweaked_xonly_pk
+    }
+
+    /// Returns the key aggregation context for the given tweak. Only a
+    /// script tree's merkle root needs a new context, which is tweaked from
+    /// the cached untweaked one without aggregating the keys again.
+    pub fn key_agg_ctx(
+        &self,
+        tweak: Option<TapNodeHash>,
+        tweak_flag: bool,
+    ) -> Result<Cow<'_, KeyAggContext>, BridgeError> {
+        if self.threshold_key {
+            return Err(BridgeError::MuSigWithThresholdKey);
+        }
+
+        match (tweak, tweak_flag) {
+            (None, false) => Ok(Cow::Borrowed(&self.untweaked_key_agg_ctx)),
+            (None, true) => Ok(Cow::Borrowed(&self.tweaked_key_agg_ctx)),
+            _ => Ok(Cow::Owned(tweak_key_agg_ctx(
+                self.untweaked_key_agg_ctx.clone(),
+                tweak,
+                tweak_flag,
+            )?)),
+        }
+    }
+
+    /// Same as [`partial_sign`], with the cached key aggregation contexts.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub fn partial_sign(
+        &self,
+        tweak: Option<TapNodeHash>,
+        tweak_flag: bool,
+        sec_nonce: MuSigSecNonce,
+        agg_nonce: MuSigAggNonce,
+        keypair: &secp256k1::Keypair,
+        sighash: MuSigSigHash,
+    ) -> Result<MuSigPartialSignature, BridgeError> {
+        sign_with_ctx(
+            &self.key_agg_ctx(tweak, tweak_flag)?,
+            sec_nonce,
+            agg_nonce,
+            keypair,
+            sighash,
+        )
+    }
+
+    /// Same as [`aggregate_partial_signatures`], with the cached key
+    /// aggregation contexts.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub fn aggregate_partial_signatures(
+        &self,
+        tweak: Option<TapNodeHash>,
+        tweak_flag: bool,
+        agg_nonce: &MuSigAggNonce,
+        partial_sigs: Vec<MuSigPartialSignature>,
+        message: MuSigSigHash,
+    ) -> Result<[u8; 64], BridgeError> {
+        aggregate_with_ctx(
+            &self.key_agg_ctx(tweak, tweak_flag)?,
+            agg_nonce,
+            partial_sigs,
+            message,
+        )
+    }
+
+    /// Same as [`verify_partial_signatures`], with the cached key aggregation
+    /// contexts.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub fn verify_partial_signatures(
+        &self,
+        tweak: Option<TapNodeHash>,
+        tweak_flag: bool,
+        agg_nonce: &MuSigAggNonce,
+        pub_nonces: Vec<MuSigPubNonce>,
+        partial_sigs: Vec<MuSigPartialSignature>,
+        message: MuSigSigHash,
+    ) -> Result<(), BridgeError> {
+        verify_with_ctx(
+            &self.key_agg_ctx(tweak, tweak_flag)?,
+            &self.pks,
+            agg_nonce,
+            pub_nonces,
+            partial_sigs,
+            message,
+        )
+    }
+}
+
 // Aggregates the public nonces into a single aggregated nonce. Wrapper for the musig2::AggNonce::sum function.
-#[tracing::instrument(ret(level = tracing::Level::TRACE))]
-pub fn aggregate_nonces(pub_nonces: Vec<MuSigPubNonce>) -> MuSigAggNonce {
-    let musig_pub_nonces: Vec<musig2::PubNonce> = pub_nonces
+// Returns the index of the first public nonce that is not a pair of valid curve points.
+#[tracing::instrument(err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+pub fn aggregate_nonces(pub_nonces: Vec<MuSigPubNonce>) -> Result<MuSigAggNonce, BridgeError> {
+    let musig_pub_nonces = pub_nonces
         .iter()
-        .map(|x| musig2::PubNonce::from_bytes(&x.0).unwrap())
-        .collect::<Vec<musig2::PubNonce>>();
+        .enumerate()
+        .map(|(i, x)| {
+            musig2::PubNonce::from_bytes(&x.0).map_err(|_| BridgeError::InvalidMuSigPubNonce(i))
+        })
+        .collect::<Result<Vec<musig2::PubNonce>, BridgeError>>()?;
     let musig_agg_nonce: AggNonce = AggNonce::sum(musig_pub_nonces);
-    ByteArray66(musig_agg_nonce.into())
+    Ok(ByteArray66(musig_agg_nonce.into()))
 }
 
 // Aggregates the partial signatures into a single final signature. Wrapper for the musig2::aggregate_partial_signatures function.
+// Returns the index of the first partial signature that is not a valid scalar.
 #[tracing::instrument(err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
 pub fn aggregate_partial_signatures(
     pks: Vec<PublicKey>,
@@ -104,32 +319,137 @@ pub fn aggregate_partial_signatures(
     message: MuSigSigHash,
 ) -> Result<[u8; 64], BridgeError> {
     let key_agg_ctx = create_key_agg_ctx(pks, tweak, tweak_flag)?;
-    let musig_partial_sigs: Vec<musig2::PartialSignature> = partial_sigs
+
+    aggregate_with_ctx(&key_agg_ctx, agg_nonce, partial_sigs, message)
+}
+
+fn aggregate_with_ctx(
+    key_agg_ctx: &KeyAggContext,
+    agg_nonce: &MuSigAggNonce,
+    partial_sigs: Vec<MuSigPartialSignature>,
+    message: MuSigSigHash,
+) -> Result<[u8; 64], BridgeError> {
+    let musig_partial_sigs = partial_sigs
         .iter()
-        .map(|x| musig2::PartialSignature::from_slice(&x.0).unwrap())
-        .collect::<Vec<musig2::PartialSignature>>();
+        .enumerate()
+        .map(|(i, x)| {
+            musig2::PartialSignature::from_slice(&x.0)
+                .map_err(|_| BridgeError::InvalidMuSigPartialSignature(i))
+        })
+        .collect::<Result<Vec<musig2::PartialSignature>, BridgeError>>()?;
     Ok(musig2::aggregate_partial_signatures(
-        &key_agg_ctx,
-        &AggNonce::from_bytes(&agg_nonce.0).unwrap(),
+        key_agg_ctx,
+        &to_musig_agg_nonce(agg_nonce)?,
         musig_partial_sigs,
         message.0,
     )?)
 }
 
+// Verifies every partial signature against its signer's public key and public nonce. Returns an error
+// with the indices of the signers whose partial signatures are invalid, so that they can be blamed.
+// Aggregated nonce must be the aggregate of the public nonces.
+#[tracing::instrument(err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+pub fn verify_partial_signatures(
+    pks: Vec<PublicKey>,
+    tweak: Option<TapNodeHash>,
+    tweak_flag: bool,
+    agg_nonce: &MuSigAggNonce,
+    pub_nonces: Vec<MuSigPubNonce>,
+    partial_sigs: Vec<MuSigPartialSignature>,
+    message: MuSigSigHash,
+) -> Result<(), BridgeError> {
+    let key_agg_ctx = create_key_agg_ctx(pks.clone(), tweak, tweak_flag)?;
+
+    verify_with_ctx(
+        &key_agg_ctx,
+        &pks,
+        agg_nonce,
+        pub_nonces,
+        partial_sigs,
+        message,
+    )
+}
+
+fn verify_with_ctx(
+    key_agg_ctx: &KeyAggContext,
+    pks: &[PublicKey],
+    agg_nonce: &MuSigAggNonce,
+    pub_nonces: Vec<MuSigPubNonce>,
+    partial_sigs: Vec<MuSigPartialSignature>,
+    message: MuSigSigHash,
+) -> Result<(), BridgeError> {
+    let musig_agg_nonce = to_musig_agg_nonce(agg_nonce)?;
+
+    let invalid_indices = (0..pks.len())
+        .filter(|&i| match (pub_nonces.get(i), partial_sigs.get(i)) {
+            (Some(pub_nonce), Some(partial_sig)) => !is_valid_partial_signature(
+                key_agg_ctx,
+                &musig_agg_nonce,
+                pks[i],
+                pub_nonce,
+                partial_sig,
+                message,
+            ),
+            _ => true,
+        })
+        .collect::<Vec<usize>>();
+
+    if !invalid_indices.is_empty() {
+        return Err(BridgeError::InvalidPartialSignatures(invalid_indices));
+    }
+
+    // Partial signatures are valid for the given aggregated nonce, which must
+    // be the one of the signers' public nonces.
+    if aggregate_nonces(pub_nonces)? != *agg_nonce {
+        return Err(BridgeError::InvalidMuSigAggNonce);
+    }
+
+    Ok(())
+}
+
+// Checks a single partial signature. Malformed public keys, nonces or partial signatures are
+// treated as invalid.
+fn is_valid_partial_signature(
+    key_agg_ctx: &KeyAggContext,
+    agg_nonce: &AggNonce,
+    pk: PublicKey,
+    pub_nonce: &MuSigPubNonce,
+    partial_sig: &MuSigPartialSignature,
+    message: MuSigSigHash,
+) -> bool {
+    let Ok(musig_pk) = musig2::secp256k1::PublicKey::from_slice(&pk.serialize()) else {
+        return false;
+    };
+    let Ok(musig_pub_nonce) = musig2::PubNonce::from_bytes(&pub_nonce.0) else {
+        return false;
+    };
+    let Ok(musig_partial_sig) = musig2::PartialSignature::from_slice(&partial_sig.0) else {
+        return false;
+    };
+
+    musig2::verify_partial(
+        key_agg_ctx,
+        musig_partial_sig,
+        agg_nonce,
+        musig_pk,
+        &musig_pub_nonce,
+        message.0,
+    )
+    .is_ok()
+}
+
 // Generates a pair of nonces, one secret and one public. Wrapper for the musig2::SecNonce::build function. Be careful,
 // DO NOT REUSE the same pair of nonces for multiple transactions. It will cause you to leak your secret key. For more information,
 // see https://medium.com/blockstream/musig-dn-schnorr-multisignatures-with-verifiably-deterministic-nonces-27424b5df9d6#e3b6.
-#[tracing::instrument(skip(rng), ret(level = tracing::Level::TRACE))]
+#[tracing::instrument(skip(rng), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
 pub fn nonce_pair(
     keypair: &secp256k1::Keypair,
     rng: &mut impl Rng,
-) -> (MuSigSecNonce, MuSigPubNonce) {
+) -> Result<(MuSigSecNonce, MuSigPubNonce), BridgeError> {
     let musig_pubkey: musig2::secp256k1::PublicKey =
-        musig2::secp256k1::PublicKey::from_slice(&keypair.public_key().serialize()).unwrap();
+        musig2::secp256k1::PublicKey::from_slice(&keypair.public_key().serialize())?;
     let rnd = rng.gen::<[u8; 32]>();
-    let spices = SecNonceSpices::new().with_seckey(
-        musig2::secp256k1::SecretKey::from_slice(&keypair.secret_key().secret_bytes()).unwrap(),
-    );
+    let spices = SecNonceSpices::new().with_seckey(to_musig_seckey(keypair)?);
 
     let sec_nonce = SecNonce::build(rnd)
         .with_pubkey(musig_pubkey)
@@ -139,11 +459,11 @@ pub fn nonce_pair(
     let pub_nonce = ByteArray66(sec_nonce.public_nonce().into());
     let sec_nonce: [u8; 64] = sec_nonce.into();
 
-    (ByteArray64(sec_nonce), pub_nonce)
+    Ok((ByteArray64(sec_nonce), pub_nonce))
 }
 
 // We are creating the key aggregation context manually here, adding the tweaks by hand.
-#[tracing::instrument(ret(level = tracing::Level::TRACE))]
+#[tracing::instrument(err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
 pub fn partial_sign(
     pks: Vec<PublicKey>,
     // Aggregated tweak, if there is any. This is useful for
@@ -154,19 +474,30 @@ pub fn partial_sign(
     agg_nonce: MuSigAggNonce,
     keypair: &secp256k1::Keypair,
     sighash: MuSigSigHash,
-) -> MuSigPartialSignature {
-    let key_agg_ctx = create_key_agg_ctx(pks, tweak, tweak_flag).unwrap();
-    let musig_sec_nonce = SecNonce::from_bytes(&sec_nonce.0).unwrap();
-    let musig_agg_nonce = AggNonce::from_bytes(&agg_nonce.0).unwrap();
+) -> Result<MuSigPartialSignature, BridgeError> {
+    let key_agg_ctx = create_key_agg_ctx(pks, tweak, tweak_flag)?;
+
+    sign_with_ctx(&key_agg_ctx, sec_nonce, agg_nonce, keypair, sighash)
+}
+
+fn sign_with_ctx(
+    key_agg_ctx: &KeyAggContext,
+    sec_nonce: MuSigSecNonce,
+    agg_nonce: MuSigAggNonce,
+    keypair: &secp256k1::Keypair,
+    sighash: MuSigSigHash,
+) -> Result<MuSigPartialSignature, BridgeError> {
+    let musig_sec_nonce =
+        SecNonce::from_bytes(&sec_nonce.0).map_err(|_| BridgeError::InvalidMuSigSecNonce)?;
+    let musig_agg_nonce = to_musig_agg_nonce(&agg_nonce)?;
     let partial_signature: [u8; 32] = sign_partial(
-        &key_agg_ctx,
-        musig2::secp256k1::SecretKey::from_slice(&keypair.secret_key().secret_bytes()).unwrap(),
+        key_agg_ctx,
+        to_musig_seckey(keypair)?,
         musig_sec_nonce,
         &musig_agg_nonce,
         sighash.0,
-    )
-    .unwrap();
-    ByteArray32(partial_signature)
+    )?;
+    Ok(ByteArray32(partial_signature))
 }
 
 #[cfg(test)]
@@ -177,11 +508,11 @@ mod tests {
         builder::{self, transaction::TxHandler},
         errors::BridgeError,
         musig2::{AggregateFromPublicKeys, MuSigPartialSignature},
-        utils, ByteArray32,
+        utils, ByteArray32, ByteArray66,
     };
     use bitcoin::{
-        hashes::Hash, opcodes::all::OP_CHECKSIG, script, Amount, OutPoint, ScriptBuf, TapNodeHash,
-        TxOut, Txid,
+        hashes::Hash, key::TapTweak, opcodes::all::OP_CHECKSIG, script, Amount, OutPoint,
+        ScriptBuf, TapNodeHash, TxOut, Txid,
     };
     use secp256k1::{rand::Rng, Keypair, Message, XOnlyPublicKey};
     use std::vec;
@@ -197,7 +528,7 @@ mod tests {
         }
         let nonce_pair_vec: Vec<MuSigNoncePair> = keypair_vec
             .iter()
-            .map(|keypair| nonce_pair(keypair, &mut secp256k1::rand::thread_rng()))
+            .map(|keypair| nonce_pair(keypair, &mut secp256k1::rand::thread_rng()).unwrap())
             .collect();
         (keypair_vec, nonce_pair_vec)
     }
@@ -217,7 +548,8 @@ mod tests {
         // Create the key aggregation context
         let key_agg_ctx = super::create_key_agg_ctx(pks.clone(), None, false).unwrap();
         // Aggregate the public nonces into the aggregated nonce
-        let agg_nonce = super::aggregate_nonces(nonce_pair_vec.iter().map(|x| x.1).collect());
+        let agg_nonce =
+            super::aggregate_nonces(nonce_pair_vec.iter().map(|x| x.1).collect()).unwrap();
         // Extract the aggregated public key
         let musig_agg_pubkey: musig2::secp256k1::PublicKey = key_agg_ctx.aggregated_pubkey();
         // Calculate the partial signatures
@@ -234,6 +566,7 @@ mod tests {
                     kp,
                     ByteArray32(message),
                 )
+                .unwrap()
             })
             .collect();
         // Aggregate the partial signatures into a final signature
@@ -260,12 +593,13 @@ mod tests {
         let message: [u8; 32] = secp256k1::rand::thread_rng().gen();
         let pks = vec![kp_0.public_key(), kp_1.public_key(), kp_2.public_key()];
         let (sec_nonce_0, pub_nonce_0) =
-            super::nonce_pair(&kp_0, &mut secp256k1::rand::thread_rng());
+            super::nonce_pair(&kp_0, &mut secp256k1::rand::thread_rng()).unwrap();
         let (sec_nonce_1, pub_nonce_1) =
-            super::nonce_pair(&kp_1, &mut secp256k1::rand::thread_rng());
+            super::nonce_pair(&kp_1, &mut secp256k1::rand::thread_rng()).unwrap();
         let (sec_nonce_2, pub_nonce_2) =
-            super::nonce_pair(&kp_2, &mut secp256k1::rand::thread_rng());
-        let agg_nonce = super::aggregate_nonces(vec![pub_nonce_0, pub_nonce_1, pub_nonce_2]);
+            super::nonce_pair(&kp_2, &mut secp256k1::rand::thread_rng()).unwrap();
+        let agg_nonce =
+            super::aggregate_nonces(vec![pub_nonce_0, pub_nonce_1, pub_nonce_2]).unwrap();
         let partial_sig_0 = super::partial_sign(
             pks.clone(),
             None,
@@ -274,7 +608,8 @@ mod tests {
             agg_nonce,
             &kp_0,
             ByteArray32(message),
-        );
+        )
+        .unwrap();
         let partial_sig_1 = super::partial_sign(
             pks.clone(),
             None,
@@ -283,7 +618,8 @@ mod tests {
             agg_nonce,
             &kp_1,
             ByteArray32(message),
-        );
+        )
+        .unwrap();
         // Oops, a verifier accidentally added some tweak!
         let partial_sig_2 = super::partial_sign(
             pks.clone(),
@@ -293,7 +629,8 @@ mod tests {
             agg_nonce,
             &kp_2,
             ByteArray32(message),
-        );
+        )
+        .unwrap();
         let partial_sigs = vec![partial_sig_0, partial_sig_1, partial_sig_2];
         let final_signature: Result<[u8; 64], BridgeError> = super::aggregate_partial_signatures(
             pks,
@@ -306,6 +643,184 @@ mod tests {
         assert!(final_signature.is_err());
     }
 
+    // Test that the misbehaving signer is blamed when its partial signature is invalid.
+    #[test]
+    fn test_verify_partial_signatures_blame() {
+        let (kp_vec, nonce_pair_vec) = generate_test_setup(3);
+        let message: [u8; 32] = secp256k1::rand::thread_rng().gen();
+        let pks = kp_vec
+            .iter()
+            .map(|kp| kp.public_key())
+            .collect::<Vec<secp256k1::PublicKey>>();
+        let pub_nonces = nonce_pair_vec.iter().map(|x| x.1).collect::<Vec<_>>();
+        let agg_nonce = super::aggregate_nonces(pub_nonces.clone()).unwrap();
+        let mut partial_sigs: Vec<MuSigPartialSignature> = kp_vec
+            .iter()
+            .zip(nonce_pair_vec.iter())
+            .map(|(kp, nonce_pair)| {
+                super::partial_sign(
+                    pks.clone(),
+                    None,
+                    false,
+                    nonce_pair.0,
+                    agg_nonce,
+                    kp,
+                    ByteArray32(message),
+                )
+                .unwrap()
+            })
+            .collect();
+
+        super::verify_partial_signatures(
+            pks.clone(),
+            None,
+            false,
+            &agg_nonce,
+            pub_nonces.clone(),
+            partial_sigs.clone(),
+            ByteArray32(message),
+        )
+        .unwrap();
+
+        // Oops, second verifier sent its partial signature of another message!
+        partial_sigs[1] = super::partial_sign(
+            pks.clone(),
+            None,
+            false,
+            nonce_pair(&kp_vec[1], &mut secp256k1::rand::thread_rng())
+                .unwrap()
+                .0,
+            agg_nonce,
+            &kp_vec[1],
+            ByteArray32([0x45; 32]),
+        )
+        .unwrap();
+
+        let res = super::verify_partial_signatures(
+            pks,
+            None,
+            false,
+            &agg_nonce,
+            pub_nonces,
+            partial_sigs,
+            ByteArray32(message),
+        );
+        assert!(matches!(
+            res,
+            Err(BridgeError::InvalidPartialSignatures(indices)) if indices == vec![1]
+        ));
+    }
+
+    // Test that partial signatures are refused when they are signed with an aggregated nonce that is not
+    // the one of the signers' public nonces.
+    #[test]
+    fn test_verify_partial_signatures_agg_nonce_mismatch() {
+        let (kp_vec, nonce_pair_vec) = generate_test_setup(3);
+        let message: [u8; 32] = secp256k1::rand::thread_rng().gen();
+        let pks = kp_vec
+            .iter()
+            .map(|kp| kp.public_key())
+            .collect::<Vec<secp256k1::PublicKey>>();
+        let pub_nonces = nonce_pair_vec.iter().map(|x| x.1).collect::<Vec<_>>();
+        // Aggregated nonce leaves out the last signer's nonce.
+        let agg_nonce = super::aggregate_nonces(pub_nonces[..2].to_vec()).unwrap();
+        let partial_sigs: Vec<MuSigPartialSignature> = kp_vec
+            .iter()
+            .zip(nonce_pair_vec.iter())
+            .map(|(kp, nonce_pair)| {
+                super::partial_sign(
+                    pks.clone(),
+                    None,
+                    false,
+                    nonce_pair.0,
+                    agg_nonce,
+                    kp,
+                    ByteArray32(message),
+                )
+                .unwrap()
+            })
+            .collect();
+
+        let res = super::verify_partial_signatures(
+            pks,
+            None,
+            false,
+            &agg_nonce,
+            pub_nonces,
+            partial_sigs,
+            ByteArray32(message),
+        );
+        assert!(matches!(res, Err(BridgeError::InvalidMuSigAggNonce)));
+    }
+
+    // Test that malformed nonces and partial signatures are reported with their index instead of panicking.
+    #[test]
+    fn test_musig2_malformed_inputs() {
+        let (kp_vec, nonce_pair_vec) = generate_test_setup(3);
+        let message: [u8; 32] = secp256k1::rand::thread_rng().gen();
+        let pks = kp_vec
+            .iter()
+            .map(|kp| kp.public_key())
+            .collect::<Vec<secp256k1::PublicKey>>();
+        let mut pub_nonces = nonce_pair_vec.iter().map(|x| x.1).collect::<Vec<_>>();
+        let agg_nonce = super::aggregate_nonces(pub_nonces.clone()).unwrap();
+
+        // Not a compressed curve point.
+        pub_nonces[2] = ByteArray66([0xFF; 66]);
+        assert!(matches!(
+            super::aggregate_nonces(pub_nonces),
+            Err(BridgeError::InvalidMuSigPubNonce(2))
+        ));
+
+        assert!(matches!(
+            super::partial_sign(
+                pks.clone(),
+                None,
+                false,
+                nonce_pair_vec[0].0,
+                ByteArray66([0xFF; 66]),
+                &kp_vec[0],
+                ByteArray32(message),
+            ),
+            Err(BridgeError::InvalidMuSigAggNonce)
+        ));
+
+        let mut partial_sigs: Vec<MuSigPartialSignature> = kp_vec
+            .iter()
+            .zip(nonce_pair_vec.iter())
+            .map(|(kp, nonce_pair)| {
+                super::partial_sign(
+                    pks.clone(),
+                    None,
+                    false,
+                    nonce_pair.0,
+                    agg_nonce,
+                    kp,
+                    ByteArray32(message),
+                )
+                .unwrap()
+            })
+            .collect();
+        // Larger than the curve order.
+        partial_sigs[1] = ByteArray32([0xFF; 32]);
+        assert!(matches!(
+            super::aggregate_partial_signatures(
+                pks.clone(),
+                None,
+                false,
+                &agg_nonce,
+                partial_sigs,
+                ByteArray32(message),
+            ),
+            Err(BridgeError::InvalidMuSigPartialSignature(1))
+        ));
+
+        assert!(matches!(
+            super::create_key_agg_ctx(pks, Some(TapNodeHash::all_zeros()), false),
+            Err(BridgeError::MuSigTweakWithoutTweakFlag)
+        ));
+    }
+
     // Test the MuSig2 signature scheme with a tweak.
     #[test]
     fn test_musig2_tweak() {
@@ -322,7 +837,8 @@ mod tests {
             true,
         )
         .unwrap();
-        let agg_nonce = super::aggregate_nonces(nonce_pair_vec.iter().map(|x| x.1).collect());
+        let agg_nonce =
+            super::aggregate_nonces(nonce_pair_vec.iter().map(|x| x.1).collect()).unwrap();
         let musig_agg_pubkey: musig2::secp256k1::PublicKey = key_agg_ctx.aggregated_pubkey();
         let partial_sigs: Vec<MuSigPartialSignature> = kp_vec
             .iter()
@@ -337,6 +853,7 @@ mod tests {
                     kp,
                     ByteArray32(message),
                 )
+                .unwrap()
             })
             .collect();
         let final_signature: [u8; 64] = super::aggregate_partial_signatures(
@@ -353,6 +870,97 @@ mod tests {
         println!("MuSig2 signature verified successfully!");
     }
 
+    // Test that the cached contexts give the same keys and signatures as building them from the public keys.
+    #[test]
+    fn test_nofn_context() {
+        let (kp_vec, nonce_pair_vec) = generate_test_setup(3);
+        let message: [u8; 32] = secp256k1::rand::thread_rng().gen();
+        let tweak: [u8; 32] = secp256k1::rand::thread_rng().gen();
+        let tweak = Some(TapNodeHash::from_slice(&tweak).unwrap());
+        let pks = kp_vec
+            .iter()
+            .map(|kp| kp.public_key())
+            .collect::<Vec<secp256k1::PublicKey>>();
+        let nofn = super::NofnContext::new(pks.clone()).unwrap();
+
+        assert_eq!(
+            nofn.xonly_pk(),
+            XOnlyPublicKey::from_musig2_pks(pks.clone(), None, false).unwrap()
+        );
+        assert_eq!(
+            nofn.tweaked_xonly_pk(),
+            XOnlyPublicKey::from_musig2_pks(pks.clone(), None, true).unwrap()
+        );
+        assert!(matches!(
+            nofn.key_agg_ctx(tweak, false),
+            Err(BridgeError::MuSigTweakWithoutTweakFlag)
+        ));
+
+        for (tweak, tweak_flag) in [(None, false), (None, true), (tweak, true)] {
+            let agg_nonce =
+                super::aggregate_nonces(nonce_pair_vec.iter().map(|x| x.1).collect()).unwrap();
+            let partial_sigs: Vec<MuSigPartialSignature> = kp_vec
+                .iter()
+                .zip(nonce_pair_vec.iter())
+                .map(|(kp, nonce_pair)| {
+                    nofn.partial_sign(
+                        tweak,
+                        tweak_flag,
+                        nonce_pair.0,
+                        agg_nonce,
+                        kp,
+                        ByteArray32(message),
+                    )
+                    .unwrap()
+                })
+                .collect();
+            assert_eq!(
+                partial_sigs[0],
+                super::partial_sign(
+                    pks.clone(),
+                    tweak,
+                    tweak_flag,
+                    nonce_pair_vec[0].0,
+                    agg_nonce,
+                    &kp_vec[0],
+                    ByteArray32(message),
+                )
+                .unwrap()
+            );
+
+            nofn.verify_partial_signatures(
+                tweak,
+                tweak_flag,
+                &agg_nonce,
+                nonce_pair_vec.iter().map(|x| x.1).collect(),
+                partial_sigs.clone(),
+                ByteArray32(message),
+            )
+            .unwrap();
+            let final_signature = nofn
+                .aggregate_partial_signatures(
+                    tweak,
+                    tweak_flag,
+                    &agg_nonce,
+                    partial_sigs,
+                    ByteArray32(message),
+                )
+                .unwrap();
+
+            let musig_agg_pubkey: musig2::secp256k1::PublicKey = if tweak_flag {
+                nofn.key_agg_ctx(tweak, tweak_flag)
+                    .unwrap()
+                    .aggregated_pubkey()
+            } else {
+                nofn.key_agg_ctx(tweak, tweak_flag)
+                    .unwrap()
+                    .aggregated_pubkey_untweaked()
+            };
+            musig2::verify_single(musig_agg_pubkey, final_signature, message)
+                .expect("Verification failed!");
+        }
+    }
+
     #[test]
     fn test_musig2_tweak_fail() {
         let kp_0 = secp256k1::Keypair::new(&utils::SECP, &mut secp256k1::rand::thread_rng());
@@ -362,12 +970,13 @@ mod tests {
         let tweak: [u8; 32] = secp256k1::rand::thread_rng().gen();
         let pks = vec![kp_0.public_key(), kp_1.public_key(), kp_2.public_key()];
         let (sec_nonce_0, pub_nonce_0) =
-            super::nonce_pair(&kp_0, &mut secp256k1::rand::thread_rng());
+            super::nonce_pair(&kp_0, &mut secp256k1::rand::thread_rng()).unwrap();
         let (sec_nonce_1, pub_nonce_1) =
-            super::nonce_pair(&kp_1, &mut secp256k1::rand::thread_rng());
+            super::nonce_pair(&kp_1, &mut secp256k1::rand::thread_rng()).unwrap();
         let (sec_nonce_2, pub_nonce_2) =
-            super::nonce_pair(&kp_2, &mut secp256k1::rand::thread_rng());
-        let agg_nonce = super::aggregate_nonces(vec![pub_nonce_0, pub_nonce_1, pub_nonce_2]);
+            super::nonce_pair(&kp_2, &mut secp256k1::rand::thread_rng()).unwrap();
+        let agg_nonce =
+            super::aggregate_nonces(vec![pub_nonce_0, pub_nonce_1, pub_nonce_2]).unwrap();
         let partial_sig_0 = super::partial_sign(
             pks.clone(),
             Some(TapNodeHash::from_slice(&tweak).unwrap()),
@@ -376,7 +985,8 @@ mod tests {
             agg_nonce,
             &kp_0,
             ByteArray32(message),
-        );
+        )
+        .unwrap();
         let partial_sig_1 = super::partial_sign(
             pks.clone(),
             Some(TapNodeHash::from_slice(&tweak).unwrap()),
@@ -385,7 +995,8 @@ mod tests {
             agg_nonce,
             &kp_1,
             ByteArray32(message),
-        );
+        )
+        .unwrap();
         // Oops, a verifier accidentally forgot to put the tweak!
         let partial_sig_2 = super::partial_sign(
             pks.clone(),
@@ -395,7 +1006,8 @@ mod tests {
             agg_nonce,
             &kp_2,
             ByteArray32(message),
-        );
+        )
+        .unwrap();
         let partial_sigs = vec![partial_sig_0, partial_sig_1, partial_sig_2];
         let final_signature = super::aggregate_partial_signatures(
             pks,
@@ -425,7 +1037,8 @@ mod tests {
                 &untweaked_pubkey.x_only_public_key().0.serialize(),
             )
             .unwrap();
-        let agg_nonce = super::aggregate_nonces(nonce_pair_vec.iter().map(|x| x.1).collect());
+        let agg_nonce =
+            super::aggregate_nonces(nonce_pair_vec.iter().map(|x| x.1).collect()).unwrap();
         let dummy_script = script::Builder::new().push_int(1).into_script();
         let scripts: Vec<ScriptBuf> = vec![dummy_script];
         let receiving_address = bitcoin::Address::p2tr(
@@ -439,7 +1052,8 @@ mod tests {
                 &scripts.clone(),
                 Some(untweaked_xonly_pubkey),
                 bitcoin::Network::Regtest,
-            );
+            )
+            .unwrap();
         let prevout = TxOut {
             value: Amount::from_sat(100_000_000),
             script_pubkey: sending_address.script_pubkey(),
@@ -477,6 +1091,7 @@ mod tests {
                     kp,
                     ByteArray32(message),
                 )
+                .unwrap()
             })
             .collect();
         let final_signature: [u8; 64] = super::aggregate_partial_signatures(
@@ -489,7 +1104,7 @@ mod tests {
         )
         .unwrap();
         let musig_agg_xonly_pubkey_wrapped =
-            XOnlyPublicKey::from_musig2_pks(pks, merkle_root, true);
+            XOnlyPublicKey::from_musig2_pks(pks, merkle_root, true).unwrap();
         // musig2::verify_single(musig_agg_pubkey, &final_signature, message)
         //     .expect("Verification failed!");
         utils::SECP
@@ -510,9 +1125,10 @@ mod tests {
             .iter()
             .map(|kp| kp.public_key())
             .collect::<Vec<secp256k1::PublicKey>>();
-        let agg_nonce = super::aggregate_nonces(nonce_pair_vec.iter().map(|x| x.1).collect());
+        let agg_nonce =
+            super::aggregate_nonces(nonce_pair_vec.iter().map(|x| x.1).collect()).unwrap();
         let musig_agg_xonly_pubkey_wrapped =
-            XOnlyPublicKey::from_musig2_pks(pks.clone(), None, false);
+            XOnlyPublicKey::from_musig2_pks(pks.clone(), None, false).unwrap();
         let musig2_script = bitcoin::script::Builder::new()
             .push_x_only_key(&musig_agg_xonly_pubkey_wrapped)
             .push_opcode(OP_CHECKSIG)
@@ -529,7 +1145,8 @@ mod tests {
                 &scripts.clone(),
                 None,
                 bitcoin::Network::Regtest,
-            );
+            )
+            .unwrap();
         let prevout = TxOut {
             value: Amount::from_sat(100_000_000),
             script_pubkey: sending_address.script_pubkey(),
@@ -567,6 +1184,7 @@ mod tests {
                     kp,
                     ByteArray32(message),
                 )
+                .unwrap()
             })
             .collect();
         let final_signature: [u8; 64] = super::aggregate_partial_signatures(
@@ -589,4 +1207,41 @@ mod tests {
             .unwrap();
         println!("MuSig2 signature verified successfully!");
     }
+
+    #[test]
+    fn test_nofn_context_threshold_key() {
+        let (kp_vec, nonce_pair_vec) = generate_test_setup(2);
+        let pks = kp_vec
+            .iter()
+            .map(|kp| kp.public_key())
+            .collect::<Vec<secp256k1::PublicKey>>();
+        let group_pk = Keypair::new(&utils::SECP, &mut secp256k1::rand::thread_rng())
+            .x_only_public_key()
+            .0;
+
+        let nofn = super::NofnContext::new(pks).unwrap();
+        assert!(!nofn.is_threshold_key());
+        let nofn = nofn.with_threshold_key(group_pk);
+        assert!(nofn.is_threshold_key());
+        assert_eq!(nofn.xonly_pk(), group_pk);
+        assert_eq!(
+            nofn.tweaked_xonly_pk(),
+            group_pk.tap_tweak(&utils::SECP, None).0.to_inner()
+        );
+
+        // Group key can't be signed with MuSig2.
+        let agg_nonce =
+            super::aggregate_nonces(nonce_pair_vec.iter().map(|x| x.1).collect()).unwrap();
+        assert!(matches!(
+            nofn.partial_sign(
+                None,
+                false,
+                nonce_pair_vec[0].0,
+                agg_nonce,
+                &kp_vec[0],
+                ByteArray32([1u8; 32]),
+            ),
+            Err(BridgeError::MuSigWithThresholdKey)
+        ));
+    }
 }
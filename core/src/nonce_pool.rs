@@ -0,0 +1,125 @@
+//! # Nonce Pool
+//!
+//! Verifiers keep a pool of pre-generated MuSig2 nonce pairs, so that the
+//! nonces of a new deposit are assigned from the pool instead of being
+//! generated while the deposit is requested. Pool nonces are saved in the
+//! `nonces` table without a deposit and every one of them is assigned to a
+//! single deposit, which keeps them from being reused.
+//!
+//! Nonces of the FROST key are generated from the verifier's key package, so
+//! they are not pooled.
+
+use crate::config::BridgeConfig;
+use crate::database::Database;
+use crate::errors::BridgeError;
+use crate::musig2::{self, MuSigPubNonce, MuSigSecNonce};
+use crate::utils;
+use secp256k1::{rand, Keypair};
+use std::time::Duration;
+
+/// Time to wait between two checks of the pool size.
+pub const NONCE_POOL_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Maximum number of nonce pairs that are added to the pool at once. Rest of
+/// the pool is filled in the next checks.
+const MAX_NONCES_PER_REPLENISH: usize = 1000;
+
+#[derive(Debug, Clone)]
+pub struct NoncePool {
+    db: Database,
+    keypair: Keypair,
+    pool_size: usize,
+}
+
+impl NoncePool {
+    pub async fn new(config: BridgeConfig) -> Result<Self, BridgeError> {
+        let db = Database::new(&config).await?;
+        let keypair = Keypair::from_secret_key(&utils::SECP, &config.secret_key);
+
+        let pool_size = match config.frost_threshold {
+            Some(_) => 0,
+            None => config.verifier_nonce_pool_size,
+        };
+
+        Ok(NoncePool {
+            db,
+            keypair,
+            pool_size,
+        })
+    }
+
+    /// Keeps the pool filled forever. Errors are logged, so that a temporary
+    /// failure doesn't stop the replenishment.
+    pub async fn run(&self, poll_interval: Duration) {
+        loop {
+            if let Err(e) = self.replenish().await {
+                tracing::error!("Nonce pool can't be replenished: {}", e);
+            }
+
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Generates nonce pairs for the missing part of the pool, up to
+    /// [`MAX_NONCES_PER_REPLENISH`] of them.
+    ///
+    /// # Returns
+    ///
+    /// Number of nonce pairs that are added to the pool.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn replenish(&self) -> Result<usize, BridgeError> {
+        let num_missing_nonces = self
+            .pool_size
+            .saturating_sub(self.db.get_nonce_pool_size(None).await?)
+            .min(MAX_NONCES_PER_REPLENISH);
+        if num_missing_nonces == 0 {
+            return Ok(0);
+        }
+
+        let nonces = (0..num_missing_nonces)
+            .map(|_| musig2::nonce_pair(&self.keypair, &mut rand::rngs::OsRng))
+            .collect::<Result<Vec<(MuSigSecNonce, MuSigPubNonce)>, BridgeError>>()?;
+        self.db.save_pool_nonces(None, &nonces).await?;
+
+        Ok(num_missing_nonces)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NoncePool;
+    use crate::database::Database;
+    use crate::mock::database::create_test_config_with_thread_name;
+    use bitcoin::{hashes::Hash, OutPoint, Txid};
+
+    #[tokio::test]
+    async fn replenish() {
+        let mut config = create_test_config_with_thread_name("test_config.toml", None).await;
+        config.verifier_nonce_pool_size = 10;
+        let db = Database::new(&config).await.unwrap();
+        let nonce_pool = NoncePool::new(config.clone()).await.unwrap();
+
+        assert_eq!(nonce_pool.replenish().await.unwrap(), 10);
+        assert_eq!(db.get_nonce_pool_size(None).await.unwrap(), 10);
+        // Pool is full.
+        assert_eq!(nonce_pool.replenish().await.unwrap(), 0);
+
+        // Only the assigned nonces are replaced.
+        let deposit_outpoint = OutPoint {
+            txid: Txid::all_zeros(),
+            vout: 0,
+        };
+        db.assign_pool_nonces(None, deposit_outpoint, 4)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(nonce_pool.replenish().await.unwrap(), 4);
+        assert_eq!(db.get_nonce_pool_size(None).await.unwrap(), 10);
+
+        // FROST nonces are not pooled.
+        config.frost_threshold = Some(2);
+        config.verifier_nonce_pool_size = 20;
+        let nonce_pool = NoncePool::new(config).await.unwrap();
+        assert_eq!(nonce_pool.replenish().await.unwrap(), 0);
+    }
+}
@@ -1,16 +1,18 @@
 use crate::actor::Actor;
-use crate::builder::transaction::KICKOFF_UTXO_AMOUNT_SATS;
 use crate::builder::{self};
+use crate::citrea::CitreaClient;
 use crate::config::BridgeConfig;
 use crate::database::Database;
 use crate::errors::BridgeError;
 use crate::extended_rpc::ExtendedRpc;
-use crate::musig2::AggregateFromPublicKeys;
+use crate::fee;
+use crate::musig2::NofnContext;
 use crate::traits::rpc::OperatorRpcServer;
 use crate::utils::handle_taproot_witness_new;
 use crate::{utils, EVMAddress, UTXO};
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::consensus::deserialize;
+use bitcoin::consensus::encode::deserialize_hex;
 use bitcoin::hashes::Hash;
 use bitcoin::script::PushBytesBuf;
 use bitcoin::sighash::SighashCache;
@@ -18,11 +20,11 @@ use bitcoin::{Address, Amount, OutPoint, TapSighash, Transaction, TxOut, Txid};
 use bitcoin_mock_rpc::RpcApiWrapper;
 use bitcoincore_rpc::RawTx;
 use jsonrpsee::core::async_trait;
-use jsonrpsee::core::client::ClientT;
-use jsonrpsee::http_client::HttpClientBuilder;
-use jsonrpsee::rpc_params;
 use secp256k1::{schnorr, Message};
-use serde_json::json;
+use std::time::Duration;
+
+/// Time to wait between two checks of the reimbursement jobs.
+pub const REIMBURSEMENT_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Clone)]
 pub struct Operator<R>
@@ -33,9 +35,9 @@ where
     db: Database,
     signer: Actor,
     config: BridgeConfig,
-    nofn_xonly_pk: secp256k1::XOnlyPublicKey,
+    nofn: NofnContext,
     idx: usize,
-    citrea_client: Option<jsonrpsee::http_client::HttpClient>,
+    citrea_client: Option<CitreaClient>,
 }
 
 impl<R> Operator<R>
@@ -51,11 +53,7 @@ where
 
         let db = Database::new(&config).await?;
 
-        let nofn_xonly_pk = secp256k1::XOnlyPublicKey::from_musig2_pks(
-            config.verifiers_public_keys.clone(),
-            None,
-            false,
-        );
+        let nofn = NofnContext::from_config(&config)?;
         let idx = config
             .operators_xonly_pks
             .iter()
@@ -72,7 +70,9 @@ where
         let mut tx = db.begin_transaction().await?;
         // check if funding utxo is already set
         if db.get_funding_utxo(Some(&mut tx)).await?.is_none() {
-            let outpoint = rpc.send_to_address(&signer.address, Amount::from_sat(200_000_000))?; // TODO: Is this OK to be a fixed value
+            let outpoint = rpc
+                .send_to_address(&signer.address, Amount::from_sat(200_000_000))
+                .await?; // TODO: Is this OK to be a fixed value
             let funding_utxo = UTXO {
                 outpoint,
                 txout: TxOut {
@@ -85,11 +85,7 @@ where
         tx.commit().await?;
 
         let citrea_client = if !config.citrea_rpc_url.is_empty() {
-            Some(
-                HttpClientBuilder::default()
-                    .build(config.citrea_rpc_url.clone())
-                    .unwrap(),
-            )
+            Some(CitreaClient::new(&config)?)
         } else {
             None
         };
@@ -105,7 +101,7 @@ where
             db,
             signer,
             config,
-            nofn_xonly_pk,
+            nofn,
             idx,
             citrea_client,
         })
@@ -135,16 +131,19 @@ where
         );
 
         // 1. Check if the deposit UTXO is valid, finalized (6 blocks confirmation) and not spent
-        self.rpc.check_deposit_utxo(
-            self.nofn_xonly_pk,
-            &deposit_outpoint,
-            &recovery_taproot_address,
-            evm_address,
-            self.config.bridge_amount_sats,
-            self.config.confirmation_threshold,
-            self.config.network,
-            self.config.user_takes_after,
-        )?;
+        let (_, bridge_amount_sats) = self
+            .rpc
+            .check_deposit_utxo(
+                self.nofn.xonly_pk(),
+                &deposit_outpoint,
+                &recovery_taproot_address,
+                evm_address,
+                &self.config.bridge_amounts_sats,
+                self.config.confirmation_threshold,
+                self.config.network,
+                self.config.user_takes_after,
+            )
+            .await?;
 
         let mut tx = self.db.begin_transaction().await?;
 
@@ -185,7 +184,12 @@ where
             .await?;
         if let Some(unused_kickoff_utxo) = unused_kickoff_utxo {
             self.db
-                .save_kickoff_utxo(Some(&mut tx), deposit_outpoint, unused_kickoff_utxo.clone())
+                .save_kickoff_utxo(
+                    Some(&mut tx),
+                    deposit_outpoint,
+                    unused_kickoff_utxo.clone(),
+                    bridge_amount_sats,
+                )
                 .await?;
 
             // self.db.unlock_operators_kickoff_utxo_table(&mut tx).await?;
@@ -216,32 +220,24 @@ where
                 BridgeError::OperatorFundingUtxoNotFound(self.signer.address.clone()),
             )?;
 
-            // if the amount is not enough, return an error
-            // The amount will be calculated as if the transaction has 1 input
-            // and (num_kickoff_utxos + 2) outputs where the first k outputs are
-            // the kickoff outputs, the penultimante output is the change output,
-            // and the last output is the anyonecanpay output for fee bumping.
-            let kickoff_tx_min_relay_fee = match self.config.operator_num_kickoff_utxos_per_tx {
-                0..=250 => 154 + 43 * self.config.operator_num_kickoff_utxos_per_tx, // Handles all values from 0 to 250
-                _ => 156 + 43 * self.config.operator_num_kickoff_utxos_per_tx, // Handles all other values
-            };
-            if funding_utxo.txout.value.to_sat()
-                < (KICKOFF_UTXO_AMOUNT_SATS.to_sat()
-                    * self.config.operator_num_kickoff_utxos_per_tx as u64
-                    + kickoff_tx_min_relay_fee as u64
-                    + 330)
-            {
-                return Err(BridgeError::OperatorFundingUtxoAmountNotEnough(
-                    self.signer.address.clone(),
-                ));
-            }
-            let mut kickoff_tx_handler = builder::transaction::create_kickoff_utxo_tx(
+            let fee_rate = fee::estimate_fee_rate(&self.rpc, &self.config).await?;
+            let mut kickoff_tx_handler = match builder::transaction::create_kickoff_utxo_tx(
                 &funding_utxo,
-                self.nofn_xonly_pk,
+                self.nofn.xonly_pk(),
                 self.signer.xonly_public_key,
                 self.config.network,
                 self.config.operator_num_kickoff_utxos_per_tx,
-            );
+                fee_rate,
+            ) {
+                Ok(tx_handler) => tx_handler,
+                // Funding UTXO can't pay for the kickoff UTXOs and the fee.
+                Err(BridgeError::NotEnoughFundsForFee) => {
+                    return Err(BridgeError::OperatorFundingUtxoAmountNotEnough(
+                        self.signer.address.clone(),
+                    ))
+                }
+                Err(e) => return Err(e),
+            };
             tracing::debug!(
                 "Funding UTXO found: {:?} kickoff UTXO is created for deposit UTXO: {:?}",
                 funding_utxo,
@@ -299,7 +295,12 @@ where
 
             // We save the funding txid and the kickoff txid to be able to track them later
             self.db
-                .save_kickoff_utxo(Some(&mut tx), deposit_outpoint, kickoff_utxo.clone())
+                .save_kickoff_utxo(
+                    Some(&mut tx),
+                    deposit_outpoint,
+                    kickoff_utxo.clone(),
+                    bridge_amount_sats,
+                )
                 .await?;
 
             self.db
@@ -336,23 +337,141 @@ where
         self.db.set_funding_utxo(None, funding_utxo).await
     }
 
+    /// Saves the deposits as the inputs of a batched move_tx, in the given
+    /// order. Every deposit must be saved with [`Self::new_deposit`] before.
+    /// Saving the same batch again is a no-op.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn new_deposit_batch(&self, deposit_outpoints: Vec<OutPoint>) -> Result<(), BridgeError> {
+        if deposit_outpoints.is_empty() {
+            return Err(BridgeError::EmptyDepositBatch);
+        }
+        for (i, deposit_outpoint) in deposit_outpoints.iter().enumerate() {
+            if deposit_outpoints[..i].contains(deposit_outpoint) {
+                return Err(BridgeError::InvalidDepositBatch(*deposit_outpoint));
+            }
+        }
+
+        let mut dbtx = self.db.begin_transaction().await?;
+        for deposit_outpoint in &deposit_outpoints {
+            self.get_bridge_amount(*deposit_outpoint).await?;
+
+            if let Some(batch) = self
+                .db
+                .get_operator_deposit_batch(Some(&mut dbtx), *deposit_outpoint)
+                .await?
+            {
+                if batch == deposit_outpoints {
+                    return Ok(());
+                }
+
+                return Err(BridgeError::InvalidDepositBatch(*deposit_outpoint));
+            }
+        }
+
+        self.db
+            .save_operator_deposit_batch(Some(&mut dbtx), &deposit_outpoints)
+            .await?;
+        dbtx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Returns the amount of a deposit, as it is checked while creating its
+    /// kickoff UTXO.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn get_bridge_amount(&self, deposit_outpoint: OutPoint) -> Result<Amount, BridgeError> {
+        self.db
+            .get_operator_deposit_amount(deposit_outpoint)
+            .await?
+            .ok_or(BridgeError::DepositInfoNotFound)
+    }
+
+    /// Returns the move_tx that moves the deposit and the index of the
+    /// deposit's input and bridge fund output in it. A deposit that is not
+    /// batched is the only deposit of its move_tx.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn get_move_tx(
+        &self,
+        deposit_outpoint: OutPoint,
+    ) -> Result<(Transaction, usize), BridgeError> {
+        let deposit_outpoints = self
+            .db
+            .get_operator_deposit_batch(None, deposit_outpoint)
+            .await?
+            .unwrap_or_else(|| vec![deposit_outpoint]);
+        let deposit_idx = deposit_outpoints
+            .iter()
+            .position(|outpoint| *outpoint == deposit_outpoint)
+            .ok_or(BridgeError::InvalidDepositBatch(deposit_outpoint))?;
+
+        let mut move_tx_deposits = Vec::with_capacity(deposit_outpoints.len());
+        for outpoint in deposit_outpoints {
+            move_tx_deposits.push((outpoint, self.get_bridge_amount(outpoint).await?));
+        }
+
+        let move_tx = builder::transaction::create_batch_move_tx(
+            &move_tx_deposits,
+            self.nofn.xonly_pk(),
+            self.config.network,
+            fee::presigned_fee_rate(&self.config)?,
+        )?;
+
+        Ok((move_tx, deposit_idx))
+    }
+
+    /// Checks Citrea if the deposit's bridge fund output is registered with
+    /// the index of the withdrawal.
+    #[tracing::instrument(skip(self, citrea_client), err(level = tracing::Level::ERROR))]
+    async fn check_deposit_idx(
+        &self,
+        citrea_client: &CitreaClient,
+        withdrawal_idx: u32,
+        deposit_outpoint: OutPoint,
+    ) -> Result<(), BridgeError> {
+        let (move_tx, batch_idx) = self.get_move_tx(deposit_outpoint).await?;
+        let bridge_fund_outpoint = OutPoint {
+            txid: move_tx.compute_txid(),
+            vout: batch_idx as u32,
+        };
+
+        let deposit_idx = citrea_client
+            .deposit_idx(bridge_fund_outpoint)
+            .await?
+            .ok_or(BridgeError::DepositNotFoundOnCitrea(bridge_fund_outpoint))?;
+        if deposit_idx != withdrawal_idx {
+            return Err(BridgeError::InvalidDepositOutpointGiven(
+                deposit_idx as usize,
+                withdrawal_idx as usize,
+            ));
+        }
+
+        Ok(())
+    }
+
     /// Checks if the withdrawal amount is within the acceptable range.
     ///
     /// # Parameters
     ///
     /// - `input_amount`:
     /// - `withdrawal_amount`:
-    fn is_profitable(&self, input_amount: Amount, withdrawal_amount: Amount) -> bool {
+    /// - `bridge_amount`: Amount of the deposit that is withdrawn, which is
+    ///   reimbursed to the operator
+    fn is_profitable(
+        &self,
+        input_amount: Amount,
+        withdrawal_amount: Amount,
+        bridge_amount: Amount,
+    ) -> bool {
         if withdrawal_amount
             .to_sat()
             .wrapping_sub(input_amount.to_sat())
-            > self.config.bridge_amount_sats.to_sat()
+            > bridge_amount.to_sat()
         {
             return false;
         }
 
         // Calculate net profit after the withdrawal.
-        let net_profit = self.config.bridge_amount_sats - withdrawal_amount;
+        let net_profit = bridge_amount - withdrawal_amount;
 
         // Net profit must be bigger than withdrawal fee.
         net_profit > self.config.operator_withdrawal_fee_sats.unwrap()
@@ -365,6 +484,8 @@ where
     /// # Parameters
     ///
     /// - `withdrawal_idx`: Citrea withdrawal UTXO index
+    /// - `deposit_outpoint`: Deposit that is withdrawn, which has the same
+    ///   index on Citrea
     /// - `user_sig`: User's signature that is going to be used for signing withdrawal transaction input
     /// - `input_utxo`:
     /// - `output_txout`:
@@ -376,37 +497,31 @@ where
     async fn new_withdrawal_sig(
         &self,
         withdrawal_idx: u32,
+        deposit_outpoint: OutPoint,
         user_sig: schnorr::Signature,
         input_utxo: UTXO,
         output_txout: TxOut,
     ) -> Result<Txid, BridgeError> {
-        if let Some(citrea_client) = &self.citrea_client {
-            // See: https://gist.github.com/okkothejawa/a9379b02a16dada07a2b85cbbd3c1e80
-            let params = rpc_params![
-                json!({
-                    "to": "0x3100000000000000000000000000000000000002",
-                    "data": format!("0x471ba1e300000000000000000000000000000000000000000000000000000000{}",
-                    hex::encode(withdrawal_idx.to_be_bytes())),
-                }),
-                "latest"
-            ];
-            let response: String = citrea_client.request("eth_call", params).await?;
+        let bridge_amount_sats = self.get_bridge_amount(deposit_outpoint).await?;
 
-            let txid_response = &response[2..66];
-            let txid = hex::decode(txid_response).unwrap();
-            // txid.reverse(); // TODO: we should need to reverse this, test this with declareWithdrawalFiller
-
-            let txid = Txid::from_slice(&txid).unwrap();
-            if txid != input_utxo.outpoint.txid || 0 != input_utxo.outpoint.vout {
-                // TODO: Fix this, vout can be different from 0 as well
+        if let Some(citrea_client) = &self.citrea_client {
+            let withdrawal_utxo = citrea_client.withdrawal_utxo(withdrawal_idx).await?;
+            if withdrawal_utxo != input_utxo.outpoint {
                 return Err(BridgeError::InvalidInputUTXO(
-                    txid,
+                    withdrawal_utxo.txid,
                     input_utxo.outpoint.txid,
                 ));
             }
+
+            self.check_deposit_idx(citrea_client, withdrawal_idx, deposit_outpoint)
+                .await?;
         }
 
-        if !self.is_profitable(input_utxo.txout.value, output_txout.value) {
+        if !self.is_profitable(
+            input_utxo.txout.value,
+            output_txout.value,
+            bridge_amount_sats,
+        ) {
             return Err(BridgeError::NotEnoughFeeForOperator);
         }
 
@@ -463,77 +578,59 @@ where
                     estimate_mode: None,
                 }),
                 None,
-            )?
+            )
+            .await?
             .hex;
 
         let signed_tx: Transaction = deserialize(
             &self
                 .rpc
-                .sign_raw_transaction_with_wallet(&funded_tx, None, None)?
+                .sign_raw_transaction_with_wallet(&funded_tx, None, None)
+                .await?
                 .hex,
         )?;
 
-        Ok(self.rpc.send_raw_transaction(&signed_tx)?)
+        self.rpc.send_raw_transaction(&signed_tx).await
     }
 
+    /// Checks if the withdrawal is paid by this operator on Citrea and
+    /// creates a reimbursement job for it. The job is driven by
+    /// [`Operator::run_reimbursements`].
+    ///
+    /// # Returns
+    ///
+    /// Txids of the reimbursement transactions in broadcast order.
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
     async fn withdrawal_proved_on_citrea(
         &self,
         withdrawal_idx: u32,
         deposit_outpoint: OutPoint,
-    ) -> Result<Vec<String>, BridgeError> {
+    ) -> Result<Vec<Txid>, BridgeError> {
         // call withdrawFillers(withdrawal_idx) check the returned id is our operator id.
         // calculate the move_txid, txIdToDepositId(move_txid) check the returned id is withdrawal_idx
         if let Some(citrea_client) = &self.citrea_client {
-            // See: https://gist.github.com/okkothejawa/a9379b02a16dada07a2b85cbbd3c1e80
-            let params = rpc_params![
-                json!({
-                    "to": "0x3100000000000000000000000000000000000002",
-                    "data": format!("0xc045577b00000000000000000000000000000000000000000000000000000000{}",
-                    hex::encode(withdrawal_idx.to_be_bytes())),
-                }),
-                "latest"
-            ];
-            let response: String = citrea_client.request("eth_call", params).await?;
-
-            let operator_idx_as_vec = hex::decode(&response[58..66]).unwrap();
-            let operator_idx = u32::from_be_bytes(operator_idx_as_vec.try_into().unwrap());
-
-            if operator_idx - 1 != self.idx as u32 {
-                return Err(BridgeError::InvalidOperatorIndex(
-                    operator_idx as usize,
-                    self.idx,
-                ));
+            match citrea_client.withdrawal_filler(withdrawal_idx).await? {
+                Some(operator_idx) if operator_idx == self.idx => {}
+                Some(operator_idx) => {
+                    return Err(BridgeError::InvalidOperatorIndex(operator_idx, self.idx));
+                }
+                None => return Err(BridgeError::WithdrawalNotFilled(withdrawal_idx)),
             }
 
-            // Calculate move_txid.
-            let move_tx = builder::transaction::create_move_tx(
-                deposit_outpoint,
-                self.nofn_xonly_pk,
-                self.config.bridge_amount_sats,
-                self.config.network,
-            );
-            let move_txid = move_tx.compute_txid();
-            let move_txid_bytes = move_txid.to_byte_array();
-
-            // See: https://gist.github.com/okkothejawa/a9379b02a16dada07a2b85cbbd3c1e80
-            let params = rpc_params![json!({
-                "to": "0x3100000000000000000000000000000000000002",
-                "data": format!("0x11e53a01{}",
-                hex::encode(move_txid_bytes)),
-            })];
-            let response: String = citrea_client.request("eth_call", params).await?;
-
-            let deposit_idx_response = &response[58..66];
-            let deposit_idx_as_vec = hex::decode(deposit_idx_response).unwrap();
-            let deposit_idx = u32::from_be_bytes(deposit_idx_as_vec.try_into().unwrap());
-
-            if deposit_idx - 1 != withdrawal_idx {
-                return Err(BridgeError::InvalidDepositOutpointGiven(
-                    deposit_idx as usize - 1,
-                    withdrawal_idx as usize,
-                ));
-            }
+            self.check_deposit_idx(citrea_client, withdrawal_idx, deposit_outpoint)
+                .await?;
+        }
+
+        // Reimbursement job is already created for this withdrawal.
+        let reimbursement_txs = self
+            .db
+            .get_reimbursement_txs(None, deposit_outpoint)
+            .await?;
+        if !reimbursement_txs.is_empty() {
+            return Ok(reimbursement_txs
+                .into_iter()
+                .map(|(txid, _, _, _)| txid)
+                .collect());
         }
 
         let kickoff_utxo = self
@@ -551,6 +648,7 @@ where
             if self
                 .rpc
                 .get_raw_transaction(&current_searching_txid, None)
+                .await
                 .is_ok()
             {
                 found_txid = true;
@@ -564,7 +662,7 @@ where
                 .await?
                 .ok_or(BridgeError::KickoffGeneratorTxNotFound)?;
 
-            txs_to_be_sent.push(raw_signed_tx);
+            txs_to_be_sent.push((current_searching_txid, raw_signed_tx));
             current_searching_txid = funding_txid;
         }
         txs_to_be_sent.reverse();
@@ -573,17 +671,20 @@ where
             return Err(BridgeError::KickoffGeneratorTxsTooManyIterations); // TODO: Fix this error
         }
 
+        let (move_tx, deposit_idx) = self.get_move_tx(deposit_outpoint).await?;
+        let move_txid = move_tx.compute_txid();
+
         let mut slash_or_take_tx_handler = builder::transaction::create_slash_or_take_tx(
-            deposit_outpoint,
+            move_txid,
             kickoff_utxo.clone(),
             self.signer.xonly_public_key,
             self.idx,
-            self.nofn_xonly_pk,
+            self.nofn.xonly_pk(),
             self.config.network,
             self.config.user_takes_after,
             self.config.operator_takes_after,
-            self.config.bridge_amount_sats,
-        );
+            fee::presigned_fee_rate(&self.config)?,
+        )?;
 
         let slash_or_take_utxo = UTXO {
             outpoint: OutPoint {
@@ -610,29 +711,29 @@ where
             Some(0),
         )?;
 
-        txs_to_be_sent.push(slash_or_take_tx_handler.tx.raw_hex());
+        txs_to_be_sent.push((
+            slash_or_take_tx_handler.tx.compute_txid(),
+            slash_or_take_tx_handler.tx.raw_hex(),
+        ));
 
-        let move_tx = builder::transaction::create_move_tx(
-            deposit_outpoint,
-            self.nofn_xonly_pk,
-            self.config.bridge_amount_sats,
-            self.config.network,
-        );
-        let bridge_fund_outpoint = OutPoint {
-            txid: move_tx.compute_txid(),
-            vout: 0,
+        let bridge_fund_utxo = UTXO {
+            outpoint: OutPoint {
+                txid: move_txid,
+                vout: deposit_idx as u32,
+            },
+            txout: move_tx.output[deposit_idx].clone(),
         };
 
         let mut operator_takes_tx = builder::transaction::create_operator_takes_tx(
-            bridge_fund_outpoint,
+            bridge_fund_utxo,
             slash_or_take_utxo,
             self.signer.xonly_public_key,
-            self.nofn_xonly_pk,
+            self.nofn.xonly_pk(),
             self.config.network,
             self.config.operator_takes_after,
-            self.config.bridge_amount_sats,
             self.config.operator_wallet_addresses[self.idx].clone(),
-        );
+            fee::presigned_fee_rate(&self.config)?,
+        )?;
 
         let operator_takes_nofn_sig = self
             .db
@@ -653,9 +754,105 @@ where
         )?;
         handle_taproot_witness_new(&mut operator_takes_tx, &[our_sig.as_ref()], 1, Some(0))?;
 
-        txs_to_be_sent.push(operator_takes_tx.tx.raw_hex());
+        txs_to_be_sent.push((
+            operator_takes_tx.tx.compute_txid(),
+            operator_takes_tx.tx.raw_hex(),
+        ));
+
+        self.db
+            .save_reimbursement_job(None, deposit_outpoint, withdrawal_idx, &txs_to_be_sent)
+            .await?;
+
+        Ok(txs_to_be_sent.into_iter().map(|(txid, _)| txid).collect())
+    }
 
-        Ok(txs_to_be_sent)
+    /// Drives the unfinished reimbursement jobs forever. Jobs are read from
+    /// the database, so they are continued after a restart.
+    pub async fn run_reimbursements(&self, poll_interval: Duration) {
+        loop {
+            match self.db.get_unfinished_reimbursements().await {
+                Ok(deposit_outpoints) => {
+                    for deposit_outpoint in deposit_outpoints {
+                        if let Err(e) = self.process_reimbursement(deposit_outpoint).await {
+                            tracing::error!(
+                                "Can't process reimbursement of deposit {}: {}",
+                                deposit_outpoint,
+                                e
+                            );
+                        }
+                    }
+                }
+                Err(e) => tracing::error!("Can't get reimbursement jobs: {}", e),
+            }
+
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Advances the reimbursement job of a deposit by a step. Every
+    /// transaction is broadcasted only after the previous one is confirmed
+    /// and `operator_takes_tx` is broadcasted only after the
+    /// `operator_takes_after` timelock of `slash_or_take_tx`.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn process_reimbursement(&self, deposit_outpoint: OutPoint) -> Result<(), BridgeError> {
+        let reimbursement_txs = self
+            .db
+            .get_reimbursement_txs(None, deposit_outpoint)
+            .await?;
+
+        for (tx_idx, (txid, raw_signed_tx, broadcasted, confirmed)) in
+            reimbursement_txs.iter().enumerate()
+        {
+            if *confirmed {
+                continue;
+            }
+            if self.rpc.confirmation_blocks(txid).await.is_ok() {
+                self.db
+                    .update_reimbursement_tx_status(deposit_outpoint, tx_idx, true, true)
+                    .await?;
+                continue;
+            }
+
+            // Last transaction is operator_takes_tx and previous one is
+            // slash_or_take_tx.
+            if tx_idx > 0 && tx_idx == reimbursement_txs.len() - 1 {
+                let slash_or_take_confirmations = self
+                    .rpc
+                    .confirmation_blocks(&reimbursement_txs[tx_idx - 1].0)
+                    .await?;
+                if slash_or_take_confirmations < self.config.operator_takes_after {
+                    return Ok(());
+                }
+            }
+
+            // Transaction might be dropped from the mempool, even if it is
+            // broadcasted before.
+            if self.rpc.get_raw_transaction(txid, None).await.is_err() {
+                let cpfp_fee_rate = fee::estimate_fee_rate(&self.rpc, &self.config).await?;
+                self.rpc
+                    .send_or_bump_transaction(&deserialize_hex(raw_signed_tx)?, cpfp_fee_rate)
+                    .await?;
+                tracing::info!(
+                    "Reimbursement tx {} of deposit {} is sent",
+                    txid,
+                    deposit_outpoint
+                );
+            }
+            if !broadcasted {
+                self.db
+                    .update_reimbursement_tx_status(deposit_outpoint, tx_idx, true, false)
+                    .await?;
+            }
+
+            return Ok(());
+        }
+
+        self.db
+            .set_reimbursement_completed(deposit_outpoint)
+            .await?;
+        tracing::info!("Reimbursement of deposit {} is completed", deposit_outpoint);
+
+        Ok(())
     }
 }
 
@@ -674,6 +871,13 @@ where
             .await
     }
 
+    async fn new_deposit_batch_rpc(
+        &self,
+        deposit_outpoints: Vec<OutPoint>,
+    ) -> Result<(), BridgeError> {
+        self.new_deposit_batch(deposit_outpoints).await
+    }
+
     async fn set_funding_utxo_rpc(&self, funding_utxo: UTXO) -> Result<(), BridgeError> {
         self.set_funding_utxo(funding_utxo).await
     }
@@ -681,19 +885,26 @@ where
     async fn new_withdrawal_sig_rpc(
         &self,
         withdrawal_idx: u32,
+        deposit_outpoint: OutPoint,
         user_sig: schnorr::Signature,
         input_utxo: UTXO,
         output_txout: TxOut,
     ) -> Result<Txid, BridgeError> {
-        self.new_withdrawal_sig(withdrawal_idx, user_sig, input_utxo, output_txout)
-            .await
+        self.new_withdrawal_sig(
+            withdrawal_idx,
+            deposit_outpoint,
+            user_sig,
+            input_utxo,
+            output_txout,
+        )
+        .await
     }
 
     async fn withdrawal_proved_on_citrea_rpc(
         &self,
         withdrawal_idx: u32,
         deposit_outpoint: OutPoint,
-    ) -> Result<Vec<String>, BridgeError> {
+    ) -> Result<Vec<Txid>, BridgeError> {
         self.withdrawal_proved_on_citrea(withdrawal_idx, deposit_outpoint)
             .await
     }
@@ -702,8 +913,9 @@ where
 #[cfg(test)]
 mod tests {
     use crate::{
-        create_extended_rpc, extended_rpc::ExtendedRpc, mock::database::create_test_config,
-        operator::Operator, servers::create_operator_server, traits::rpc::OperatorRpcClient, UTXO,
+        create_extended_rpc, errors::BridgeError, extended_rpc::ExtendedRpc,
+        mock::database::create_test_config, operator::Operator, servers::create_operator_server,
+        traits::rpc::OperatorRpcClient, UTXO,
     };
     use bitcoin::{hashes::Hash, Amount, OutPoint, ScriptBuf, TxOut, Txid};
 
@@ -759,34 +971,67 @@ mod tests {
         // checks if added in the future.
     }
 
+    #[tokio::test]
+    async fn new_deposit_batch() {
+        let mut config = create_test_config("new_deposit_batch", "test_config.toml").await;
+        let rpc = create_extended_rpc!(config);
+
+        let operator = Operator::new(config, rpc).await.unwrap();
+
+        let deposit_outpoint = OutPoint {
+            txid: Txid::all_zeros(),
+            vout: 0x45,
+        };
+
+        assert!(matches!(
+            operator.new_deposit_batch(vec![]).await,
+            Err(BridgeError::EmptyDepositBatch)
+        ));
+        assert!(matches!(
+            operator
+                .new_deposit_batch(vec![deposit_outpoint, deposit_outpoint])
+                .await,
+            Err(BridgeError::InvalidDepositBatch(_))
+        ));
+        // Deposit is not saved with a kickoff UTXO.
+        assert!(matches!(
+            operator.new_deposit_batch(vec![deposit_outpoint]).await,
+            Err(BridgeError::DepositInfoNotFound)
+        ));
+    }
+
     #[tokio::test]
     async fn is_profitable() {
         let mut config = create_test_config("is_profitable", "test_config.toml").await;
         let rpc = create_extended_rpc!(config);
 
-        config.bridge_amount_sats = Amount::from_sat(0x45);
+        let bridge_amount = Amount::from_sat(0x45);
         config.operator_withdrawal_fee_sats = Some(Amount::from_sat(0x1F));
 
         let operator = Operator::new(config.clone(), rpc).await.unwrap();
 
         // Smaller input amount must not cause a panic.
-        operator.is_profitable(Amount::from_sat(3), Amount::from_sat(1));
+        operator.is_profitable(Amount::from_sat(3), Amount::from_sat(1), bridge_amount);
         // Bigger input amount must not cause a panic.
-        operator.is_profitable(Amount::from_sat(6), Amount::from_sat(9));
+        operator.is_profitable(Amount::from_sat(6), Amount::from_sat(9), bridge_amount);
 
         // False because difference between input and withdrawal amount is
-        // bigger than `config.bridge_amount_sats`.
-        assert!(!operator.is_profitable(Amount::from_sat(6), Amount::from_sat(90)));
+        // bigger than `bridge_amount`.
+        assert!(!operator.is_profitable(Amount::from_sat(6), Amount::from_sat(90), bridge_amount));
 
         // False because net profit is smaller than
         // `config.operator_withdrawal_fee_sats`.
-        assert!(!operator.is_profitable(Amount::from_sat(0), config.bridge_amount_sats));
+        assert!(!operator.is_profitable(Amount::from_sat(0), bridge_amount, bridge_amount));
 
         // True because net profit is bigger than
         // `config.operator_withdrawal_fee_sats`.
         assert!(operator.is_profitable(
             Amount::from_sat(0),
-            config.operator_withdrawal_fee_sats.unwrap() - Amount::from_sat(1)
+            config.operator_withdrawal_fee_sats.unwrap() - Amount::from_sat(1),
+            bridge_amount
         ));
+
+        // Same withdrawal is profitable for a bigger deposit.
+        assert!(operator.is_profitable(Amount::from_sat(0), bridge_amount, bridge_amount * 2));
     }
 }
@@ -6,28 +6,96 @@ use crate::traits::rpc::AggregatorServer;
 use crate::{aggregator, create_extended_rpc};
 use crate::{
     config::BridgeConfig,
+    deposit_tracker::{DepositTracker, DEPOSIT_TRACKER_POLL_INTERVAL},
     errors,
     extended_rpc::ExtendedRpc,
-    operator,
+    header_indexer::{HeaderIndexer, HEADER_INDEXER_POLL_INTERVAL},
+    nonce_pool::{NoncePool, NONCE_POOL_POLL_INTERVAL},
+    operator::{self, REIMBURSEMENT_POLL_INTERVAL},
     traits::{self, rpc::VerifierRpcServer},
     verifier::Verifier,
+    watchtower::{Watchtower, WATCHTOWER_POLL_INTERVAL},
 };
 use bitcoin_mock_rpc::RpcApiWrapper;
 use errors::BridgeError;
+use futures::FutureExt;
 use jsonrpsee::{
     http_client::{HttpClient, HttpClientBuilder},
-    server::{Server, ServerHandle},
+    server::{AlreadyStoppedError, Server, ServerHandle},
 };
 use operator::Operator;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
 use std::thread;
+use tokio::task::JoinHandle;
 use traits::rpc::OperatorRpcServer;
 
+/// Handle of a running server and its background tasks, like the watchtower
+/// of a verifier. Background tasks run until the server is stopped, or every
+/// clone of the handle is dropped, which stops the server too.
+#[derive(Debug, Clone)]
+pub struct BridgeServerHandle {
+    server: ServerHandle,
+    tasks: Arc<BackgroundTasks>,
+}
+
+impl BridgeServerHandle {
+    /// Stops the server and aborts its background tasks.
+    pub fn stop(&self) -> Result<(), AlreadyStoppedError> {
+        self.tasks.abort();
+        self.server.stop()
+    }
+
+    /// Waits until the server is stopped, then aborts its background tasks.
+    pub async fn stopped(self) {
+        self.server.clone().stopped().await;
+        self.tasks.abort();
+    }
+}
+
+/// Background tasks of a server, which are aborted when they are dropped.
+#[derive(Debug, Default)]
+struct BackgroundTasks(Vec<JoinHandle<()>>);
+
+impl BackgroundTasks {
+    /// Spawns an endless task of the server. A panic of the task is logged,
+    /// instead of being lost with it.
+    fn spawn<F>(&mut self, name: &'static str, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.0.push(tokio::spawn(async move {
+            if let Err(panic) = AssertUnwindSafe(task).catch_unwind().await {
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .copied()
+                    .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+                    .unwrap_or("unknown panic");
+                tracing::error!("Background task {} panicked: {}", name, message);
+            }
+        }));
+    }
+
+    fn abort(&self) {
+        for task in &self.0 {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for BackgroundTasks {
+    fn drop(&mut self) {
+        self.abort();
+    }
+}
+
 /// Starts a server for a verifier.
 #[tracing::instrument(skip(rpc), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
 pub async fn create_verifier_server<R>(
     config: BridgeConfig,
     rpc: ExtendedRpc<R>,
-) -> Result<(HttpClient, ServerHandle, std::net::SocketAddr), BridgeError>
+) -> Result<(HttpClient, BridgeServerHandle, std::net::SocketAddr), BridgeError>
 where
     R: RpcApiWrapper,
 {
@@ -38,10 +106,27 @@ where
         Ok(s) => s,
         Err(e) => return Err(BridgeError::ServerError(e)),
     };
+    let mut tasks = BackgroundTasks::default();
+    let watchtower = Watchtower::new(rpc.clone(), config.clone()).await?;
+    tasks.spawn("watchtower", async move {
+        watchtower.run(WATCHTOWER_POLL_INTERVAL).await
+    });
+    let deposit_tracker = DepositTracker::new(rpc.clone(), config.clone()).await?;
+    tasks.spawn("deposit tracker", async move {
+        deposit_tracker.run(DEPOSIT_TRACKER_POLL_INTERVAL).await
+    });
+    let nonce_pool = NoncePool::new(config.clone()).await?;
+    tasks.spawn("nonce pool", async move {
+        nonce_pool.run(NONCE_POOL_POLL_INTERVAL).await
+    });
+
     let verifier = Verifier::new(rpc, config).await?;
 
     let addr: std::net::SocketAddr = server.local_addr().map_err(BridgeError::ServerError)?;
-    let handle = server.start(verifier.into_rpc());
+    let handle = BridgeServerHandle {
+        server: server.start(verifier.into_rpc()),
+        tasks: Arc::new(tasks),
+    };
 
     let client =
         HttpClientBuilder::default().build(format!("http://{}:{}/", addr.ip(), addr.port()))?;
@@ -56,11 +141,26 @@ where
 pub async fn create_operator_server<R>(
     config: BridgeConfig,
     rpc: ExtendedRpc<R>,
-) -> Result<(HttpClient, ServerHandle, std::net::SocketAddr), BridgeError>
+) -> Result<(HttpClient, BridgeServerHandle, std::net::SocketAddr), BridgeError>
 where
     R: RpcApiWrapper,
 {
-    let operator = Operator::new(config.clone(), rpc).await?;
+    let operator = Operator::new(config.clone(), rpc.clone()).await?;
+
+    let mut tasks = BackgroundTasks::default();
+    // Continue the reimbursements that were not completed before a shutdown.
+    let reimbursing_operator = operator.clone();
+    tasks.spawn("reimbursements", async move {
+        reimbursing_operator
+            .run_reimbursements(REIMBURSEMENT_POLL_INTERVAL)
+            .await
+    });
+
+    // Headers are indexed for the proofs of the operator.
+    let header_indexer = HeaderIndexer::new(rpc, config.clone()).await?;
+    tasks.spawn("header indexer", async move {
+        header_indexer.run(HEADER_INDEXER_POLL_INTERVAL).await
+    });
 
     let server = match Server::builder()
         .build(format!("{}:{}", config.host, config.port))
@@ -71,7 +171,10 @@ where
     };
 
     let addr: std::net::SocketAddr = server.local_addr().map_err(BridgeError::ServerError)?;
-    let handle = server.start(operator.into_rpc());
+    let handle = BridgeServerHandle {
+        server: server.start(operator.into_rpc()),
+        tasks: Arc::new(tasks),
+    };
 
     let client =
         HttpClientBuilder::default().build(format!("http://{}:{}/", addr.ip(), addr.port()))?;
@@ -85,9 +188,17 @@ where
 #[tracing::instrument(err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
 pub async fn create_aggregator_server(
     config: BridgeConfig,
-) -> Result<(HttpClient, ServerHandle, std::net::SocketAddr), BridgeError> {
+) -> Result<(HttpClient, BridgeServerHandle, std::net::SocketAddr), BridgeError> {
     let aggregator = aggregator::Aggregator::new(config.clone()).await?;
 
+    // Continue the deposits that were interrupted by a previous shutdown.
+    // Errors are logged by `resume_deposits`.
+    let mut tasks = BackgroundTasks::default();
+    let resuming_aggregator = aggregator.clone();
+    tasks.spawn("deposit resumption", async move {
+        let _ = resuming_aggregator.resume_deposits().await;
+    });
+
     let server = match Server::builder()
         .build(format!("{}:{}", config.host, config.port))
         .await
@@ -97,7 +208,10 @@ pub async fn create_aggregator_server(
     };
 
     let addr: std::net::SocketAddr = server.local_addr().map_err(BridgeError::ServerError)?;
-    let handle = server.start(aggregator.into_rpc());
+    let handle = BridgeServerHandle {
+        server: server.start(aggregator.into_rpc()),
+        tasks: Arc::new(tasks),
+    };
 
     let client =
         HttpClientBuilder::default().build(format!("http://{}:{}/", addr.ip(), addr.port()))?;
@@ -130,9 +244,9 @@ pub async fn create_verifiers_and_operators(
     config_name: &str,
     // rpc: ExtendedRpc<R>,
 ) -> (
-    Vec<(HttpClient, ServerHandle, std::net::SocketAddr)>, // Verifier clients
-    Vec<(HttpClient, ServerHandle, std::net::SocketAddr)>, // Operator clients
-    (HttpClient, ServerHandle, std::net::SocketAddr),      // Aggregator client
+    Vec<(HttpClient, BridgeServerHandle, std::net::SocketAddr)>, // Verifier clients
+    Vec<(HttpClient, BridgeServerHandle, std::net::SocketAddr)>, // Operator clients
+    (HttpClient, BridgeServerHandle, std::net::SocketAddr),      // Aggregator client
 ) {
     let mut config = create_test_config_with_thread_name(config_name, None).await;
     let start_port = config.port;
@@ -162,7 +276,7 @@ pub async fn create_verifiers_and_operators(
                 .await?;
                 Ok::<
                     (
-                        (HttpClient, ServerHandle, std::net::SocketAddr),
+                        (HttpClient, BridgeServerHandle, std::net::SocketAddr),
                         BridgeConfig,
                     ),
                     BridgeError,
@@ -217,6 +331,18 @@ pub async fn create_verifiers_and_operators(
         + all_operators_secret_keys.len() as u16;
     let aggregator = create_aggregator_server(BridgeConfig {
         port: if is_test_env() { 0 } else { port },
+        verifier_endpoints: Some(
+            verifier_endpoints
+                .iter()
+                .map(|(_, _, addr)| format!("http://{}:{}/", addr.ip(), addr.port()))
+                .collect(),
+        ),
+        operator_endpoints: Some(
+            operator_endpoints
+                .iter()
+                .map(|(_, _, addr)| format!("http://{}:{}/", addr.ip(), addr.port()))
+                .collect(),
+        ),
         ..config
     })
     .await
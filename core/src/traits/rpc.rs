@@ -1,10 +1,15 @@
+use crate::builder::transaction::MoveTxDeposit;
+use crate::epoch::SweptDepositGraph;
+use crate::frost::{EncryptedRound2Package, FrostCommitment, FrostSignatureShare, FrostSignerId};
 use crate::musig2::{MuSigAggNonce, MuSigPartialSignature, MuSigPubNonce};
 use crate::UTXO;
 use crate::{errors::BridgeError, EVMAddress};
 use bitcoin::address::NetworkUnchecked;
-use bitcoin::{Address, OutPoint, TxOut, Txid};
+use bitcoin::{Address, Amount, OutPoint, TxOut, Txid};
+use frost_secp256k1_tr::keys::{dkg, PublicKeyPackage};
 use jsonrpsee::proc_macros::rpc;
-use secp256k1::schnorr;
+use secp256k1::{schnorr, XOnlyPublicKey};
+use std::collections::BTreeMap;
 
 #[rpc(client, server, namespace = "verifier")]
 pub trait VerifierRpc {
@@ -20,10 +25,18 @@ pub trait VerifierRpc {
         evm_address: EVMAddress,
     ) -> Result<Vec<MuSigPubNonce>, BridgeError>;
 
+    #[method(name = "new_deposit_batch")]
+    /// - Check that every deposit is saved and not signed yet
+    /// - Save the deposits as the inputs of a batched move_tx, in order
+    async fn verifier_new_deposit_batch_rpc(
+        &self,
+        deposit_outpoints: Vec<OutPoint>,
+    ) -> Result<(), BridgeError>;
+
     #[method(name = "operator_kickoffs_generated")]
     /// - Check the kickoff_utxos
     /// - for every kickoff_utxo, calculate kickoff2_tx
-    /// - for every kickoff2_tx, partial sign burn_tx (ommitted for now)
+    /// - for every kickoff2_tx, partial sign burn_tx
     async fn operator_kickoffs_generated_rpc(
         &self,
         deposit_outpoint: OutPoint,
@@ -32,6 +45,26 @@ pub trait VerifierRpc {
         agg_nonces: Vec<MuSigAggNonce>,
     ) -> Result<(Vec<MuSigPartialSignature>, Vec<MuSigPartialSignature>), BridgeError>;
 
+    #[method(name = "frost_operator_kickoffs_generated")]
+    /// - Same as operator_kickoffs_generated, with the FROST key
+    /// - Check and save the commitments of the signers of the deposit
+    async fn frost_operator_kickoffs_generated_rpc(
+        &self,
+        deposit_outpoint: OutPoint,
+        kickoff_utxos: Vec<UTXO>,
+        operators_kickoff_sigs: Vec<schnorr::Signature>,
+        signing_commitments: BTreeMap<FrostSignerId, Vec<FrostCommitment>>,
+    ) -> Result<(Vec<FrostSignatureShare>, Vec<FrostSignatureShare>), BridgeError>;
+
+    #[method(name = "frost_renew_deposit_nonces")]
+    /// - Check that nothing is signed with the deposit's FROST nonces
+    /// - Replace them with fresh ones for a new signing set
+    /// - Return the new commitments
+    async fn frost_renew_deposit_nonces_rpc(
+        &self,
+        deposit_outpoint: OutPoint,
+    ) -> Result<Vec<FrostCommitment>, BridgeError>;
+
     #[method(name = "burn_txs_signed")]
     /// verify burn txs are signed by verifiers
     /// sign operator_takes_txs
@@ -51,6 +84,47 @@ pub trait VerifierRpc {
         deposit_outpoint: OutPoint,
         operator_take_sigs: Vec<schnorr::Signature>,
     ) -> Result<MuSigPartialSignature, BridgeError>;
+
+    #[method(name = "frost_dkg_round1")]
+    /// - Start the DKG ceremony of the FROST key
+    /// - Return the round 1 package
+    async fn frost_dkg_round1_rpc(&self) -> Result<dkg::round1::Package, BridgeError>;
+
+    #[method(name = "frost_dkg_round2")]
+    /// - Check the round 1 packages of every verifier
+    /// - Return the round 2 packages, encrypted to their recipients
+    async fn frost_dkg_round2_rpc(
+        &self,
+        round1_packages: BTreeMap<FrostSignerId, dkg::round1::Package>,
+    ) -> Result<BTreeMap<FrostSignerId, EncryptedRound2Package>, BridgeError>;
+
+    #[method(name = "frost_dkg_finalize")]
+    /// - Decrypt the round 2 packages sent to this verifier
+    /// - Save the key package and return the group's public key package
+    async fn frost_dkg_finalize_rpc(
+        &self,
+        round2_packages: BTreeMap<FrostSignerId, EncryptedRound2Package>,
+    ) -> Result<PublicKeyPackage, BridgeError>;
+
+    #[method(name = "sweep_nonces")]
+    /// - Check that the deposits are signed under the current epoch
+    /// - Return the pubNonces of their sweep_tx inputs to the next epoch
+    async fn sweep_nonces_rpc(
+        &self,
+        deposit_outpoints: Vec<OutPoint>,
+    ) -> Result<Vec<MuSigPubNonce>, BridgeError>;
+
+    #[method(name = "sign_sweep")]
+    /// - Check that no withdrawal of the deposits is in progress
+    /// - Build the sweep_tx of the deposits to the next epoch
+    /// - Verify the next epoch's graphs of the swept outputs
+    /// - Partial sign its inputs with the sweep nonces
+    async fn sign_sweep_rpc(
+        &self,
+        deposit_outpoints: Vec<OutPoint>,
+        agg_nonces: Vec<MuSigAggNonce>,
+        swept_graphs: Vec<SweptDepositGraph>,
+    ) -> Result<Vec<MuSigPartialSignature>, BridgeError>;
 }
 
 #[rpc(client, server, namespace = "operator")]
@@ -64,17 +138,27 @@ pub trait OperatorRpc {
         evm_address: EVMAddress,
     ) -> Result<(UTXO, secp256k1::schnorr::Signature), BridgeError>;
 
+    #[method(name = "new_deposit_batch")]
+    /// - Check that every deposit has a kickoff UTXO
+    /// - Save the deposits as the inputs of a batched move_tx, in order
+    async fn new_deposit_batch_rpc(
+        &self,
+        deposit_outpoints: Vec<OutPoint>,
+    ) -> Result<(), BridgeError>;
+
     #[method(name = "set_funding_utxo")]
     async fn set_funding_utxo_rpc(&self, funding_utxo: UTXO) -> Result<(), BridgeError>;
 
     #[method(name = "new_withdrawal_sig")]
     /// Gets the withdrawal utxo from citrea,
     /// checks wheter sig is for a correct withdrawal from citrea,
+    /// gets the bridge amount of the withdrawn deposit,
     /// checks the signature, calls is_profitable, if is profitable pays the withdrawal,
     /// adds it to flow, when its finalized, proves on citrea, sends kickoff2
     async fn new_withdrawal_sig_rpc(
         &self,
         withdrawal_idx: u32,
+        deposit_outpoint: OutPoint,
         user_sig: schnorr::Signature,
         input_utxo: UTXO,
         output_txout: TxOut,
@@ -83,12 +167,15 @@ pub trait OperatorRpc {
     #[method(name = "withdrawal_proved_on_citrea")]
     /// 1- Calculate move_txid, check if the withdrawal idx matches the move_txid
     /// 2- Check if it is really proved on citrea
-    /// 3- If it is, send operator_take_txs
+    /// 3- If it is, create a reimbursement job that sends the kickoff
+    ///    generator txs, slash_or_take_tx and operator_takes_tx
+    ///
+    /// Returns the txids of the reimbursement transactions in broadcast order.
     async fn withdrawal_proved_on_citrea_rpc(
         &self,
         withdrawal_idx: u32,
         deposit_outpoint: OutPoint,
-    ) -> Result<Vec<String>, BridgeError>;
+    ) -> Result<Vec<Txid>, BridgeError>;
 
     // #[method(name = "operator_take_sendable")]
     // async fn operator_take_sendable_rpc(&self, withdrawal_idx: usize) -> Result<(), BridgeError>;
@@ -106,8 +193,25 @@ pub trait Aggregator {
     async fn aggregate_slash_or_take_sigs_rpc(
         &self,
         deposit_outpoint: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        bridge_amount_sats: Amount,
+        kickoff_utxos: Vec<UTXO>,
+        agg_nonces: Vec<MuSigAggNonce>,
+        pub_nonces: Vec<Vec<MuSigPubNonce>>,
+        partial_sigs: Vec<Vec<MuSigPartialSignature>>,
+    ) -> Result<Vec<schnorr::Signature>, BridgeError>;
+
+    #[method(name = "aggregate_burn_sigs")]
+    async fn aggregate_burn_sigs_rpc(
+        &self,
+        deposit_outpoint: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        bridge_amount_sats: Amount,
         kickoff_utxos: Vec<UTXO>,
         agg_nonces: Vec<MuSigAggNonce>,
+        pub_nonces: Vec<Vec<MuSigPubNonce>>,
         partial_sigs: Vec<Vec<MuSigPartialSignature>>,
     ) -> Result<Vec<schnorr::Signature>, BridgeError>;
 
@@ -115,8 +219,12 @@ pub trait Aggregator {
     async fn aggregate_operator_take_sigs_rpc(
         &self,
         deposit_outpoint: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        bridge_amount_sats: Amount,
         kickoff_utxos: Vec<UTXO>,
         agg_nonces: Vec<MuSigAggNonce>,
+        pub_nonces: Vec<Vec<MuSigPubNonce>>,
         partial_sigs: Vec<Vec<MuSigPartialSignature>>,
     ) -> Result<Vec<schnorr::Signature>, BridgeError>;
 
@@ -126,7 +234,48 @@ pub trait Aggregator {
         deposit_outpoint: OutPoint,
         recovery_taproot_address: Address<NetworkUnchecked>,
         evm_address: EVMAddress,
+        bridge_amount_sats: Amount,
+        kickoff_utxos: Vec<UTXO>,
         agg_nonce: MuSigAggNonce,
+        pub_nonces: Vec<MuSigPubNonce>,
         partial_sigs: Vec<MuSigPartialSignature>,
     ) -> Result<(String, Txid), BridgeError>;
+
+    #[method(name = "new_deposit")]
+    /// - Collect pub nonces from verifiers and aggregate them
+    /// - Collect kickoff UTXOs from operators
+    /// - Collect and aggregate slash_or_take, burn, operator_takes and move_tx sigs
+    /// - Return the signed move_tx
+    async fn aggregator_new_deposit_rpc(
+        &self,
+        deposit_outpoint: OutPoint,
+        recovery_taproot_address: Address<NetworkUnchecked>,
+        evm_address: EVMAddress,
+        bridge_amount_sats: Amount,
+    ) -> Result<(String, Txid), BridgeError>;
+
+    #[method(name = "new_deposit_batch")]
+    /// - Run the deposit flow of every deposit with a single batched move_tx
+    /// - Collect and aggregate move_tx sigs of every input
+    /// - Return the signed batched move_tx
+    async fn aggregator_new_deposit_batch_rpc(
+        &self,
+        deposits: Vec<MoveTxDeposit>,
+    ) -> Result<(String, Txid), BridgeError>;
+
+    #[method(name = "frost_dkg")]
+    /// - Run the DKG ceremony of the FROST key between every verifier
+    /// - Return the x-only group key
+    async fn aggregator_frost_dkg_rpc(&self) -> Result<XOnlyPublicKey, BridgeError>;
+
+    #[method(name = "sweep_epoch")]
+    /// - Verify the next epoch's graphs of the swept outputs
+    /// - Collect sweep nonces and partial sigs of the deposits from verifiers
+    /// - Return the signed sweep_tx, which moves their bridge funds to the
+    ///   next epoch
+    async fn aggregator_sweep_epoch_rpc(
+        &self,
+        deposit_outpoints: Vec<OutPoint>,
+        swept_graphs: Vec<SweptDepositGraph>,
+    ) -> Result<(String, Txid), BridgeError>;
 }
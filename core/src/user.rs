@@ -3,11 +3,13 @@ use crate::builder;
 use crate::config::BridgeConfig;
 use crate::errors::BridgeError;
 use crate::extended_rpc::ExtendedRpc;
-use crate::musig2::AggregateFromPublicKeys;
+use crate::fee;
+use crate::musig2::NofnContext;
+use crate::utils::handle_taproot_witness_new;
 use crate::{EVMAddress, UTXO};
+use bitcoin::TapSighashType;
 use bitcoin::{Address, TxOut};
-use bitcoin::{Amount, OutPoint};
-use bitcoin::{TapSighashType, XOnlyPublicKey};
+use bitcoin::{Amount, OutPoint, Txid};
 use bitcoin_mock_rpc::RpcApiWrapper;
 use secp256k1::schnorr;
 use secp256k1::SecretKey;
@@ -19,7 +21,7 @@ pub struct User<R> {
     rpc: ExtendedRpc<R>,
     signer: Actor,
     config: BridgeConfig,
-    nofn_xonly_pk: XOnlyPublicKey,
+    nofn: NofnContext,
 }
 
 impl<R> User<R>
@@ -27,48 +29,127 @@ where
     R: RpcApiWrapper,
 {
     /// Creates a new `User`.
-    pub fn new(rpc: ExtendedRpc<R>, sk: SecretKey, config: BridgeConfig) -> Self {
+    pub fn new(
+        rpc: ExtendedRpc<R>,
+        sk: SecretKey,
+        config: BridgeConfig,
+    ) -> Result<Self, BridgeError> {
         let signer = Actor::new(sk, config.network);
 
-        let nofn_xonly_pk = secp256k1::XOnlyPublicKey::from_musig2_pks(
-            config.verifiers_public_keys.clone(),
-            None,
-            false,
-        );
+        let nofn = NofnContext::from_config(&config)?;
 
-        User {
+        Ok(User {
             rpc,
             signer,
             config,
-            nofn_xonly_pk,
-        }
+            nofn,
+        })
     }
 
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub fn deposit_tx(&self, evm_address: EVMAddress) -> Result<OutPoint, BridgeError> {
-        let deposit_address = self.get_deposit_address(evm_address)?;
+    pub async fn deposit_tx(
+        &self,
+        evm_address: EVMAddress,
+        bridge_amount_sats: Amount,
+    ) -> Result<OutPoint, BridgeError> {
+        let deposit_address = self.get_deposit_address(evm_address, bridge_amount_sats)?;
 
         let deposit_outpoint = self
             .rpc
-            .send_to_address(&deposit_address, self.config.bridge_amount_sats)?;
+            .send_to_address(&deposit_address, bridge_amount_sats)
+            .await?;
 
         Ok(deposit_outpoint)
     }
 
+    /// Returns the deposit address for a deposit of `bridge_amount_sats`,
+    /// which must be one of the bridge amounts.
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub fn get_deposit_address(&self, evm_address: EVMAddress) -> Result<Address, BridgeError> {
+    pub fn get_deposit_address(
+        &self,
+        evm_address: EVMAddress,
+        bridge_amount_sats: Amount,
+    ) -> Result<Address, BridgeError> {
+        if !self
+            .config
+            .bridge_amounts_sats
+            .contains(&bridge_amount_sats)
+        {
+            return Err(BridgeError::InvalidBridgeAmount(bridge_amount_sats));
+        }
+
         let (deposit_address, _) = builder::address::generate_deposit_address(
-            self.nofn_xonly_pk,
+            self.nofn.xonly_pk(),
             self.signer.address.as_unchecked(),
             evm_address,
-            self.config.bridge_amount_sats,
+            bridge_amount_sats,
             self.config.network,
             self.config.user_takes_after,
-        );
+        )?;
 
         Ok(deposit_address)
     }
 
+    /// Takes back a deposit that isn't moved by the verifiers, by spending it
+    /// with the user's timelock script after `user_takes_after` blocks. Deposit
+    /// must be made to this user's deposit address for `evm_address` and the
+    /// amount of the deposit UTXO.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BridgeError::DepositTimelockNotMatured`] if the deposit isn't
+    /// confirmed for `user_takes_after` blocks yet.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn recover_deposit(
+        &self,
+        deposit_outpoint: OutPoint,
+        evm_address: EVMAddress,
+        destination: Address,
+    ) -> Result<Txid, BridgeError> {
+        let confirmations = match self.rpc.confirmation_blocks(&deposit_outpoint.txid).await {
+            Ok(confirmations) => confirmations,
+            Err(BridgeError::NoConfirmationData) => 0,
+            Err(e) => return Err(e),
+        };
+        // Recovery tx can be mined in the next block, once it is
+        // `user_takes_after` blocks after the deposit's block.
+        if confirmations < self.config.user_takes_after {
+            return Err(BridgeError::DepositTimelockNotMatured(
+                deposit_outpoint,
+                self.config.user_takes_after - confirmations,
+            ));
+        }
+
+        // Deposit script commits to the amount, which is the UTXO's value
+        // even if it is not one of the bridge amounts.
+        let deposit_amount = self
+            .rpc
+            .get_txout_from_outpoint(&deposit_outpoint)
+            .await?
+            .value;
+
+        let mut tx_handler = builder::transaction::create_deposit_recovery_tx(
+            deposit_outpoint,
+            evm_address,
+            self.signer.address.as_unchecked(),
+            self.nofn.xonly_pk(),
+            self.config.network,
+            self.config.user_takes_after,
+            deposit_amount,
+            &destination,
+            fee::estimate_fee_rate(&self.rpc, &self.config).await?,
+        )?;
+
+        // Timelock script is locked to the key of the user's address, which is
+        // tweaked.
+        let sig = self
+            .signer
+            .sign_taproot_script_spend_tx_new_tweaked(&mut tx_handler, 0, 1)?;
+        handle_taproot_witness_new(&mut tx_handler, &[sig.as_ref()], 0, Some(1))?;
+
+        self.rpc.send_raw_transaction(&tx_handler.tx).await
+    }
+
     /// Generates a withdrawal transaction and it's signature.
     ///
     /// # Returns
@@ -77,14 +158,15 @@ where
     /// - `TxOut`: Withdrawal transaction output
     /// - `Signature`: Schnorr signature of the withdrawal transaction
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    pub fn generate_withdrawal_transaction_and_signature(
+    pub async fn generate_withdrawal_transaction_and_signature(
         &self,
         withdrawal_address: Address,
         withdrawal_amount: Amount,
     ) -> Result<(UTXO, TxOut, schnorr::Signature), BridgeError> {
         let dust_outpoint = self
             .rpc
-            .send_to_address(&self.signer.address, WITHDRAWAL_EMPTY_UTXO_SATS)?;
+            .send_to_address(&self.signer.address, WITHDRAWAL_EMPTY_UTXO_SATS)
+            .await?;
         let dust_utxo = UTXO {
             outpoint: dust_outpoint,
             txout: TxOut {
@@ -116,10 +198,12 @@ where
 
 #[cfg(test)]
 mod tests {
+    use crate::errors::BridgeError;
     use crate::extended_rpc::ExtendedRpc;
     use crate::user::User;
     use crate::EVMAddress;
     use crate::{create_extended_rpc, mock::database::create_test_config};
+    use bitcoin::Amount;
     use secp256k1::{rand, SecretKey};
 
     #[tokio::test]
@@ -129,10 +213,16 @@ mod tests {
 
         let evm_address = EVMAddress([0x45u8; 20]);
         let sk = SecretKey::new(&mut rand::thread_rng());
-        let user = User::new(rpc.clone(), sk, config.clone());
+        let user = User::new(rpc.clone(), sk, config.clone()).unwrap();
 
-        let deposit_utxo = user.deposit_tx(evm_address).unwrap();
-        let deposit_txout = rpc.get_raw_transaction(&deposit_utxo.txid, None).unwrap();
+        let deposit_utxo = user
+            .deposit_tx(evm_address, config.bridge_amounts_sats[0])
+            .await
+            .unwrap();
+        let deposit_txout = rpc
+            .get_raw_transaction(&deposit_utxo.txid, None)
+            .await
+            .unwrap();
 
         assert_eq!(
             deposit_txout
@@ -140,10 +230,12 @@ mod tests {
                 .get(deposit_utxo.vout as usize)
                 .unwrap()
                 .value,
-            config.bridge_amount_sats
+            config.bridge_amounts_sats[0]
         );
 
-        let deposit_address = user.get_deposit_address(evm_address).unwrap();
+        let deposit_address = user
+            .get_deposit_address(evm_address, config.bridge_amounts_sats[0])
+            .unwrap();
         assert_eq!(
             deposit_txout
                 .output
@@ -152,5 +244,55 @@ mod tests {
                 .script_pubkey,
             deposit_address.script_pubkey()
         );
+
+        // Deposit addresses commit to the amount.
+        assert_ne!(
+            user.get_deposit_address(evm_address, config.bridge_amounts_sats[1])
+                .unwrap(),
+            deposit_address
+        );
+        assert!(matches!(
+            user.deposit_tx(evm_address, Amount::from_sat(0x45)).await,
+            Err(BridgeError::InvalidBridgeAmount(amount)) if amount == Amount::from_sat(0x45)
+        ));
+    }
+
+    #[tokio::test]
+    async fn recover_deposit() {
+        let mut config = create_test_config("recover_deposit", "test_config.toml").await;
+        let rpc = create_extended_rpc!(config);
+
+        let evm_address = EVMAddress([0x45u8; 20]);
+        let sk = SecretKey::new(&mut rand::thread_rng());
+        let user = User::new(rpc.clone(), sk, config.clone()).unwrap();
+        let destination = user.signer.address.clone();
+
+        let deposit_outpoint = user
+            .deposit_tx(evm_address, config.bridge_amounts_sats[1])
+            .await
+            .unwrap();
+        rpc.mine_blocks((config.user_takes_after - 1).into())
+            .await
+            .unwrap();
+        assert!(matches!(
+            user.recover_deposit(deposit_outpoint, evm_address, destination.clone())
+                .await,
+            Err(BridgeError::DepositTimelockNotMatured(..))
+        ));
+
+        rpc.mine_blocks(1).await.unwrap();
+        let recovery_txid = user
+            .recover_deposit(deposit_outpoint, evm_address, destination.clone())
+            .await
+            .unwrap();
+        rpc.mine_blocks(1).await.unwrap();
+
+        let recovery_tx = rpc.get_raw_transaction(&recovery_txid, None).await.unwrap();
+        assert_eq!(recovery_tx.input[0].previous_output, deposit_outpoint);
+        assert_eq!(
+            recovery_tx.output[0].script_pubkey,
+            destination.script_pubkey()
+        );
+        assert!(rpc.is_utxo_spent(&deposit_outpoint).await.unwrap());
     }
 }
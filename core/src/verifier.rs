@@ -1,24 +1,36 @@
 use crate::actor::Actor;
-use crate::builder::transaction::{TxHandler, KICKOFF_UTXO_AMOUNT_SATS};
-use crate::builder::{self};
+use crate::builder::deposit_graph::{DepositGraph, GraphSighash, NonceIndex};
+use crate::builder::transaction::KICKOFF_UTXO_AMOUNT_SATS;
+use crate::citrea::CitreaClient;
 use crate::config::BridgeConfig;
 use crate::database::Database;
+use crate::epoch::{self, EpochSweep, SweptDepositGraph, VerifierEpoch};
 use crate::errors::BridgeError;
 use crate::extended_rpc::ExtendedRpc;
-use crate::musig2::{
-    self, AggregateFromPublicKeys, MuSigAggNonce, MuSigPartialSignature, MuSigPubNonce,
-    MuSigSigHash,
+use crate::frost::{
+    self, DkgParticipant, EncryptedRound2Package, FrostCommitment, FrostSignatureShare,
+    FrostSignerId,
 };
+use crate::musig2::{self, MuSigAggNonce, MuSigPartialSignature, MuSigPubNonce, NofnContext};
 use crate::traits::rpc::VerifierRpcServer;
-use crate::{utils, ByteArray32, ByteArray64, ByteArray66, EVMAddress, UTXO};
+use crate::{utils, ByteArray64, ByteArray66, EVMAddress, UTXO};
 use bitcoin::address::NetworkUnchecked;
-use bitcoin::hashes::Hash;
 use bitcoin::Address;
 use bitcoin::{secp256k1, OutPoint};
 use bitcoin_mock_rpc::RpcApiWrapper;
 use bitcoincore_rpc::RawTx;
+use frost_secp256k1_tr::keys::{dkg, KeyPackage, PublicKeyPackage};
 use jsonrpsee::core::async_trait;
 use secp256k1::{rand, schnorr};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Minimum number of blocks that must be left before the depositor of a
+/// batched deposit can take it back, while the batch is saved and while its
+/// move_tx is signed. A deposit that is taken back voids the move_tx of the
+/// whole batch.
+pub const MIN_BATCH_RECOVERY_MARGIN: u32 = 6;
 
 #[derive(Debug, Clone)]
 pub struct Verifier<R>
@@ -29,8 +41,11 @@ where
     signer: Actor,
     db: Database,
     config: BridgeConfig,
-    nofn_xonly_pk: secp256k1::XOnlyPublicKey,
+    nofn: NofnContext,
     operator_xonly_pks: Vec<secp256k1::XOnlyPublicKey>,
+    citrea_client: Option<CitreaClient>,
+    /// This verifier's side of an unfinished FROST DKG ceremony.
+    frost_dkg: Arc<Mutex<Option<DkgParticipant>>>,
 }
 
 impl<R> Verifier<R>
@@ -47,30 +62,71 @@ where
             return Err(BridgeError::PublicKeyNotFound);
         }
 
+        if let Some(threshold) = config.frost_threshold {
+            frost::check_threshold(threshold, config.verifiers_public_keys.len())?;
+        }
+
         let db = Database::new(&config).await?;
 
-        let nofn_xonly_pk = secp256k1::XOnlyPublicKey::from_musig2_pks(
-            config.verifiers_public_keys.clone(),
-            None,
-            false,
-        );
+        let nofn = NofnContext::from_config(&config)?;
+        epoch::save_config_epochs(&db, &config, &nofn).await?;
 
         let operator_xonly_pks = config.operators_xonly_pks.clone();
 
+        let citrea_client = if !config.citrea_rpc_url.is_empty() {
+            Some(CitreaClient::new(&config)?)
+        } else {
+            None
+        };
+
         Ok(Verifier {
             rpc,
             signer,
             db,
             config,
-            nofn_xonly_pk,
+            nofn,
             operator_xonly_pks,
+            citrea_client,
+            frost_dkg: Arc::new(Mutex::new(None)),
         })
     }
 
+    /// Returns the identifier of this verifier in the FROST group.
+    fn frost_signer_id(&self) -> Result<FrostSignerId, BridgeError> {
+        let verifier_idx = self
+            .config
+            .verifiers_public_keys
+            .iter()
+            .position(|pk| *pk == self.signer.public_key)
+            .ok_or(BridgeError::PublicKeyNotFound)?;
+
+        frost::signer_id(verifier_idx)
+    }
+
+    /// Returns this verifier's FROST key package, which must be of the
+    /// configured group key.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR))]
+    async fn frost_key_package(&self) -> Result<KeyPackage, BridgeError> {
+        let (key_package, public_key_package) = self
+            .db
+            .get_frost_key_package(None)
+            .await?
+            .ok_or(BridgeError::FrostKeyNotFound)?;
+
+        if !self.nofn.is_threshold_key()
+            || frost::group_xonly_pk(&public_key_package)? != self.nofn.xonly_pk()
+        {
+            return Err(BridgeError::FrostGroupKeyMismatch);
+        }
+
+        Ok(key_package)
+    }
+
     /// Inform verifiers about the new deposit request
     ///
     /// 1. Check if the deposit UTXO is valid, finalized (6 blocks confirmation) and not spent
-    /// 2. Generate random pubNonces, secNonces
+    /// 2. Assign pubNonces, secNonces from the nonce pool, or generate random
+    ///    ones if the pool doesn't have enough of them
     /// 3. Save pubNonces and secNonces to a db
     /// 4. Return pubNonces
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
@@ -80,19 +136,21 @@ where
         recovery_taproot_address: Address<NetworkUnchecked>,
         evm_address: EVMAddress,
     ) -> Result<Vec<MuSigPubNonce>, BridgeError> {
-        self.rpc.check_deposit_utxo(
-            self.nofn_xonly_pk,
-            &deposit_outpoint,
-            &recovery_taproot_address,
-            evm_address,
-            self.config.bridge_amount_sats,
-            self.config.confirmation_threshold,
-            self.config.network,
-            self.config.user_takes_after,
-        )?;
+        let (block_hash, bridge_amount_sats) = self
+            .rpc
+            .check_deposit_utxo(
+                self.nofn.xonly_pk(),
+                &deposit_outpoint,
+                &recovery_taproot_address,
+                evm_address,
+                &self.config.bridge_amounts_sats,
+                self.config.confirmation_threshold,
+                self.config.network,
+                self.config.user_takes_after,
+            )
+            .await?;
 
-        // For now we multiply by 2 since we do not give signatures for burn_txs. // TODO: Change this in future.
-        let num_required_nonces = 2 * self.operator_xonly_pks.len() + 1;
+        let num_required_nonces = NonceIndex::new(self.operator_xonly_pks.len()).num_nonces();
 
         let mut dbtx = self.db.begin_transaction().await?;
         // Check if we already have pub_nonces for this deposit_outpoint.
@@ -106,33 +164,124 @@ where
                     return Err(BridgeError::NoncesNotFound);
                 }
                 dbtx.commit().await?;
+
+                // Deposit might be confirmed again after a reorg.
+                self.db
+                    .set_deposit_confirmed(deposit_outpoint, block_hash)
+                    .await?;
                 return Ok(pub_nonces);
             }
         }
 
-        let nonces = (0..num_required_nonces)
-            .map(|_| musig2::nonce_pair(&self.signer.keypair, &mut rand::rngs::OsRng))
-            .collect::<Vec<_>>();
-        let nonces: Vec<(ByteArray64, ByteArray66)> = nonces
-            .into_iter()
-            .collect::<Vec<(ByteArray64, ByteArray66)>>();
-
         self.db
             .save_deposit_info(
                 Some(&mut dbtx),
                 deposit_outpoint,
                 recovery_taproot_address,
                 evm_address,
+                bridge_amount_sats,
+                block_hash,
+                self.config.verifier_epoch,
             )
             .await?;
+
+        // FROST nonces are not pooled.
+        let pool_pub_nonces = match self.config.frost_threshold {
+            Some(_) => None,
+            None => {
+                self.db
+                    .assign_pool_nonces(Some(&mut dbtx), deposit_outpoint, num_required_nonces)
+                    .await?
+            }
+        };
+
+        let pub_nonces = match pool_pub_nonces {
+            Some(pub_nonces) => pub_nonces,
+            // FROST key is used, or the pool is drained faster than it is
+            // replenished.
+            None => {
+                let nonces = match self.config.frost_threshold {
+                    Some(_) => {
+                        let key_package = self.frost_key_package().await?;
+
+                        (0..num_required_nonces)
+                            .map(|_| frost::nonce_pair(&key_package, &mut rand::rngs::OsRng))
+                            .collect::<Result<Vec<(ByteArray64, ByteArray66)>, BridgeError>>()?
+                    }
+                    None => (0..num_required_nonces)
+                        .map(|_| musig2::nonce_pair(&self.signer.keypair, &mut rand::rngs::OsRng))
+                        .collect::<Result<Vec<(ByteArray64, ByteArray66)>, BridgeError>>()?,
+                };
+                self.db
+                    .save_nonces(Some(&mut dbtx), deposit_outpoint, &nonces)
+                    .await?;
+
+                nonces.iter().map(|(_, pub_nonce)| *pub_nonce).collect()
+            }
+        };
+        dbtx.commit().await?;
+
+        Ok(pub_nonces)
+    }
+
+    /// Saves the deposits as the inputs of a batched move_tx, in the given
+    /// order. Every deposit must be saved with [`Self::new_deposit`] before
+    /// and its graph must not be signed yet, because the move_tx of a deposit
+    /// can't change after its slash_or_take_txs are signed. Saving the same
+    /// batch again is a no-op.
+    ///
+    /// A depositor can take their deposit back after `user_takes_after`
+    /// blocks, which spends an input of the move_tx and voids it for every
+    /// deposit of the batch. Since the graphs of the other deposits are
+    /// signed with that move_tx, they can't be batched again and their
+    /// depositors must take them back too. To keep a depositor from doing
+    /// this, every deposit of a batch must be unspent and at least
+    /// [`MIN_BATCH_RECOVERY_MARGIN`] blocks away from its recovery, both when
+    /// the batch is saved and when its move_tx is signed.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn new_deposit_batch(&self, deposit_outpoints: Vec<OutPoint>) -> Result<(), BridgeError> {
+        if deposit_outpoints.is_empty() {
+            return Err(BridgeError::EmptyDepositBatch);
+        }
+        for (i, deposit_outpoint) in deposit_outpoints.iter().enumerate() {
+            if deposit_outpoints[..i].contains(deposit_outpoint) {
+                return Err(BridgeError::InvalidDepositBatch(*deposit_outpoint));
+            }
+        }
+
+        let mut dbtx = self.db.begin_transaction().await?;
+        for deposit_outpoint in &deposit_outpoints {
+            self.check_deposit_not_invalidated(Some(&mut dbtx), *deposit_outpoint)
+                .await?;
+            self.check_deposit_batchable(*deposit_outpoint).await?;
+
+            if let Some(batch) = self
+                .db
+                .get_deposit_batch(Some(&mut dbtx), *deposit_outpoint)
+                .await?
+            {
+                if batch == deposit_outpoints {
+                    return Ok(());
+                }
+
+                return Err(BridgeError::InvalidDepositBatch(*deposit_outpoint));
+            }
+            if self
+                .db
+                .get_kickoff_utxos(Some(&mut dbtx), *deposit_outpoint)
+                .await?
+                .is_some()
+            {
+                return Err(BridgeError::InvalidDepositBatch(*deposit_outpoint));
+            }
+        }
+
         self.db
-            .save_nonces(Some(&mut dbtx), deposit_outpoint, &nonces)
+            .save_deposit_batch(Some(&mut dbtx), &deposit_outpoints)
             .await?;
         dbtx.commit().await?;
 
-        let pub_nonces = nonces.iter().map(|(_, pub_nonce)| *pub_nonce).collect();
-
-        Ok(pub_nonces)
+        Ok(())
     }
 
     /// - Verify operators signatures about kickoffs
@@ -140,11 +289,11 @@ where
     /// - Save agg_nonces to a db for future use
     /// - for every kickoff_utxo, calculate slash_or_take_tx
     /// - for every slash_or_take_tx, partial sign slash_or_take_tx
-    /// - for every slash_or_take_tx, partial sign burn_tx (omitted for now)
-    /// - return burn_txs partial signatures (omitted for now) TODO: For this bit,
+    /// - for every slash_or_take_tx, partial sign burn_tx
+    /// - return slash_or_take_txs and burn_txs partial signatures
     ///
-    /// do not forget to add tweak when signing since this address has n_of_n as internal_key
-    /// and operator_timelock as script.
+    /// burn_txs are signed with the tweak of the slash_or_take address, since it
+    /// has n_of_n as internal_key and operator_timelock as script.
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
     async fn operator_kickoffs_generated(
         &self,
@@ -161,11 +310,175 @@ where
             agg_nonces
         );
 
+        if self.config.frost_threshold.is_some() {
+            return Err(BridgeError::MuSigWithThresholdKey);
+        }
+
+        let mut dbtx = self.db.begin_transaction().await?;
+
+        let graph = self
+            .check_kickoffs(
+                &mut dbtx,
+                deposit_outpoint,
+                &kickoff_utxos,
+                &operators_kickoff_sigs,
+            )
+            .await?;
+
+        self.db
+            .save_agg_nonces(Some(&mut dbtx), deposit_outpoint, &agg_nonces)
+            .await?;
+
+        let partial_sigs = self
+            .sign_kickoffs(&mut dbtx, deposit_outpoint, &graph)
+            .await?;
+
+        dbtx.commit().await?;
+
+        Ok(partial_sigs)
+    }
+
+    /// Same as [`Self::operator_kickoffs_generated`], for a deposit that is
+    /// signed with the FROST key by the given signers. Commitments of the
+    /// signers are saved instead of agg nonces. Signers of a deposit can't
+    /// change once they are saved, since a nonce must not be signed with
+    /// different commitments.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn frost_operator_kickoffs_generated(
+        &self,
+        deposit_outpoint: OutPoint,
+        kickoff_utxos: Vec<UTXO>,
+        operators_kickoff_sigs: Vec<secp256k1::schnorr::Signature>,
+        signing_commitments: BTreeMap<FrostSignerId, Vec<FrostCommitment>>,
+    ) -> Result<(Vec<FrostSignatureShare>, Vec<FrostSignatureShare>), BridgeError> {
+        let threshold = self
+            .config
+            .frost_threshold
+            .ok_or(BridgeError::FrostNotEnabled)?;
+
+        let mut dbtx = self.db.begin_transaction().await?;
+
+        let graph = self
+            .check_kickoffs(
+                &mut dbtx,
+                deposit_outpoint,
+                &kickoff_utxos,
+                &operators_kickoff_sigs,
+            )
+            .await?;
+
+        // Signers must include this verifier, with its own commitments.
+        let pub_nonces = self
+            .db
+            .get_pub_nonces(Some(&mut dbtx), deposit_outpoint)
+            .await?
+            .ok_or(BridgeError::NoncesNotFound)?;
+        if signing_commitments.len() < usize::from(threshold)
+            || signing_commitments.get(&self.frost_signer_id()?) != Some(&pub_nonces)
+            || signing_commitments.iter().any(|(id, commitments)| {
+                *id == 0
+                    || usize::from(*id) > self.config.verifiers_public_keys.len()
+                    || commitments.len() != pub_nonces.len()
+            })
+        {
+            return Err(BridgeError::InvalidFrostSigningSet);
+        }
+
+        match self
+            .db
+            .get_frost_signing_commitments(Some(&mut dbtx), deposit_outpoint)
+            .await?
+        {
+            Some(saved_commitments) if saved_commitments != signing_commitments => {
+                return Err(BridgeError::InvalidFrostSigningSet);
+            }
+            Some(_) => {}
+            None => {
+                self.db
+                    .save_frost_signing_commitments(
+                        Some(&mut dbtx),
+                        deposit_outpoint,
+                        &signing_commitments,
+                    )
+                    .await?
+            }
+        }
+
+        let signature_shares = self
+            .sign_kickoffs(&mut dbtx, deposit_outpoint, &graph)
+            .await?;
+
+        dbtx.commit().await?;
+
+        Ok(signature_shares)
+    }
+
+    /// Replaces this verifier's FROST nonces of a deposit with fresh ones and
+    /// returns their commitments, so that the deposit can be signed by a new
+    /// signing set when a signer of the old one is offline. Nonces can't be
+    /// replaced once something is signed with them, since the signature
+    /// shares are bound to the commitments of the old set.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn frost_renew_deposit_nonces(
+        &self,
+        deposit_outpoint: OutPoint,
+    ) -> Result<Vec<FrostCommitment>, BridgeError> {
+        if self.config.frost_threshold.is_none() {
+            return Err(BridgeError::FrostNotEnabled);
+        }
+
+        let mut dbtx = self.db.begin_transaction().await?;
+        // Deposit is locked, so that it can't be signed while its nonces are
+        // replaced.
+        self.check_deposit_not_invalidated(Some(&mut dbtx), deposit_outpoint)
+            .await?;
+
+        let num_nonces = self
+            .db
+            .get_pub_nonces(Some(&mut dbtx), deposit_outpoint)
+            .await?
+            .ok_or(BridgeError::NoncesNotFound)?
+            .len();
+        if self
+            .db
+            .has_signed_nonces(Some(&mut dbtx), deposit_outpoint)
+            .await?
+        {
+            return Err(BridgeError::FrostNoncesAlreadySigned(deposit_outpoint));
+        }
+
+        let key_package = self.frost_key_package().await?;
+        let nonces = (0..num_nonces)
+            .map(|_| frost::nonce_pair(&key_package, &mut rand::rngs::OsRng))
+            .collect::<Result<Vec<(ByteArray64, ByteArray66)>, BridgeError>>()?;
+        self.db
+            .delete_nonces(Some(&mut dbtx), deposit_outpoint)
+            .await?;
+        self.db
+            .save_nonces(Some(&mut dbtx), deposit_outpoint, &nonces)
+            .await?;
+        dbtx.commit().await?;
+
+        Ok(nonces.iter().map(|(_, pub_nonce)| *pub_nonce).collect())
+    }
+
+    /// Verifies the operators' signatures of their kickoff UTXOs and builds
+    /// the deposit graph with them. Deposit is locked with `dbtx`, so that
+    /// its batch can't change until the kickoff UTXOs are saved.
+    #[tracing::instrument(skip(self, dbtx), err(level = tracing::Level::ERROR))]
+    async fn check_kickoffs(
+        &self,
+        dbtx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        deposit_outpoint: OutPoint,
+        kickoff_utxos: &[UTXO],
+        operators_kickoff_sigs: &[secp256k1::schnorr::Signature],
+    ) -> Result<DepositGraph, BridgeError> {
         if operators_kickoff_sigs.len() != kickoff_utxos.len() {
             return Err(BridgeError::InvalidKickoffUtxo); // TODO: Better error
         }
-
-        let mut slash_or_take_sighashes: Vec<MuSigSigHash> = Vec::new();
+        self.check_deposit_not_invalidated(Some(dbtx), deposit_outpoint)
+            .await?;
+        self.check_deposit_epoch(deposit_outpoint).await?;
 
         for (i, kickoff_utxo) in kickoff_utxos.iter().enumerate() {
             let value = kickoff_utxo.txout.value;
@@ -186,130 +499,351 @@ where
                 &secp256k1::Message::from_digest(kickoff_sig_hash),
                 &self.config.operators_xonly_pks[i],
             )?;
-
-            // Check if for each operator the address of the kickoff_utxo is correct TODO: Maybe handle the possible errors better
-            let (musig2_and_operator_address, spend_info) =
-                builder::address::create_kickoff_address(
-                    self.nofn_xonly_pk,
-                    self.operator_xonly_pks[i],
-                    self.config.network,
-                );
-            tracing::debug!(
-                "musig2_and_operator_address.script_pubkey: {:?}",
-                musig2_and_operator_address.script_pubkey()
-            );
-            tracing::debug!("Kickoff UTXO: {:?}", kickoff_utxo.txout.script_pubkey);
-            tracing::debug!("Spend Info: {:?}", spend_info);
-            assert!(
-                kickoff_utxo.txout.script_pubkey == musig2_and_operator_address.script_pubkey()
-            );
-
-            let mut slash_or_take_tx_handler = builder::transaction::create_slash_or_take_tx(
-                deposit_outpoint,
-                kickoff_utxo.clone(),
-                self.config.operators_xonly_pks[i],
-                i,
-                self.nofn_xonly_pk,
-                self.config.network,
-                self.config.user_takes_after,
-                self.config.operator_takes_after,
-                self.config.bridge_amount_sats,
-            );
-            let slash_or_take_tx_sighash =
-                Actor::convert_tx_to_sighash_script_spend(&mut slash_or_take_tx_handler, 0, 0)?;
-            slash_or_take_sighashes.push(ByteArray32(slash_or_take_tx_sighash.to_byte_array()));
-            // let spend_kickoff_utxo_tx_handler = builder::transaction::create_slash_or_take_tx(deposit_outpoint, kickoff_outpoint, kickoff_txout, operator_address, operator_idx, nofn_xonly_pk, network)
         }
+
+        // Addresses of the kickoff_utxos are checked while building the graph.
+        let graph = self
+            .create_deposit_graph(deposit_outpoint, kickoff_utxos.to_vec())
+            .await?;
         tracing::debug!(
             "Slash or take sighashes for verifier: {:?}: {:?}",
             self.signer.xonly_public_key.to_string(),
-            slash_or_take_sighashes
+            graph.slash_or_take_sighashes()
         );
 
-        let mut dbtx = self.db.begin_transaction().await?;
+        Ok(graph)
+    }
+
+    /// Saves the kickoff UTXOs of the deposit graph and partially signs its
+    /// slash_or_take_txs and burn_txs.
+    #[tracing::instrument(skip(self, dbtx, graph), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn sign_kickoffs(
+        &self,
+        dbtx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        deposit_outpoint: OutPoint,
+        graph: &DepositGraph,
+    ) -> Result<(Vec<MuSigPartialSignature>, Vec<MuSigPartialSignature>), BridgeError> {
+        let nonce_index = graph.nonce_index();
 
         self.db
-            .save_agg_nonces(Some(&mut dbtx), deposit_outpoint, &agg_nonces)
+            .save_kickoff_utxos(Some(&mut *dbtx), deposit_outpoint, graph.kickoff_utxos())
             .await?;
 
-        self.db
-            .save_kickoff_utxos(Some(&mut dbtx), deposit_outpoint, &kickoff_utxos)
+        let slash_or_take_partial_sigs = self
+            .partial_sign(
+                dbtx,
+                deposit_outpoint,
+                nonce_index.slash_or_take().start,
+                graph.slash_or_take_sighashes(),
+            )
+            .await?;
+        let burn_partial_sigs = self
+            .partial_sign(
+                dbtx,
+                deposit_outpoint,
+                nonce_index.burn().start,
+                graph.burn_sighashes(),
+            )
             .await?;
 
+        Ok((slash_or_take_partial_sigs, burn_partial_sigs))
+    }
+
+    /// Saves the sighashes of the deposit graph, starting at nonce index
+    /// `nonce_idx`, and partially signs them with their nonces.
+    #[tracing::instrument(skip(self, dbtx), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn partial_sign(
+        &self,
+        dbtx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        deposit_outpoint: OutPoint,
+        nonce_idx: usize,
+        sighashes: &[GraphSighash],
+    ) -> Result<Vec<MuSigPartialSignature>, BridgeError> {
+        if self.config.frost_threshold.is_some() {
+            return self
+                .frost_partial_sign(dbtx, deposit_outpoint, nonce_idx, sighashes)
+                .await;
+        }
+
         let nonces = self
             .db
             .save_sighashes_and_get_nonces(
-                Some(&mut dbtx),
+                Some(dbtx),
                 deposit_outpoint,
-                self.config.num_operators + 1,
-                &slash_or_take_sighashes,
+                nonce_idx,
+                &sighashes
+                    .iter()
+                    .map(|sighash| sighash.sighash)
+                    .collect::<Vec<_>>(),
             )
             .await?
             .ok_or(BridgeError::NoncesNotFound)?;
         tracing::debug!(
-            "SIGNING slash or take for outpoint: {:?} with nonces {:?}",
+            "SIGNING sighashes from nonce {} for outpoint: {:?} with nonces {:?}",
+            nonce_idx,
             deposit_outpoint,
             nonces
         );
-        let slash_or_take_partial_sigs = slash_or_take_sighashes
+
+        sighashes
             .iter()
             .zip(nonces.iter())
             .map(|(sighash, (sec_nonce, agg_nonce))| {
-                musig2::partial_sign(
-                    self.config.verifiers_public_keys.clone(),
-                    None,
-                    false,
+                self.nofn.partial_sign(
+                    sighash.tweak,
+                    sighash.tweak_flag,
                     *sec_nonce,
                     *agg_nonce,
                     &self.signer.keypair,
-                    *sighash,
+                    sighash.sighash,
                 )
             })
-            .collect::<Vec<_>>();
+            .collect()
+    }
 
-        dbtx.commit().await?;
+    /// Same as [`Self::partial_sign`], with the FROST key and the saved
+    /// commitments of the deposit's signers.
+    #[tracing::instrument(skip(self, dbtx), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn frost_partial_sign(
+        &self,
+        dbtx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        deposit_outpoint: OutPoint,
+        nonce_idx: usize,
+        sighashes: &[GraphSighash],
+    ) -> Result<Vec<FrostSignatureShare>, BridgeError> {
+        let key_package = self.frost_key_package().await?;
+        let signing_commitments = self
+            .db
+            .get_frost_signing_commitments(Some(&mut *dbtx), deposit_outpoint)
+            .await?
+            .ok_or(BridgeError::NoncesNotFound)?;
+        let nonces = self
+            .db
+            .save_sighashes_and_get_sec_nonces(
+                Some(dbtx),
+                deposit_outpoint,
+                nonce_idx,
+                &sighashes
+                    .iter()
+                    .map(|sighash| sighash.sighash)
+                    .collect::<Vec<_>>(),
+            )
+            .await?
+            .ok_or(BridgeError::NoncesNotFound)?;
+        if nonces.len() != sighashes.len() {
+            return Err(BridgeError::NoncesNotFound);
+        }
+
+        sighashes
+            .iter()
+            .zip(nonces)
+            .enumerate()
+            .map(|(i, (sighash, nonce))| {
+                let commitments = signing_commitments
+                    .iter()
+                    .map(|(id, commitments)| {
+                        commitments
+                            .get(nonce_idx + i)
+                            .map(|commitment| (*id, *commitment))
+                    })
+                    .collect::<Option<BTreeMap<_, _>>>()
+                    .ok_or(BridgeError::NoncesNotFound)?;
+
+                frost::sign(
+                    &key_package,
+                    sighash.tweak,
+                    sighash.tweak_flag,
+                    nonce,
+                    &commitments,
+                    sighash.sighash,
+                )
+            })
+            .collect()
+    }
+
+    /// Starts the DKG ceremony of the FROST key and returns this verifier's
+    /// round 1 package. A verifier that already has a FROST key can't start
+    /// a new ceremony.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn frost_dkg_round1(&self) -> Result<dkg::round1::Package, BridgeError> {
+        let threshold = self
+            .config
+            .frost_threshold
+            .ok_or(BridgeError::FrostNotEnabled)?;
+        if self.db.get_frost_key_package(None).await?.is_some() {
+            return Err(BridgeError::FrostKeyAlreadyGenerated);
+        }
+
+        let mut participant = DkgParticipant::new(
+            self.config.secret_key,
+            self.config.verifiers_public_keys.clone(),
+            threshold,
+        )?;
+        let round1_package = participant.round1(&mut rand::rngs::OsRng)?;
+        *self.frost_dkg.lock().await = Some(participant);
+
+        Ok(round1_package)
+    }
+
+    /// Returns this verifier's round 2 packages of the DKG ceremony, which are
+    /// encrypted to their recipients.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn frost_dkg_round2(
+        &self,
+        round1_packages: BTreeMap<FrostSignerId, dkg::round1::Package>,
+    ) -> Result<BTreeMap<FrostSignerId, EncryptedRound2Package>, BridgeError> {
+        self.frost_dkg
+            .lock()
+            .await
+            .as_mut()
+            .ok_or(BridgeError::FrostDkgNotStarted)?
+            .round2(round1_packages, &mut rand::rngs::OsRng)
+    }
+
+    /// Finishes the DKG ceremony, saves this verifier's key package and
+    /// returns the public key package of the group.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn frost_dkg_finalize(
+        &self,
+        round2_packages: BTreeMap<FrostSignerId, EncryptedRound2Package>,
+    ) -> Result<PublicKeyPackage, BridgeError> {
+        let mut frost_dkg = self.frost_dkg.lock().await;
+        let (key_package, public_key_package) = frost_dkg
+            .as_mut()
+            .ok_or(BridgeError::FrostDkgNotStarted)?
+            .finalize(round2_packages)?;
+
+        self.db
+            .save_frost_key_package(None, &key_package, &public_key_package)
+            .await?;
+        *frost_dkg = None;
+
+        Ok(public_key_package)
+    }
+
+    /// Refuses the signing rounds of a deposit that is reorged out, until it
+    /// is confirmed again. Deposit is locked until the end of `tx`.
+    #[tracing::instrument(skip(self, tx), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn check_deposit_not_invalidated(
+        &self,
+        tx: Option<&mut sqlx::Transaction<'_, sqlx::Postgres>>,
+        deposit_outpoint: OutPoint,
+    ) -> Result<(), BridgeError> {
+        if self.db.is_deposit_invalidated(tx, deposit_outpoint).await? {
+            return Err(BridgeError::DepositReorged(deposit_outpoint));
+        }
 
-        // TODO: Sign burn txs
-        Ok((slash_or_take_partial_sigs, vec![]))
+        Ok(())
     }
 
+    /// Refuses to batch a deposit that is already taken back by its
+    /// depositor, or can be taken back in less than
+    /// [`MIN_BATCH_RECOVERY_MARGIN`] blocks.
     #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
-    async fn create_deposit_details(
+    async fn check_deposit_batchable(&self, deposit_outpoint: OutPoint) -> Result<(), BridgeError> {
+        if self.rpc.is_utxo_spent(&deposit_outpoint).await? {
+            return Err(BridgeError::InvalidDepositBatch(deposit_outpoint));
+        }
+
+        let confirmations = self.rpc.confirmation_blocks(&deposit_outpoint.txid).await?;
+        if self.config.user_takes_after.saturating_sub(confirmations) < MIN_BATCH_RECOVERY_MARGIN {
+            return Err(BridgeError::DepositBatchRecoveryTooClose(deposit_outpoint));
+        }
+
+        Ok(())
+    }
+
+    /// Refuses to sign a deposit that is saved under another verifier epoch,
+    /// because its graph is locked to the N-of-N key of that epoch.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn check_deposit_epoch(&self, deposit_outpoint: OutPoint) -> Result<(), BridgeError> {
+        match self.db.get_deposit_epoch(None, deposit_outpoint).await? {
+            Some(epoch) if epoch == self.config.verifier_epoch => Ok(()),
+            _ => Err(BridgeError::InvalidDepositEpoch(deposit_outpoint)),
+        }
+    }
+
+    /// Refuses to sweep a deposit whose bridge funds an operator is taking:
+    /// one of its kickoff UTXOs is spent, its withdrawal is filled on Citrea
+    /// or its bridge fund output is already spent. Kickoff UTXOs are created
+    /// when the operator starts a withdrawal, so a kickoff UTXO that is not on
+    /// chain yet is not spent.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn check_deposit_sweepable(
         &self,
         deposit_outpoint: OutPoint,
-    ) -> Result<(Vec<UTXO>, TxHandler, OutPoint), BridgeError> {
+        bridge_fund_outpoint: OutPoint,
+    ) -> Result<(), BridgeError> {
+        self.check_deposit_epoch(deposit_outpoint).await?;
+
         let kickoff_utxos = self
             .db
-            .get_kickoff_utxos(deposit_outpoint)
+            .get_kickoff_utxos(None, deposit_outpoint)
             .await?
-            .ok_or(BridgeError::KickoffOutpointsNotFound)?;
+            .unwrap_or_default();
+        for kickoff_utxo in kickoff_utxos {
+            if self.rpc.is_tx_known(&kickoff_utxo.outpoint.txid).await?
+                && self.rpc.is_utxo_spent(&kickoff_utxo.outpoint).await?
+            {
+                return Err(BridgeError::DepositWithdrawalInProgress(deposit_outpoint));
+            }
+        }
 
-        // let kickoff_outpoints = kickoff_utxos
-        //     .iter()
-        //     .map(|utxo| utxo.outpoint)
-        //     .collect::<Vec<_>>();
+        if self.rpc.is_utxo_spent(&bridge_fund_outpoint).await? {
+            return Err(BridgeError::DepositWithdrawalInProgress(deposit_outpoint));
+        }
 
-        let (recovery_taproot_address, evm_address) = self
-            .db
-            .get_deposit_info(deposit_outpoint)
-            .await?
-            .ok_or(BridgeError::DepositInfoNotFound)?;
+        if let Some(citrea_client) = &self.citrea_client {
+            if let Some(withdrawal_idx) = citrea_client.deposit_idx(bridge_fund_outpoint).await? {
+                if citrea_client
+                    .withdrawal_filler(withdrawal_idx)
+                    .await?
+                    .is_some()
+                {
+                    return Err(BridgeError::DepositWithdrawalInProgress(deposit_outpoint));
+                }
+            }
+        }
 
-        let move_tx_handler = builder::transaction::create_move_tx_handler(
+        Ok(())
+    }
+
+    /// Builds the graph of the deposit with the saved deposit infos of its
+    /// move_tx.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR))]
+    async fn create_deposit_graph(
+        &self,
+        deposit_outpoint: OutPoint,
+        kickoff_utxos: Vec<UTXO>,
+    ) -> Result<DepositGraph, BridgeError> {
+        let move_tx_deposits = self.db.get_move_tx_deposits(deposit_outpoint).await?;
+
+        DepositGraph::new(
+            &move_tx_deposits,
             deposit_outpoint,
-            evm_address,
-            &recovery_taproot_address,
-            self.nofn_xonly_pk,
-            self.config.network,
-            self.config.user_takes_after,
-            self.config.bridge_amount_sats,
-        );
+            kickoff_utxos,
+            &self.nofn,
+            &self.config,
+        )
+    }
 
-        let bridge_fund_outpoint = OutPoint {
-            txid: move_tx_handler.tx.compute_txid(),
-            vout: 0,
-        };
-        Ok((kickoff_utxos, move_tx_handler, bridge_fund_outpoint))
+    /// Builds the graph of the deposit with the saved kickoff UTXOs.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR))]
+    async fn get_deposit_graph(
+        &self,
+        deposit_outpoint: OutPoint,
+    ) -> Result<DepositGraph, BridgeError> {
+        self.check_deposit_not_invalidated(None, deposit_outpoint)
+            .await?;
+
+        let kickoff_utxos = self
+            .db
+            .get_kickoff_utxos(None, deposit_outpoint)
+            .await?
+            .ok_or(BridgeError::KickoffOutpointsNotFound)?;
+
+        self.create_deposit_graph(deposit_outpoint, kickoff_utxos)
+            .await
     }
 
     /// verify burn txs are signed by verifiers
@@ -319,93 +853,53 @@ where
     async fn burn_txs_signed(
         &self,
         deposit_outpoint: OutPoint,
-        _burn_sigs: Vec<schnorr::Signature>,
+        burn_sigs: Vec<schnorr::Signature>,
         slash_or_take_sigs: Vec<schnorr::Signature>,
     ) -> Result<Vec<MuSigPartialSignature>, BridgeError> {
-        // TODO: Verify burn txs are signed by verifiers
-        let (kickoff_utxos, _, bridge_fund_outpoint) =
-            self.create_deposit_details(deposit_outpoint).await?;
+        let graph = self.get_deposit_graph(deposit_outpoint).await?;
 
-        let operator_takes_sighashes: Vec<MuSigSigHash> = kickoff_utxos
-            .iter()
-            .enumerate()
-            .map(|(index, kickoff_utxo)| {
-                let mut slash_or_take_tx_handler = builder::transaction::create_slash_or_take_tx(
-                    deposit_outpoint,
-                    kickoff_utxo.clone(),
-                    self.operator_xonly_pks[index],
-                    index,
-                    self.nofn_xonly_pk,
-                    self.config.network,
-                    self.config.user_takes_after,
-                    self.config.operator_takes_after,
-                    self.config.bridge_amount_sats,
-                );
-                let slash_or_take_sighash =
-                    Actor::convert_tx_to_sighash_script_spend(&mut slash_or_take_tx_handler, 0, 0)
-                        .unwrap();
-
-                utils::SECP
-                    .verify_schnorr(
-                        &slash_or_take_sigs[index],
-                        &secp256k1::Message::from_digest(slash_or_take_sighash.to_byte_array()),
-                        &self.nofn_xonly_pk,
-                    )
-                    .unwrap();
-
-                let slash_or_take_utxo = UTXO {
-                    outpoint: OutPoint {
-                        txid: slash_or_take_tx_handler.tx.compute_txid(),
-                        vout: 0,
-                    },
-                    txout: slash_or_take_tx_handler.tx.output[0].clone(),
-                };
-
-                let mut operator_takes_tx = builder::transaction::create_operator_takes_tx(
-                    bridge_fund_outpoint,
-                    slash_or_take_utxo,
-                    self.operator_xonly_pks[index],
-                    self.nofn_xonly_pk,
-                    self.config.network,
-                    self.config.operator_takes_after,
-                    self.config.bridge_amount_sats,
-                    self.config.operator_wallet_addresses[index].clone(),
-                );
-                ByteArray32(
-                    Actor::convert_tx_to_sighash_pubkey_spend(&mut operator_takes_tx, 0)
-                        .unwrap()
-                        .to_byte_array(),
+        if burn_sigs.len() != graph.kickoff_utxos().len() {
+            return Err(BridgeError::InvalidBurnSignatures);
+        }
+        if slash_or_take_sigs.len() != graph.kickoff_utxos().len() {
+            return Err(BridgeError::InvalidSlashOrTakeSignatures);
+        }
+        for (index, burn_tx) in graph.burn_txs().iter().enumerate() {
+            // burn_tx spends the slash_or_take output with the tweaked N-of-N key.
+            utils::SECP
+                .verify_schnorr(
+                    &burn_sigs[index],
+                    &secp256k1::Message::from_digest(graph.burn_sighashes()[index].sighash.0),
+                    &burn_tx.taproot_spend_infos[0].output_key().to_inner(),
                 )
-            })
-            .collect::<Vec<_>>();
+                .map_err(|_| BridgeError::InvalidBurnSignatures)?;
+        }
+        for (index, sighash) in graph.slash_or_take_sighashes().iter().enumerate() {
+            utils::SECP
+                .verify_schnorr(
+                    &slash_or_take_sigs[index],
+                    &secp256k1::Message::from_digest(sighash.sighash.0),
+                    &self.nofn.xonly_pk(),
+                )
+                .map_err(|_| BridgeError::InvalidSlashOrTakeSignatures)?;
+        }
 
+        self.db.save_burn_sigs(deposit_outpoint, burn_sigs).await?;
         self.db
             .save_slash_or_take_sigs(deposit_outpoint, slash_or_take_sigs)
             .await?;
 
-        // println!("Operator takes sighashes: {:?}", operator_takes_sighashes);
-        let nonces = self
-            .db
-            .save_sighashes_and_get_nonces(None, deposit_outpoint, 1, &operator_takes_sighashes)
-            .await?
-            .ok_or(BridgeError::NoncesNotFound)?;
-        // println!("Nonces: {:?}", nonces);
-        // now iterate over nonces and sighashes and sign the operator_takes_txs
-        let operator_takes_partial_sigs = operator_takes_sighashes
-            .iter()
-            .zip(nonces.iter())
-            .map(|(sighash, (sec_nonce, agg_nonce))| {
-                musig2::partial_sign(
-                    self.config.verifiers_public_keys.clone(),
-                    None,
-                    true,
-                    *sec_nonce,
-                    *agg_nonce,
-                    &self.signer.keypair,
-                    *sighash,
-                )
-            })
-            .collect::<Vec<_>>();
+        let mut dbtx = self.db.begin_transaction().await?;
+
+        let operator_takes_partial_sigs = self
+            .partial_sign(
+                &mut dbtx,
+                deposit_outpoint,
+                graph.nonce_index().operator_takes().start,
+                graph.operator_takes_sighashes(),
+            )
+            .await?;
+        dbtx.commit().await?;
 
         Ok(operator_takes_partial_sigs)
     }
@@ -418,115 +912,198 @@ where
         deposit_outpoint: OutPoint,
         operator_take_sigs: Vec<schnorr::Signature>,
     ) -> Result<MuSigPartialSignature, BridgeError> {
-        // println!("Operator take signed: {:?}", operator_take_sigs);
-        let (kickoff_utxos, mut move_tx_handler, bridge_fund_outpoint) =
-            self.create_deposit_details(deposit_outpoint).await?;
-        let nofn_taproot_xonly_pk = secp256k1::XOnlyPublicKey::from_slice(
-            &Address::p2tr(&utils::SECP, self.nofn_xonly_pk, None, self.config.network)
-                .script_pubkey()
-                .as_bytes()[2..34],
-        )?;
-        kickoff_utxos
-            .iter()
-            .enumerate()
-            .for_each(|(index, kickoff_utxo)| {
-                let slash_or_take_tx = builder::transaction::create_slash_or_take_tx(
-                    deposit_outpoint,
-                    kickoff_utxo.clone(),
-                    self.operator_xonly_pks[index],
-                    index,
-                    self.nofn_xonly_pk,
-                    self.config.network,
-                    self.config.user_takes_after,
-                    self.config.operator_takes_after,
-                    self.config.bridge_amount_sats,
-                );
-                let slash_or_take_utxo = UTXO {
-                    outpoint: OutPoint {
-                        txid: slash_or_take_tx.tx.compute_txid(),
-                        vout: 0,
-                    },
-                    txout: slash_or_take_tx.tx.output[0].clone(),
-                };
-                let mut operator_takes_tx = builder::transaction::create_operator_takes_tx(
-                    bridge_fund_outpoint,
-                    slash_or_take_utxo,
-                    self.operator_xonly_pks[index],
-                    self.nofn_xonly_pk,
-                    self.config.network,
-                    self.config.operator_takes_after,
-                    self.config.bridge_amount_sats,
-                    self.config.operator_wallet_addresses[index].clone(),
-                );
-                tracing::debug!(
-                    "INDEXXX: {:?} Operator takes tx hex: {:?}",
-                    index,
-                    operator_takes_tx.tx.raw_hex()
-                );
-
-                let sig_hash =
-                    Actor::convert_tx_to_sighash_pubkey_spend(&mut operator_takes_tx, 0).unwrap();
-
-                // verify the operator_take_sigs
-                utils::SECP
-                    .verify_schnorr(
-                        &operator_take_sigs[index],
-                        &secp256k1::Message::from_digest(sig_hash.to_byte_array()),
-                        &nofn_taproot_xonly_pk,
-                    )
-                    .unwrap();
-            });
+        let graph = self.get_deposit_graph(deposit_outpoint).await?;
 
-        let kickoff_utxos = kickoff_utxos
-            .into_iter()
-            .enumerate()
-            .map(|(index, utxo)| (utxo, operator_take_sigs[index]));
+        if operator_take_sigs.len() != graph.kickoff_utxos().len() {
+            return Err(BridgeError::InvalidOperatorTakeSignatures);
+        }
+        for (index, operator_takes_tx) in graph.operator_takes_txs().iter().enumerate() {
+            tracing::debug!(
+                "INDEXXX: {:?} Operator takes tx hex: {:?}",
+                index,
+                operator_takes_tx.tx.raw_hex()
+            );
+
+            // verify the operator_take_sigs
+            utils::SECP
+                .verify_schnorr(
+                    &operator_take_sigs[index],
+                    &secp256k1::Message::from_digest(
+                        graph.operator_takes_sighashes()[index].sighash.0,
+                    ),
+                    &operator_takes_tx.taproot_spend_infos[0]
+                        .output_key()
+                        .to_inner(),
+                )
+                .map_err(|_| BridgeError::InvalidOperatorTakeSignatures)?;
+        }
+
+        let kickoff_utxos = graph
+            .kickoff_utxos()
+            .iter()
+            .cloned()
+            .zip(operator_take_sigs);
 
         self.db
             .save_operator_take_sigs(deposit_outpoint, kickoff_utxos)
             .await?;
 
-        // println!("MOVE_TX: {:?}", move_tx_handler);
-        // println!("MOVE_TXID: {:?}", move_tx_handler.tx.compute_txid());
-        let move_tx_sighash =
-            Actor::convert_tx_to_sighash_script_spend(&mut move_tx_handler, 0, 0)?; // TODO: This should be musig
-
-        // let move_reveal_sighash =
-        //     Actor::convert_tx_to_sighash_script_spend(&mut move_reveal_tx_handler, 0, 0)?; // TODO: This should be musig
+        // Every deposit of the batch must still be in the move_tx when it is
+        // broadcasted.
+        if let Some(batch) = self.db.get_deposit_batch(None, deposit_outpoint).await? {
+            for batch_deposit_outpoint in batch {
+                self.check_deposit_batchable(batch_deposit_outpoint).await?;
+            }
+        }
 
-        let nonces = self
-            .db
-            .save_sighashes_and_get_nonces(
-                None,
+        let mut dbtx = self.db.begin_transaction().await?;
+        let move_tx_sig = self
+            .partial_sign(
+                &mut dbtx,
                 deposit_outpoint,
-                0,
-                &[ByteArray32(move_tx_sighash.to_byte_array())],
+                graph.nonce_index().move_tx(),
+                &[graph.move_tx_sighash()],
             )
-            .await?
-            .ok_or(BridgeError::NoncesNotFound)?;
+            .await?;
+        dbtx.commit().await?;
 
-        let move_tx_sig = musig2::partial_sign(
-            self.config.verifiers_public_keys.clone(),
-            None,
-            false,
-            nonces[0].0,
-            nonces[0].1,
-            &self.signer.keypair,
-            ByteArray32(move_tx_sighash.to_byte_array()),
-        );
+        move_tx_sig
+            .into_iter()
+            .next()
+            .ok_or(BridgeError::NoncesNotFound)
+    }
 
-        // let move_reveal_sig = musig2::partial_sign(
-        //     self.config.verifiers_public_keys.clone(),
-        //     None,
-        //     nonces[1].0,
-        //     nonces[2].1.clone(),
-        //     &self.signer.keypair,
-        //     move_reveal_sighash.to_byte_array(),
-        // );
-
-        Ok(
-            move_tx_sig as MuSigPartialSignature, // move_reveal_sig as MuSigPartialSignature,
-        )
+    /// Returns the current and the next verifier epochs, which a sweep moves
+    /// bridge funds between.
+    fn sweep_epochs(&self) -> Result<(VerifierEpoch, VerifierEpoch), BridgeError> {
+        // Sweep is signed with MuSig2 by the whole verifier set.
+        if self.nofn.is_threshold_key() {
+            return Err(BridgeError::MuSigWithThresholdKey);
+        }
+
+        let next_epoch = VerifierEpoch::next(&self.config)?.ok_or(
+            BridgeError::VerifierEpochNotFound(self.config.verifier_epoch.saturating_add(1)),
+        )?;
+
+        Ok((VerifierEpoch::current(&self.config, &self.nofn), next_epoch))
+    }
+
+    /// Returns the public nonces that sign the sweep_tx inputs of the
+    /// deposits, which move their bridge funds to the next verifier epoch.
+    /// Nonces are generated once per deposit, so calling this again returns
+    /// the same nonces.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn sweep_nonces(
+        &self,
+        deposit_outpoints: Vec<OutPoint>,
+    ) -> Result<Vec<MuSigPubNonce>, BridgeError> {
+        if deposit_outpoints.is_empty() {
+            return Err(BridgeError::EmptySweep);
+        }
+        let (_, next_epoch) = self.sweep_epochs()?;
+
+        let mut dbtx = self.db.begin_transaction().await?;
+        let mut pub_nonces = Vec::with_capacity(deposit_outpoints.len());
+        for deposit_outpoint in deposit_outpoints {
+            self.check_deposit_epoch(deposit_outpoint).await?;
+
+            let pub_nonce = match self
+                .db
+                .get_sweep_pub_nonce(Some(&mut dbtx), deposit_outpoint, next_epoch.epoch)
+                .await?
+            {
+                Some(pub_nonce) => pub_nonce,
+                None => {
+                    let (sec_nonce, pub_nonce) =
+                        musig2::nonce_pair(&self.signer.keypair, &mut rand::rngs::OsRng)?;
+                    self.db
+                        .save_sweep_nonce(
+                            Some(&mut dbtx),
+                            deposit_outpoint,
+                            next_epoch.epoch,
+                            sec_nonce,
+                            pub_nonce,
+                        )
+                        .await?;
+
+                    pub_nonce
+                }
+            };
+            pub_nonces.push(pub_nonce);
+        }
+        dbtx.commit().await?;
+
+        Ok(pub_nonces)
+    }
+
+    /// Partially signs the sweep_tx of the deposits, in the given order, with
+    /// the nonces from [`Self::sweep_nonces`]. A nonce only signs the sweep it
+    /// is first used for.
+    ///
+    /// Sweep invalidates the deposits' operator_takes_txs, so it is only
+    /// signed if no withdrawal of the deposits is in progress and every
+    /// deposit has a signed graph of the next epoch in `swept_graphs`.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn sign_sweep(
+        &self,
+        deposit_outpoints: Vec<OutPoint>,
+        agg_nonces: Vec<MuSigAggNonce>,
+        swept_graphs: Vec<SweptDepositGraph>,
+    ) -> Result<Vec<MuSigPartialSignature>, BridgeError> {
+        if agg_nonces.len() != deposit_outpoints.len() {
+            return Err(BridgeError::InvalidMuSigAggNonce);
+        }
+        let (current_epoch, next_epoch) = self.sweep_epochs()?;
+
+        let mut deposits = Vec::with_capacity(deposit_outpoints.len());
+        for deposit_outpoint in deposit_outpoints {
+            let move_tx_deposits = self.db.get_move_tx_deposits(deposit_outpoint).await?;
+            let bridge_fund_utxo = epoch::bridge_fund_utxo(
+                &move_tx_deposits,
+                deposit_outpoint,
+                self.nofn.xonly_pk(),
+                &self.config,
+            )?;
+            self.check_deposit_sweepable(deposit_outpoint, bridge_fund_utxo.outpoint)
+                .await?;
+
+            deposits.push((deposit_outpoint, move_tx_deposits));
+        }
+        let sweep = EpochSweep::new(&deposits, &current_epoch, &next_epoch, &self.config)?;
+        sweep.check_swept_graphs(&swept_graphs, &next_epoch, &self.config)?;
+
+        let mut dbtx = self.db.begin_transaction().await?;
+        let mut partial_sigs = Vec::with_capacity(agg_nonces.len());
+        for ((deposit_outpoint, sighash), agg_nonce) in sweep
+            .deposit_outpoints()
+            .iter()
+            .zip(sweep.sighashes())
+            .zip(agg_nonces)
+        {
+            let sec_nonce = self
+                .db
+                .save_sweep_sighash_and_get_sec_nonce(
+                    Some(&mut dbtx),
+                    *deposit_outpoint,
+                    next_epoch.epoch,
+                    agg_nonce,
+                    sighash.sighash,
+                )
+                .await?
+                .ok_or(BridgeError::SweepNonceAlreadyUsed(*deposit_outpoint))?;
+
+            partial_sigs.push(self.nofn.partial_sign(
+                sighash.tweak,
+                sighash.tweak_flag,
+                sec_nonce,
+                agg_nonce,
+                &self.signer.keypair,
+                sighash.sighash,
+            )?);
+        }
+        dbtx.commit().await?;
+
+        Ok(partial_sigs)
     }
 }
 
@@ -545,6 +1122,13 @@ where
             .await
     }
 
+    async fn verifier_new_deposit_batch_rpc(
+        &self,
+        deposit_outpoints: Vec<OutPoint>,
+    ) -> Result<(), BridgeError> {
+        self.new_deposit_batch(deposit_outpoints).await
+    }
+
     async fn operator_kickoffs_generated_rpc(
         &self,
         deposit_outpoint: OutPoint,
@@ -561,6 +1145,29 @@ where
         .await
     }
 
+    async fn frost_operator_kickoffs_generated_rpc(
+        &self,
+        deposit_outpoint: OutPoint,
+        kickoff_utxos: Vec<UTXO>,
+        operators_kickoff_sigs: Vec<schnorr::Signature>,
+        signing_commitments: BTreeMap<FrostSignerId, Vec<FrostCommitment>>,
+    ) -> Result<(Vec<FrostSignatureShare>, Vec<FrostSignatureShare>), BridgeError> {
+        self.frost_operator_kickoffs_generated(
+            deposit_outpoint,
+            kickoff_utxos,
+            operators_kickoff_sigs,
+            signing_commitments,
+        )
+        .await
+    }
+
+    async fn frost_renew_deposit_nonces_rpc(
+        &self,
+        deposit_outpoint: OutPoint,
+    ) -> Result<Vec<FrostCommitment>, BridgeError> {
+        self.frost_renew_deposit_nonces(deposit_outpoint).await
+    }
+
     async fn burn_txs_signed_rpc(
         &self,
         deposit_outpoint: OutPoint,
@@ -579,18 +1186,56 @@ where
         self.operator_take_txs_signed(deposit_outpoint, operator_take_sigs)
             .await
     }
+
+    async fn frost_dkg_round1_rpc(&self) -> Result<dkg::round1::Package, BridgeError> {
+        self.frost_dkg_round1().await
+    }
+
+    async fn frost_dkg_round2_rpc(
+        &self,
+        round1_packages: BTreeMap<FrostSignerId, dkg::round1::Package>,
+    ) -> Result<BTreeMap<FrostSignerId, EncryptedRound2Package>, BridgeError> {
+        self.frost_dkg_round2(round1_packages).await
+    }
+
+    async fn frost_dkg_finalize_rpc(
+        &self,
+        round2_packages: BTreeMap<FrostSignerId, EncryptedRound2Package>,
+    ) -> Result<PublicKeyPackage, BridgeError> {
+        self.frost_dkg_finalize(round2_packages).await
+    }
+
+    async fn sweep_nonces_rpc(
+        &self,
+        deposit_outpoints: Vec<OutPoint>,
+    ) -> Result<Vec<MuSigPubNonce>, BridgeError> {
+        self.sweep_nonces(deposit_outpoints).await
+    }
+
+    async fn sign_sweep_rpc(
+        &self,
+        deposit_outpoints: Vec<OutPoint>,
+        agg_nonces: Vec<MuSigAggNonce>,
+        swept_graphs: Vec<SweptDepositGraph>,
+    ) -> Result<Vec<MuSigPartialSignature>, BridgeError> {
+        self.sign_sweep(deposit_outpoints, agg_nonces, swept_graphs)
+            .await
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::actor::Actor;
+    use crate::builder::{self, deposit_graph::NonceIndex};
     use crate::errors::BridgeError;
     use crate::extended_rpc::ExtendedRpc;
     use crate::musig2::nonce_pair;
+    use crate::nonce_pool::NoncePool;
     use crate::user::User;
     use crate::verifier::Verifier;
     use crate::EVMAddress;
     use crate::{create_extended_rpc, mock::database::create_test_config};
+    use bitcoin::Amount;
     use secp256k1::rand;
 
     #[tokio::test]
@@ -612,27 +1257,31 @@ mod tests {
         let mut config = create_test_config("new_deposit_nonce_checks", "test_config.toml").await;
         let rpc = create_extended_rpc!(config);
         let verifier = Verifier::new(rpc.clone(), config.clone()).await.unwrap();
-        let user = User::new(rpc.clone(), config.secret_key, config.clone());
+        let user = User::new(rpc.clone(), config.secret_key, config.clone()).unwrap();
 
         let evm_address = EVMAddress([1u8; 20]);
-        let deposit_address = user.get_deposit_address(evm_address).unwrap();
+        let deposit_address = user
+            .get_deposit_address(evm_address, config.bridge_amounts_sats[0])
+            .unwrap();
 
         let signer_address = Actor::new(config.secret_key, config.network)
             .address
             .as_unchecked()
             .clone();
 
-        let required_nonce_count = 2 * config.operators_xonly_pks.len() + 1;
+        let required_nonce_count = NonceIndex::new(config.operators_xonly_pks.len()).num_nonces();
 
         // Not enough nonces.
         let deposit_outpoint = rpc
-            .send_to_address(&deposit_address.clone(), config.bridge_amount_sats)
+            .send_to_address(&deposit_address.clone(), config.bridge_amounts_sats[0])
+            .await
             .unwrap();
         rpc.mine_blocks((config.confirmation_threshold + 2).into())
+            .await
             .unwrap();
 
         let nonces = (0..required_nonce_count / 2)
-            .map(|_| nonce_pair(&verifier.signer.keypair, &mut rand::rngs::OsRng))
+            .map(|_| nonce_pair(&verifier.signer.keypair, &mut rand::rngs::OsRng).unwrap())
             .collect::<Vec<_>>();
         verifier
             .db
@@ -654,13 +1303,15 @@ mod tests {
 
         // Enough nonces.
         let deposit_outpoint = rpc
-            .send_to_address(&deposit_address.clone(), config.bridge_amount_sats)
+            .send_to_address(&deposit_address.clone(), config.bridge_amounts_sats[0])
+            .await
             .unwrap();
         rpc.mine_blocks((config.confirmation_threshold + 2).into())
+            .await
             .unwrap();
 
         let nonces = (0..required_nonce_count)
-            .map(|_| nonce_pair(&verifier.signer.keypair, &mut rand::rngs::OsRng))
+            .map(|_| nonce_pair(&verifier.signer.keypair, &mut rand::rngs::OsRng).unwrap())
             .collect::<Vec<_>>();
         verifier
             .db
@@ -673,4 +1324,118 @@ mod tests {
             .await
             .unwrap();
     }
+
+    #[tokio::test]
+    async fn new_deposit_uses_nonce_pool() {
+        let mut config =
+            create_test_config("new_deposit_uses_nonce_pool", "test_config.toml").await;
+        let rpc = create_extended_rpc!(config);
+        let verifier = Verifier::new(rpc.clone(), config.clone()).await.unwrap();
+        let user = User::new(rpc.clone(), config.secret_key, config.clone()).unwrap();
+
+        NoncePool::new(config.clone())
+            .await
+            .unwrap()
+            .replenish()
+            .await
+            .unwrap();
+        let pool_size = verifier.db.get_nonce_pool_size(None).await.unwrap();
+
+        let evm_address = EVMAddress([1u8; 20]);
+        let deposit_address = user
+            .get_deposit_address(evm_address, config.bridge_amounts_sats[0])
+            .unwrap();
+        let signer_address = Actor::new(config.secret_key, config.network)
+            .address
+            .as_unchecked()
+            .clone();
+
+        let deposit_outpoint = rpc
+            .send_to_address(&deposit_address, config.bridge_amounts_sats[0])
+            .await
+            .unwrap();
+        rpc.mine_blocks((config.confirmation_threshold + 2).into())
+            .await
+            .unwrap();
+
+        let pub_nonces = verifier
+            .new_deposit(deposit_outpoint, signer_address.clone(), evm_address)
+            .await
+            .unwrap();
+        let num_nonces = NonceIndex::new(config.operators_xonly_pks.len()).num_nonces();
+        assert_eq!(pub_nonces.len(), num_nonces);
+        assert_eq!(
+            verifier.db.get_nonce_pool_size(None).await.unwrap(),
+            pool_size - num_nonces
+        );
+
+        // Same nonces are returned again, without using the pool.
+        assert_eq!(
+            verifier
+                .new_deposit(deposit_outpoint, signer_address, evm_address)
+                .await
+                .unwrap(),
+            pub_nonces
+        );
+        assert_eq!(
+            verifier.db.get_nonce_pool_size(None).await.unwrap(),
+            pool_size - num_nonces
+        );
+    }
+
+    #[tokio::test]
+    async fn frost_renew_deposit_nonces_without_frost() {
+        let mut config = create_test_config(
+            "frost_renew_deposit_nonces_without_frost",
+            "test_config.toml",
+        )
+        .await;
+        let rpc = create_extended_rpc!(config);
+        let verifier = Verifier::new(rpc.clone(), config.clone()).await.unwrap();
+
+        // MuSig2 nonces are bound to the agg nonces, not to a signing set.
+        assert!(matches!(
+            verifier
+                .frost_renew_deposit_nonces(bitcoin::OutPoint::null())
+                .await,
+            Err(BridgeError::FrostNotEnabled)
+        ));
+    }
+
+    #[tokio::test]
+    async fn new_deposit_invalid_amount() {
+        let mut config = create_test_config("new_deposit_invalid_amount", "test_config.toml").await;
+        let rpc = create_extended_rpc!(config);
+        let verifier = Verifier::new(rpc.clone(), config.clone()).await.unwrap();
+
+        let evm_address = EVMAddress([1u8; 20]);
+        let signer_address = Actor::new(config.secret_key, config.network)
+            .address
+            .as_unchecked()
+            .clone();
+
+        // Deposit address is valid for the amount, but the amount is not one
+        // of the bridge amounts.
+        let amount = Amount::from_sat(0x45 * 100_000);
+        let (deposit_address, _) = builder::address::generate_deposit_address(
+            verifier.nofn.xonly_pk(),
+            &signer_address,
+            evm_address,
+            amount,
+            config.network,
+            config.user_takes_after,
+        )
+        .unwrap();
+        let deposit_outpoint = rpc.send_to_address(&deposit_address, amount).await.unwrap();
+        rpc.mine_blocks((config.confirmation_threshold + 2).into())
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            verifier
+                .new_deposit(deposit_outpoint, signer_address, evm_address)
+                .await,
+            Err(BridgeError::InvalidBridgeAmount(deposit_amount)) if deposit_amount == amount
+        ));
+    }
 }
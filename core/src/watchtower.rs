@@ -0,0 +1,307 @@
+//! # Watchtower
+//!
+//! Watchtower follows the Bitcoin chain on behalf of a verifier. When an
+//! operator spends a kickoff UTXO with a `slash_or_take_tx` without paying the
+//! corresponding withdrawal, watchtower broadcasts the pre-signed `burn_tx`
+//! before the operator's timelock expires.
+
+use crate::builder;
+use crate::citrea::CitreaClient;
+use crate::config::BridgeConfig;
+use crate::database::Database;
+use crate::errors::BridgeError;
+use crate::extended_rpc::{self, ExtendedRpc};
+use crate::fee;
+use crate::musig2::NofnContext;
+use crate::utils::handle_taproot_witness_new;
+use crate::UTXO;
+use bitcoin::{OutPoint, Txid};
+use bitcoin_mock_rpc::RpcApiWrapper;
+use secp256k1::schnorr;
+use std::time::Duration;
+
+/// Time to wait between two checks for a new block.
+pub const WATCHTOWER_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub struct Watchtower<R>
+where
+    R: RpcApiWrapper,
+{
+    rpc: ExtendedRpc<R>,
+    db: Database,
+    config: BridgeConfig,
+    nofn: NofnContext,
+    citrea_client: Option<CitreaClient>,
+}
+
+impl<R> Watchtower<R>
+where
+    R: RpcApiWrapper,
+{
+    pub async fn new(rpc: ExtendedRpc<R>, config: BridgeConfig) -> Result<Self, BridgeError> {
+        let db = Database::new(&config).await?;
+
+        let nofn = NofnContext::from_config(&config)?;
+
+        let citrea_client = if !config.citrea_rpc_url.is_empty() {
+            Some(CitreaClient::new(&config)?)
+        } else {
+            None
+        };
+
+        Ok(Watchtower {
+            rpc,
+            db,
+            config,
+            nofn,
+            citrea_client,
+        })
+    }
+
+    /// Follows the chain forever and checks the kickoff UTXOs every time a new
+    /// block is found. Errors are logged, so that a temporary failure doesn't
+    /// stop the watchtower. Checks of a block that fail are run again on the
+    /// next poll.
+    pub async fn run(&self, poll_interval: Duration) {
+        let mut last_block_count = None;
+
+        loop {
+            match self.rpc.get_block_count().await {
+                Ok(block_count) if last_block_count != Some(block_count) => {
+                    match self.check_kickoffs().await {
+                        Ok(_) => last_block_count = Some(block_count),
+                        Err(e) => tracing::error!("Watchtower can't check kickoffs: {}", e),
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("Watchtower can't get block count: {}", e),
+            }
+
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Checks every kickoff UTXO that has a burn signature and broadcasts the
+    /// burn tx of the ones that are taken by an operator who didn't pay the
+    /// withdrawal. Errors of a kickoff are logged, so that it doesn't stop the
+    /// checks of the others.
+    ///
+    /// # Returns
+    ///
+    /// Txids of the broadcasted burn txs.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BridgeError::KickoffChecksFailed`] if any kickoff can't be
+    /// checked, after the others are checked, so that the checks are run
+    /// again.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    pub async fn check_kickoffs(&self) -> Result<Vec<Txid>, BridgeError> {
+        let mut burn_txids = Vec::new();
+        let mut num_failed_checks = 0;
+
+        for (deposit_outpoint, operator_idx, kickoff_utxo, burn_sig) in
+            self.db.get_unburned_kickoff_utxos().await?
+        {
+            match self
+                .check_kickoff(deposit_outpoint, operator_idx, kickoff_utxo, burn_sig)
+                .await
+            {
+                Ok(Some(burn_txid)) => burn_txids.push(burn_txid),
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::error!(
+                        "Watchtower can't check kickoff of operator {} for deposit {}: {}",
+                        operator_idx,
+                        deposit_outpoint,
+                        e
+                    );
+                    num_failed_checks += 1;
+                }
+            }
+        }
+
+        if num_failed_checks > 0 {
+            return Err(BridgeError::KickoffChecksFailed(num_failed_checks));
+        }
+
+        Ok(burn_txids)
+    }
+
+    /// Burns a spent kickoff UTXO if its operator cheated and saves the burn
+    /// txid.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn check_kickoff(
+        &self,
+        deposit_outpoint: OutPoint,
+        operator_idx: usize,
+        kickoff_utxo: UTXO,
+        burn_sig: schnorr::Signature,
+    ) -> Result<Option<Txid>, BridgeError> {
+        if !self.rpc.is_utxo_spent(&kickoff_utxo.outpoint).await? {
+            return Ok(None);
+        }
+
+        let burn_txid = self
+            .burn_if_cheated(deposit_outpoint, operator_idx, kickoff_utxo, burn_sig)
+            .await?;
+        if let Some(burn_txid) = burn_txid {
+            self.db
+                .save_burn_txid(deposit_outpoint, operator_idx, burn_txid)
+                .await?;
+        }
+
+        Ok(burn_txid)
+    }
+
+    /// Broadcasts the burn tx of a spent kickoff UTXO, if it is spent by the
+    /// slash_or_take_tx, `operator_takes_after` window is still open and the
+    /// operator didn't pay the withdrawal.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn burn_if_cheated(
+        &self,
+        deposit_outpoint: OutPoint,
+        operator_idx: usize,
+        kickoff_utxo: UTXO,
+        burn_sig: schnorr::Signature,
+    ) -> Result<Option<Txid>, BridgeError> {
+        let operator_xonly_pk = *self.config.operators_xonly_pks.get(operator_idx).ok_or(
+            BridgeError::InvalidOperatorIndex(operator_idx, self.config.operators_xonly_pks.len()),
+        )?;
+        let (move_txid, batch_idx) = self.get_move_txid(deposit_outpoint).await?;
+
+        let slash_or_take_tx_handler = builder::transaction::create_slash_or_take_tx(
+            move_txid,
+            kickoff_utxo,
+            operator_xonly_pk,
+            operator_idx,
+            self.nofn.xonly_pk(),
+            self.config.network,
+            self.config.user_takes_after,
+            self.config.operator_takes_after,
+            fee::presigned_fee_rate(&self.config)?,
+        )?;
+        let slash_or_take_utxo = UTXO {
+            outpoint: OutPoint {
+                txid: slash_or_take_tx_handler.tx.compute_txid(),
+                vout: 0,
+            },
+            txout: slash_or_take_tx_handler.tx.output[0].clone(),
+        };
+
+        // Kickoff UTXO is spent by something else or slash_or_take_tx is not
+        // mined yet.
+        let confirmations = match self
+            .rpc
+            .confirmation_blocks(&slash_or_take_utxo.outpoint.txid)
+            .await
+        {
+            Ok(confirmations) => confirmations,
+            Err(BridgeError::NoConfirmationData) => return Ok(None),
+            Err(e) if extended_rpc::is_tx_not_found(&e) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        // Operator can already spend the slash_or_take_tx output.
+        if confirmations >= self.config.operator_takes_after {
+            tracing::warn!(
+                "Burn window of operator {} for deposit {} is closed",
+                operator_idx,
+                deposit_outpoint
+            );
+            return Ok(None);
+        }
+        if self.rpc.is_utxo_spent(&slash_or_take_utxo.outpoint).await? {
+            return Ok(None);
+        }
+
+        if self
+            .is_withdrawal_paid(deposit_outpoint, move_txid, batch_idx, operator_idx)
+            .await?
+        {
+            return Ok(None);
+        }
+
+        let mut burn_tx_handler = builder::transaction::create_burn_tx(
+            slash_or_take_utxo,
+            operator_xonly_pk,
+            self.nofn.xonly_pk(),
+            self.config.network,
+            self.config.operator_takes_after,
+            fee::presigned_fee_rate(&self.config)?,
+        )?;
+        handle_taproot_witness_new(&mut burn_tx_handler, &[burn_sig.as_ref()], 0, None)?;
+
+        let cpfp_fee_rate = fee::estimate_fee_rate(&self.rpc, &self.config).await?;
+        let burn_txid = self
+            .rpc
+            .send_or_bump_transaction(&burn_tx_handler.tx, cpfp_fee_rate)
+            .await?;
+        tracing::info!(
+            "Operator {} didn't pay the withdrawal of deposit {}, burn tx {} is sent",
+            operator_idx,
+            deposit_outpoint,
+            burn_txid
+        );
+
+        Ok(Some(burn_txid))
+    }
+
+    /// Returns the txid of the move_tx that moves the deposit and the index of
+    /// the deposit in it.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn get_move_txid(
+        &self,
+        deposit_outpoint: OutPoint,
+    ) -> Result<(Txid, usize), BridgeError> {
+        let move_tx_deposits = self.db.get_move_tx_deposits(deposit_outpoint).await?;
+        let batch_idx = move_tx_deposits
+            .iter()
+            .position(|deposit| deposit.deposit_outpoint == deposit_outpoint)
+            .ok_or(BridgeError::InvalidDepositBatch(deposit_outpoint))?;
+
+        let move_txid = builder::transaction::create_batch_move_tx(
+            &move_tx_deposits
+                .iter()
+                .map(|deposit| (deposit.deposit_outpoint, deposit.bridge_amount_sats))
+                .collect::<Vec<_>>(),
+            self.nofn.xonly_pk(),
+            self.config.network,
+            fee::presigned_fee_rate(&self.config)?,
+        )?
+        .compute_txid();
+
+        Ok((move_txid, batch_idx))
+    }
+
+    /// Checks Citrea if the withdrawal of the deposit is filled by the
+    /// operator. Without a Citrea client, cheating can't be proven, so the
+    /// operator is assumed to be honest.
+    #[tracing::instrument(skip(self), err(level = tracing::Level::ERROR), ret(level = tracing::Level::TRACE))]
+    async fn is_withdrawal_paid(
+        &self,
+        deposit_outpoint: OutPoint,
+        move_txid: Txid,
+        batch_idx: usize,
+        operator_idx: usize,
+    ) -> Result<bool, BridgeError> {
+        let Some(citrea_client) = &self.citrea_client else {
+            tracing::warn!(
+                "Citrea client is not set, withdrawal of deposit {} can't be checked",
+                deposit_outpoint
+            );
+            return Ok(true);
+        };
+
+        // Deposit is not registered on Citrea, so there can't be a withdrawal.
+        let bridge_fund_outpoint = OutPoint {
+            txid: move_txid,
+            vout: batch_idx as u32,
+        };
+        let Some(withdrawal_idx) = citrea_client.deposit_idx(bridge_fund_outpoint).await? else {
+            return Ok(false);
+        };
+
+        Ok(citrea_client.withdrawal_filler(withdrawal_idx).await? == Some(operator_idx))
+    }
+}
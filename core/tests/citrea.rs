@@ -0,0 +1,183 @@
+//! # Citrea Tests
+//!
+//! This tests checks if operators verify withdrawals on Citrea, using the mock
+//! Citrea server.
+
+use bitcoin::{Address, Amount, OutPoint};
+use bitcoin_mock_rpc::RpcApiWrapper;
+use clementine_core::builder;
+use clementine_core::config::BridgeConfig;
+use clementine_core::errors::BridgeError;
+use clementine_core::extended_rpc::ExtendedRpc;
+use clementine_core::fee;
+use clementine_core::mock::citrea::MockCitrea;
+use clementine_core::musig2::AggregateFromPublicKeys;
+use clementine_core::operator::{Operator, REIMBURSEMENT_POLL_INTERVAL};
+use clementine_core::traits::rpc::OperatorRpcServer;
+use clementine_core::utils::SECP;
+use clementine_core::{create_extended_rpc, user::User};
+use common::run_single_deposit;
+use secp256k1::SecretKey;
+
+mod common;
+
+/// Creates an operator that verifies withdrawals on the given Citrea. It uses
+/// the database of the operator server with the same index, so that it knows
+/// the kickoff UTXO of the deposit and the server drives its reimbursements.
+async fn create_citrea_operator<R>(
+    config: &BridgeConfig,
+    operator_idx: usize,
+    citrea_rpc_url: String,
+    rpc: ExtendedRpc<R>,
+) -> Operator<R>
+where
+    R: RpcApiWrapper,
+{
+    let config = BridgeConfig {
+        secret_key: config.all_operators_secret_keys.clone().unwrap()[operator_idx],
+        db_name: format!("{}{}", config.db_name, operator_idx),
+        citrea_rpc_url,
+        ..config.clone()
+    };
+
+    Operator::new(config, rpc).await.unwrap()
+}
+
+/// Returns the bridge fund output of the deposit's move_tx, which is
+/// registered on Citrea.
+fn calculate_bridge_fund_outpoint(config: &BridgeConfig, deposit_outpoint: OutPoint) -> OutPoint {
+    let nofn_xonly_pk = secp256k1::XOnlyPublicKey::from_musig2_pks(
+        config.verifiers_public_keys.clone(),
+        None,
+        false,
+    )
+    .unwrap();
+
+    let move_txid = builder::transaction::create_move_tx(
+        deposit_outpoint,
+        nofn_xonly_pk,
+        config.bridge_amounts_sats[0],
+        config.network,
+        fee::presigned_fee_rate(config).unwrap(),
+    )
+    .unwrap()
+    .compute_txid();
+
+    OutPoint {
+        txid: move_txid,
+        vout: 0,
+    }
+}
+
+#[tokio::test]
+async fn citrea_verified_withdrawal_is_reimbursed() {
+    let (_verifiers, _operators, mut config, deposit_outpoint) =
+        run_single_deposit("test_config.toml").await.unwrap();
+    let rpc = create_extended_rpc!(config);
+
+    let citrea = MockCitrea::new();
+    let (citrea_rpc_url, _citrea_handle) = citrea.start().await.unwrap();
+    let operator = create_citrea_operator(&config, 1, citrea_rpc_url, rpc.clone()).await;
+    let deposit_idx = citrea.add_deposit(calculate_bridge_fund_outpoint(&config, deposit_outpoint));
+
+    let user_sk = SecretKey::from_slice(&[14u8; 32]).unwrap();
+    let user = User::new(rpc.clone(), user_sk, config.clone()).unwrap();
+    let withdrawal_address = Address::p2tr(
+        &SECP,
+        user_sk.x_only_public_key(&SECP).0,
+        None,
+        config.network,
+    );
+    let withdrawal_amount = Amount::from_sat(
+        config.bridge_amounts_sats[0].to_sat()
+            - 2 * config.operator_withdrawal_fee_sats.unwrap().to_sat(),
+    );
+    let (withdrawal_utxo, withdrawal_txout, user_sig) = user
+        .generate_withdrawal_transaction_and_signature(withdrawal_address, withdrawal_amount)
+        .await
+        .unwrap();
+    let withdrawal_idx = citrea.add_withdrawal(withdrawal_utxo.outpoint);
+    assert_eq!(withdrawal_idx, deposit_idx);
+
+    operator
+        .new_withdrawal_sig_rpc(
+            withdrawal_idx,
+            deposit_outpoint,
+            user_sig,
+            withdrawal_utxo,
+            withdrawal_txout,
+        )
+        .await
+        .unwrap();
+    citrea.fill_withdrawal(withdrawal_idx, 1);
+
+    let reimbursement_txids = operator
+        .withdrawal_proved_on_citrea_rpc(withdrawal_idx, deposit_outpoint)
+        .await
+        .unwrap();
+    let operator_take_txid = *reimbursement_txids.last().unwrap();
+
+    let max_blocks = reimbursement_txids.len() as u32 + config.operator_takes_after + 5;
+    for _ in 0..max_blocks {
+        tokio::time::sleep(2 * REIMBURSEMENT_POLL_INTERVAL).await;
+        rpc.mine_blocks(1).await.unwrap();
+
+        if rpc.confirmation_blocks(&operator_take_txid).await.is_ok() {
+            break;
+        }
+    }
+    let operator_take_tx = rpc
+        .get_raw_transaction(&operator_take_txid, None)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        operator_take_tx.output[0].script_pubkey,
+        config.operator_wallet_addresses[1]
+            .clone()
+            .assume_checked()
+            .script_pubkey()
+    );
+}
+
+#[tokio::test]
+async fn citrea_rejects_mismatched_withdrawals() {
+    let (_verifiers, _operators, mut config, deposit_outpoint) =
+        run_single_deposit("test_config.toml").await.unwrap();
+    let rpc = create_extended_rpc!(config);
+
+    let citrea = MockCitrea::new();
+    let (citrea_rpc_url, _citrea_handle) = citrea.start().await.unwrap();
+    let operator = create_citrea_operator(&config, 1, citrea_rpc_url, rpc).await;
+
+    // Deposit is registered with index 1, so withdrawal 0 doesn't belong to
+    // it.
+    citrea.add_deposit(OutPoint::null());
+    let deposit_idx = citrea.add_deposit(calculate_bridge_fund_outpoint(&config, deposit_outpoint));
+    let other_withdrawal_idx = citrea.add_withdrawal(OutPoint::null());
+    let withdrawal_idx = citrea.add_withdrawal(OutPoint::null());
+    assert_eq!(withdrawal_idx, deposit_idx);
+
+    citrea.fill_withdrawal(other_withdrawal_idx, 1);
+    assert!(matches!(
+        operator
+            .withdrawal_proved_on_citrea_rpc(other_withdrawal_idx, deposit_outpoint)
+            .await,
+        Err(BridgeError::InvalidDepositOutpointGiven(1, 0))
+    ));
+
+    citrea.fill_withdrawal(withdrawal_idx, 2);
+    assert!(matches!(
+        operator
+            .withdrawal_proved_on_citrea_rpc(withdrawal_idx, deposit_outpoint)
+            .await,
+        Err(BridgeError::InvalidOperatorIndex(2, 1))
+    ));
+
+    assert!(matches!(
+        operator
+            .withdrawal_proved_on_citrea_rpc(withdrawal_idx + 1, deposit_outpoint)
+            .await,
+        Err(BridgeError::WithdrawalNotFilled(_))
+    ));
+}
@@ -6,6 +6,7 @@ use bitcoin::Amount;
 use bitcoin::OutPoint;
 use bitcoin::Transaction;
 use clementine_core::actor::Actor;
+use clementine_core::builder::deposit_graph::NonceIndex;
 use clementine_core::config::BridgeConfig;
 use clementine_core::create_extended_rpc;
 use clementine_core::errors::BridgeError;
@@ -19,12 +20,12 @@ use clementine_core::traits::rpc::VerifierRpcClient;
 use clementine_core::user::User;
 use clementine_core::EVMAddress;
 use jsonrpsee::http_client::HttpClient;
-use jsonrpsee::server::ServerHandle;
 use std::net::SocketAddr;
 
 pub async fn run_multiple_deposits(test_config_name: &str) {
     let mut config = create_test_config_with_thread_name(test_config_name, None).await;
     let rpc = create_extended_rpc!(config);
+    let nonce_index = NonceIndex::new(config.num_operators);
     let secp = secp256k1::Secp256k1::new();
     let (verifiers, operators, aggregator) =
         create_verifiers_and_operators("test_config.toml").await;
@@ -38,17 +39,20 @@ pub async fn run_multiple_deposits(test_config_name: &str) {
         .address
         .as_unchecked()
         .clone();
-    let user = User::new(rpc.clone(), secret_key, config.clone());
+    let user = User::new(rpc.clone(), secret_key, config.clone()).unwrap();
 
     let evm_address = EVMAddress([1u8; 20]);
-    let deposit_address = user.get_deposit_address(evm_address).unwrap();
+    let deposit_address = user
+        .get_deposit_address(evm_address, config.bridge_amounts_sats[0])
+        .unwrap();
     let mut deposit_outpoints = Vec::new();
     for _ in 0..config.operator_num_kickoff_utxos_per_tx + 1 {
         let deposit_outpoint = rpc
-            .send_to_address(&deposit_address, config.bridge_amount_sats)
+            .send_to_address(&deposit_address, config.bridge_amounts_sats[0])
+            .await
             .unwrap();
 
-        rpc.mine_blocks(18).unwrap();
+        rpc.mine_blocks(18).await.unwrap();
 
         let mut pub_nonces = Vec::new();
 
@@ -63,7 +67,7 @@ pub async fn run_multiple_deposits(test_config_name: &str) {
 
         let agg_nonces = aggregator
             .0
-            .aggregate_pub_nonces_rpc(pub_nonces)
+            .aggregate_pub_nonces_rpc(pub_nonces.clone())
             .await
             .unwrap();
         let mut kickoff_utxos = Vec::new();
@@ -81,9 +85,10 @@ pub async fn run_multiple_deposits(test_config_name: &str) {
 
         println!("Now the verifiers sequence starts");
         let mut slash_or_take_partial_sigs = Vec::new();
+        let mut burn_partial_sigs = Vec::new();
 
         for (client, ..) in verifiers.iter() {
-            let (partial_sigs, _) = client
+            let (partial_sigs, burn_partial_sig) = client
                 .operator_kickoffs_generated_rpc(
                     deposit_outpoint,
                     kickoff_utxos.clone(),
@@ -94,22 +99,51 @@ pub async fn run_multiple_deposits(test_config_name: &str) {
                 .unwrap();
 
             slash_or_take_partial_sigs.push(partial_sigs);
+            burn_partial_sigs.push(burn_partial_sig);
         }
 
         let slash_or_take_sigs = aggregator
             .0
             .aggregate_slash_or_take_sigs_rpc(
                 deposit_outpoint,
+                signer_address.clone(),
+                evm_address,
+                config.bridge_amounts_sats[0],
                 kickoff_utxos.clone(),
-                agg_nonces[config.num_operators + 1..2 * config.num_operators + 1].to_vec(),
+                agg_nonces[nonce_index.slash_or_take()].to_vec(),
+                pub_nonces
+                    .iter()
+                    .map(|v| v[nonce_index.slash_or_take()].to_vec())
+                    .collect(),
                 slash_or_take_partial_sigs,
             )
             .await
             .unwrap();
+        let burn_sigs = aggregator
+            .0
+            .aggregate_burn_sigs_rpc(
+                deposit_outpoint,
+                signer_address.clone(),
+                evm_address,
+                config.bridge_amounts_sats[0],
+                kickoff_utxos.clone(),
+                agg_nonces[nonce_index.burn()].to_vec(),
+                pub_nonces
+                    .iter()
+                    .map(|v| v[nonce_index.burn()].to_vec())
+                    .collect(),
+                burn_partial_sigs,
+            )
+            .await
+            .unwrap();
         let mut operator_take_partial_sigs: Vec<Vec<MuSigPartialSignature>> = Vec::new();
         for (client, ..) in verifiers.iter() {
             let partial_sigs = client
-                .burn_txs_signed_rpc(deposit_outpoint, vec![], slash_or_take_sigs.clone())
+                .burn_txs_signed_rpc(
+                    deposit_outpoint,
+                    burn_sigs.clone(),
+                    slash_or_take_sigs.clone(),
+                )
                 .await
                 .unwrap();
             operator_take_partial_sigs.push(partial_sigs);
@@ -119,8 +153,15 @@ pub async fn run_multiple_deposits(test_config_name: &str) {
             .0
             .aggregate_operator_take_sigs_rpc(
                 deposit_outpoint,
+                signer_address.clone(),
+                evm_address,
+                config.bridge_amounts_sats[0],
                 kickoff_utxos.clone(),
-                agg_nonces[1..config.num_operators + 1].to_vec(),
+                agg_nonces[nonce_index.operator_takes()].to_vec(),
+                pub_nonces
+                    .iter()
+                    .map(|v| v[nonce_index.operator_takes()].to_vec())
+                    .collect(),
                 operator_take_partial_sigs,
             )
             .await
@@ -141,7 +182,13 @@ pub async fn run_multiple_deposits(test_config_name: &str) {
                 deposit_outpoint,
                 signer_address.clone(),
                 evm_address,
-                agg_nonces[0],
+                config.bridge_amounts_sats[0],
+                kickoff_utxos.clone(),
+                agg_nonces[nonce_index.move_tx()],
+                pub_nonces
+                    .iter()
+                    .map(|v| v[nonce_index.move_tx()])
+                    .collect(),
                 move_tx_partial_sigs,
             )
             .await
@@ -149,7 +196,7 @@ pub async fn run_multiple_deposits(test_config_name: &str) {
         let move_tx: Transaction = deserialize_hex(&move_tx).unwrap();
 
         println!("Move tx weight: {:?}", move_tx.weight());
-        let move_txid = rpc.send_raw_transaction(&move_tx).unwrap();
+        let move_txid = rpc.send_raw_transaction(&move_tx).await.unwrap();
         println!("Move txid: {:?}", move_txid);
         deposit_outpoints.push(deposit_outpoint);
     }
@@ -163,14 +210,15 @@ pub async fn run_multiple_deposits(test_config_name: &str) {
         .generate_withdrawal_transaction_and_signature(
             withdrawal_address.clone(),
             Amount::from_sat(
-                config.bridge_amount_sats.to_sat()
+                config.bridge_amounts_sats[0].to_sat()
                     - 2 * config.operator_withdrawal_fee_sats.unwrap().to_sat(),
             ),
         )
+        .await
         .unwrap();
     let withdrawal_provide_txid = operators[0]
         .0
-        .new_withdrawal_sig_rpc(0, user_sig, user_utxo, user_txout)
+        .new_withdrawal_sig_rpc(0, deposit_outpoints[0], user_sig, user_utxo, user_txout)
         .await
         .unwrap();
     println!("Withdrawal provide txid: {:?}", withdrawal_provide_txid);
@@ -184,15 +232,17 @@ pub async fn run_multiple_deposits(test_config_name: &str) {
         .generate_withdrawal_transaction_and_signature(
             withdrawal_address.clone(),
             Amount::from_sat(
-                config.bridge_amount_sats.to_sat()
+                config.bridge_amounts_sats[0].to_sat()
                     - 2 * config.operator_withdrawal_fee_sats.unwrap().to_sat(),
             ),
         )
+        .await
         .unwrap();
     let withdrawal_provide_txid = operators[1]
         .0
         .new_withdrawal_sig_rpc(
             config.operator_num_kickoff_utxos_per_tx as u32 - 1,
+            deposit_outpoints[config.operator_num_kickoff_utxos_per_tx - 1],
             user_sig,
             user_utxo,
             user_txout,
@@ -213,14 +263,15 @@ pub async fn run_multiple_deposits(test_config_name: &str) {
         .generate_withdrawal_transaction_and_signature(
             withdrawal_address.clone(),
             Amount::from_sat(
-                config.bridge_amount_sats.to_sat()
+                config.bridge_amounts_sats[0].to_sat()
                     - 2 * config.operator_withdrawal_fee_sats.unwrap().to_sat(),
             ),
         )
+        .await
         .unwrap();
     let withdrawal_provide_txid = operators[0]
         .0
-        .new_withdrawal_sig_rpc(2, user_sig, user_utxo, user_txout)
+        .new_withdrawal_sig_rpc(2, deposit_outpoints[2], user_sig, user_utxo, user_txout)
         .await
         .unwrap();
     println!("Withdrawal provide txid: {:?}", withdrawal_provide_txid);
@@ -239,8 +290,8 @@ pub async fn run_single_deposit(
     test_config_name: &str,
 ) -> Result<
     (
-        Vec<(HttpClient, ServerHandle, SocketAddr)>,
-        Vec<(HttpClient, ServerHandle, SocketAddr)>,
+        Vec<(HttpClient, BridgeServerHandle, SocketAddr)>,
+        Vec<(HttpClient, BridgeServerHandle, SocketAddr)>,
         BridgeConfig,
         OutPoint,
     ),
@@ -248,6 +299,7 @@ pub async fn run_single_deposit(
 > {
     let mut config = create_test_config_with_thread_name(test_config_name, None).await;
     let rpc = create_extended_rpc!(config);
+    let nonce_index = NonceIndex::new(config.num_operators);
 
     let secret_key = secp256k1::SecretKey::new(&mut secp256k1::rand::thread_rng());
     let signer_address = Actor::new(secret_key, config.network)
@@ -255,18 +307,21 @@ pub async fn run_single_deposit(
         .as_unchecked()
         .clone();
 
-    let user = User::new(rpc.clone(), secret_key, config.clone());
+    let user = User::new(rpc.clone(), secret_key, config.clone()).unwrap();
 
     let evm_address = EVMAddress([1u8; 20]);
-    let deposit_address = user.get_deposit_address(evm_address).unwrap();
+    let deposit_address = user
+        .get_deposit_address(evm_address, config.bridge_amounts_sats[0])
+        .unwrap();
 
     let (verifiers, operators, aggregator) =
         create_verifiers_and_operators("test_config.toml").await;
 
     let deposit_outpoint = rpc
-        .send_to_address(&deposit_address, config.bridge_amount_sats)
+        .send_to_address(&deposit_address, config.bridge_amounts_sats[0])
+        .await
         .unwrap();
-    rpc.mine_blocks(18).unwrap();
+    rpc.mine_blocks(18).await.unwrap();
 
     // for every verifier, we call new_deposit
     // aggregate nonces
@@ -282,7 +337,7 @@ pub async fn run_single_deposit(
 
     let agg_nonces = aggregator
         .0
-        .aggregate_pub_nonces_rpc(pub_nonces)
+        .aggregate_pub_nonces_rpc(pub_nonces.clone())
         .await
         .unwrap();
 
@@ -302,8 +357,9 @@ pub async fn run_single_deposit(
 
     // Verifiers part starts here.
     let mut slash_or_take_partial_sigs = Vec::new();
+    let mut burn_partial_sigs = Vec::new();
     for (client, ..) in verifiers.iter() {
-        let (partial_sigs, _) = client
+        let (partial_sigs, burn_partial_sig) = client
             .operator_kickoffs_generated_rpc(
                 deposit_outpoint,
                 kickoff_utxos.clone(),
@@ -314,24 +370,53 @@ pub async fn run_single_deposit(
             .unwrap();
 
         slash_or_take_partial_sigs.push(partial_sigs);
+        burn_partial_sigs.push(burn_partial_sig);
     }
 
     let slash_or_take_sigs = aggregator
         .0
         .aggregate_slash_or_take_sigs_rpc(
             deposit_outpoint,
+            signer_address.clone(),
+            evm_address,
+            config.bridge_amounts_sats[0],
             kickoff_utxos.clone(),
-            agg_nonces[config.num_operators + 1..2 * config.num_operators + 1].to_vec(),
+            agg_nonces[nonce_index.slash_or_take()].to_vec(),
+            pub_nonces
+                .iter()
+                .map(|v| v[nonce_index.slash_or_take()].to_vec())
+                .collect(),
             slash_or_take_partial_sigs,
         )
         .await
         .unwrap();
+    let burn_sigs = aggregator
+        .0
+        .aggregate_burn_sigs_rpc(
+            deposit_outpoint,
+            signer_address.clone(),
+            evm_address,
+            config.bridge_amounts_sats[0],
+            kickoff_utxos.clone(),
+            agg_nonces[nonce_index.burn()].to_vec(),
+            pub_nonces
+                .iter()
+                .map(|v| v[nonce_index.burn()].to_vec())
+                .collect(),
+            burn_partial_sigs,
+        )
+        .await
+        .unwrap();
 
     // call burn_txs_signed_rpc
     let mut operator_take_partial_sigs: Vec<Vec<MuSigPartialSignature>> = Vec::new();
     for (client, ..) in verifiers.iter() {
         let partial_sigs = client
-            .burn_txs_signed_rpc(deposit_outpoint, vec![], slash_or_take_sigs.clone())
+            .burn_txs_signed_rpc(
+                deposit_outpoint,
+                burn_sigs.clone(),
+                slash_or_take_sigs.clone(),
+            )
             .await
             .unwrap();
 
@@ -342,8 +427,15 @@ pub async fn run_single_deposit(
         .0
         .aggregate_operator_take_sigs_rpc(
             deposit_outpoint,
+            signer_address.clone(),
+            evm_address,
+            config.bridge_amounts_sats[0],
             kickoff_utxos.clone(),
-            agg_nonces[1..config.num_operators + 1].to_vec(),
+            agg_nonces[nonce_index.operator_takes()].to_vec(),
+            pub_nonces
+                .iter()
+                .map(|v| v[nonce_index.operator_takes()].to_vec())
+                .collect(),
             operator_take_partial_sigs,
         )
         .await
@@ -367,7 +459,13 @@ pub async fn run_single_deposit(
             deposit_outpoint,
             signer_address,
             evm_address,
-            agg_nonces[0],
+            config.bridge_amounts_sats[0],
+            kickoff_utxos.clone(),
+            agg_nonces[nonce_index.move_tx()],
+            pub_nonces
+                .iter()
+                .map(|v| v[nonce_index.move_tx()])
+                .collect(),
             move_tx_partial_sigs,
         )
         .await
@@ -376,7 +474,7 @@ pub async fn run_single_deposit(
     let move_tx: Transaction = deserialize_hex(&move_tx).unwrap();
     println!("Move tx weight: {:?}", move_tx.weight());
 
-    let move_txid = rpc.send_raw_transaction(&move_tx).unwrap();
+    let move_txid = rpc.send_raw_transaction(&move_tx).await.unwrap();
     println!("Move txid: {:?}", move_txid);
 
     Ok((verifiers, operators, config, deposit_outpoint))
@@ -3,6 +3,8 @@
 use bitcoin::consensus::encode::deserialize_hex;
 use bitcoin::Transaction;
 use clementine_core::actor::Actor;
+use clementine_core::builder::deposit_graph::NonceIndex;
+use clementine_core::builder::transaction::MoveTxDeposit;
 use clementine_core::create_extended_rpc;
 use clementine_core::extended_rpc::ExtendedRpc;
 use clementine_core::mock::database::create_test_config_with_thread_name;
@@ -12,33 +14,39 @@ use clementine_core::traits::rpc::AggregatorClient;
 use clementine_core::traits::rpc::OperatorRpcClient;
 use clementine_core::traits::rpc::VerifierRpcClient;
 use clementine_core::user::User;
+use clementine_core::verifier::MIN_BATCH_RECOVERY_MARGIN;
 use clementine_core::EVMAddress;
 
 #[tokio::test]
 async fn deposit_with_retry_checks() {
     let mut config = create_test_config_with_thread_name("test_config.toml", None).await;
     let rpc = create_extended_rpc!(config);
+    let nonce_index = NonceIndex::new(config.num_operators);
 
     let secret_key = secp256k1::SecretKey::new(&mut secp256k1::rand::thread_rng());
     let signer_address = Actor::new(secret_key, config.network)
         .address
         .as_unchecked()
         .clone();
-    let user = User::new(rpc.clone(), secret_key, config.clone());
+    let user = User::new(rpc.clone(), secret_key, config.clone()).unwrap();
 
     let evm_address: EVMAddress = EVMAddress([1u8; 20]);
-    let deposit_address = user.get_deposit_address(evm_address).unwrap();
+    let deposit_address = user
+        .get_deposit_address(evm_address, config.bridge_amounts_sats[0])
+        .unwrap();
 
     let deposit_outpoint = rpc
-        .send_to_address(&deposit_address, config.bridge_amount_sats)
+        .send_to_address(&deposit_address, config.bridge_amounts_sats[0])
+        .await
         .unwrap();
     rpc.mine_blocks((config.confirmation_threshold + 2).into())
+        .await
         .unwrap();
 
     let (verifiers, operators, aggregator) =
         create_verifiers_and_operators("test_config.toml").await;
 
-    let agg_nonces = {
+    let (pub_nonces, agg_nonces) = {
         let mut pub_nonces = Vec::new();
 
         for (client, _, _) in verifiers.iter() {
@@ -51,7 +59,7 @@ async fn deposit_with_retry_checks() {
         }
         let agg_nonces = aggregator
             .0
-            .aggregate_pub_nonces_rpc(pub_nonces)
+            .aggregate_pub_nonces_rpc(pub_nonces.clone())
             .await
             .unwrap();
 
@@ -72,7 +80,7 @@ async fn deposit_with_retry_checks() {
 
         assert_eq!(agg_nonces, agg_nonces_retry);
 
-        agg_nonces
+        (pub_nonces, agg_nonces)
     };
 
     let (kickoff_utxos, signatures) = {
@@ -108,10 +116,11 @@ async fn deposit_with_retry_checks() {
 
     // Operator part is done; Verifier part starts.
 
-    let slash_or_take_sigs = {
+    let (slash_or_take_sigs, burn_sigs) = {
         let mut slash_or_take_partial_sigs = Vec::new();
+        let mut burn_partial_sigs = Vec::new();
         for (client, ..) in verifiers.iter() {
-            let (partial_sigs, _) = client
+            let (partial_sigs, burn_partial_sig) = client
                 .operator_kickoffs_generated_rpc(
                     deposit_outpoint,
                     kickoff_utxos.clone(),
@@ -122,21 +131,48 @@ async fn deposit_with_retry_checks() {
                 .unwrap();
 
             slash_or_take_partial_sigs.push(partial_sigs);
+            burn_partial_sigs.push(burn_partial_sig);
         }
         let slash_or_take_sigs = aggregator
             .0
             .aggregate_slash_or_take_sigs_rpc(
                 deposit_outpoint,
+                signer_address.clone(),
+                evm_address,
+                config.bridge_amounts_sats[0],
                 kickoff_utxos.clone(),
-                agg_nonces[config.num_operators + 1..2 * config.num_operators + 1].to_vec(),
+                agg_nonces[nonce_index.slash_or_take()].to_vec(),
+                pub_nonces
+                    .iter()
+                    .map(|v| v[nonce_index.slash_or_take()].to_vec())
+                    .collect(),
                 slash_or_take_partial_sigs.clone(),
             )
             .await
             .unwrap();
 
+        let burn_sigs = aggregator
+            .0
+            .aggregate_burn_sigs_rpc(
+                deposit_outpoint,
+                signer_address.clone(),
+                evm_address,
+                config.bridge_amounts_sats[0],
+                kickoff_utxos.clone(),
+                agg_nonces[nonce_index.burn()].to_vec(),
+                pub_nonces
+                    .iter()
+                    .map(|v| v[nonce_index.burn()].to_vec())
+                    .collect(),
+                burn_partial_sigs.clone(),
+            )
+            .await
+            .unwrap();
+
         let mut slash_or_take_partial_sigs_retry = Vec::new();
+        let mut burn_partial_sigs_retry = Vec::new();
         for (client, ..) in verifiers.iter() {
-            let (partial_sigs, _) = client
+            let (partial_sigs, burn_partial_sig) = client
                 .operator_kickoffs_generated_rpc(
                     deposit_outpoint,
                     kickoff_utxos.clone(),
@@ -147,13 +183,21 @@ async fn deposit_with_retry_checks() {
                 .unwrap();
 
             slash_or_take_partial_sigs_retry.push(partial_sigs);
+            burn_partial_sigs_retry.push(burn_partial_sig);
         }
         let slash_or_take_sigs_retry = aggregator
             .0
             .aggregate_slash_or_take_sigs_rpc(
                 deposit_outpoint,
+                signer_address.clone(),
+                evm_address,
+                config.bridge_amounts_sats[0],
                 kickoff_utxos.clone(),
-                agg_nonces[config.num_operators + 1..2 * config.num_operators + 1].to_vec(),
+                agg_nonces[nonce_index.slash_or_take()].to_vec(),
+                pub_nonces
+                    .iter()
+                    .map(|v| v[nonce_index.slash_or_take()].to_vec())
+                    .collect(),
                 slash_or_take_partial_sigs_retry.clone(),
             )
             .await
@@ -161,15 +205,20 @@ async fn deposit_with_retry_checks() {
 
         assert_eq!(slash_or_take_partial_sigs, slash_or_take_partial_sigs_retry);
         assert_eq!(slash_or_take_sigs, slash_or_take_sigs_retry);
+        assert_eq!(burn_partial_sigs, burn_partial_sigs_retry);
 
-        slash_or_take_sigs
+        (slash_or_take_sigs, burn_sigs)
     };
 
     let operator_take_sigs = {
         let mut operator_take_partial_sigs: Vec<Vec<MuSigPartialSignature>> = Vec::new();
         for (client, ..) in verifiers.iter() {
             let partial_sigs = client
-                .burn_txs_signed_rpc(deposit_outpoint, vec![], slash_or_take_sigs.clone())
+                .burn_txs_signed_rpc(
+                    deposit_outpoint,
+                    burn_sigs.clone(),
+                    slash_or_take_sigs.clone(),
+                )
                 .await
                 .unwrap();
             operator_take_partial_sigs.push(partial_sigs);
@@ -178,8 +227,15 @@ async fn deposit_with_retry_checks() {
             .0
             .aggregate_operator_take_sigs_rpc(
                 deposit_outpoint,
+                signer_address.clone(),
+                evm_address,
+                config.bridge_amounts_sats[0],
                 kickoff_utxos.clone(),
-                agg_nonces[1..config.num_operators + 1].to_vec(),
+                agg_nonces[nonce_index.operator_takes()].to_vec(),
+                pub_nonces
+                    .iter()
+                    .map(|v| v[nonce_index.operator_takes()].to_vec())
+                    .collect(),
                 operator_take_partial_sigs,
             )
             .await
@@ -188,7 +244,11 @@ async fn deposit_with_retry_checks() {
         let mut operator_take_partial_sigs_retry = Vec::new();
         for (client, ..) in verifiers.iter() {
             let partial_sigs = client
-                .burn_txs_signed_rpc(deposit_outpoint, vec![], slash_or_take_sigs.clone())
+                .burn_txs_signed_rpc(
+                    deposit_outpoint,
+                    burn_sigs.clone(),
+                    slash_or_take_sigs.clone(),
+                )
                 .await
                 .unwrap();
             operator_take_partial_sigs_retry.push(partial_sigs);
@@ -197,8 +257,15 @@ async fn deposit_with_retry_checks() {
             .0
             .aggregate_operator_take_sigs_rpc(
                 deposit_outpoint,
+                signer_address.clone(),
+                evm_address,
+                config.bridge_amounts_sats[0],
                 kickoff_utxos.clone(),
-                agg_nonces[1..config.num_operators + 1].to_vec(),
+                agg_nonces[nonce_index.operator_takes()].to_vec(),
+                pub_nonces
+                    .iter()
+                    .map(|v| v[nonce_index.operator_takes()].to_vec())
+                    .collect(),
                 operator_take_partial_sigs_retry,
             )
             .await
@@ -240,7 +307,13 @@ async fn deposit_with_retry_checks() {
                 deposit_outpoint,
                 signer_address.clone(),
                 evm_address,
-                agg_nonces[0],
+                config.bridge_amounts_sats[0],
+                kickoff_utxos.clone(),
+                agg_nonces[nonce_index.move_tx()],
+                pub_nonces
+                    .iter()
+                    .map(|v| v[nonce_index.move_tx()])
+                    .collect(),
                 move_tx_partial_sigs.clone(),
             )
             .await
@@ -252,7 +325,13 @@ async fn deposit_with_retry_checks() {
                 deposit_outpoint,
                 signer_address,
                 evm_address,
-                agg_nonces[0],
+                config.bridge_amounts_sats[0],
+                kickoff_utxos.clone(),
+                agg_nonces[nonce_index.move_tx()],
+                pub_nonces
+                    .iter()
+                    .map(|v| v[nonce_index.move_tx()])
+                    .collect(),
                 move_tx_partial_sigs,
             )
             .await
@@ -267,3 +346,200 @@ async fn deposit_with_retry_checks() {
 
     println!("Move tx weight: {:?}", move_tx.weight());
 }
+
+#[tokio::test]
+async fn aggregator_new_deposit() {
+    let mut config = create_test_config_with_thread_name("test_config.toml", None).await;
+    let rpc = create_extended_rpc!(config);
+
+    let secret_key = secp256k1::SecretKey::new(&mut secp256k1::rand::thread_rng());
+    let signer_address = Actor::new(secret_key, config.network)
+        .address
+        .as_unchecked()
+        .clone();
+    let user = User::new(rpc.clone(), secret_key, config.clone()).unwrap();
+
+    // Deposit is made with another denomination than the other tests.
+    let evm_address: EVMAddress = EVMAddress([1u8; 20]);
+    let bridge_amount = config.bridge_amounts_sats[1];
+    let deposit_address = user
+        .get_deposit_address(evm_address, bridge_amount)
+        .unwrap();
+
+    let deposit_outpoint = rpc
+        .send_to_address(&deposit_address, bridge_amount)
+        .await
+        .unwrap();
+    rpc.mine_blocks((config.confirmation_threshold + 2).into())
+        .await
+        .unwrap();
+
+    let (_verifiers, _operators, aggregator) =
+        create_verifiers_and_operators("test_config.toml").await;
+
+    let (move_tx, move_txid) = aggregator
+        .0
+        .aggregator_new_deposit_rpc(
+            deposit_outpoint,
+            signer_address.clone(),
+            evm_address,
+            bridge_amount,
+        )
+        .await
+        .unwrap();
+
+    // Completed deposit is served from the aggregator's signing session.
+    let (move_tx_retry, move_txid_retry) = aggregator
+        .0
+        .aggregator_new_deposit_rpc(
+            deposit_outpoint,
+            signer_address.clone(),
+            evm_address,
+            bridge_amount,
+        )
+        .await
+        .unwrap();
+    assert_eq!(move_tx, move_tx_retry);
+    assert_eq!(move_txid, move_txid_retry);
+
+    // Session can't be served for other deposit infos.
+    assert!(aggregator
+        .0
+        .aggregator_new_deposit_rpc(
+            deposit_outpoint,
+            signer_address,
+            EVMAddress([2u8; 20]),
+            bridge_amount
+        )
+        .await
+        .is_err());
+
+    let move_tx: Transaction = deserialize_hex(&move_tx).unwrap();
+    assert_eq!(move_tx.compute_txid(), move_txid);
+    assert!(move_tx.output[0].value < bridge_amount);
+
+    assert_eq!(rpc.send_raw_transaction(&move_tx).await.unwrap(), move_txid);
+}
+
+#[tokio::test]
+async fn aggregator_new_deposit_batch() {
+    let mut config = create_test_config_with_thread_name("test_config.toml", None).await;
+    let rpc = create_extended_rpc!(config);
+
+    let secret_key = secp256k1::SecretKey::new(&mut secp256k1::rand::thread_rng());
+    let signer_address = Actor::new(secret_key, config.network)
+        .address
+        .as_unchecked()
+        .clone();
+    let user = User::new(rpc.clone(), secret_key, config.clone()).unwrap();
+
+    // Every denomination is moved with the same move_tx.
+    let mut deposits = Vec::new();
+    for (i, bridge_amount) in config.bridge_amounts_sats.iter().enumerate() {
+        let evm_address = EVMAddress([i as u8; 20]);
+        let deposit_address = user
+            .get_deposit_address(evm_address, *bridge_amount)
+            .unwrap();
+        let deposit_outpoint = rpc
+            .send_to_address(&deposit_address, *bridge_amount)
+            .await
+            .unwrap();
+
+        deposits.push(MoveTxDeposit {
+            deposit_outpoint,
+            recovery_taproot_address: signer_address.clone(),
+            evm_address,
+            bridge_amount_sats: *bridge_amount,
+        });
+    }
+    rpc.mine_blocks((config.confirmation_threshold + 2).into())
+        .await
+        .unwrap();
+
+    let (_verifiers, _operators, aggregator) =
+        create_verifiers_and_operators("test_config.toml").await;
+
+    let (move_tx, move_txid) = aggregator
+        .0
+        .aggregator_new_deposit_batch_rpc(deposits.clone())
+        .await
+        .unwrap();
+
+    // Completed batch is served from the aggregator's signing sessions.
+    let (move_tx_retry, move_txid_retry) = aggregator
+        .0
+        .aggregator_new_deposit_batch_rpc(deposits.clone())
+        .await
+        .unwrap();
+    assert_eq!(move_tx, move_tx_retry);
+    assert_eq!(move_txid, move_txid_retry);
+
+    // A batched deposit can't be signed with its own move_tx.
+    assert!(aggregator
+        .0
+        .aggregator_new_deposit_rpc(
+            deposits[1].deposit_outpoint,
+            deposits[1].recovery_taproot_address.clone(),
+            deposits[1].evm_address,
+            deposits[1].bridge_amount_sats,
+        )
+        .await
+        .is_err());
+
+    let move_tx: Transaction = deserialize_hex(&move_tx).unwrap();
+    assert_eq!(move_tx.compute_txid(), move_txid);
+    assert_eq!(move_tx.input.len(), deposits.len());
+    assert_eq!(move_tx.output.len(), deposits.len() + 1);
+    for (i, deposit) in deposits.iter().enumerate() {
+        assert_eq!(move_tx.input[i].previous_output, deposit.deposit_outpoint);
+        assert!(move_tx.output[i].value < deposit.bridge_amount_sats);
+    }
+
+    assert_eq!(rpc.send_raw_transaction(&move_tx).await.unwrap(), move_txid);
+}
+
+#[tokio::test]
+async fn aggregator_new_deposit_batch_close_to_recovery() {
+    let mut config = create_test_config_with_thread_name("test_config.toml", None).await;
+    let rpc = create_extended_rpc!(config);
+
+    let secret_key = secp256k1::SecretKey::new(&mut secp256k1::rand::thread_rng());
+    let signer_address = Actor::new(secret_key, config.network)
+        .address
+        .as_unchecked()
+        .clone();
+    let user = User::new(rpc.clone(), secret_key, config.clone()).unwrap();
+
+    let mut deposits = Vec::new();
+    for i in 0..2 {
+        let evm_address = EVMAddress([i as u8; 20]);
+        let bridge_amount = config.bridge_amounts_sats[0];
+        let deposit_address = user
+            .get_deposit_address(evm_address, bridge_amount)
+            .unwrap();
+        let deposit_outpoint = rpc
+            .send_to_address(&deposit_address, bridge_amount)
+            .await
+            .unwrap();
+
+        deposits.push(MoveTxDeposit {
+            deposit_outpoint,
+            recovery_taproot_address: signer_address.clone(),
+            evm_address,
+            bridge_amount_sats: bridge_amount,
+        });
+    }
+    // Depositors can take their deposits back before a move_tx confirms.
+    rpc.mine_blocks((config.user_takes_after - MIN_BATCH_RECOVERY_MARGIN + 1).into())
+        .await
+        .unwrap();
+
+    let (_verifiers, _operators, aggregator) =
+        create_verifiers_and_operators("test_config.toml").await;
+
+    assert!(aggregator
+        .0
+        .aggregator_new_deposit_batch_rpc(deposits)
+        .await
+        .is_err());
+}
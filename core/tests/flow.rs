@@ -5,6 +5,7 @@
 use bitcoin::{Address, Amount};
 use clementine_core::errors::BridgeError;
 use clementine_core::extended_rpc::ExtendedRpc;
+use clementine_core::operator::REIMBURSEMENT_POLL_INTERVAL;
 use clementine_core::utils::SECP;
 use clementine_core::{create_extended_rpc, traits::rpc::OperatorRpcClient, user::User};
 use common::run_single_deposit;
@@ -19,7 +20,7 @@ async fn honest_operator_takes_refund() {
     let rpc = create_extended_rpc!(config);
 
     let user_sk = SecretKey::from_slice(&[13u8; 32]).unwrap();
-    let user = User::new(rpc.clone(), user_sk, config.clone());
+    let user = User::new(rpc.clone(), user_sk, config.clone()).unwrap();
 
     let withdrawal_address = Address::p2tr(
         &SECP,
@@ -31,38 +32,43 @@ async fn honest_operator_takes_refund() {
     // We are giving enough sats to the user so that the operator can pay the
     // withdrawal and profit.
     let withdrawal_amount = Amount::from_sat(
-        config.bridge_amount_sats.to_sat()
+        config.bridge_amounts_sats[0].to_sat()
             - 2 * config.operator_withdrawal_fee_sats.unwrap().to_sat(),
     );
 
     let (empty_utxo, withdrawal_tx_out, user_sig) = user
         .generate_withdrawal_transaction_and_signature(withdrawal_address, withdrawal_amount)
+        .await
         .unwrap();
 
     let _withdrawal_provide_txid = operators[1]
         .0
-        .new_withdrawal_sig_rpc(0, user_sig, empty_utxo, withdrawal_tx_out)
+        .new_withdrawal_sig_rpc(0, deposit_outpoint, user_sig, empty_utxo, withdrawal_tx_out)
         .await
         .unwrap();
 
-    let txs_to_be_sent = operators[1]
+    let reimbursement_txids = operators[1]
         .0
         .withdrawal_proved_on_citrea_rpc(0, deposit_outpoint)
         .await
         .unwrap();
-
-    for tx in txs_to_be_sent.iter().take(txs_to_be_sent.len() - 1) {
-        rpc.send_raw_transaction(tx.clone()).unwrap();
-        rpc.mine_blocks(1).unwrap();
+    let operator_take_txid = *reimbursement_txids.last().unwrap();
+
+    // Operator broadcasts every reimbursement tx after the previous one is
+    // confirmed and operator_takes_tx after the timelock.
+    let max_blocks = reimbursement_txids.len() as u32 + config.operator_takes_after + 5;
+    for _ in 0..max_blocks {
+        tokio::time::sleep(2 * REIMBURSEMENT_POLL_INTERVAL).await;
+        rpc.mine_blocks(1).await.unwrap();
+
+        if rpc.confirmation_blocks(&operator_take_txid).await.is_ok() {
+            break;
+        }
     }
-    rpc.mine_blocks(1 + config.operator_takes_after as u64)
-        .unwrap();
-
-    // Send last transaction.
-    let operator_take_txid = rpc
-        .send_raw_transaction(txs_to_be_sent.last().unwrap().clone())
+    let operator_take_tx = rpc
+        .get_raw_transaction(&operator_take_txid, None)
+        .await
         .unwrap();
-    let operator_take_tx = rpc.get_raw_transaction(&operator_take_txid, None).unwrap();
 
     assert!(operator_take_tx.output[0].value > withdrawal_amount);
 
@@ -89,20 +95,21 @@ async fn withdrawal_fee_too_low() {
         config.network,
     );
 
-    let user = User::new(rpc.clone(), user_sk, config.clone());
+    let user = User::new(rpc.clone(), user_sk, config.clone()).unwrap();
 
     // We are giving too much sats to the user so that operator won't pay it.
     let (empty_utxo, withdrawal_tx_out, user_sig) = user
         .generate_withdrawal_transaction_and_signature(
             withdrawal_address,
-            Amount::from_sat(config.bridge_amount_sats.to_sat()),
+            Amount::from_sat(config.bridge_amounts_sats[0].to_sat()),
         )
+        .await
         .unwrap();
 
     // Operator will reject because it its not profitable.
     assert!(operators[0]
         .0
-        .new_withdrawal_sig_rpc(0, user_sig, empty_utxo, withdrawal_tx_out)
+        .new_withdrawal_sig_rpc(0, deposit_outpoint, user_sig, empty_utxo, withdrawal_tx_out)
         .await
         .is_err_and(|err| {
             if let jsonrpsee::core::client::Error::Call(err) = err {
@@ -50,7 +50,7 @@ fn get_verifiers_keys(
 fn get_nonces(verifiers_secret_public_keys: Vec<Keypair>) -> (Vec<MuSigNoncePair>, ByteArray66) {
     let nonce_pairs: Vec<MuSigNoncePair> = verifiers_secret_public_keys
         .iter()
-        .map(|kp| nonce_pair(kp, &mut secp256k1::rand::thread_rng()))
+        .map(|kp| nonce_pair(kp, &mut secp256k1::rand::thread_rng()).unwrap())
         .collect();
 
     let agg_nonce = aggregate_nonces(
@@ -58,7 +58,8 @@ fn get_nonces(verifiers_secret_public_keys: Vec<Keypair>) -> (Vec<MuSigNoncePair
             .iter()
             .map(|x| ByteArray66(x.1 .0))
             .collect::<Vec<MuSigPubNonce>>(),
-    );
+    )
+    .unwrap();
 
     (nonce_pairs, agg_nonce)
 }
@@ -73,14 +74,17 @@ async fn key_spend() {
         get_verifiers_keys(&config);
     let (nonce_pairs, agg_nonce) = get_nonces(verifiers_secret_public_keys.clone());
 
-    let (to_address, _) = builder::address::create_taproot_address(&[], None, config.network);
+    let (to_address, _) =
+        builder::address::create_taproot_address(&[], None, config.network).unwrap();
     let (from_address, from_address_spend_info) =
-        builder::address::create_taproot_address(&[], Some(untweaked_xonly_pubkey), config.network);
+        builder::address::create_taproot_address(&[], Some(untweaked_xonly_pubkey), config.network)
+            .unwrap();
 
     let utxo = rpc
         .send_to_address(&from_address, Amount::from_sat(100_000_000))
+        .await
         .unwrap();
-    let prevout = rpc.get_txout_from_outpoint(&utxo).unwrap();
+    let prevout = rpc.get_txout_from_outpoint(&utxo).await.unwrap();
 
     let tx_ins = builder::transaction::create_tx_ins(vec![utxo]);
     let tx_outs = builder::transaction::create_tx_outs(vec![(
@@ -114,6 +118,7 @@ async fn key_spend() {
                 kp,
                 ByteArray32(message),
             )
+            .unwrap()
         })
         .collect();
     let final_signature = aggregate_partial_signatures(
@@ -142,7 +147,7 @@ async fn key_spend() {
     .unwrap();
 
     tx_details.tx.input[0].witness.push(final_signature);
-    rpc.send_raw_transaction(&tx_details.tx).unwrap();
+    rpc.send_raw_transaction(&tx_details.tx).await.unwrap();
 }
 
 #[tokio::test]
@@ -158,17 +163,20 @@ async fn key_spend_with_script() {
     let dummy_script = script::Builder::new().push_int(1).into_script();
     let scripts: Vec<ScriptBuf> = vec![dummy_script];
 
-    let (to_address, _) = builder::address::create_taproot_address(&[], None, config.network);
+    let (to_address, _) =
+        builder::address::create_taproot_address(&[], None, config.network).unwrap();
     let (from_address, from_address_spend_info) = builder::address::create_taproot_address(
         &scripts,
         Some(untweaked_xonly_pubkey),
         config.network,
-    );
+    )
+    .unwrap();
 
     let utxo = rpc
         .send_to_address(&from_address, Amount::from_sat(100_000_000))
+        .await
         .unwrap();
-    let prevout = rpc.get_txout_from_outpoint(&utxo).unwrap();
+    let prevout = rpc.get_txout_from_outpoint(&utxo).await.unwrap();
     let tx_outs = builder::transaction::create_tx_outs(vec![(
         Amount::from_sat(99_000_000),
         to_address.script_pubkey(),
@@ -202,6 +210,7 @@ async fn key_spend_with_script() {
                 kp,
                 ByteArray32(message),
             )
+            .unwrap()
         })
         .collect();
     let final_signature: [u8; 64] = aggregate_partial_signatures(
@@ -230,7 +239,7 @@ async fn key_spend_with_script() {
     .unwrap();
 
     tx_details.tx.input[0].witness.push(final_signature);
-    rpc.send_raw_transaction(&tx_details.tx).unwrap();
+    rpc.send_raw_transaction(&tx_details.tx).await.unwrap();
 }
 
 #[tokio::test]
@@ -264,12 +273,14 @@ async fn script_spend() {
         bitcoin::Network::Regtest,
     );
     let (from_address, from_address_spend_info) =
-        builder::address::create_taproot_address(&scripts, None, bitcoin::Network::Regtest);
+        builder::address::create_taproot_address(&scripts, None, bitcoin::Network::Regtest)
+            .unwrap();
 
     let utxo = rpc
         .send_to_address(&from_address, Amount::from_sat(100_000_000))
+        .await
         .unwrap();
-    let prevout = rpc.get_txout_from_outpoint(&utxo).unwrap();
+    let prevout = rpc.get_txout_from_outpoint(&utxo).await.unwrap();
     let tx_outs = builder::transaction::create_tx_outs(vec![(
         Amount::from_sat(99_000_000),
         to_address.script_pubkey(),
@@ -300,6 +311,7 @@ async fn script_spend() {
                 kp,
                 ByteArray32(message),
             )
+            .unwrap()
         })
         .collect();
     let final_signature: [u8; 64] = aggregate_partial_signatures(
@@ -324,5 +336,5 @@ async fn script_spend() {
     let witness_elements = vec![schnorr_sig.as_ref()];
     handle_taproot_witness_new(&mut tx_details, &witness_elements, 0, Some(0)).unwrap();
 
-    rpc.send_raw_transaction(&tx_details.tx).unwrap();
+    rpc.send_raw_transaction(&tx_details.tx).await.unwrap();
 }
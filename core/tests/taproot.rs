@@ -38,11 +38,13 @@ async fn create_address_and_transaction_then_sign_transaction() {
         .push_opcode(OP_CHECKSIG)
         .into_script();
     let (taproot_address, taproot_spend_info) =
-        builder::address::create_taproot_address(&[to_pay_script.clone()], None, config.network);
+        builder::address::create_taproot_address(&[to_pay_script.clone()], None, config.network)
+            .unwrap();
 
     // Create a new transaction.
     let utxo = rpc
         .send_to_address(&taproot_address, Amount::from_sat(1000))
+        .await
         .unwrap();
     let tx_ins = builder::transaction::create_tx_ins(vec![utxo]);
     let tx_outs = vec![TxOut {
@@ -69,5 +71,5 @@ async fn create_address_and_transaction_then_sign_transaction() {
     handle_taproot_witness_new(&mut tx_details, &[sig.as_ref()], 0, Some(0)).unwrap();
 
     // New transaction should be OK to send.
-    rpc.send_raw_transaction(&tx_details.tx).unwrap();
+    rpc.send_raw_transaction(&tx_details.tx).await.unwrap();
 }
@@ -1,6 +1,9 @@
 #![no_main]
 #![no_std]
 
+extern crate alloc;
+
+use alloc::vec::Vec;
 use clementine_circuits::bridge::bridge_proof;
 use crypto_bigint::Encoding;
 use guest::env::RealEnvironment;
@@ -8,7 +11,9 @@ use risc0_zkvm::guest::env;
 risc0_zkvm::guest::entry!(main); 
 
 pub fn main() {
-    let (verifiers_pow_u256, verifiers_last_finalized_blockhash, verifiers_challenge_period) = bridge_proof::<RealEnvironment>();
+    let bridge_amounts_sats: Vec<u64> = env::read();
+    let (verifiers_pow_u256, verifiers_last_finalized_blockhash, verifiers_challenge_period) = bridge_proof::<RealEnvironment>(&bridge_amounts_sats);
+    env::commit(&bridge_amounts_sats);
     let verifiers_pow_bytes = verifiers_pow_u256.to_le_bytes();
     env::commit(&verifiers_pow_bytes);
     env::commit(&verifiers_last_finalized_blockhash);
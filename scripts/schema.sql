@@ -1,32 +1,69 @@
 BEGIN;
 
+-- Table for verifier-set epochs
+/* This table holds the verifier set of every known epoch and its N-of-N key,
+which the bridge funds of the epoch's deposits are locked to. Verifier set of an
+epoch can't change once it is saved. */
+create table if not exists verifier_epochs (
+    epoch int primary key not null check (epoch >= 0),
+    verifiers_public_keys jsonb not null,
+    nofn_xonly_pk text not null check (nofn_xonly_pk ~ '^[a-fA-F0-9]{64}'),
+    created_at timestamp not null default now()
+);
+
 -- Verifier table for deposit details
-/* This table holds the information related to a deposit. */
+/* This table holds the information related to a deposit. amount is the deposit
+denomination in satoshis. block_hash is the
+block the deposit is confirmed in. A deposit is invalidated when that block is
+reorged out, until the deposit is confirmed again. epoch is the verifier epoch
+the deposit is signed under. */
 create table if not exists deposit_infos (
     deposit_outpoint text primary key not null check (deposit_outpoint ~ '^[a-fA-F0-9]{64}:(0|[1-9][0-9]{0,9})$'),
     recovery_taproot_address text not null,
     evm_address text not null check (evm_address ~ '^[a-fA-F0-9]{40}'),
+    amount bigint not null check (amount > 0),
+    block_hash text not null check (block_hash ~ '^[a-fA-F0-9]{64}'),
+    invalidated boolean not null default false,
+    epoch int not null default 0 check (epoch >= 0),
     created_at timestamp not null default now()
 );
 
+-- Verifier table for batched move transactions
+/* This table holds the deposits that are moved by a batched move_tx. batch_outpoint
+is the outpoint of the first deposit of the batch and batch_idx is the index of
+the deposit's input and bridge fund output in the move_tx. Deposits that are not
+in this table have their own move_tx. */
+create table if not exists deposit_batches (
+    deposit_outpoint text primary key not null check (deposit_outpoint ~ '^[a-fA-F0-9]{64}:(0|[1-9][0-9]{0,9})$'),
+    batch_outpoint text not null check (batch_outpoint ~ '^[a-fA-F0-9]{64}:(0|[1-9][0-9]{0,9})$'),
+    batch_idx int not null check (batch_idx >= 0),
+    created_at timestamp not null default now(),
+    unique (batch_outpoint, batch_idx)
+);
+
 -- Verifier table for nonces related to deposits
 /* This table holds the public, secret, and aggregated nonces related to a deposit.
 For each deposit, we have (2 + num_operators) nonce triples. The first triple is for 
 move_commit_tx, the second triple is for move_reveal_tx, and the rest is for operator_takes_tx
 for each operator. Also for each triple, we hold the sig_hash to be signed to prevent reuse
-of the nonces. */ 
+of the nonces. Nonces without a deposit_outpoint are the verifier's nonce pool. They
+are pre-generated and every one of them is assigned to a single deposit. */ 
 create table if not exists nonces (
-    deposit_outpoint text not null check (deposit_outpoint ~ '^[a-fA-F0-9]{64}:(0|[1-9][0-9]{0,9})$'),
-    internal_idx int not null,
+    id bigserial primary key,
+    deposit_outpoint text check (deposit_outpoint ~ '^[a-fA-F0-9]{64}:(0|[1-9][0-9]{0,9})$'),
+    internal_idx int,
     pub_nonce bytea not null check (length(pub_nonce) = 66),
     sec_nonce bytea not null check (length(sec_nonce) = 64),
     agg_nonce bytea check (length(agg_nonce) = 66),
     sighash bytea check (length(sighash) = 32),
     partial_sig bytea check (length(partial_sig) = 32),
     created_at timestamp not null default now(),
-    primary key (deposit_outpoint, internal_idx)
+    unique (deposit_outpoint, internal_idx),
+    check ((deposit_outpoint is null) = (internal_idx is null))
 );
 
+create index if not exists nonces_pool_idx on nonces (id) where deposit_outpoint is null;
+
 CREATE OR REPLACE FUNCTION prevent_sighash_update()
 RETURNS TRIGGER AS $$
 BEGIN
@@ -53,6 +90,46 @@ BEGIN
     END IF;
 END $$;
 
+-- Verifier table for nonces of sweep transactions
+/* This table holds the nonces that sign a deposit's bridge fund input of the
+sweep_tx, which moves the bridge funds to the N-of-N key of new_epoch. A nonce
+signs a single sweep_tx, so its agg_nonce and sighash can't change once they are
+saved. */
+create table if not exists sweep_nonces (
+    deposit_outpoint text not null check (deposit_outpoint ~ '^[a-fA-F0-9]{64}:(0|[1-9][0-9]{0,9})$'),
+    new_epoch int not null check (new_epoch > 0),
+    pub_nonce bytea not null check (length(pub_nonce) = 66),
+    sec_nonce bytea not null check (length(sec_nonce) = 64),
+    agg_nonce bytea check (length(agg_nonce) = 66),
+    sighash bytea check (length(sighash) = 32),
+    created_at timestamp not null default now(),
+    primary key (deposit_outpoint, new_epoch)
+);
+
+-- Verifier table for FROST signing sets of deposits
+/* This table holds the verifiers that sign a deposit with the FROST key and their
+commitments, in the order of the deposit's nonces. Signing set of a deposit can't
+change once it is saved, since a nonce must not be signed with different
+commitments. */
+create table if not exists frost_signing_commitments (
+    deposit_outpoint text not null check (deposit_outpoint ~ '^[a-fA-F0-9]{64}:(0|[1-9][0-9]{0,9})$'),
+    signer_id int not null check (signer_id > 0),
+    commitments jsonb not null,
+    created_at timestamp not null default now(),
+    primary key (deposit_outpoint, signer_id)
+);
+
+-- Verifier table for the FROST key
+/* This table holds the verifier's key package and the public key package of the
+group, which are generated by the DKG ceremony. There is a single FROST key, so
+the table has at most one row. */
+create table if not exists frost_key_packages (
+    id int primary key not null default 0 check (id = 0),
+    key_package bytea not null,
+    public_key_package bytea not null,
+    created_at timestamp not null default now()
+);
+
 -- Verifier table for kickoff for deposits
 /* This table holds the kickoff utxos sent by the operators for each deposit. */
 create table if not exists deposit_kickoff_utxos (
@@ -62,6 +139,7 @@ create table if not exists deposit_kickoff_utxos (
     slash_or_take_sig text,
     operator_take_sig text,
     burn_sig text,
+    burn_txid text check (burn_txid ~ '^[a-fA-F0-9]{64}'),
     created_at timestamp not null default now(),
     primary key (deposit_outpoint, operator_idx)
 );
@@ -79,13 +157,26 @@ create table if not exists deposit_kickoff_generator_txs (
 );
 
 -- Operator table for kickoff utxo related to deposits
-/* This table holds the kickoff utxos sent by the operators for each deposit. */
+/* This table holds the kickoff utxos sent by the operators for each deposit.
+amount is the deposit denomination in satoshis, as it is checked by the
+operator. */
 create table if not exists operators_kickoff_utxo (
     deposit_outpoint text primary key not null check (deposit_outpoint ~ '^[a-fA-F0-9]{64}:(0|[1-9][0-9]{0,9})$'),
     kickoff_utxo jsonb not null,
+    amount bigint not null check (amount > 0),
     created_at timestamp not null default now()
 );
 
+-- Operator table for batched move transactions
+/* Same as deposit_batches, for the deposits of the operator. */
+create table if not exists operator_deposit_batches (
+    deposit_outpoint text primary key not null references operators_kickoff_utxo (deposit_outpoint),
+    batch_outpoint text not null check (batch_outpoint ~ '^[a-fA-F0-9]{64}:(0|[1-9][0-9]{0,9})$'),
+    batch_idx int not null check (batch_idx >= 0),
+    created_at timestamp not null default now(),
+    unique (batch_outpoint, batch_idx)
+);
+
 -- Operator table for funding utxo used for deposits
 create table if not exists funding_utxos (
     id serial primary key,
@@ -93,4 +184,71 @@ create table if not exists funding_utxos (
     created_at timestamp not null default now()
 );
 
+-- Operator table for reimbursements of paid withdrawals
+/* This table holds a reimbursement job for every withdrawal that is paid by the
+operator. A job is completed when all of its transactions are confirmed. */
+create table if not exists operator_reimbursements (
+    deposit_outpoint text primary key not null check (deposit_outpoint ~ '^[a-fA-F0-9]{64}:(0|[1-9][0-9]{0,9})$'),
+    withdrawal_idx int not null,
+    completed boolean not null default false,
+    created_at timestamp not null default now()
+);
+
+-- Operator table for transactions of reimbursement jobs
+/* This table holds the transactions of a reimbursement job in broadcast order:
+kickoff generator txs, slash_or_take_tx and finally operator_takes_tx. */
+create table if not exists operator_reimbursement_txs (
+    deposit_outpoint text not null references operator_reimbursements (deposit_outpoint),
+    tx_idx int not null,
+    txid text not null check (txid ~ '^[a-fA-F0-9]{64}'),
+    raw_signed_tx text not null,
+    broadcasted boolean not null default false,
+    confirmed boolean not null default false,
+    created_at timestamp not null default now(),
+    primary key (deposit_outpoint, tx_idx)
+);
+
+-- Aggregator table for deposit signing sessions
+/* This table holds the progress of the deposits driven by the aggregator. Every
+completed signing step is recorded in the session, so that an interrupted
+deposit can be resumed from the last completed step. epoch is the verifier epoch
+the deposit is signed under. A session is run by a single call at a time, which
+holds its lease until lease_expires_at. */
+create table if not exists aggregator_deposit_sessions (
+    deposit_outpoint text primary key not null check (deposit_outpoint ~ '^[a-fA-F0-9]{64}:(0|[1-9][0-9]{0,9})$'),
+    recovery_taproot_address text not null,
+    evm_address text not null check (evm_address ~ '^[a-fA-F0-9]{40}'),
+    amount bigint not null check (amount > 0),
+    session jsonb not null,
+    completed boolean not null default false,
+    epoch int not null default 0 check (epoch >= 0),
+    lease_owner text,
+    lease_expires_at timestamp,
+    created_at timestamp not null default now(),
+    updated_at timestamp not null default now()
+);
+
+-- Aggregator table for the FROST key
+/* This table holds the public key package of the verifiers' FROST key, which is
+used to verify and aggregate their signature shares. There is a single FROST key,
+so the table has at most one row. */
+create table if not exists aggregator_frost_public_key_packages (
+    id int primary key not null default 0 check (id = 0),
+    public_key_package bytea not null,
+    created_at timestamp not null default now()
+);
+
+-- Header indexer table for the best chain
+/* This table holds the block headers of the best chain, indexed by height. On a
+reorg, headers after the fork point are replaced with the new chain's.
+chainwork is the total work of the chain up to the block, in big endian. */
+create table if not exists block_headers (
+    height int primary key not null check (height >= 0),
+    block_hash text unique not null check (block_hash ~ '^[a-fA-F0-9]{64}'),
+    prev_block_hash text not null check (prev_block_hash ~ '^[a-fA-F0-9]{64}'),
+    header bytea not null check (length(header) = 80),
+    chainwork bytea not null check (length(chainwork) = 32),
+    created_at timestamp not null default now()
+);
+
 COMMIT;